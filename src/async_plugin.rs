@@ -0,0 +1,160 @@
+use std::future::Future;
+
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+use crate::{Plugin, PluginContext, PluginError};
+
+/// Async command execution for plugins that spend most of their time
+/// waiting on I/O.
+///
+/// Implement this alongside [`Plugin`] and forward `Plugin::execute` to
+/// [`block_on_execute`], which drives the future on the runtime the host
+/// shared through [`PluginContext::runtime_handle`]:
+///
+/// ```ignore
+/// impl Plugin for MyPlugin {
+///     // name(), commands() ...
+///     fn execute(&self, command: &str, args: &[String], ctx: &PluginContext) -> anyhow::Result<()> {
+///         meta_plugin_api::block_on_execute(self, command, args, ctx)
+///     }
+/// }
+///
+/// impl AsyncPlugin for MyPlugin {
+///     async fn execute_async(&self, command: &str, args: &[String], ctx: &PluginContext) -> anyhow::Result<()> {
+///         // .await freely here
+///         Ok(())
+///     }
+/// }
+/// ```
+#[allow(async_fn_in_trait)]
+pub trait AsyncPlugin: Plugin {
+    async fn execute_async(
+        &self,
+        command: &str,
+        args: &[String],
+        ctx: &PluginContext,
+    ) -> anyhow::Result<()>;
+}
+
+/// Run [`AsyncPlugin::execute_async`] to completion, as
+/// [`PluginContext::block_on`] does.
+pub fn block_on_execute<P: AsyncPlugin + ?Sized>(
+    plugin: &P,
+    command: &str,
+    args: &[String],
+    ctx: &PluginContext,
+) -> anyhow::Result<()> {
+    ctx.block_on(plugin.execute_async(command, args, ctx))?
+}
+
+/// Drive `future` to completion from synchronous code.
+///
+/// Uses `handle` when given, otherwise a private current-thread runtime.
+/// Called on a worker thread of the host's multi-threaded runtime, where
+/// Tokio refuses to nest `block_on`, the worker is handed off with
+/// `block_in_place` first. Blocking the only thread of a current-thread
+/// runtime would deadlock, so that fails with
+/// [`PluginError::NestedRuntime`].
+pub(crate) fn block_on<F: Future>(
+    handle: Option<&Handle>,
+    future: F,
+) -> Result<F::Output, PluginError> {
+    if let Ok(current) = Handle::try_current() {
+        if current.runtime_flavor() != RuntimeFlavor::MultiThread {
+            return Err(PluginError::NestedRuntime);
+        }
+        let handle = handle.unwrap_or(&current);
+        return Ok(tokio::task::block_in_place(|| handle.block_on(future)));
+    }
+    match handle {
+        Some(handle) => Ok(handle.block_on(future)),
+        None => Ok(tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?
+            .block_on(future)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct SleepyPlugin;
+
+    impl Plugin for SleepyPlugin {
+        fn name(&self) -> &'static str {
+            "sleepy"
+        }
+        fn commands(&self) -> Vec<&'static str> {
+            vec!["nap"]
+        }
+        fn execute(
+            &self,
+            command: &str,
+            args: &[String],
+            ctx: &PluginContext,
+        ) -> anyhow::Result<()> {
+            block_on_execute(self, command, args, ctx)
+        }
+    }
+
+    impl AsyncPlugin for SleepyPlugin {
+        async fn execute_async(
+            &self,
+            command: &str,
+            _args: &[String],
+            _ctx: &PluginContext,
+        ) -> anyhow::Result<()> {
+            tokio::task::yield_now().await;
+            anyhow::ensure!(command == "nap", "unknown command {}", command);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_execute_on_shared_runtime() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let ctx =
+            PluginContext::new("/ws", "/ws", "1.0.0").with_runtime_handle(runtime.handle().clone());
+        assert!(SleepyPlugin.execute("nap", &[], &ctx).is_ok());
+        assert!(SleepyPlugin.execute("run", &[], &ctx).is_err());
+    }
+
+    #[test]
+    fn test_execute_without_runtime_falls_back() {
+        let ctx = PluginContext::new("/ws", "/ws", "1.0.0");
+        assert!(SleepyPlugin.execute("nap", &[], &ctx).is_ok());
+    }
+
+    #[test]
+    fn test_execute_from_inside_an_async_host() {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .build()
+            .unwrap();
+        let ctx =
+            PluginContext::new("/ws", "/ws", "1.0.0").with_runtime_handle(runtime.handle().clone());
+        let nested = runtime.block_on(async {
+            let ctx = ctx.clone();
+            tokio::spawn(async move {
+                SleepyPlugin.execute("nap", &[], &ctx)?;
+                let answer = ctx.block_on(async { tokio::spawn(async { 42 }).await.unwrap() })?;
+                anyhow::Ok(answer)
+            })
+            .await
+            .unwrap()
+        });
+        assert_eq!(nested.unwrap(), 42);
+
+        let single = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let err = single.block_on(async { SleepyPlugin.execute("nap", &[], &ctx) });
+        assert!(matches!(
+            err.unwrap_err().downcast_ref(),
+            Some(PluginError::NestedRuntime)
+        ));
+    }
+}
@@ -0,0 +1,138 @@
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::PluginError;
+
+/// A mutating action, for the host's audit trail. Plugins report these
+/// through [`PluginContext::audit`](crate::PluginContext::audit) after the
+/// change is made; the schema is shared so one trail covers every plugin.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AuditEvent {
+    /// Who made the change, usually the plugin name, e.g. `git`
+    pub actor: String,
+    /// What was done, e.g. `branch.delete`
+    pub action: String,
+    /// What it was done to, e.g. `api:refs/heads/old`
+    pub target: String,
+    /// Anything else worth keeping, e.g. the commit the branch pointed at
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub details: serde_json::Map<String, serde_json::Value>,
+}
+
+impl AuditEvent {
+    pub fn new(
+        actor: impl Into<String>,
+        action: impl Into<String>,
+        target: impl Into<String>,
+    ) -> Self {
+        Self {
+            actor: actor.into(),
+            action: action.into(),
+            target: target.into(),
+            details: serde_json::Map::new(),
+        }
+    }
+
+    pub fn detail(mut self, key: impl Into<String>, value: impl Into<serde_json::Value>) -> Self {
+        self.details.insert(key.into(), value.into());
+        self
+    }
+}
+
+/// Host-side receiver of audit events. Unlike telemetry, a failure to
+/// record is reported back to the plugin.
+pub trait AuditSink: Send + Sync {
+    fn record(&self, event: &AuditEvent) -> Result<(), PluginError>;
+}
+
+/// One line of an [`AuditLog`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Milliseconds since the Unix epoch
+    pub recorded_at_ms: u64,
+    #[serde(flatten)]
+    pub event: AuditEvent,
+}
+
+/// An append-only JSON Lines file of [`AuditEntry`]s, the default trail
+/// for hosts without a central one.
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+impl AuditLog {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Every entry so far, oldest first. A missing file has none.
+    pub fn entries(&self) -> Result<Vec<AuditEntry>, PluginError> {
+        let Some(bytes) = crate::state::read_if_exists(&self.path)? else {
+            return Ok(Vec::new());
+        };
+        bytes
+            .split(|&b| b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| Ok(serde_json::from_slice(line).map_err(io::Error::from)?))
+            .collect()
+    }
+}
+
+impl AuditSink for AuditLog {
+    fn record(&self, event: &AuditEvent) -> Result<(), PluginError> {
+        let entry = AuditEntry {
+            recorded_at_ms: crate::cache::now_ms(),
+            event: event.clone(),
+        };
+        let mut line = serde_json::to_vec(&entry).expect("audit entry serializes");
+        line.push(b'\n');
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // One write per line, so concurrent appenders do not interleave.
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(&line)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_appends_entries() {
+        let dir =
+            std::env::temp_dir().join(format!("meta_plugin_api-audit-{}", std::process::id()));
+        let log = AuditLog::new(dir.join("audit.jsonl"));
+        assert!(log.entries().unwrap().is_empty());
+
+        let event = AuditEvent::new("git", "branch.delete", "api:refs/heads/old")
+            .detail("commit", "1a2b3c");
+        log.record(&event).unwrap();
+        log.record(&AuditEvent::new("project", "project.add", "web"))
+            .unwrap();
+
+        let entries = log.entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].event, event);
+        assert!(entries[0].recorded_at_ms > 0);
+
+        let json = serde_json::to_value(&entries[1]).unwrap();
+        assert_eq!(json["action"], "project.add");
+        assert!(json.get("details").is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
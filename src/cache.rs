@@ -0,0 +1,221 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::{state, PluginError};
+
+/// Size limit of a [`Cache`] unless the plugin picks another.
+pub const DEFAULT_CACHE_LIMIT: u64 = 64 * 1024 * 1024;
+
+/// Key-value cache for responses from remote APIs, from
+/// [`PluginContext::cache`](crate::PluginContext::cache). Entries live
+/// under the plugin's cache directory, so `meta cache clear` removes them
+/// along with everything else the host caches.
+///
+/// Each entry has an optional time to live. When a [`put`](Self::put)
+/// takes the cache over its size limit, the least recently written
+/// entries are evicted.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires_at_ms: Option<u64>,
+}
+
+impl Cache {
+    /// A cache storing its entries in `dir`, created on first write.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            max_bytes: DEFAULT_CACHE_LIMIT,
+        }
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The value stored under `key`, None if absent or expired.
+    pub fn get(&self, key: &str) -> Result<Option<Vec<u8>>, PluginError> {
+        let path = self.entry_path(key);
+        let Some(contents) = state::read_if_exists(&path)? else {
+            return Ok(None);
+        };
+        match parse_entry(&contents) {
+            Some((header, value)) if header.key == key => {
+                if header.expires_at_ms.is_some_and(|at| at <= now_ms()) {
+                    remove_if_exists(&path)?;
+                    Ok(None)
+                } else {
+                    Ok(Some(value.to_vec()))
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Store `value` under `key`, replacing any previous value. With a
+    /// `ttl`, [`get`](Self::get) stops returning it once the time has
+    /// passed.
+    pub fn put(&self, key: &str, value: &[u8], ttl: Option<Duration>) -> Result<(), PluginError> {
+        let header = Header {
+            key: key.to_string(),
+            expires_at_ms: ttl.map(|ttl| now_ms().saturating_add(ttl.as_millis() as u64)),
+        };
+        let mut contents = serde_json::to_vec(&header).expect("cache header serializes");
+        contents.push(b'\n');
+        contents.extend_from_slice(value);
+        state::write_atomic(&self.entry_path(key), &contents)?;
+        self.evict()
+    }
+
+    /// Drop the value under `key`. Returns whether there was one.
+    pub fn invalidate(&self, key: &str) -> Result<bool, PluginError> {
+        remove_if_exists(&self.entry_path(key))
+    }
+
+    /// Drop every entry.
+    pub fn clear(&self) -> Result<(), PluginError> {
+        match fs::remove_dir_all(&self.dir) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// [`get`](Self::get) a value stored with [`put_json`](Self::put_json).
+    /// An entry that no longer parses is treated as absent.
+    pub fn get_json<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, PluginError> {
+        Ok(self
+            .get(key)?
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok()))
+    }
+
+    pub fn put_json<T: Serialize>(
+        &self,
+        key: &str,
+        value: &T,
+        ttl: Option<Duration>,
+    ) -> Result<(), PluginError> {
+        let bytes = serde_json::to_vec(value).map_err(io::Error::from)?;
+        self.put(key, &bytes, ttl)
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir
+            .join(format!("{:016x}.entry", state::stable_hash(key.as_bytes())))
+    }
+
+    /// Remove entries, oldest first, until the cache fits its limit.
+    fn evict(&self) -> Result<(), PluginError> {
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            // Another process may evict the same entry while we scan
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            if metadata.is_file() && entry.path().extension().is_some_and(|e| e == "entry") {
+                let written = metadata.modified().unwrap_or(UNIX_EPOCH);
+                entries.push((written, metadata.len(), entry.path()));
+            }
+        }
+        let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        entries.sort();
+        for (_, len, path) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            remove_if_exists(&path)?;
+            total -= len;
+        }
+        Ok(())
+    }
+}
+
+fn parse_entry(contents: &[u8]) -> Option<(Header, &[u8])> {
+    let newline = contents.iter().position(|&b| b == b'\n')?;
+    let header = serde_json::from_slice(&contents[..newline]).ok()?;
+    Some((header, &contents[newline + 1..]))
+}
+
+fn remove_if_exists(path: &Path) -> Result<bool, PluginError> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub(crate) fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_cache(name: &str) -> Cache {
+        let dir =
+            std::env::temp_dir().join(format!("meta_plugin_api-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        Cache::new(dir)
+    }
+
+    #[test]
+    fn test_get_put_invalidate_and_ttl() {
+        let cache = temp_cache("cache");
+        assert_eq!(cache.get("repos").unwrap(), None);
+        cache.put("repos", b"[1,2]", None).unwrap();
+        assert_eq!(cache.get("repos").unwrap(), Some(b"[1,2]".to_vec()));
+        assert_eq!(
+            cache.get_json::<Vec<u32>>("repos").unwrap(),
+            Some(vec![1, 2])
+        );
+
+        cache.put("stale", b"x", Some(Duration::ZERO)).unwrap();
+        assert_eq!(cache.get("stale").unwrap(), None);
+        cache
+            .put_json("fresh", &"y", Some(Duration::from_secs(3600)))
+            .unwrap();
+        assert_eq!(
+            cache.get_json::<String>("fresh").unwrap().as_deref(),
+            Some("y")
+        );
+
+        assert!(cache.invalidate("repos").unwrap());
+        assert!(!cache.invalidate("repos").unwrap());
+        cache.clear().unwrap();
+        assert!(!cache.dir().exists());
+        cache.clear().unwrap();
+    }
+
+    #[test]
+    fn test_size_limit_evicts_oldest() {
+        let cache = temp_cache("cache-limit").with_max_bytes(100);
+        cache.put("old", &[0; 60], None).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        cache.put("new", &[1; 60], None).unwrap();
+        assert_eq!(cache.get("old").unwrap(), None);
+        assert_eq!(cache.get("new").unwrap(), Some(vec![1; 60]));
+        cache.clear().unwrap();
+    }
+}
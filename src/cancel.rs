@@ -0,0 +1,254 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::{Deadline, PluginError};
+
+type Callback = Box<dyn FnOnce() + Send>;
+
+enum Flag {
+    Owned(AtomicBool),
+    /// Flag owned by the host on the other side of the FFI boundary
+    External(*const AtomicBool),
+}
+
+struct Inner {
+    flag: Flag,
+    callbacks: Mutex<Vec<Callback>>,
+}
+
+// SAFETY: the external pointer is only read atomically, and `from_ffi`
+// requires the host to keep the flag alive while the token is in use.
+unsafe impl Send for Inner {}
+unsafe impl Sync for Inner {}
+
+/// Signals that the user asked to stop (e.g. pressed Ctrl-C).
+///
+/// Cheap to clone; all clones observe the same flag. Well-behaved plugins
+/// poll [`is_cancelled`](Self::is_cancelled) (or call [`check`](Self::check))
+/// between units of work and return promptly once it is set.
+#[derive(Clone)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::with_flag(Flag::Owned(AtomicBool::new(false)))
+    }
+
+    fn with_flag(flag: Flag) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                flag,
+                callbacks: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Rebuild a token from a flag passed across the FFI boundary.
+    /// Callbacks registered on the result only run if `cancel` is called
+    /// on this side; plugins must poll.
+    ///
+    /// # Safety
+    /// `flag` must be non-null and stay valid for as long as the token (or
+    /// any clone of it) is alive.
+    pub unsafe fn from_ffi(flag: *const AtomicBool) -> Self {
+        Self::with_flag(Flag::External(flag))
+    }
+
+    /// Pointer to the underlying flag for passing across the FFI boundary.
+    pub fn as_ffi(&self) -> *const AtomicBool {
+        self.flag()
+    }
+
+    fn flag(&self) -> &AtomicBool {
+        match &self.inner.flag {
+            Flag::Owned(flag) => flag,
+            Flag::External(ptr) => unsafe { &**ptr },
+        }
+    }
+
+    /// Request cancellation and run registered callbacks (once).
+    pub fn cancel(&self) {
+        if self.flag().swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let callbacks = std::mem::take(&mut *self.inner.callbacks.lock().unwrap());
+        for callback in callbacks {
+            callback();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.flag().load(Ordering::SeqCst)
+    }
+
+    /// Fail with [`PluginError::Cancelled`] if cancellation was requested.
+    pub fn check(&self) -> Result<(), PluginError> {
+        if self.is_cancelled() {
+            Err(PluginError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Run `callback` when the token is cancelled, or immediately if it
+    /// already is.
+    pub fn on_cancel(&self, callback: impl FnOnce() + Send + 'static) {
+        let mut callbacks = self.inner.callbacks.lock().unwrap();
+        if self.is_cancelled() {
+            drop(callbacks);
+            callback();
+        } else {
+            callbacks.push(Box::new(callback));
+        }
+    }
+}
+
+/// How often a [`Watchdog`] looks at the cancellation token.
+const WATCHDOG_POLL: Duration = Duration::from_millis(10);
+
+/// Runs `interrupt` once a command is cancelled or past its deadline, for
+/// work that cannot poll the token itself, such as a guest module or a
+/// child process. Stops watching when dropped.
+pub(crate) struct Watchdog {
+    done: Option<mpsc::Sender<()>>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Watchdog {
+    pub(crate) fn start(
+        cancellation: CancellationToken,
+        deadline: Option<Deadline>,
+        interrupt: impl FnOnce() + Send + 'static,
+    ) -> Self {
+        let (done, finished) = mpsc::channel::<()>();
+        let thread = thread::spawn(move || loop {
+            let wait = deadline.map_or(WATCHDOG_POLL, |d| d.remaining().min(WATCHDOG_POLL));
+            match finished.recv_timeout(wait) {
+                Err(RecvTimeoutError::Timeout) => {
+                    if cancellation.is_cancelled() || deadline.is_some_and(|d| d.is_expired()) {
+                        interrupt();
+                        return;
+                    }
+                }
+                _ => return,
+            }
+        });
+        Self {
+            done: Some(done),
+            thread: Some(thread),
+        }
+    }
+
+    /// Why the watched work should have stopped, if it should have.
+    pub(crate) fn reason(
+        cancellation: &CancellationToken,
+        deadline: Option<&Deadline>,
+    ) -> Option<PluginError> {
+        if cancellation.is_cancelled() {
+            return Some(PluginError::Cancelled);
+        }
+        deadline
+            .filter(|d| d.is_expired())
+            .map(|d| PluginError::TimedOut(d.timeout()))
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        drop(self.done.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn test_cancel_runs_callbacks_once() {
+        let token = CancellationToken::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        token.on_cancel(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        assert!(token.check().is_ok());
+
+        let clone = token.clone();
+        clone.cancel();
+        token.cancel();
+        assert!(token.is_cancelled());
+        assert!(matches!(token.check(), Err(PluginError::Cancelled)));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let counter = calls.clone();
+        token.on_cancel(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_watchdog_fires_on_cancel_or_deadline() {
+        let token = CancellationToken::new();
+        let fired = Arc::new(AtomicUsize::new(0));
+        let counter = fired.clone();
+        let watchdog = Watchdog::start(token.clone(), None, move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        token.cancel();
+        std::thread::sleep(Duration::from_millis(50));
+        drop(watchdog);
+        assert_eq!(fired.load(Ordering::SeqCst), 1);
+        assert!(matches!(
+            Watchdog::reason(&token, None),
+            Some(PluginError::Cancelled)
+        ));
+
+        let deadline = Deadline::after(Duration::from_millis(5));
+        let counter = fired.clone();
+        drop(Watchdog::start(CancellationToken::new(), None, || {}));
+        let watchdog = Watchdog::start(CancellationToken::new(), Some(deadline), move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        std::thread::sleep(Duration::from_millis(50));
+        drop(watchdog);
+        assert_eq!(fired.load(Ordering::SeqCst), 2);
+        assert!(matches!(
+            Watchdog::reason(&CancellationToken::new(), Some(&deadline)),
+            Some(PluginError::TimedOut(_))
+        ));
+    }
+
+    #[test]
+    fn test_ffi_token_observes_host_flag() {
+        let host = CancellationToken::new();
+        let plugin = unsafe { CancellationToken::from_ffi(host.as_ffi()) };
+        assert!(!plugin.is_cancelled());
+        host.cancel();
+        assert!(plugin.is_cancelled());
+    }
+}
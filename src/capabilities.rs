@@ -0,0 +1,148 @@
+use std::fmt;
+use std::ops::{BitOr, BitOrAssign};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// What a plugin needs beyond computing on its inputs, declared through
+/// [`Plugin::capabilities`](crate::Plugin::capabilities). The host shows
+/// these at install time; future sandboxing may enforce them.
+///
+/// Serializes as a list of names, e.g. `["network", "spawn_processes"]`.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Capabilities(u32);
+
+impl Capabilities {
+    /// Talks to remote hosts (git remotes, registries, APIs)
+    pub const NETWORK: Self = Self(1 << 0);
+    /// Modifies files inside the workspace
+    pub const WRITE_WORKSPACE: Self = Self(1 << 1);
+    /// Runs external programs such as `git` or `npm`
+    pub const SPAWN_PROCESSES: Self = Self(1 << 2);
+    /// Reads tokens, keys or credential helpers
+    pub const CREDENTIALS: Self = Self(1 << 3);
+
+    const NAMED: [(Self, &'static str); 4] = [
+        (Self::NETWORK, "network"),
+        (Self::WRITE_WORKSPACE, "write_workspace"),
+        (Self::SPAWN_PROCESSES, "spawn_processes"),
+        (Self::CREDENTIALS, "credentials"),
+    ];
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn all() -> Self {
+        Self(
+            Self::NETWORK.0
+                | Self::WRITE_WORKSPACE.0
+                | Self::SPAWN_PROCESSES.0
+                | Self::CREDENTIALS.0,
+        )
+    }
+
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Keep only known bits, so values from newer plugins stay valid.
+    pub const fn from_bits_truncate(bits: u32) -> Self {
+        Self(bits & Self::all().0)
+    }
+
+    /// Names of the declared capabilities, in a fixed order.
+    pub fn names(self) -> impl Iterator<Item = &'static str> {
+        Self::NAMED
+            .into_iter()
+            .filter(move |(flag, _)| self.contains(*flag))
+            .map(|(_, name)| name)
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::NAMED
+            .iter()
+            .find(|(_, n)| *n == name)
+            .map(|(flag, _)| *flag)
+    }
+}
+
+impl BitOr for Capabilities {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        self.union(other)
+    }
+}
+
+impl BitOrAssign for Capabilities {
+    fn bitor_assign(&mut self, other: Self) {
+        *self = self.union(other);
+    }
+}
+
+impl fmt::Debug for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.names()).finish()
+    }
+}
+
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.names().collect::<Vec<_>>().join(", "))
+    }
+}
+
+impl Serialize for Capabilities {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.names())
+    }
+}
+
+impl<'de> Deserialize<'de> for Capabilities {
+    /// Unknown names are skipped, like unknown [`Feature`](crate::Feature)s.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let names = Vec::<String>::deserialize(deserializer)?;
+        Ok(names
+            .iter()
+            .filter_map(|name| Self::from_name(name))
+            .fold(Self::empty(), Self::union))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capabilities_set_operations() {
+        let caps = Capabilities::NETWORK | Capabilities::SPAWN_PROCESSES;
+        assert!(caps.contains(Capabilities::NETWORK));
+        assert!(!caps.contains(Capabilities::NETWORK | Capabilities::CREDENTIALS));
+        assert!(Capabilities::all().contains(caps));
+        assert!(Capabilities::default().is_empty());
+        assert_eq!(caps.to_string(), "network, spawn_processes");
+        assert_eq!(Capabilities::from_bits_truncate(0xff), Capabilities::all());
+    }
+
+    #[test]
+    fn test_capabilities_serde() {
+        let caps = Capabilities::WRITE_WORKSPACE | Capabilities::CREDENTIALS;
+        let json = serde_json::to_string(&caps).unwrap();
+        assert_eq!(json, r#"["write_workspace","credentials"]"#);
+        let parsed: Capabilities =
+            serde_json::from_str(r#"["credentials","gpu","write_workspace"]"#).unwrap();
+        assert_eq!(parsed, caps);
+    }
+}
@@ -0,0 +1,264 @@
+use std::collections::HashSet;
+use std::io::Write as _;
+use std::sync::{Mutex, OnceLock};
+
+use clap::error::ErrorKind;
+
+use crate::{ArgSpec, CommandSpec, Plugin, PluginContext, PluginError};
+
+/// Commands defined with clap, parsed before the plugin sees them.
+///
+/// Implement this alongside [`Plugin`] and forward `Plugin::execute` to
+/// [`execute_clap`] and `Plugin::command_specs` to [`clap_command_specs`],
+/// so flags are validated, and usage errors worded, the same way as the
+/// host's own:
+///
+/// ```ignore
+/// impl Plugin for MyPlugin {
+///     // name(), commands() ...
+///     fn command_specs(&self) -> Vec<CommandSpec> {
+///         meta_plugin_api::clap_command_specs(self)
+///     }
+///     fn execute(&self, command: &str, args: &[String], ctx: &PluginContext) -> anyhow::Result<()> {
+///         meta_plugin_api::execute_clap(self, command, args, ctx)
+///     }
+/// }
+///
+/// impl ClapPlugin for MyPlugin {
+///     fn clap_commands(&self) -> Vec<clap::Command> {
+///         vec![clap::Command::new("release").arg(clap::arg!(--tag <TAG>))]
+///     }
+///     fn execute_parsed(&self, command: &str, matches: &clap::ArgMatches, ctx: &PluginContext) -> anyhow::Result<()> {
+///         let tag = matches.get_one::<String>("tag");
+///         Ok(())
+///     }
+/// }
+/// ```
+pub trait ClapPlugin: Plugin {
+    /// One clap command per top-level command; subcommands nest as usual.
+    fn clap_commands(&self) -> Vec<clap::Command>;
+
+    /// Run `command` (the top-level name) with its parsed arguments.
+    fn execute_parsed(
+        &self,
+        command: &str,
+        matches: &clap::ArgMatches,
+        ctx: &PluginContext,
+    ) -> anyhow::Result<()>;
+}
+
+/// [`CommandSpec`]s describing [`ClapPlugin::clap_commands`].
+pub fn clap_command_specs<P: ClapPlugin + ?Sized>(plugin: &P) -> Vec<CommandSpec> {
+    plugin
+        .clap_commands()
+        .iter()
+        .map(CommandSpec::from_clap)
+        .collect()
+}
+
+/// Parse `args` with the clap definition of `command` and pass the matches
+/// to [`ClapPlugin::execute_parsed`].
+///
+/// `command` may be a [`CommandTree`](crate::CommandTree) path such as
+/// `"release prepare"`; the words after the first are parsed as clap
+/// subcommands. `--help` and `--version` are written to the context's
+/// stdout. Any other parse error is returned as
+/// [`PluginError::InvalidArguments`] carrying clap's message.
+pub fn execute_clap<P: ClapPlugin + ?Sized>(
+    plugin: &P,
+    command: &str,
+    args: &[String],
+    ctx: &PluginContext,
+) -> anyhow::Result<()> {
+    let mut words = command.split_whitespace();
+    let name = words.next().unwrap_or_default();
+    let clap_command = plugin
+        .clap_commands()
+        .into_iter()
+        .find(|c| c.get_name() == name)
+        .ok_or_else(|| PluginError::CommandNotFound(command.to_string()))?
+        .bin_name(format!("meta {}", name));
+    let argv = std::iter::once(name)
+        .chain(words)
+        .chain(args.iter().map(String::as_str));
+    match clap_command.try_get_matches_from(argv) {
+        Ok(matches) => plugin.execute_parsed(name, &matches, ctx),
+        Err(err)
+            if matches!(
+                err.kind(),
+                ErrorKind::DisplayHelp | ErrorKind::DisplayVersion
+            ) =>
+        {
+            write!(ctx.stdout(), "{}", err.render())?;
+            Ok(())
+        }
+        Err(err) => {
+            let rendered = err.render().to_string();
+            Err(PluginError::InvalidArguments {
+                command: command.to_string(),
+                reason: rendered.trim().trim_start_matches("error: ").to_string(),
+            }
+            .into())
+        }
+    }
+}
+
+impl CommandSpec {
+    /// Describe a clap command, including its visible arguments, aliases
+    /// and subcommands. Strings are interned, so building specs on every
+    /// call does not grow memory without bound.
+    pub fn from_clap(command: &clap::Command) -> Self {
+        let mut spec = CommandSpec::new(intern(command.get_name())).hidden(command.is_hide_set());
+        if let Some(about) = command.get_about() {
+            spec = spec.about(intern(&about.to_string()));
+        }
+        for alias in command.get_visible_aliases() {
+            spec = spec.alias(intern(alias));
+        }
+        for arg in command.get_arguments().filter(|a| !a.is_hide_set()) {
+            spec = spec.arg(ArgSpec::from_clap(arg));
+        }
+        for sub in command.get_subcommands() {
+            spec = spec.subcommand(CommandSpec::from_clap(sub));
+        }
+        spec
+    }
+}
+
+impl ArgSpec {
+    /// Describe a clap argument; options are named by their long flag.
+    pub fn from_clap(arg: &clap::Arg) -> Self {
+        let long = intern(arg.get_long().unwrap_or(arg.get_id().as_str()));
+        let mut spec = if arg.is_positional() {
+            ArgSpec::positional(intern(arg.get_id().as_str()))
+        } else if arg.get_action().takes_values() {
+            ArgSpec::option(long)
+        } else {
+            ArgSpec::flag(long)
+        };
+        spec.short = arg.get_short();
+        spec.required = arg.is_required_set();
+        if let Some(help) = arg.get_help() {
+            spec.help = intern(&help.to_string());
+        }
+        spec
+    }
+}
+
+/// `&'static` copy of `s`, leaked at most once per distinct string.
+fn intern(s: &str) -> &'static str {
+    static INTERNED: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+    let mut interned = INTERNED.get_or_init(Default::default).lock().unwrap();
+    match interned.get(s) {
+        Some(existing) => existing,
+        None => {
+            let leaked: &'static str = Box::leak(s.to_string().into_boxed_str());
+            interned.insert(leaked);
+            leaked
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use clap::{arg, Command};
+
+    use super::*;
+    use crate::CapturedOutput;
+
+    struct Release;
+
+    impl Plugin for Release {
+        fn name(&self) -> &'static str {
+            "release"
+        }
+        fn commands(&self) -> Vec<&'static str> {
+            vec!["release"]
+        }
+        fn command_specs(&self) -> Vec<CommandSpec> {
+            clap_command_specs(self)
+        }
+        fn execute(
+            &self,
+            command: &str,
+            args: &[String],
+            ctx: &PluginContext,
+        ) -> anyhow::Result<()> {
+            execute_clap(self, command, args, ctx)
+        }
+    }
+
+    impl ClapPlugin for Release {
+        fn clap_commands(&self) -> Vec<Command> {
+            vec![Command::new("release")
+                .about("Cut a release")
+                .visible_alias("rel")
+                .subcommand(
+                    Command::new("prepare")
+                        .arg(arg!(<version> "Version to release"))
+                        .arg(arg!(-n --"dry-run" "Only print the plan")),
+                )]
+        }
+        fn execute_parsed(
+            &self,
+            command: &str,
+            matches: &clap::ArgMatches,
+            ctx: &PluginContext,
+        ) -> anyhow::Result<()> {
+            let (sub, matches) = matches.subcommand().unwrap();
+            writeln!(
+                ctx.stdout(),
+                "{} {} {} {}",
+                command,
+                sub,
+                matches.get_one::<String>("version").unwrap(),
+                matches.get_flag("dry-run")
+            )?;
+            Ok(())
+        }
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_specs_from_clap() {
+        let specs = Release.command_specs();
+        assert_eq!(specs[0].name, "release");
+        assert_eq!(specs[0].about, "Cut a release");
+        assert_eq!(specs[0].aliases, vec!["rel"]);
+        let prepare = specs[0].find_subcommand("prepare").unwrap();
+        assert_eq!(prepare.usage_line(), "prepare <version> [--dry-run]");
+        assert_eq!(prepare.args[1].short, Some('n'));
+    }
+
+    #[test]
+    fn test_execute_parses_args() {
+        let captured = Arc::new(CapturedOutput::new());
+        let ctx = PluginContext::new("/ws", "/ws", "1.0.0").with_output(captured.clone());
+        Release
+            .execute("release prepare", &args(&["1.2.0", "-n"]), &ctx)
+            .unwrap();
+        assert_eq!(captured.stdout(), "release prepare 1.2.0 true\n");
+
+        let err = Release
+            .execute("release", &args(&["prepare", "--bogus"]), &ctx)
+            .unwrap_err();
+        match PluginError::find(&err) {
+            Some(PluginError::InvalidArguments { command, reason }) => {
+                assert_eq!(command, "release");
+                assert!(reason.starts_with("unexpected argument '--bogus'"));
+                assert!(reason.contains("Usage: meta release prepare"));
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+
+        Release
+            .execute("release", &args(&["--help"]), &ctx)
+            .unwrap();
+        assert!(captured.stdout().contains("Cut a release"));
+    }
+}
@@ -0,0 +1,470 @@
+use std::time::Duration;
+
+use crate::PluginError;
+
+/// How an argument appears on the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgKind {
+    /// Bare value identified by position, e.g. `<repo>`
+    Positional,
+    /// Boolean switch, e.g. `--force`
+    Flag,
+    /// Named option taking a value, e.g. `--branch <value>`
+    Option,
+}
+
+/// Description of a single command argument.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ArgSpec {
+    pub name: &'static str,
+    pub help: &'static str,
+    pub kind: ArgKind,
+    pub short: Option<char>,
+    pub required: bool,
+}
+
+impl ArgSpec {
+    fn new(name: &'static str, kind: ArgKind) -> Self {
+        Self {
+            name,
+            help: "",
+            kind,
+            short: None,
+            required: false,
+        }
+    }
+
+    pub fn positional(name: &'static str) -> Self {
+        Self::new(name, ArgKind::Positional)
+    }
+
+    pub fn flag(name: &'static str) -> Self {
+        Self::new(name, ArgKind::Flag)
+    }
+
+    pub fn option(name: &'static str) -> Self {
+        Self::new(name, ArgKind::Option)
+    }
+
+    pub fn help(mut self, help: &'static str) -> Self {
+        self.help = help;
+        self
+    }
+
+    pub fn short(mut self, short: char) -> Self {
+        self.short = Some(short);
+        self
+    }
+
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    /// Usage fragment for this argument, e.g. `<repo>` or `[--force]`.
+    pub fn usage(&self) -> String {
+        let inner = match self.kind {
+            ArgKind::Positional => format!("<{}>", self.name),
+            ArgKind::Flag => format!("--{}", self.name),
+            ArgKind::Option => format!("--{} <value>", self.name),
+        };
+        if self.required {
+            inner
+        } else {
+            format!("[{}]", inner)
+        }
+    }
+}
+
+/// Marks a command as deprecated. The host warns when it is invoked and
+/// strikes it through in help; see [`CommandSpec::deprecation_warning`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Deprecation {
+    /// Plugin version that deprecated the command
+    pub since: &'static str,
+    pub note: Option<&'static str>,
+    /// Command to use instead, e.g. `"git status"`
+    pub replacement: Option<&'static str>,
+}
+
+impl Deprecation {
+    pub fn new(since: &'static str) -> Self {
+        Self {
+            since,
+            note: None,
+            replacement: None,
+        }
+    }
+
+    pub fn note(mut self, note: &'static str) -> Self {
+        self.note = Some(note);
+        self
+    }
+
+    pub fn replacement(mut self, replacement: &'static str) -> Self {
+        self.replacement = Some(replacement);
+        self
+    }
+}
+
+/// Environment variable that opts into experimental commands, like
+/// `meta --experimental`.
+pub const EXPERIMENTAL_ENV: &str = "META_EXPERIMENTAL";
+
+/// Structured description of a plugin command, used by the host to render
+/// help and validate arguments consistently across plugins.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CommandSpec {
+    pub name: &'static str,
+    /// One-line summary shown in command lists
+    pub about: &'static str,
+    /// Usage string; when empty the host derives one from `args`
+    pub usage: &'static str,
+    pub args: Vec<ArgSpec>,
+    pub aliases: Vec<&'static str>,
+    /// Nested commands, e.g. `prepare` under `release`; see
+    /// [`CommandTree`](crate::CommandTree)
+    pub subcommands: Vec<CommandSpec>,
+    /// Omit the command from help listings
+    pub hidden: bool,
+    /// Omitted from default help, and only runs when the user opts in; see
+    /// [`check_enabled`](Self::check_enabled)
+    pub experimental: bool,
+    pub deprecated: Option<Deprecation>,
+    /// Default time limit; see [`Deadline::effective`](crate::Deadline::effective)
+    pub timeout: Option<Duration>,
+}
+
+impl CommandSpec {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            about: "",
+            usage: "",
+            args: Vec::new(),
+            aliases: Vec::new(),
+            subcommands: Vec::new(),
+            hidden: false,
+            experimental: false,
+            deprecated: None,
+            timeout: None,
+        }
+    }
+
+    pub fn about(mut self, about: &'static str) -> Self {
+        self.about = about;
+        self
+    }
+
+    pub fn usage(mut self, usage: &'static str) -> Self {
+        self.usage = usage;
+        self
+    }
+
+    pub fn arg(mut self, arg: ArgSpec) -> Self {
+        self.args.push(arg);
+        self
+    }
+
+    pub fn alias(mut self, alias: &'static str) -> Self {
+        self.aliases.push(alias);
+        self
+    }
+
+    pub fn subcommand(mut self, subcommand: CommandSpec) -> Self {
+        self.subcommands.push(subcommand);
+        self
+    }
+
+    /// The direct subcommand named by `token`, following aliases.
+    pub fn find_subcommand(&self, token: &str) -> Option<&CommandSpec> {
+        self.subcommands.iter().find(|spec| spec.matches(token))
+    }
+
+    pub fn hidden(mut self, hidden: bool) -> Self {
+        self.hidden = hidden;
+        self
+    }
+
+    pub fn experimental(mut self, experimental: bool) -> Self {
+        self.experimental = experimental;
+        self
+    }
+
+    pub fn deprecated(mut self, deprecation: Deprecation) -> Self {
+        self.deprecated = Some(deprecation);
+        self
+    }
+
+    /// The warning the host prints to stderr before running a deprecated
+    /// command, e.g. `warning: 'st' is deprecated since 2.0; use 'status'
+    /// instead`. `None` when the command is not deprecated.
+    pub fn deprecation_warning(&self) -> Option<String> {
+        let deprecation = self.deprecated.as_ref()?;
+        let mut warning = format!(
+            "warning: '{}' is deprecated since {}",
+            self.name, deprecation.since
+        );
+        if let Some(replacement) = deprecation.replacement {
+            warning.push_str(&format!("; use '{}' instead", replacement));
+        }
+        if let Some(note) = deprecation.note {
+            warning.push_str(&format!(" ({})", note));
+        }
+        Some(warning)
+    }
+
+    /// Whether default help lists the command.
+    pub fn is_listed(&self) -> bool {
+        !self.hidden && !self.experimental
+    }
+
+    /// Refuse to run an experimental command unless the user opted in with
+    /// `--experimental` or [`EXPERIMENTAL_ENV`].
+    pub fn check_enabled(&self, experimental_enabled: bool) -> Result<(), PluginError> {
+        if self.experimental && !experimental_enabled {
+            Err(PluginError::Experimental(self.name.to_string()))
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Whether `token` names this command or one of its aliases.
+    pub fn matches(&self, token: &str) -> bool {
+        self.name == token || self.aliases.contains(&token)
+    }
+
+    /// Check that `args` supply every required argument, so the host can
+    /// report a missing one with usage before the command runs. Only
+    /// presence is checked; values and unknown flags are left to the
+    /// command.
+    pub fn validate(&self, args: &[String]) -> Result<(), PluginError> {
+        let mut positionals = 0;
+        let mut present = Vec::new();
+        let mut tokens = args.iter();
+        while let Some(token) = tokens.next() {
+            if token == "--" {
+                positionals += tokens.len();
+                break;
+            }
+            let arg = if let Some(long) = token.strip_prefix("--") {
+                let name = long.split('=').next().unwrap_or_default();
+                self.args
+                    .iter()
+                    .find(|a| a.kind != ArgKind::Positional && a.name == name)
+            } else if let Some(short) = token.strip_prefix('-').filter(|s| !s.is_empty()) {
+                self.args
+                    .iter()
+                    .find(|a| short.starts_with(a.short.unwrap_or('\0')))
+            } else {
+                positionals += 1;
+                continue;
+            };
+            if let Some(arg) = arg {
+                present.push(arg.name);
+                let inline_value =
+                    token.contains('=') || (!token.starts_with("--") && token.len() > 2);
+                if arg.kind == ArgKind::Option && !inline_value {
+                    tokens.next();
+                }
+            }
+        }
+
+        let missing = self
+            .args
+            .iter()
+            .filter(|a| a.kind == ArgKind::Positional)
+            .enumerate()
+            .find(|(index, a)| a.required && *index >= positionals)
+            .map(|(_, a)| a)
+            .or_else(|| {
+                self.args.iter().find(|a| {
+                    a.kind != ArgKind::Positional && a.required && !present.contains(&a.name)
+                })
+            });
+        match missing {
+            Some(arg) => Err(PluginError::MissingArgument {
+                command: self.name.to_string(),
+                argument: arg.usage(),
+                usage: self.usage_line(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// The explicit usage string, or one derived from the argument list.
+    pub fn usage_line(&self) -> String {
+        if !self.usage.is_empty() {
+            return self.usage.to_string();
+        }
+        std::iter::once(self.name.to_string())
+            .chain(self.args.iter().map(ArgSpec::usage))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Exit status of a plugin command, as propagated to the shell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandOutcome {
+    pub exit_code: i32,
+    /// Error or summary message for the host to print, if any
+    pub message: Option<String>,
+}
+
+impl CommandOutcome {
+    pub fn success() -> Self {
+        Self {
+            exit_code: 0,
+            message: None,
+        }
+    }
+
+    pub fn failure(exit_code: i32, message: impl Into<String>) -> Self {
+        Self {
+            exit_code,
+            message: Some(message.into()),
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.exit_code == 0
+    }
+
+    /// Map the result of `Plugin::execute` to an outcome. `Ok` is exit code
+    /// 0; a [`PluginError::ExitCode`] anywhere in the error chain sets the
+    /// code without a message; any other error is exit code 1.
+    pub fn from_result(result: &anyhow::Result<()>) -> Self {
+        let err = match result {
+            Ok(()) => return Self::success(),
+            Err(err) => err,
+        };
+        let exit_code = err
+            .chain()
+            .find_map(|cause| match cause.downcast_ref::<PluginError>() {
+                Some(PluginError::ExitCode(code)) => Some(*code),
+                _ => None,
+            });
+        match exit_code {
+            Some(exit_code) => Self {
+                exit_code,
+                message: None,
+            },
+            None => Self::failure(1, format!("{:#}", err)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage_line_derived_from_args() {
+        let spec = CommandSpec::new("clone")
+            .about("Clone all repositories")
+            .arg(ArgSpec::positional("url").required())
+            .arg(ArgSpec::positional("dir"))
+            .arg(ArgSpec::flag("force").short('f'))
+            .arg(ArgSpec::option("branch").required());
+        assert_eq!(
+            spec.usage_line(),
+            "clone <url> [<dir>] [--force] --branch <value>"
+        );
+        assert_eq!(spec.usage("clone URL").usage_line(), "clone URL");
+    }
+
+    #[test]
+    fn test_validate_reports_missing_argument() {
+        let spec = CommandSpec::new("clone")
+            .arg(ArgSpec::positional("url").required())
+            .arg(ArgSpec::option("branch").short('b').required())
+            .arg(ArgSpec::flag("force"));
+        let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+
+        assert!(spec
+            .validate(&args(&["git@x:y", "--branch", "main"]))
+            .is_ok());
+        assert!(spec
+            .validate(&args(&["-bmain", "--force", "git@x:y"]))
+            .is_ok());
+        assert!(spec
+            .validate(&args(&["--branch=main", "--", "--weird-url"]))
+            .is_ok());
+
+        let err = spec.validate(&args(&["--branch", "main"])).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Missing required argument <url> for 'clone'\n\n\
+             Usage: meta clone <url> --branch <value> [--force]"
+        );
+        let err = spec.validate(&args(&["git@x:y", "--force"])).unwrap_err();
+        assert!(matches!(
+            err,
+            PluginError::MissingArgument { ref argument, .. } if argument == "--branch <value>"
+        ));
+    }
+
+    #[test]
+    fn test_deprecation_warning() {
+        assert_eq!(CommandSpec::new("status").deprecation_warning(), None);
+        let spec = CommandSpec::new("update").deprecated(
+            Deprecation::new("2.0")
+                .replacement("pull")
+                .note("removed in 3.0"),
+        );
+        assert_eq!(
+            spec.deprecation_warning().unwrap(),
+            "warning: 'update' is deprecated since 2.0; use 'pull' instead (removed in 3.0)"
+        );
+        let spec = CommandSpec::new("legacy").deprecated(Deprecation::new("1.1"));
+        assert_eq!(
+            spec.deprecation_warning().unwrap(),
+            "warning: 'legacy' is deprecated since 1.1"
+        );
+    }
+
+    #[test]
+    fn test_experimental_requires_opt_in() {
+        let spec = CommandSpec::new("rewrite-history").experimental(true);
+        assert!(!spec.is_listed());
+        assert!(!CommandSpec::new("debug").hidden(true).is_listed());
+        assert!(CommandSpec::new("status").is_listed());
+
+        assert!(spec.check_enabled(true).is_ok());
+        let err = spec.check_enabled(false).unwrap_err();
+        assert!(matches!(err, PluginError::Experimental(ref c) if c == "rewrite-history"));
+        assert!(CommandSpec::new("status").check_enabled(false).is_ok());
+    }
+
+    #[test]
+    fn test_outcome_from_result() {
+        assert_eq!(
+            CommandOutcome::from_result(&Ok(())),
+            CommandOutcome::success()
+        );
+
+        let err: anyhow::Result<()> = Err(PluginError::ExitCode(3).into());
+        let outcome = CommandOutcome::from_result(&err);
+        assert_eq!(outcome.exit_code, 3);
+        assert_eq!(outcome.message, None);
+
+        let err: anyhow::Result<()> =
+            Err(anyhow::Error::from(PluginError::ExitCode(4)).context("tests failed"));
+        assert_eq!(CommandOutcome::from_result(&err).exit_code, 4);
+
+        let err: anyhow::Result<()> = Err(anyhow::anyhow!("boom"));
+        assert_eq!(
+            CommandOutcome::from_result(&err),
+            CommandOutcome::failure(1, "boom")
+        );
+    }
+}
@@ -0,0 +1,181 @@
+use std::fmt::Write as _;
+
+use crate::CommandSpec;
+
+/// A plugin's commands arranged by their
+/// [`subcommands`](CommandSpec::subcommands), so the host can route
+/// `meta release prepare --dry-run` to the `release prepare` command
+/// instead of every plugin parsing its own subcommands out of `args`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommandTree {
+    pub commands: Vec<CommandSpec>,
+}
+
+/// Result of [`CommandTree::route`]: the command a command line names and
+/// the arguments left over for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandRoute<'t, 'a> {
+    /// Canonical names from the top-level command down, aliases resolved
+    pub path: Vec<&'static str>,
+    pub spec: &'t CommandSpec,
+    pub args: &'a [String],
+}
+
+impl CommandRoute<'_, '_> {
+    /// The space-separated path, e.g. `"release prepare"`, which the host
+    /// passes to [`Plugin::execute`](crate::Plugin::execute) as `command`.
+    pub fn command(&self) -> String {
+        self.path.join(" ")
+    }
+}
+
+impl CommandTree {
+    pub fn new(commands: Vec<CommandSpec>) -> Self {
+        Self { commands }
+    }
+
+    /// The command at `path`, following aliases at every level.
+    pub fn find<S: AsRef<str>>(&self, path: &[S]) -> Option<&CommandSpec> {
+        let (first, rest) = path.split_first()?;
+        let top = self
+            .commands
+            .iter()
+            .find(|spec| spec.matches(first.as_ref()))?;
+        rest.iter()
+            .try_fold(top, |spec, token| spec.find_subcommand(token.as_ref()))
+    }
+
+    /// Match the leading words of `args` against the tree, descending as
+    /// long as the next word names a subcommand. Returns `None` when the
+    /// first word is not a command.
+    pub fn route<'t, 'a>(&'t self, args: &'a [String]) -> Option<CommandRoute<'t, 'a>> {
+        let (first, _) = args.split_first()?;
+        let mut spec = self.commands.iter().find(|spec| spec.matches(first))?;
+        let mut path = vec![spec.name];
+        let mut consumed = 1;
+        while let Some(sub) = args.get(consumed).and_then(|t| spec.find_subcommand(t)) {
+            spec = sub;
+            path.push(sub.name);
+            consumed += 1;
+        }
+        Some(CommandRoute {
+            path,
+            spec,
+            args: &args[consumed..],
+        })
+    }
+
+    /// Completion candidates for the word after `words`: the listed
+    /// commands under that path whose name starts with `prefix`.
+    pub fn complete<S: AsRef<str>>(&self, words: &[S], prefix: &str) -> Vec<&'static str> {
+        let children = if words.is_empty() {
+            &self.commands
+        } else {
+            match self.find(words) {
+                Some(spec) => &spec.subcommands,
+                None => return Vec::new(),
+            }
+        };
+        children
+            .iter()
+            .filter(|spec| spec.is_listed() && spec.name.starts_with(prefix))
+            .map(|spec| spec.name)
+            .collect()
+    }
+
+    /// Indented listing of the listed commands and their subcommands, one
+    /// `name  about` line each, for `meta <plugin> --help`. Deprecated
+    /// commands are marked `(deprecated)`.
+    pub fn render(&self) -> String {
+        self.render_with(false)
+    }
+
+    /// Like [`render`](Self::render); with `color`, deprecated command
+    /// names are struck through instead of marked.
+    pub fn render_with(&self, color: bool) -> String {
+        let mut out = String::new();
+        render_level(&mut out, &self.commands, 1, color);
+        out
+    }
+}
+
+impl From<Vec<CommandSpec>> for CommandTree {
+    fn from(commands: Vec<CommandSpec>) -> Self {
+        Self::new(commands)
+    }
+}
+
+fn render_level(out: &mut String, specs: &[CommandSpec], depth: usize, color: bool) {
+    for spec in specs.iter().filter(|spec| spec.is_listed()) {
+        let indent = "  ".repeat(depth);
+        let name = match (&spec.deprecated, color) {
+            (None, _) => spec.name.to_string(),
+            (Some(_), true) => format!("\x1b[9m{}\x1b[29m", spec.name),
+            (Some(_), false) => format!("{} (deprecated)", spec.name),
+        };
+        if spec.about.is_empty() {
+            let _ = writeln!(out, "{}{}", indent, name);
+        } else {
+            let _ = writeln!(out, "{}{}  {}", indent, name, spec.about);
+        }
+        render_level(out, &spec.subcommands, depth + 1, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree() -> CommandTree {
+        CommandTree::new(vec![
+            CommandSpec::new("release")
+                .about("Manage releases")
+                .subcommand(CommandSpec::new("prepare").about("Bump versions"))
+                .subcommand(CommandSpec::new("publish").alias("pub"))
+                .subcommand(CommandSpec::new("rollback").hidden(true)),
+            CommandSpec::new("status").alias("st"),
+            CommandSpec::new("update").deprecated(crate::Deprecation::new("2.0")),
+        ])
+    }
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn test_route_descends_into_subcommands() {
+        let tree = tree();
+        let args = words(&["release", "pub", "--tag", "v1"]);
+        let route = tree.route(&args).unwrap();
+        assert_eq!(route.command(), "release publish");
+        assert_eq!(route.spec.name, "publish");
+        assert_eq!(route.args, &args[2..]);
+
+        let args = words(&["st", "prepare"]);
+        let route = tree.route(&args).unwrap();
+        assert_eq!(route.path, vec!["status"]);
+        assert_eq!(route.args, &args[1..]);
+
+        assert!(tree.route(&words(&["deploy"])).is_none());
+        assert!(tree.route(&[]).is_none());
+    }
+
+    #[test]
+    fn test_complete_and_render_skip_hidden() {
+        let tree = tree();
+        assert_eq!(tree.complete(&["release"], "p"), vec!["prepare", "publish"]);
+        assert_eq!(tree.complete(&["release"], "r"), Vec::<&str>::new());
+        assert_eq!(
+            tree.complete::<&str>(&[], ""),
+            vec!["release", "status", "update"]
+        );
+        assert!(tree.complete(&["nope"], "").is_empty());
+        assert_eq!(
+            tree.render(),
+            "  release  Manage releases\n    prepare  Bump versions\n    publish\n  status\n  update (deprecated)\n"
+        );
+        assert!(tree
+            .render_with(true)
+            .ends_with("  \x1b[9mupdate\x1b[29m\n"));
+    }
+}
@@ -0,0 +1,188 @@
+use std::fmt;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Env;
+
+/// Shells that `meta completions` can generate scripts for, and that
+/// [`PluginContext::shell`](crate::PluginContext::shell) reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    PowerShell,
+}
+
+impl Shell {
+    pub const ALL: [Shell; 4] = [Shell::Bash, Shell::Zsh, Shell::Fish, Shell::PowerShell];
+
+    /// Canonical lowercase name, as accepted on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Shell::Bash => "bash",
+            Shell::Zsh => "zsh",
+            Shell::Fish => "fish",
+            Shell::PowerShell => "powershell",
+        }
+    }
+
+    /// Parse a shell name (case-insensitive; `pwsh` is accepted for PowerShell).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "bash" => Some(Shell::Bash),
+            "zsh" => Some(Shell::Zsh),
+            "fish" => Some(Shell::Fish),
+            "powershell" | "pwsh" => Some(Shell::PowerShell),
+            _ => None,
+        }
+    }
+
+    /// The shell named by a program path such as `/usr/bin/zsh` or
+    /// `pwsh.exe`.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        Self::from_name(path.as_ref().file_stem()?.to_str()?)
+    }
+
+    /// The user's shell according to `$SHELL`. Hosts that know the
+    /// invoking shell better, e.g. from the parent process, pass it with
+    /// [`PluginContext::with_shell`](crate::PluginContext::with_shell).
+    pub fn from_env(env: &Env) -> Option<Self> {
+        env.get("SHELL").and_then(Self::from_path)
+    }
+
+    /// A line setting the environment variable `name` to `value`, e.g.
+    /// `export NAME='value'`.
+    pub fn export_var(self, name: &str, value: &str) -> String {
+        match self {
+            Shell::Bash | Shell::Zsh => format!("export {}={}", name, self.quote(value)),
+            Shell::Fish => format!("set -gx {} {}", name, self.quote(value)),
+            Shell::PowerShell => format!("$env:{} = {}", name, self.quote(value)),
+        }
+    }
+
+    /// A line defining `name` as a shorthand for `command`.
+    pub fn alias(self, name: &str, command: &str) -> String {
+        match self {
+            Shell::Bash | Shell::Zsh => format!("alias {}={}", name, self.quote(command)),
+            Shell::Fish => format!("alias {} {}", name, self.quote(command)),
+            Shell::PowerShell => format!("function {} {{ {} @args }}", name, command),
+        }
+    }
+
+    /// `value` as a single-quoted literal.
+    pub fn quote(self, value: &str) -> String {
+        match self {
+            Shell::Bash | Shell::Zsh => format!("'{}'", value.replace('\'', "'\\''")),
+            Shell::Fish => format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'")),
+            Shell::PowerShell => format!("'{}'", value.replace('\'', "''")),
+        }
+    }
+}
+
+impl fmt::Display for Shell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A candidate value offered at tab time by
+/// [`Plugin::complete`](crate::Plugin::complete).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionItem {
+    pub value: String,
+    /// Shown next to the value by shells that support it (zsh, fish)
+    pub description: Option<String>,
+}
+
+impl CompletionItem {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self {
+            value: value.into(),
+            description: None,
+        }
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// One line in the format `shell`'s completion function expects:
+    /// `value:description` for zsh, `value<TAB>description` for fish, and
+    /// the bare value otherwise.
+    pub fn render(&self, shell: Shell) -> String {
+        match (&self.description, shell) {
+            (Some(description), Shell::Zsh) => {
+                format!("{}:{}", self.value.replace(':', "\\:"), description)
+            }
+            (Some(description), Shell::Fish) => format!("{}\t{}", self.value, description),
+            _ => self.value.clone(),
+        }
+    }
+}
+
+impl From<&str> for CompletionItem {
+    fn from(value: &str) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<String> for CompletionItem {
+    fn from(value: String) -> Self {
+        Self::new(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_names_round_trip() {
+        for shell in Shell::ALL {
+            assert_eq!(Shell::from_name(shell.name()), Some(shell));
+        }
+        assert_eq!(Shell::from_name("PWSH"), Some(Shell::PowerShell));
+        assert_eq!(Shell::from_name("tcsh"), None);
+        assert_eq!(Shell::from_path("/usr/bin/zsh"), Some(Shell::Zsh));
+        assert_eq!(Shell::from_path("pwsh.exe"), Some(Shell::PowerShell));
+        assert_eq!(
+            Shell::from_env(&Env::new().with_var("SHELL", "/opt/homebrew/bin/fish")),
+            Some(Shell::Fish)
+        );
+        assert_eq!(Shell::from_env(&Env::new()), None);
+    }
+
+    #[test]
+    fn test_shell_snippets() {
+        assert_eq!(
+            Shell::Bash.export_var("META_ROOT", "it's"),
+            "export META_ROOT='it'\\''s'"
+        );
+        assert_eq!(
+            Shell::Fish.export_var("META_ROOT", "it's"),
+            "set -gx META_ROOT 'it\\'s'"
+        );
+        assert_eq!(
+            Shell::PowerShell.export_var("META_ROOT", "it's"),
+            "$env:META_ROOT = 'it''s'"
+        );
+        assert_eq!(Shell::Zsh.alias("mg", "meta git"), "alias mg='meta git'");
+        assert_eq!(
+            Shell::PowerShell.alias("mg", "meta git"),
+            "function mg { meta git @args }"
+        );
+    }
+
+    #[test]
+    fn test_completion_item_render() {
+        let item = CompletionItem::new("feature:x").description("Feature branch");
+        assert_eq!(item.render(Shell::Zsh), "feature\\:x:Feature branch");
+        assert_eq!(item.render(Shell::Fish), "feature:x\tFeature branch");
+        assert_eq!(item.render(Shell::Bash), "feature:x");
+        assert_eq!(CompletionItem::from("main").render(Shell::Zsh), "main");
+    }
+}
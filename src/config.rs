@@ -0,0 +1,274 @@
+use std::fmt;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::PluginError;
+
+/// Where a configuration value comes from, lowest precedence first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigLayer {
+    /// The user's global config, e.g. `~/.metarc`
+    Global,
+    /// The workspace's `.meta`
+    Workspace,
+    /// Overrides for the repo the command runs in
+    Repo,
+    /// Environment variables
+    Env,
+}
+
+impl fmt::Display for ConfigLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ConfigLayer::Global => "global",
+            ConfigLayer::Workspace => "workspace",
+            ConfigLayer::Repo => "repo",
+            ConfigLayer::Env => "env",
+        })
+    }
+}
+
+/// An effective value from [`PluginConfig::resolve`] and the layer that
+/// set it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedValue {
+    pub value: Value,
+    pub layer: ConfigLayer,
+}
+
+/// One configuration document the host read, e.g. the parsed `~/.metarc`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct LayerDocument {
+    layer: ConfigLayer,
+    document: Value,
+}
+
+/// A plugin's own section of the `.meta` file, keyed by
+/// [`Plugin::config_namespace`](crate::Plugin::config_namespace).
+///
+/// Hosts that layer configuration the way built-in commands see it add
+/// each document with [`with_layer`](Self::with_layer); the section is
+/// then the merge of the plugin's section in every layer.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PluginConfig {
+    namespace: String,
+    section: Value,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    layers: Vec<LayerDocument>,
+}
+
+impl PluginConfig {
+    pub fn new(namespace: impl Into<String>, section: Value) -> Self {
+        Self {
+            namespace: namespace.into(),
+            section,
+            layers: Vec::new(),
+        }
+    }
+
+    /// Extract the section named `namespace` from a parsed `.meta` document.
+    /// Missing sections yield an empty config.
+    pub fn from_meta(namespace: impl Into<String>, meta: &Value) -> Self {
+        let namespace = namespace.into();
+        let section = meta.get(&namespace).cloned().unwrap_or(Value::Null);
+        Self {
+            namespace,
+            section,
+            layers: Vec::new(),
+        }
+    }
+
+    /// Add a whole configuration document, such as the parsed `~/.metarc`,
+    /// as `layer`, replacing an earlier document for the same layer. The
+    /// section becomes the plugin's section of every layer merged,
+    /// objects key by key, higher layers winning.
+    pub fn with_layer(mut self, layer: ConfigLayer, document: Value) -> Self {
+        self.layers.retain(|l| l.layer != layer);
+        self.layers.push(LayerDocument { layer, document });
+        self.layers.sort_by_key(|l| l.layer);
+        self.section = Value::Null;
+        for layer in &self.layers {
+            if let Some(section) = layer.document.get(&self.namespace) {
+                merge(&mut self.section, section);
+            }
+        }
+        self
+    }
+
+    /// The effective value at the dot-separated `path`, e.g.
+    /// `my_plugin.remote`, and the layer it came from. Without layers, the
+    /// section counts as the workspace layer.
+    pub fn resolve(&self, path: &str) -> Option<ResolvedValue> {
+        if self.layers.is_empty() {
+            let (namespace, rest) = path.split_once('.').unwrap_or((path, ""));
+            if namespace != self.namespace {
+                return None;
+            }
+            return lookup(&self.section, rest).map(|value| ResolvedValue {
+                value: value.clone(),
+                layer: ConfigLayer::Workspace,
+            });
+        }
+        self.layers.iter().rev().find_map(|l| {
+            lookup(&l.document, path).map(|value| ResolvedValue {
+                value: value.clone(),
+                layer: l.layer,
+            })
+        })
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    /// The whole section as raw JSON (`null` when absent).
+    pub fn section(&self) -> &Value {
+        &self.section
+    }
+
+    /// A single top-level key of the section.
+    pub fn get_raw(&self, key: &str) -> Option<&Value> {
+        self.section.get(key)
+    }
+
+    /// Deserialize a single key, returning None when it is absent.
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, PluginError> {
+        self.get_raw(key)
+            .map(|value| {
+                T::deserialize(value)
+                    .map_err(|e| PluginError::config(format!("{}.{}: {}", self.namespace, key, e)))
+            })
+            .transpose()
+    }
+
+    /// Deserialize the whole section. An absent section deserializes as an
+    /// empty object, so structs using `#[serde(default)]` work unconfigured.
+    pub fn get_typed<T: DeserializeOwned>(&self) -> Result<T, PluginError> {
+        let empty = Value::Object(Default::default());
+        let section = if self.section.is_null() {
+            &empty
+        } else {
+            &self.section
+        };
+        T::deserialize(section)
+            .map_err(|e| PluginError::config(format!("{}: {}", self.namespace, e)))
+    }
+
+    /// Top-level keys of the section that are not in `known`, for
+    /// reporting typos from [`Plugin::validate_config`](crate::Plugin::validate_config).
+    pub fn unknown_keys(&self, known: &[&str]) -> Vec<&str> {
+        match self.section.as_object() {
+            Some(map) => map
+                .keys()
+                .map(String::as_str)
+                .filter(|key| !known.contains(key))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    if path.is_empty() {
+        return (!value.is_null()).then_some(value);
+    }
+    path.split('.').try_fold(value, |value, key| value.get(key))
+}
+
+fn merge(into: &mut Value, from: &Value) {
+    match (into, from) {
+        (Value::Object(into), Value::Object(from)) => {
+            for (key, value) in from {
+                merge(into.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+        (into, from) => *into = from.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct ReleaseConfig {
+        #[serde(default)]
+        branch: String,
+        #[serde(default)]
+        sign: bool,
+    }
+
+    #[test]
+    fn test_typed_section() {
+        let meta = json!({
+            "projects": {},
+            "release": { "branch": "main", "sign": true }
+        });
+        let config = PluginConfig::from_meta("release", &meta);
+        assert_eq!(config.get_raw("branch"), Some(&json!("main")));
+        assert_eq!(config.get::<bool>("sign").unwrap(), Some(true));
+        assert_eq!(config.get::<bool>("missing").unwrap(), None);
+        assert_eq!(
+            config.get_typed::<ReleaseConfig>().unwrap(),
+            ReleaseConfig {
+                branch: "main".to_string(),
+                sign: true
+            }
+        );
+    }
+
+    #[test]
+    fn test_layered_resolution() {
+        let config = PluginConfig::from_meta("release", &json!({}))
+            .with_layer(
+                ConfigLayer::Workspace,
+                json!({ "release": { "branch": "main", "remotes": { "a": 1 } } }),
+            )
+            .with_layer(
+                ConfigLayer::Global,
+                json!({ "release": { "sign": true, "branch": "master", "remotes": { "b": 2 } } }),
+            )
+            .with_layer(ConfigLayer::Env, json!({ "release": { "sign": false } }));
+
+        let resolved = config.resolve("release.branch").unwrap();
+        assert_eq!(resolved.value, json!("main"));
+        assert_eq!(resolved.layer, ConfigLayer::Workspace);
+        assert_eq!(
+            config.resolve("release.sign").unwrap().layer,
+            ConfigLayer::Env
+        );
+        assert_eq!(config.resolve("release.missing"), None);
+        assert_eq!(config.get::<bool>("sign").unwrap(), Some(false));
+        assert_eq!(config.get_raw("remotes"), Some(&json!({ "a": 1, "b": 2 })));
+
+        let plain = PluginConfig::new("release", json!({ "branch": "main" }));
+        assert_eq!(
+            plain.resolve("release.branch").unwrap().layer,
+            ConfigLayer::Workspace
+        );
+        assert_eq!(plain.resolve("other.branch"), None);
+    }
+
+    #[test]
+    fn test_missing_section_and_type_errors() {
+        let config = PluginConfig::from_meta("release", &json!({}));
+        assert_eq!(
+            config.get_typed::<ReleaseConfig>().unwrap(),
+            ReleaseConfig {
+                branch: String::new(),
+                sign: false
+            }
+        );
+
+        let config = PluginConfig::new("release", json!({ "sign": "yes" }));
+        assert!(config.unknown_keys(&["sign"]).is_empty());
+        assert_eq!(config.unknown_keys(&["branch"]), vec!["sign"]);
+        let err = config.get::<bool>("sign").unwrap_err();
+        assert!(matches!(err, PluginError::ConfigError(ref m) if m.starts_with("release.sign")));
+    }
+}
@@ -0,0 +1,1706 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::progress::ProgressDisplay;
+use crate::{
+    prompt_for_grant, state, AuditEvent, AuditSink, Cache, CancellationToken, Capabilities,
+    CapabilityRequest, CommandOutcome, Deadline, DiffOptions, EditTarget, EditedContent, Env,
+    EnvSecrets, GitCli, GitOps, Grant, HttpClient, Locale, LockGuard, LockScope, MetaFile,
+    MultiProgress, NetworkSettings, NonInteractivePrompter, Notification, Notifier, NotifyLevel,
+    OutputSink, OutputStream, OutputWriter, Pager, PermissionBroker, PipedStdin, PluginConfig,
+    PluginError, PluginHost, PluginValue, ProgressBar, ProgressReporter, Prompt, Prompter,
+    RateLimiter, RateLimiters, RatePolicy, RedactingSink, Redactor, RepoFilter, RepoHandle,
+    RepoResults, ScopedWriter, Secret, SecretStore, SecretsProvider, Shell, Spawner, Spinner,
+    StdinReader, StdioSink, Styler, Table, TaskSet, Telemetry, TempSpace, TerminalHost,
+    TerminalInfo, Theme, UndoJournal, UserIdentity, DEFAULT_LOCK_WAIT, META_FILE,
+};
+
+/// A project entry parsed from the workspace's `.meta` file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectInfo {
+    /// Project name (the key in the `.meta` projects map)
+    pub name: String,
+    /// Path of the project relative to the workspace root
+    pub path: PathBuf,
+    /// Repository URL the project is cloned from
+    pub repo: String,
+    /// Tags from `.meta`, used to select subsets of projects
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl ProjectInfo {
+    pub fn new(name: impl Into<String>, path: impl Into<PathBuf>, repo: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            path: path.into(),
+            repo: repo.into(),
+            tags: Vec::new(),
+        }
+    }
+
+    pub fn with_tags<T: Into<String>>(mut self, tags: impl IntoIterator<Item = T>) -> Self {
+        self.tags = tags.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Repository URL the project is cloned from.
+    pub fn url(&self) -> &str {
+        &self.repo
+    }
+}
+
+/// How the user asked for command output to be formatted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    /// Human-oriented text (the default)
+    #[default]
+    Human,
+    /// Machine-readable JSON (`--json`); plugins should suppress decorative output
+    Json,
+}
+
+/// How much output the user asked for with `-q`/`-v`. Ordered, so plugins
+/// can write `ctx.verbosity() >= Verbosity::Verbose`.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Verbosity {
+    /// `-q`: errors only
+    Quiet,
+    #[default]
+    Normal,
+    /// `-v`
+    Verbose,
+    /// `-vv`
+    Debug,
+    /// `-vvv` and beyond
+    Trace,
+}
+
+impl Verbosity {
+    /// Map the host's `-q` flag and `-v` count; `-q` wins.
+    pub fn from_flags(quiet: bool, verbose: u8) -> Self {
+        match (quiet, verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, 0) => Verbosity::Normal,
+            (false, 1) => Verbosity::Verbose,
+            (false, 2) => Verbosity::Debug,
+            (false, _) => Verbosity::Trace,
+        }
+    }
+
+    /// The `log` level the host installs for this verbosity.
+    pub fn log_level(self) -> log::LevelFilter {
+        match self {
+            Verbosity::Quiet => log::LevelFilter::Error,
+            Verbosity::Normal => log::LevelFilter::Warn,
+            Verbosity::Verbose => log::LevelFilter::Info,
+            Verbosity::Debug => log::LevelFilter::Debug,
+            Verbosity::Trace => log::LevelFilter::Trace,
+        }
+    }
+}
+
+/// Whether the command should make changes (`meta --dry-run`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionMode {
+    #[default]
+    Normal,
+    /// Report what would be done without touching repos, files or remotes
+    DryRun,
+}
+
+/// Information the host has already discovered about the workspace,
+/// handed to every plugin command so plugins don't re-derive it.
+#[derive(Clone)]
+pub struct PluginContext {
+    workspace_root: PathBuf,
+    projects: Vec<ProjectInfo>,
+    repo_filter: RepoFilter,
+    cwd: PathBuf,
+    host_version: String,
+    output_format: OutputFormat,
+    execution_mode: ExecutionMode,
+    read_only: bool,
+    verbosity: Verbosity,
+    terminal: TerminalInfo,
+    locale: Locale,
+    env: Arc<Env>,
+    network: Option<NetworkSettings>,
+    user: Arc<OnceLock<UserIdentity>>,
+    shell: Option<Shell>,
+    theme: Option<Theme>,
+    stdin: PipedStdin,
+    output: Arc<dyn OutputSink>,
+    redactor: Redactor,
+    /// `output` behind `redactor`, what the writers use
+    redacted: Arc<dyn OutputSink>,
+    progress: ProgressReporter,
+    telemetry: Telemetry,
+    undo: UndoJournal,
+    audit: Option<Arc<dyn AuditSink>>,
+    notifier: Option<Arc<dyn Notifier>>,
+    prompter: Arc<dyn Prompter>,
+    granted: Capabilities,
+    permissions: Option<Arc<dyn PermissionBroker>>,
+    secrets: Option<Arc<dyn SecretsProvider>>,
+    cancellation: CancellationToken,
+    tasks: TaskSet,
+    rate_limiters: RateLimiters,
+    deadline: Option<Deadline>,
+    config: PluginConfig,
+    state_dir: Option<PathBuf>,
+    cache_dir: Option<PathBuf>,
+    lock_dir: Option<PathBuf>,
+    temp: TempSpace,
+    host: Option<Arc<dyn PluginHost>>,
+    http: Option<Arc<dyn HttpClient>>,
+    terminal_host: Option<Arc<dyn TerminalHost>>,
+    git: Option<Arc<dyn GitOps>>,
+    #[cfg(feature = "async")]
+    runtime: Option<tokio::runtime::Handle>,
+    #[cfg(feature = "tracing")]
+    trace_parent: Option<crate::TraceParent>,
+    #[cfg(feature = "instrumentation")]
+    timings: crate::Timings,
+}
+
+impl PluginContext {
+    pub fn new(
+        workspace_root: impl Into<PathBuf>,
+        cwd: impl Into<PathBuf>,
+        host_version: impl Into<String>,
+    ) -> Self {
+        let redactor = Redactor::new();
+        Self {
+            workspace_root: workspace_root.into(),
+            projects: Vec::new(),
+            repo_filter: RepoFilter::all(),
+            cwd: cwd.into(),
+            host_version: host_version.into(),
+            output_format: OutputFormat::default(),
+            execution_mode: ExecutionMode::default(),
+            read_only: false,
+            verbosity: Verbosity::default(),
+            terminal: TerminalInfo::default(),
+            locale: Locale::default(),
+            env: Arc::new(Env::from_process()),
+            network: None,
+            user: Arc::default(),
+            shell: None,
+            theme: None,
+            stdin: PipedStdin::none(),
+            output: Arc::new(StdioSink),
+            redacted: Arc::new(RedactingSink::new(Arc::new(StdioSink), redactor.clone())),
+            redactor,
+            progress: ProgressReporter::disabled(),
+            telemetry: Telemetry::disabled(),
+            undo: UndoJournal::disabled(),
+            audit: None,
+            notifier: None,
+            prompter: Arc::new(NonInteractivePrompter),
+            granted: Capabilities::all(),
+            permissions: None,
+            secrets: None,
+            cancellation: CancellationToken::new(),
+            tasks: TaskSet::new(),
+            rate_limiters: RateLimiters::new(),
+            deadline: None,
+            config: PluginConfig::default(),
+            state_dir: None,
+            cache_dir: None,
+            lock_dir: None,
+            temp: TempSpace::new(),
+            host: None,
+            http: None,
+            terminal_host: None,
+            git: None,
+            #[cfg(feature = "async")]
+            runtime: None,
+            #[cfg(feature = "tracing")]
+            trace_parent: None,
+            #[cfg(feature = "instrumentation")]
+            timings: crate::Timings::disabled(),
+        }
+    }
+
+    /// Set the project list parsed from `.meta`.
+    pub fn with_projects(mut self, projects: Vec<ProjectInfo>) -> Self {
+        self.projects = projects;
+        self
+    }
+
+    /// Restrict commands to the repos the user selected.
+    pub fn with_repo_filter(mut self, filter: RepoFilter) -> Self {
+        self.repo_filter = filter;
+        self
+    }
+
+    pub fn with_output_format(mut self, format: OutputFormat) -> Self {
+        self.output_format = format;
+        self
+    }
+
+    pub fn with_execution_mode(mut self, mode: ExecutionMode) -> Self {
+        self.execution_mode = mode;
+        self
+    }
+
+    /// Forbid changes to the workspace, e.g. for report-only runs in CI.
+    /// Unlike a dry run, mutating plugins fail instead of describing what
+    /// they would do.
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    pub fn with_verbosity(mut self, verbosity: Verbosity) -> Self {
+        self.verbosity = verbosity;
+        self
+    }
+
+    /// Describe the host's terminal, as detected once at startup.
+    pub fn with_terminal(mut self, terminal: TerminalInfo) -> Self {
+        self.terminal = terminal;
+        self
+    }
+
+    /// Set the user's language, usually [`Locale::from_env`].
+    pub fn with_locale(mut self, locale: Locale) -> Self {
+        self.locale = locale;
+        self
+    }
+
+    /// Set the environment plugin commands see. Defaults to the host
+    /// process environment.
+    pub fn with_env(mut self, env: Env) -> Self {
+        self.env = Arc::new(env);
+        self
+    }
+
+    /// Share the host's proxy, CA bundle and offline settings. Without
+    /// this, [`network`](Self::network) reads them from the environment.
+    pub fn with_network(mut self, network: NetworkSettings) -> Self {
+        self.network = Some(network);
+        self
+    }
+
+    /// Set who is running `meta`, as the host resolved it. Without this,
+    /// [`user`](Self::user) detects it on first use.
+    pub fn with_user(mut self, user: UserIdentity) -> Self {
+        self.user = Arc::new(OnceLock::from(user));
+        self
+    }
+
+    /// Style output with the user's theme instead of the default one.
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = Some(theme);
+        self
+    }
+
+    /// The shell `meta` was invoked from, as the host detected it.
+    /// Without this, [`shell`](Self::shell) goes by `$SHELL`.
+    pub fn with_shell(mut self, shell: Shell) -> Self {
+        self.shell = Some(shell);
+        self
+    }
+
+    /// Hand piped input to the plugin, usually [`PipedStdin::from_process`].
+    /// Without this the plugin sees no stdin.
+    pub fn with_stdin(mut self, stdin: PipedStdin) -> Self {
+        self.stdin = stdin;
+        self
+    }
+
+    /// Send plugin output through the host instead of straight to stdio.
+    pub fn with_output(mut self, sink: Arc<dyn OutputSink>) -> Self {
+        self.output = sink;
+        self.redacted = Arc::new(RedactingSink::new(
+            self.output.clone(),
+            self.redactor.clone(),
+        ));
+        self
+    }
+
+    /// Share the host's redactor, so values plugins register are also
+    /// masked in the host's logs.
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.redactor = redactor;
+        self.redacted = Arc::new(RedactingSink::new(
+            self.output.clone(),
+            self.redactor.clone(),
+        ));
+        self
+    }
+
+    /// Route plugin progress to the host's renderer.
+    pub fn with_progress(mut self, progress: ProgressReporter) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Share the host's telemetry handle. Leave it unset, or pass
+    /// [`Telemetry::disabled`], when the user opted out.
+    pub fn with_telemetry(mut self, telemetry: Telemetry) -> Self {
+        self.telemetry = telemetry;
+        self
+    }
+
+    /// Resolve [`get_secret`](Self::get_secret) through the host's
+    /// credential store. Without this, secrets come from
+    /// [`EnvSecrets`] over the context's environment.
+    pub fn with_secrets(mut self, secrets: Arc<dyn SecretsProvider>) -> Self {
+        self.secrets = Some(secrets);
+        self
+    }
+
+    /// Collect undo entries into the host's journal for this command.
+    pub fn with_undo_journal(mut self, journal: UndoJournal) -> Self {
+        self.undo = journal;
+        self
+    }
+
+    /// Send [`audit`](Self::audit) events to the host's audit trail.
+    pub fn with_audit_sink(mut self, sink: Arc<dyn AuditSink>) -> Self {
+        self.audit = Some(sink);
+        self
+    }
+
+    /// Deliver [`notify`](Self::notify) calls through the host's backend.
+    pub fn with_notifier(mut self, notifier: Arc<dyn Notifier>) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    /// Let the plugin ask the user questions, e.g. through the host's
+    /// terminal UI. Without this, prompts answer with their defaults.
+    pub fn with_prompter(mut self, prompter: Arc<dyn Prompter>) -> Self {
+        self.prompter = prompter;
+        self
+    }
+
+    /// Track plugin tasks in the host's set for this command, so it can
+    /// [`shutdown`](TaskSet::shutdown) them afterwards.
+    pub fn with_task_set(mut self, tasks: TaskSet) -> Self {
+        self.tasks = tasks;
+        self
+    }
+
+    /// Hand out limiters from the host's registry, shared by every plugin
+    /// it runs, instead of one private to this context.
+    pub fn with_rate_limiters(mut self, limiters: RateLimiters) -> Self {
+        self.rate_limiters = limiters;
+        self
+    }
+
+    /// Share the host's Ctrl-C token with the plugin.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = token;
+        self
+    }
+
+    pub fn with_deadline(mut self, deadline: Deadline) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Provide the plugin's own `.meta` section.
+    pub fn with_config(mut self, config: PluginConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Set this plugin's durable state directory for the workspace; see
+    /// [`state::workspace_scoped_dir`]. Created lazily on first use.
+    pub fn with_state_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.state_dir = Some(dir.into());
+        self
+    }
+
+    /// Set this plugin's cache directory for the workspace. Created lazily
+    /// on first use.
+    pub fn with_cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(dir.into());
+        self
+    }
+
+    /// Share the host's HTTP client, with its proxy and TLS settings.
+    pub fn with_http_client(mut self, client: Arc<dyn HttpClient>) -> Self {
+        self.http = Some(client);
+        self
+    }
+
+    /// Let [`TuiPlugin`](crate::TuiPlugin)s take over the terminal.
+    pub fn with_terminal_host(mut self, host: Arc<dyn TerminalHost>) -> Self {
+        self.terminal_host = Some(host);
+        self
+    }
+
+    /// Route [`git`](Self::git) through the host's implementation.
+    pub fn with_git(mut self, git: Arc<dyn GitOps>) -> Self {
+        self.git = Some(git);
+        self
+    }
+
+    /// Keep [`lock`](Self::lock) files in `dir` instead of
+    /// [`default_lock_dir`](crate::default_lock_dir). Every plugin of the
+    /// workspace must get the same directory.
+    pub fn with_lock_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.lock_dir = Some(dir.into());
+        self
+    }
+
+    /// Use the host's temporary space for this invocation, e.g. to clean it
+    /// up as soon as the command returns. Otherwise the context has its
+    /// own, removed when the last clone of the context is dropped.
+    pub fn with_temp_space(mut self, temp: TempSpace) -> Self {
+        self.temp = temp;
+        self
+    }
+
+    /// Capabilities the user granted the plugin, e.g. from its
+    /// [`SandboxProfile`](crate::SandboxProfile). Defaults to all.
+    pub fn with_granted_capabilities(mut self, granted: Capabilities) -> Self {
+        self.granted = granted;
+        self
+    }
+
+    /// Decide [`request_capability`](Self::request_capability) calls
+    /// through the host. Without this, the user is asked through the
+    /// [`prompter`](Self::prompter), which denies when non-interactive.
+    pub fn with_permission_broker(mut self, broker: Arc<dyn PermissionBroker>) -> Self {
+        self.permissions = Some(broker);
+        self
+    }
+
+    /// Let the plugin invoke other plugins through the host.
+    pub fn with_host(mut self, host: Arc<dyn PluginHost>) -> Self {
+        self.host = Some(host);
+        self
+    }
+
+    /// Share the host's Tokio runtime with async plugins.
+    #[cfg(feature = "async")]
+    pub fn with_runtime_handle(mut self, handle: tokio::runtime::Handle) -> Self {
+        self.runtime = Some(handle);
+        self
+    }
+
+    /// Attach the host's span so plugin work is traced as its child.
+    #[cfg(feature = "tracing")]
+    pub fn with_trace_parent(mut self, parent: crate::TraceParent) -> Self {
+        self.trace_parent = Some(parent);
+        self
+    }
+
+    /// Share the host's timing recorder when the user asked for
+    /// `--timings`.
+    #[cfg(feature = "instrumentation")]
+    pub fn with_timings(mut self, timings: crate::Timings) -> Self {
+        self.timings = timings;
+        self
+    }
+
+    /// Directory containing the `.meta` file.
+    pub fn workspace_root(&self) -> &Path {
+        &self.workspace_root
+    }
+
+    /// Projects listed in `.meta`, in file order.
+    pub fn projects(&self) -> &[ProjectInfo] {
+        &self.projects
+    }
+
+    /// The user's repo selection; see [`selected_repos`](Self::selected_repos).
+    pub fn repo_filter(&self) -> &RepoFilter {
+        &self.repo_filter
+    }
+
+    /// Directory the host was invoked from.
+    pub fn cwd(&self) -> &Path {
+        &self.cwd
+    }
+
+    /// Version string of the meta host binary.
+    pub fn host_version(&self) -> &str {
+        &self.host_version
+    }
+
+    /// Output format requested by the user.
+    pub fn output_format(&self) -> OutputFormat {
+        self.output_format
+    }
+
+    /// Execution mode requested by the user.
+    pub fn execution_mode(&self) -> ExecutionMode {
+        self.execution_mode
+    }
+
+    /// Shorthand for `execution_mode() == ExecutionMode::DryRun`. Only
+    /// honored by plugins that report
+    /// [`supports_dry_run`](crate::Plugin::supports_dry_run).
+    pub fn is_dry_run(&self) -> bool {
+        self.execution_mode == ExecutionMode::DryRun
+    }
+
+    /// Whether changes to the workspace are forbidden for this command.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Fail with [`PluginError::ReadOnly`] in a read-only run. Mutating
+    /// commands call this before their first change.
+    pub fn assert_writable(&self) -> Result<(), PluginError> {
+        if self.read_only {
+            Err(PluginError::ReadOnly)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Global verbosity; plugins should not add their own `--verbose`.
+    pub fn verbosity(&self) -> Verbosity {
+        self.verbosity
+    }
+
+    /// The terminal plugin output ends up on; use it to decide on colors
+    /// and wrapping.
+    pub fn terminal(&self) -> &TerminalInfo {
+        &self.terminal
+    }
+
+    /// The user's language, for messages and
+    /// [`localized_help`](crate::Plugin::localized_help).
+    pub fn locale(&self) -> &Locale {
+        &self.locale
+    }
+
+    /// Environment variables for this command. Prefer this over
+    /// `std::env`, and [`Env::command`] over `Command::new`.
+    pub fn env(&self) -> &Env {
+        &self.env
+    }
+
+    /// Proxy, CA bundle and offline settings for connections the plugin
+    /// makes itself.
+    pub fn network(&self) -> NetworkSettings {
+        self.network
+            .clone()
+            .unwrap_or_else(|| NetworkSettings::from_env(&self.env))
+    }
+
+    /// Who is running `meta`, for attributing commits, tags and tickets.
+    /// Detected from the environment and the workspace's git config once
+    /// per context, unless the host set it.
+    pub fn user(&self) -> &UserIdentity {
+        self.user
+            .get_or_init(|| UserIdentity::detect(&self.env, &self.workspace_root))
+    }
+
+    /// Styles for [`style`](crate::style) roles, with color only when
+    /// the terminal, `--color` and `NO_COLOR` allow it.
+    pub fn style(&self) -> Styler {
+        Styler::for_terminal(
+            self.theme.clone().unwrap_or_default(),
+            &self.terminal,
+            &self.env,
+        )
+    }
+
+    /// The invoking shell, for plugins that print snippets to `eval`;
+    /// None when unknown.
+    pub fn shell(&self) -> Option<Shell> {
+        self.shell.or_else(|| Shell::from_env(&self.env))
+    }
+
+    /// Claim the input piped into `meta`. The first caller across all
+    /// clones of this context gets it; after that, and whenever stdin is a
+    /// terminal or the host kept it, this returns `None`.
+    pub fn stdin(&self) -> Option<StdinReader> {
+        self.stdin.take()
+    }
+
+    /// Whether input was piped into `meta`, even if already claimed.
+    pub fn is_stdin_piped(&self) -> bool {
+        self.stdin.is_piped()
+    }
+
+    /// Where user-facing output goes. Plugins write here instead of using
+    /// `println!`, so the host can capture, prefix or silence it.
+    pub fn stdout(&self) -> OutputWriter {
+        OutputWriter::new(self.redacted.clone(), OutputStream::Stdout)
+    }
+
+    /// Like [`stdout`](Self::stdout), for warnings and diagnostics.
+    pub fn stderr(&self) -> OutputWriter {
+        OutputWriter::new(self.redacted.clone(), OutputStream::Stderr)
+    }
+
+    /// A line-buffered stdout labeled `label`, rendered by the host as
+    /// `[label] line`. Open one per repo when working on repos in
+    /// parallel.
+    pub fn scoped_stdout(&self, label: impl Into<String>) -> ScopedWriter {
+        ScopedWriter::new(self.redacted.clone(), OutputStream::Stdout, label)
+    }
+
+    /// Like [`scoped_stdout`](Self::scoped_stdout), for stderr.
+    pub fn scoped_stderr(&self, label: impl Into<String>) -> ScopedWriter {
+        ScopedWriter::new(self.redacted.clone(), OutputStream::Stderr, label)
+    }
+
+    /// [`render_diff`](crate::render_diff) options matching the host's
+    /// own diffs: colored as [`style`](Self::style) allows and cut to the
+    /// terminal width.
+    pub fn diff_options(&self) -> DiffOptions {
+        let options = DiffOptions::new().color(self.style().use_color());
+        match self.terminal.width {
+            Some(width) if self.terminal.is_tty => options.width(width.into()),
+            _ => options,
+        }
+    }
+
+    /// Write `table` to stdout, laid out for [`terminal`](Self::terminal).
+    pub fn print_table(&self, table: &Table) -> std::io::Result<()> {
+        self.redacted.write(
+            OutputStream::Stdout,
+            table.render_for(&self.terminal).as_bytes(),
+        )
+    }
+
+    /// A writer for long reports that goes through `$PAGER` (`less -R`
+    /// by default) on a terminal, and straight to [`stdout`](Self::stdout)
+    /// in a pipe, in JSON mode, or when the pager will not start. Output
+    /// is masked like stdout.
+    pub fn pager(&self) -> Pager {
+        if !self.terminal.is_tty || self.output_format == OutputFormat::Json {
+            return Pager::direct(self.stdout());
+        }
+        Pager::spawn(&self.env, self.redactor.clone(), self.stdout())
+    }
+
+    /// The sink behind [`stdout`](Self::stdout) and
+    /// [`stderr`](Self::stderr), for adapters forwarding output from
+    /// out-of-process plugins. Writes are masked like theirs.
+    pub fn output_sink(&self) -> &Arc<dyn OutputSink> {
+        &self.redacted
+    }
+
+    /// Values masked in this command's output.
+    pub fn redactor(&self) -> &Redactor {
+        &self.redactor
+    }
+
+    /// Mask `value`, e.g. a token obtained from an OAuth exchange, in
+    /// everything written from now on. Secrets from
+    /// [`secrets`](Self::secrets) are masked without this.
+    pub fn redact(&self, value: impl Into<String>) {
+        self.redactor.add(value);
+    }
+
+    /// Reporter for long-running work. Discards events unless the host
+    /// installed a renderer.
+    pub fn progress(&self) -> &ProgressReporter {
+        &self.progress
+    }
+
+    /// A spinner for work of unknown length, drawn by the host's
+    /// renderer on a terminal and logged to stderr otherwise.
+    pub fn spinner(&self, label: impl Into<String>) -> Spinner {
+        Spinner::start(self.progress_display(), label)
+    }
+
+    /// Like [`spinner`](Self::spinner), for `total` units of work.
+    pub fn progress_bar(&self, label: impl Into<String>, total: u64) -> ProgressBar {
+        ProgressBar::start(self.progress_display(), label, total)
+    }
+
+    /// A group of spinners and bars, e.g. one per repo being cloned.
+    pub fn multi_progress(&self, label: impl Into<String>) -> MultiProgress {
+        MultiProgress::start(self.progress_display(), label)
+    }
+
+    /// Log lines outside a terminal, unless `-q` asked for errors only.
+    fn progress_display(&self) -> ProgressDisplay {
+        if self.terminal.is_tty || self.verbosity == Verbosity::Quiet {
+            ProgressDisplay::Renderer(self.progress.clone())
+        } else {
+            ProgressDisplay::Log(self.redacted.clone())
+        }
+    }
+
+    /// Usage telemetry, for plugins that report finer-grained commands
+    /// than the host sees. Discards events unless the host enabled it.
+    pub fn telemetry(&self) -> &Telemetry {
+        &self.telemetry
+    }
+
+    /// The host's secrets, for reading and storing credentials.
+    pub fn secrets(&self) -> SecretStore {
+        SecretStore::new(match &self.secrets {
+            Some(secrets) => secrets.clone(),
+            None => Arc::new(EnvSecrets::new(self.env.clone())),
+        })
+        .redacting(self.redactor.clone())
+    }
+
+    /// The secret called `name`, e.g. `github_token`, None if the user
+    /// has not configured it.
+    pub fn get_secret(&self, name: &str) -> Result<Option<Secret>, PluginError> {
+        self.secrets().get(name)
+    }
+
+    /// Like [`get_secret`](Self::get_secret), failing with
+    /// [`PluginError::Unavailable`] when the secret is not configured.
+    pub fn require_secret(&self, name: &str) -> Result<Secret, PluginError> {
+        self.secrets().require(name)
+    }
+
+    /// Where to record how to reverse changes, for `meta undo`. Discards
+    /// entries unless the host keeps a journal.
+    pub fn undo(&self) -> &UndoJournal {
+        &self.undo
+    }
+
+    /// Report a change to the host's audit trail, after making it. Events
+    /// are dropped when the host keeps no trail.
+    pub fn audit(&self, event: AuditEvent) -> Result<(), PluginError> {
+        match &self.audit {
+            Some(sink) => sink.record(&event),
+            None => Ok(()),
+        }
+    }
+
+    /// Capabilities the user granted this plugin.
+    pub fn granted_capabilities(&self) -> Capabilities {
+        self.granted
+    }
+
+    /// Ask for `capability` before using it, with `reason` shown to the
+    /// user. Capabilities already granted are [`Grant::Always`] without
+    /// asking.
+    pub fn request_capability(&self, capability: Capabilities, reason: &str) -> Grant {
+        if self.granted.contains(capability) {
+            return Grant::Always;
+        }
+        let request = CapabilityRequest {
+            capability,
+            reason: reason.to_string(),
+        };
+        match &self.permissions {
+            Some(broker) => broker.request(&request),
+            None => prompt_for_grant(&*self.prompter, &request),
+        }
+    }
+
+    /// Alert the user, e.g. when a long command finishes. The host decides
+    /// how, if at all; without a notifier this does nothing.
+    pub fn notify(&self, title: &str, body: &str, level: NotifyLevel) {
+        if let Some(notifier) = &self.notifier {
+            notifier.notify(&Notification {
+                title: title.to_string(),
+                body: body.to_string(),
+                level,
+            });
+        }
+    }
+
+    /// How to ask the user for input. Plugins must not read stdin
+    /// themselves except through [`stdin`](Self::stdin); it may not be a
+    /// terminal.
+    pub fn prompter(&self) -> &dyn Prompter {
+        &*self.prompter
+    }
+
+    /// Ready-made questions such as
+    /// [`select_repos`](Prompt::select_repos), asked through the
+    /// [`prompter`](Self::prompter).
+    pub fn prompt(&self) -> Prompt<'_> {
+        Prompt::new(self)
+    }
+
+    /// Token set when the user interrupts the command. Long-running
+    /// commands should poll it between units of work.
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    /// Start background work that ends with this command instead of
+    /// spawning threads directly.
+    pub fn spawner(&self) -> Spawner {
+        let spawner = Spawner::new(self.tasks.clone(), self.cancellation.clone());
+        #[cfg(feature = "async")]
+        let spawner = spawner.with_runtime(self.runtime.clone());
+        spawner
+    }
+
+    /// The shared limiter for requests to `key`, e.g. `api.github.com`.
+    /// Plugins using the same key draw from one bucket, whose policy is
+    /// set by the first of them to ask; waiting for a token stops when
+    /// the command is cancelled.
+    pub fn rate_limiter(&self, key: &str, policy: RatePolicy) -> RateLimiter {
+        self.rate_limiters
+            .get(key, policy)
+            .with_cancellation(self.cancellation.clone())
+    }
+
+    /// Effective deadline for this command, if it has a time limit.
+    pub fn deadline(&self) -> Option<&Deadline> {
+        self.deadline.as_ref()
+    }
+
+    /// This plugin's configuration section from `.meta`.
+    pub fn config(&self) -> &PluginConfig {
+        &self.config
+    }
+
+    /// Durable per-plugin, per-workspace directory, created on demand.
+    pub fn state_dir(&self) -> Result<&Path, PluginError> {
+        ensure_dir(self.state_dir.as_deref(), "a state directory")
+    }
+
+    /// Per-plugin, per-workspace cache directory, created on demand. Its
+    /// contents may be deleted by the host at any time.
+    pub fn cache_dir(&self) -> Result<&Path, PluginError> {
+        ensure_dir(self.cache_dir.as_deref(), "a cache directory")
+    }
+
+    /// A key-value cache with TTLs in the `kv` subdirectory of
+    /// [`cache_dir`](Self::cache_dir), limited to
+    /// [`DEFAULT_CACHE_LIMIT`](crate::DEFAULT_CACHE_LIMIT) bytes.
+    pub fn cache(&self) -> Result<Cache, PluginError> {
+        Ok(Cache::new(self.cache_dir()?.join("kv")))
+    }
+
+    /// Lock `scope` against other plugins and `meta` processes, waiting
+    /// up to [`DEFAULT_LOCK_WAIT`](crate::DEFAULT_LOCK_WAIT) (or until the
+    /// deadline or cancellation) for a current holder to finish.
+    pub fn lock(&self, scope: LockScope) -> Result<LockGuard, PluginError> {
+        let wait = match &self.deadline {
+            Some(deadline) => DEFAULT_LOCK_WAIT.min(deadline.remaining()),
+            None => DEFAULT_LOCK_WAIT,
+        };
+        LockGuard::acquire(&self.lock_dir(), scope, wait, &self.cancellation)
+    }
+
+    /// Edit the workspace's `.meta` file under the workspace lock, saving
+    /// it afterwards if `edit` changed anything and succeeded. Fails with
+    /// [`PluginError::ReadOnly`] in a read-only run.
+    pub fn edit_meta<T>(
+        &self,
+        edit: impl FnOnce(&mut MetaFile) -> Result<T, PluginError>,
+    ) -> Result<T, PluginError> {
+        self.assert_writable()?;
+        let _lock = self.lock(LockScope::Workspace)?;
+        let mut meta = MetaFile::load(self.workspace_root.join(META_FILE))?;
+        let result = edit(&mut meta)?;
+        if meta.is_modified() {
+            meta.save()?;
+        }
+        Ok(result)
+    }
+
+    /// Like [`lock`](Self::lock), failing with
+    /// [`PluginError::LockContended`] at once if the lock is held.
+    pub fn try_lock(&self, scope: LockScope) -> Result<LockGuard, PluginError> {
+        LockGuard::try_acquire(&self.lock_dir(), scope)
+    }
+
+    fn lock_dir(&self) -> PathBuf {
+        self.lock_dir
+            .clone()
+            .unwrap_or_else(|| crate::default_lock_dir(&self.workspace_root))
+    }
+
+    /// A temporary directory for this invocation, created on first use and
+    /// removed afterwards however the command ends.
+    pub fn temp_dir(&self) -> Result<PathBuf, PluginError> {
+        self.temp.path()
+    }
+
+    /// Create an empty file called `name` (or a numbered variant) in
+    /// [`temp_dir`](Self::temp_dir).
+    pub fn temp_file(&self, name: &str) -> Result<(PathBuf, fs::File), PluginError> {
+        self.temp.create_file(name)
+    }
+
+    /// Let the user edit `target` in `$VISUAL` or `$EDITOR`, waiting for
+    /// the editor to exit. Buffers are written to a file in
+    /// [`temp_dir`](Self::temp_dir) first. Fails with
+    /// [`PluginError::NonInteractive`] when there is no terminal.
+    pub fn open_in_editor(
+        &self,
+        target: impl Into<EditTarget>,
+    ) -> Result<EditedContent, PluginError> {
+        let target = target.into();
+        if !self.terminal.is_tty {
+            let what = match &target {
+                EditTarget::Path(path) => path.display().to_string(),
+                EditTarget::Buffer { name, .. } => name.clone(),
+            };
+            return Err(PluginError::NonInteractive(format!("edit {}", what)));
+        }
+        let (path, before) = match target {
+            EditTarget::Path(path) => {
+                let before = state::read_if_exists(&path)?.unwrap_or_default();
+                (path, String::from_utf8_lossy(&before).into_owned())
+            }
+            EditTarget::Buffer { name, text } => {
+                let (path, _) = self.temp_file(&name)?;
+                fs::write(&path, &text)?;
+                (path, text)
+            }
+        };
+        crate::editor::run_editor(&self.env, &path)?;
+        let text = fs::read_to_string(&path)?;
+        Ok(EditedContent {
+            changed: text != before,
+            text,
+            path,
+        })
+    }
+
+    /// Read a small file from the state directory, None if absent.
+    pub fn read_state(&self, name: &str) -> Result<Option<Vec<u8>>, PluginError> {
+        state::read_if_exists(&self.state_dir()?.join(name))
+    }
+
+    /// Atomically replace a small file in the state directory.
+    pub fn write_state(&self, name: &str, contents: &[u8]) -> Result<(), PluginError> {
+        state::write_atomic(&self.state_dir()?.join(name), contents)
+    }
+
+    /// The host's HTTP client, or [`PluginError::Unavailable`] if it did
+    /// not share one.
+    pub fn http(&self) -> Result<&dyn HttpClient, PluginError> {
+        self.http
+            .as_deref()
+            .ok_or_else(|| PluginError::Unavailable("an HTTP client".to_string()))
+    }
+
+    /// The host's terminal handover, or [`PluginError::Unavailable`] if
+    /// it cannot give up the terminal, e.g. when output is piped.
+    pub fn terminal_host(&self) -> Result<&dyn TerminalHost, PluginError> {
+        self.terminal_host
+            .as_deref()
+            .ok_or_else(|| PluginError::Unavailable("terminal handover".to_string()))
+    }
+
+    /// Git operations, done by the host if it installed an implementation
+    /// and otherwise by [`GitCli`] with the context's environment. In a
+    /// read-only run, cloning and fetching fail with
+    /// [`PluginError::ReadOnly`].
+    pub fn git(&self) -> Arc<dyn GitOps> {
+        let git: Arc<dyn GitOps> = match &self.git {
+            Some(git) => git.clone(),
+            None => Arc::new(GitCli::new(self.env.clone())),
+        };
+        if self.read_only {
+            Arc::new(crate::git::ReadOnlyGit(git))
+        } else {
+            git
+        }
+    }
+
+    /// The host's service handle, if it supports inter-plugin calls.
+    pub fn host(&self) -> Option<&dyn PluginHost> {
+        self.host.as_deref()
+    }
+
+    /// Run another plugin's command through the host.
+    pub fn invoke(
+        &self,
+        plugin: &str,
+        command: &str,
+        args: &[String],
+    ) -> anyhow::Result<CommandOutcome> {
+        match self.host() {
+            Some(host) => host.invoke(plugin, command, args),
+            None => Err(PluginError::Unavailable("inter-plugin invocation".to_string()).into()),
+        }
+    }
+
+    /// Run another plugin's command through the host for its
+    /// [`PluginValue`], e.g. to aggregate `status` across plugins.
+    pub fn invoke_for_value(
+        &self,
+        plugin: &str,
+        command: &str,
+        args: &[String],
+    ) -> anyhow::Result<PluginValue> {
+        match self.host() {
+            Some(host) => host.invoke_for_value(plugin, command, args),
+            None => Err(PluginError::Unavailable("inter-plugin invocation".to_string()).into()),
+        }
+    }
+
+    /// Handle to the host's Tokio runtime, if the host is async.
+    #[cfg(feature = "async")]
+    pub fn runtime_handle(&self) -> Option<&tokio::runtime::Handle> {
+        self.runtime.as_ref()
+    }
+
+    /// Wait for `future` from synchronous plugin code, on the host's
+    /// runtime if it shared one. Safe to call from a thread of the host's
+    /// multi-threaded runtime, where a plugin-built runtime would panic.
+    #[cfg(feature = "async")]
+    pub fn block_on<F: std::future::Future>(&self, future: F) -> Result<F::Output, PluginError> {
+        crate::async_plugin::block_on(self.runtime.as_ref(), future)
+    }
+
+    /// The host's timing recorder, for `meta --timings`.
+    #[cfg(feature = "instrumentation")]
+    pub fn timings(&self) -> &crate::Timings {
+        &self.timings
+    }
+
+    /// Start a named timing span, e.g. `let _t = ctx.timer("fetch");`. It
+    /// ends when the guard drops and is reported under the running
+    /// command. Free when the host is not collecting timings.
+    #[cfg(feature = "instrumentation")]
+    pub fn timer(&self, name: impl Into<String>) -> crate::Timer {
+        self.timings.timer(name)
+    }
+
+    /// The host's tracing parent, if the host propagated one.
+    #[cfg(feature = "tracing")]
+    pub fn trace_parent(&self) -> Option<&crate::TraceParent> {
+        self.trace_parent.as_ref()
+    }
+
+    /// Run `f` inside a `plugin_command` span that is a child of the host's
+    /// span, with the host's subscriber installed. Runs `f` directly when
+    /// the host did not propagate a trace parent.
+    #[cfg(feature = "tracing")]
+    pub fn in_trace_span<R>(&self, command: &str, f: impl FnOnce() -> R) -> R {
+        match &self.trace_parent {
+            Some(parent) => tracing::dispatcher::with_default(parent.dispatch(), || {
+                tracing::info_span!(parent: parent.span_id().cloned(), "plugin_command", command)
+                    .in_scope(f)
+            }),
+            None => f(),
+        }
+    }
+
+    /// Projects resolved to absolute checkout directories, in `.meta`
+    /// order. Use [`RepoHandle::run_in`] to work inside each one.
+    pub fn repos(&self) -> Vec<RepoHandle> {
+        self.projects
+            .iter()
+            .map(|p| RepoHandle::new(&self.workspace_root, p))
+            .collect()
+    }
+
+    /// The [`repos`](Self::repos) the user's [`RepoFilter`] selects. Plugin
+    /// commands that work across repos should iterate these.
+    pub fn selected_repos(&self) -> Vec<RepoHandle> {
+        self.repos()
+            .into_iter()
+            .filter(|repo| self.repo_filter.matches(repo, &self.env))
+            .collect()
+    }
+
+    /// Run `f` on every selected repo with at most `concurrency` threads and
+    /// collect each repo's result. Repos not yet started when the user
+    /// cancels fail with [`PluginError::Cancelled`], and a panic in `f`
+    /// fails only the repo it panicked on. `f` should return its output
+    /// rather than print it, so the host can show repos one after another
+    /// instead of interleaved.
+    pub fn for_each_repo_parallel<R, F>(&self, concurrency: usize, f: F) -> RepoResults<R>
+    where
+        R: Send,
+        F: Fn(&RepoHandle) -> anyhow::Result<R> + Sync,
+    {
+        crate::repo::for_each_parallel(
+            self.selected_repos(),
+            concurrency,
+            &self.cancellation,
+            self.config.namespace(),
+            f,
+        )
+    }
+
+    /// Look up a project by name.
+    pub fn project(&self, name: &str) -> Option<&ProjectInfo> {
+        self.projects.iter().find(|p| p.name == name)
+    }
+
+    /// The environment handed to out-of-process plugins: all of it when
+    /// [`granted_capabilities`](Self::granted_capabilities) is everything,
+    /// otherwise only what [`Env::restricted_to`] allows.
+    pub(crate) fn plugin_env(&self) -> Env {
+        if self.granted == Capabilities::all() {
+            (*self.env).clone()
+        } else {
+            self.env.restricted_to(self.granted)
+        }
+    }
+
+    /// The serializable part of this context, for plugins running outside
+    /// the host process.
+    pub fn snapshot(&self) -> ContextSnapshot {
+        ContextSnapshot {
+            workspace_root: self.workspace_root.clone(),
+            cwd: self.cwd.clone(),
+            host_version: self.host_version.clone(),
+            projects: self.projects.clone(),
+            repo_filter: self.repo_filter.clone(),
+            output_format: self.output_format,
+            execution_mode: self.execution_mode,
+            read_only: self.read_only,
+            granted: self.granted,
+            verbosity: self.verbosity,
+            terminal: self.terminal,
+            locale: self.locale.clone(),
+            env: Some(self.plugin_env()),
+            network: self.network.clone(),
+            user: self.user.get().cloned(),
+            shell: self.shell,
+            theme: self.theme.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// Plain-data subset of [`PluginContext`] sent to WASM and subprocess
+/// plugins. Service handles (progress, cancellation, the plugin host,
+/// piped stdin) do not cross the boundary; the receiving side gets fresh
+/// defaults.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContextSnapshot {
+    pub workspace_root: PathBuf,
+    pub cwd: PathBuf,
+    pub host_version: String,
+    #[serde(default)]
+    pub projects: Vec<ProjectInfo>,
+    #[serde(default)]
+    pub repo_filter: RepoFilter,
+    #[serde(default)]
+    pub output_format: OutputFormat,
+    #[serde(default)]
+    pub execution_mode: ExecutionMode,
+    #[serde(default)]
+    pub read_only: bool,
+    /// Absent means nothing granted, so an older host's snapshot does not
+    /// widen what the plugin may do
+    #[serde(default = "Capabilities::empty")]
+    pub granted: Capabilities,
+    #[serde(default)]
+    pub verbosity: Verbosity,
+    #[serde(default)]
+    pub terminal: TerminalInfo,
+    #[serde(default)]
+    pub locale: Locale,
+    /// Absent means the receiving process's own environment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<Env>,
+    /// Absent means [`NetworkSettings::from_env`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<NetworkSettings>,
+    /// Absent means detected by the receiving side
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<UserIdentity>,
+    /// Absent means detected by the receiving side
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shell: Option<Shell>,
+    /// Absent means the default theme
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<Theme>,
+    #[serde(default)]
+    pub config: PluginConfig,
+}
+
+impl ContextSnapshot {
+    pub fn into_context(self) -> PluginContext {
+        let ctx = PluginContext::new(self.workspace_root, self.cwd, self.host_version);
+        let ctx = match self.env {
+            Some(env) => ctx.with_env(env),
+            None => ctx,
+        };
+        let ctx = match self.network {
+            Some(network) => ctx.with_network(network),
+            None => ctx,
+        };
+        let ctx = match self.user {
+            Some(user) => ctx.with_user(user),
+            None => ctx,
+        };
+        let ctx = match self.shell {
+            Some(shell) => ctx.with_shell(shell),
+            None => ctx,
+        };
+        let ctx = match self.theme {
+            Some(theme) => ctx.with_theme(theme),
+            None => ctx,
+        };
+        ctx.with_projects(self.projects)
+            .with_repo_filter(self.repo_filter)
+            .with_output_format(self.output_format)
+            .with_execution_mode(self.execution_mode)
+            .with_read_only(self.read_only)
+            .with_granted_capabilities(self.granted)
+            .with_verbosity(self.verbosity)
+            .with_terminal(self.terminal)
+            .with_locale(self.locale)
+            .with_config(self.config)
+    }
+}
+
+impl fmt::Debug for PluginContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PluginContext")
+            .field("workspace_root", &self.workspace_root)
+            .field("projects", &self.projects)
+            .field("repo_filter", &self.repo_filter)
+            .field("cwd", &self.cwd)
+            .field("host_version", &self.host_version)
+            .field("output_format", &self.output_format)
+            .field("execution_mode", &self.execution_mode)
+            .field("read_only", &self.read_only)
+            .field("verbosity", &self.verbosity)
+            .field("terminal", &self.terminal)
+            .field("locale", &self.locale)
+            .field("stdin", &self.stdin)
+            .field("deadline", &self.deadline)
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+fn ensure_dir<'a>(dir: Option<&'a Path>, what: &str) -> Result<&'a Path, PluginError> {
+    let dir = dir.ok_or_else(|| PluginError::Unavailable(what.to_string()))?;
+    fs::create_dir_all(dir)?;
+    Ok(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_accessors() {
+        let ctx = PluginContext::new("/work", "/work/api", "1.2.3").with_projects(vec![
+            ProjectInfo::new("api", "api", "git@example.com:org/api.git"),
+            ProjectInfo::new("web", "web", "git@example.com:org/web.git"),
+        ]);
+        assert_eq!(ctx.workspace_root(), Path::new("/work"));
+        assert_eq!(ctx.cwd(), Path::new("/work/api"));
+        assert_eq!(ctx.host_version(), "1.2.3");
+        assert_eq!(ctx.projects().len(), 2);
+        assert_eq!(
+            ctx.project("web").unwrap().repo,
+            "git@example.com:org/web.git"
+        );
+        assert!(ctx.project("missing").is_none());
+        let repos = ctx.repos();
+        assert_eq!(repos[1].path, Path::new("/work/web"));
+        let ctx = ctx.with_repo_filter(RepoFilter::all().exclude("api"));
+        let selected: Vec<_> = ctx.selected_repos().into_iter().map(|r| r.name).collect();
+        assert_eq!(selected, ["web"]);
+        assert_eq!(ctx.output_format(), OutputFormat::Human);
+        assert!(!ctx.is_dry_run());
+    }
+
+    #[test]
+    fn test_verbosity_from_flags() {
+        assert_eq!(Verbosity::from_flags(false, 0), Verbosity::Normal);
+        assert_eq!(Verbosity::from_flags(false, 2), Verbosity::Debug);
+        assert_eq!(Verbosity::from_flags(false, 9), Verbosity::Trace);
+        assert_eq!(Verbosity::from_flags(true, 2), Verbosity::Quiet);
+        assert!(Verbosity::Verbose > Verbosity::Normal);
+        assert_eq!(Verbosity::Quiet.log_level(), log::LevelFilter::Error);
+        assert_eq!(Verbosity::Verbose.log_level(), log::LevelFilter::Info);
+    }
+
+    struct Answers;
+
+    impl Prompter for Answers {
+        fn confirm(&self, _message: &str, _default: bool) -> Result<bool, PluginError> {
+            Ok(false)
+        }
+        fn input(&self, _message: &str, _default: Option<&str>) -> Result<String, PluginError> {
+            Ok("typed".to_string())
+        }
+        fn select(
+            &self,
+            _message: &str,
+            items: &[&str],
+            _default: Option<usize>,
+        ) -> Result<usize, PluginError> {
+            Ok(items.len() - 1)
+        }
+        fn multi_select(
+            &self,
+            _message: &str,
+            _items: &[&str],
+            _defaults: &[bool],
+        ) -> Result<Vec<usize>, PluginError> {
+            Ok(vec![])
+        }
+    }
+
+    #[test]
+    fn test_output_goes_to_host_sink() {
+        use std::io::Write;
+
+        let captured = Arc::new(crate::CapturedOutput::new());
+        let ctx = PluginContext::new("/work", "/work", "1.0.0").with_output(captured.clone());
+        writeln!(ctx.stdout(), "done").unwrap();
+        writeln!(ctx.stderr(), "careful").unwrap();
+        assert_eq!(captured.stdout(), "done\n");
+        assert_eq!(captured.stderr(), "careful\n");
+    }
+
+    #[test]
+    fn test_secrets_and_redacted_values_are_masked() {
+        use std::io::Write;
+
+        let captured = Arc::new(crate::CapturedOutput::new());
+        let host = Redactor::new();
+        let ctx = PluginContext::new("/work", "/work", "1.0.0")
+            .with_output(captured.clone())
+            .with_env(Env::new().with_var("META_SECRET_GITHUB_TOKEN", "ghp_abc123"))
+            .with_redactor(host.clone());
+        let token = ctx.require_secret("github_token").unwrap();
+        let session = String::from("session-42");
+        ctx.redact(session.clone());
+        writeln!(ctx.stdout(), "token {} session {}", token.expose(), session).unwrap();
+        writeln!(ctx.scoped_stderr("api"), "retry with ghp_abc123").unwrap();
+        assert_eq!(captured.stdout(), "token *** session ***\n");
+        assert_eq!(captured.stderr(), "[api] retry with ***\n");
+        assert_eq!(host.redact("log: session-42"), "log: ***");
+    }
+
+    #[test]
+    fn test_prompter_defaults_to_non_interactive() {
+        let ctx = PluginContext::new("/work", "/work", "1.0.0");
+        assert!(ctx.prompter().confirm("Continue?", true).unwrap());
+        assert!(ctx.prompter().input("Name", None).is_err());
+
+        let ctx = ctx.with_prompter(Arc::new(Answers));
+        assert!(!ctx.prompter().confirm("Continue?", true).unwrap());
+        assert_eq!(ctx.prompter().input("Name", None).unwrap(), "typed");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_open_in_editor() {
+        let ctx = PluginContext::new("/work", "/work", "1.0.0").with_env(
+            Env::from_process().with_var("VISUAL", r#"sh -c 'printf "fix: typo" > "$0"'"#),
+        );
+        assert!(matches!(
+            ctx.open_in_editor(EditTarget::buffer("COMMIT_EDITMSG", "")),
+            Err(PluginError::NonInteractive(_))
+        ));
+
+        let ctx = ctx.with_terminal(TerminalInfo {
+            is_tty: true,
+            ..TerminalInfo::default()
+        });
+        let edited = ctx
+            .open_in_editor(EditTarget::buffer("COMMIT_EDITMSG", "# message"))
+            .unwrap();
+        assert_eq!(edited.text, "fix: typo");
+        assert!(edited.changed);
+        assert!(edited.path.ends_with("COMMIT_EDITMSG"));
+
+        let ctx = ctx.with_env(Env::from_process().with_var("VISUAL", "false"));
+        assert!(matches!(
+            ctx.open_in_editor(edited.path.as_path()),
+            Err(PluginError::Editor { .. })
+        ));
+    }
+
+    #[test]
+    fn test_edit_meta() {
+        let root =
+            std::env::temp_dir().join(format!("meta_plugin_api-edit-meta-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(
+            root.join(META_FILE),
+            "{\n  // none yet\n  \"projects\": {}\n}\n",
+        )
+        .unwrap();
+        let ctx = PluginContext::new(&root, &root, "1.0.0").with_lock_dir(root.join("locks"));
+
+        let added = ctx
+            .edit_meta(|meta| meta.add_project(&ProjectInfo::new("api", "api", "u")))
+            .unwrap();
+        assert!(added);
+        let text = fs::read_to_string(root.join(META_FILE)).unwrap();
+        assert_eq!(
+            text,
+            "{\n  // none yet\n  \"projects\": {\n    \"api\": \"u\"\n  }\n}\n"
+        );
+        assert!(matches!(
+            ctx.clone()
+                .with_read_only(true)
+                .edit_meta(|meta| meta.remove_project("api")),
+            Err(PluginError::ReadOnly)
+        ));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_assert_writable() {
+        let ctx = PluginContext::new("/work", "/work", "1.0.0");
+        assert!(ctx.assert_writable().is_ok());
+        let ctx = ctx.with_read_only(true);
+        assert!(ctx.is_read_only());
+        assert!(matches!(ctx.assert_writable(), Err(PluginError::ReadOnly)));
+        let git = ctx.git();
+        assert!(matches!(
+            GitOps::clone(&*git, "git@example.com:org/api.git", Path::new("/work/api")),
+            Err(PluginError::ReadOnly)
+        ));
+        assert!(matches!(
+            git.fetch(Path::new("/work/api"), Some("origin")),
+            Err(PluginError::ReadOnly)
+        ));
+    }
+
+    #[test]
+    fn test_request_capability() {
+        let ctx = PluginContext::new("/work", "/work", "1.0.0")
+            .with_granted_capabilities(Capabilities::SPAWN_PROCESSES);
+        assert_eq!(
+            ctx.request_capability(Capabilities::SPAWN_PROCESSES, "run git"),
+            Grant::Always
+        );
+        assert_eq!(
+            ctx.request_capability(Capabilities::NETWORK, "fetch"),
+            Grant::Denied
+        );
+
+        // Answers picks the last choice, "Deny"; a broker overrides it.
+        let ctx = ctx.with_prompter(Arc::new(Answers));
+        assert_eq!(
+            ctx.request_capability(Capabilities::NETWORK, "fetch"),
+            Grant::Denied
+        );
+        let ctx = ctx.with_permission_broker(Arc::new(crate::FixedGrant(Grant::Once)));
+        assert!(ctx
+            .request_capability(Capabilities::NETWORK, "fetch")
+            .is_granted());
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let ctx = PluginContext::new("/work", "/work/api", "1.2.3")
+            .with_projects(vec![ProjectInfo::new(
+                "api",
+                "api",
+                "git@example.com:org/api.git",
+            )])
+            .with_output_format(OutputFormat::Json)
+            .with_execution_mode(ExecutionMode::DryRun)
+            .with_read_only(true)
+            .with_verbosity(Verbosity::Debug)
+            .with_env(Env::new().with_var("META_PROFILE", "ci"))
+            .with_network(NetworkSettings {
+                offline: true,
+                ..NetworkSettings::default()
+            })
+            .with_user(UserIdentity {
+                name: Some("Ada".to_string()),
+                ..UserIdentity::default()
+            })
+            .with_shell(Shell::Fish)
+            .with_theme(crate::Theme::plain())
+            .with_locale(Locale::new("pt_BR"))
+            .with_terminal(TerminalInfo {
+                is_tty: true,
+                color: crate::ColorChoice::Never,
+                width: Some(100),
+                height: None,
+            })
+            .with_config(PluginConfig::new(
+                "release",
+                serde_json::json!({ "sign": true }),
+            ));
+        let json = serde_json::to_string(&ctx.snapshot()).unwrap();
+        let restored: ContextSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, ctx.snapshot());
+
+        let ctx = restored.into_context();
+        assert_eq!(ctx.output_format(), OutputFormat::Json);
+        assert!(ctx.is_dry_run());
+        assert_eq!(ctx.verbosity(), Verbosity::Debug);
+        assert_eq!(ctx.env().get("META_PROFILE"), Some("ci"));
+        assert_eq!(ctx.terminal().width, Some(100));
+        assert_eq!(ctx.locale().tag(), "pt-BR");
+        assert!(!ctx.terminal().use_color());
+        assert_eq!(ctx.project("api").unwrap().path, Path::new("api"));
+        assert_eq!(ctx.config().get::<bool>("sign").unwrap(), Some(true));
+        assert_eq!(ctx.granted_capabilities(), Capabilities::all());
+
+        let sandboxed = PluginContext::new("/work", "/work", "1.0.0")
+            .with_granted_capabilities(Capabilities::NETWORK)
+            .snapshot()
+            .into_context();
+        assert_eq!(sandboxed.granted_capabilities(), Capabilities::NETWORK);
+        let old: ContextSnapshot = serde_json::from_value(serde_json::json!({
+            "workspace_root": "/work",
+            "cwd": "/work",
+            "host_version": "1.0.0"
+        }))
+        .unwrap();
+        assert_eq!(
+            old.into_context().granted_capabilities(),
+            Capabilities::empty()
+        );
+    }
+
+    #[test]
+    fn test_sandboxed_snapshot_drops_secrets() {
+        let env = Env::new()
+            .with_var("PATH", "/usr/bin")
+            .with_var("META_SECRET_GITHUB_TOKEN", "ghp_abc123")
+            .with_var("NPM_TOKEN", "npm_abc123");
+        let ctx = PluginContext::new("/work", "/work", "1.0.0").with_env(env);
+        let full = ctx.snapshot().env.unwrap();
+        assert_eq!(full.get("META_SECRET_GITHUB_TOKEN"), Some("ghp_abc123"));
+
+        let sandboxed = ctx
+            .with_granted_capabilities(Capabilities::SPAWN_PROCESSES)
+            .snapshot()
+            .env
+            .unwrap();
+        assert_eq!(sandboxed.get("META_SECRET_GITHUB_TOKEN"), None);
+        assert_eq!(sandboxed.get("NPM_TOKEN"), None);
+        assert_eq!(sandboxed.get("PATH"), Some("/usr/bin"));
+    }
+
+    #[test]
+    fn test_secrets_default_to_env() {
+        let ctx = PluginContext::new("/work", "/work", "1.0.0")
+            .with_env(Env::new().with_var("META_SECRET_GITHUB_TOKEN", "ghp"));
+        assert_eq!(ctx.require_secret("github_token").unwrap().expose(), "ghp");
+        assert_eq!(
+            ctx.require_secret("jira_token").unwrap_err().to_string(),
+            "Host does not provide the secret 'jira_token'"
+        );
+
+        let ctx = ctx.with_secrets(Arc::new(crate::SecretsChain::new()));
+        assert_eq!(ctx.get_secret("github_token").unwrap(), None);
+    }
+
+    #[test]
+    fn test_lock_contended_across_contexts() {
+        let dir =
+            std::env::temp_dir().join(format!("meta_plugin_api-ctx-locks-{}", std::process::id()));
+        let ctx = PluginContext::new("/work", "/work", "1.0.0").with_lock_dir(&dir);
+        let other = ctx
+            .clone()
+            .with_deadline(Deadline::after(std::time::Duration::ZERO));
+
+        let guard = ctx.lock(LockScope::Workspace).unwrap();
+        assert!(matches!(
+            other.lock(LockScope::Workspace),
+            Err(PluginError::LockContended(_))
+        ));
+        assert!(other.try_lock(LockScope::repo("api")).is_ok());
+        drop(guard);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rate_limiter_shared_across_contexts() {
+        let limiters = RateLimiters::new();
+        let git =
+            PluginContext::new("/work", "/work", "1.0.0").with_rate_limiters(limiters.clone());
+        let release = PluginContext::new("/work", "/work", "1.0.0").with_rate_limiters(limiters);
+        let policy = RatePolicy::per_hour(1);
+        assert!(git.rate_limiter("api.github.com", policy).try_acquire());
+        let limiter = release.rate_limiter("api.github.com", policy);
+        assert!(!limiter.try_acquire());
+        release.cancellation().cancel();
+        assert!(matches!(limiter.acquire(), Err(PluginError::Cancelled)));
+    }
+
+    #[test]
+    fn test_pager_writes_directly_without_terminal() {
+        use std::io::Write;
+
+        let output = Arc::new(crate::CapturedOutput::new());
+        let ctx = PluginContext::new("/work", "/work", "1.0.0")
+            .with_output(output.clone())
+            .with_env(Env::new().with_var("PAGER", "meta-no-such-pager"));
+        let mut pager = ctx.pager();
+        assert!(!pager.is_paging());
+        writeln!(pager, "line 1").unwrap();
+        pager.finish().unwrap();
+        assert_eq!(output.stdout(), "line 1\n");
+    }
+
+    #[test]
+    fn test_state_dir_created_lazily() {
+        let dir =
+            std::env::temp_dir().join(format!("meta_plugin_api-ctx-state-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let ctx = PluginContext::new("/work", "/work", "1.0.0").with_state_dir(&dir);
+        assert!(!dir.exists());
+        assert_eq!(ctx.read_state("token").unwrap(), None);
+        assert!(dir.is_dir());
+        ctx.write_state("token", b"abc").unwrap();
+        assert_eq!(ctx.read_state("token").unwrap(), Some(b"abc".to_vec()));
+        fs::remove_dir_all(&dir).unwrap();
+
+        let ctx = PluginContext::new("/work", "/work", "1.0.0");
+        assert!(matches!(ctx.cache_dir(), Err(PluginError::Unavailable(_))));
+        assert!(matches!(ctx.cache(), Err(PluginError::Unavailable(_))));
+    }
+
+    struct EchoHost;
+
+    impl PluginHost for EchoHost {
+        fn invoke(
+            &self,
+            plugin: &str,
+            command: &str,
+            args: &[String],
+        ) -> anyhow::Result<CommandOutcome> {
+            Ok(CommandOutcome::failure(
+                args.len() as i32,
+                format!("{} {}", plugin, command),
+            ))
+        }
+    }
+
+    #[test]
+    fn test_invoke_through_host() {
+        let ctx = PluginContext::new("/work", "/work", "1.0.0");
+        assert!(ctx.host().is_none());
+        assert!(ctx.invoke("changelog", "generate", &[]).is_err());
+
+        let ctx = ctx.with_host(Arc::new(EchoHost));
+        let outcome = ctx
+            .invoke(
+                "changelog",
+                "generate",
+                &["--since".to_string(), "v1".to_string()],
+            )
+            .unwrap();
+        assert_eq!(outcome, CommandOutcome::failure(2, "changelog generate"));
+        assert!(matches!(
+            PluginError::find(&ctx.invoke_for_value("git", "status", &[]).unwrap_err()),
+            Some(PluginError::Unavailable(_))
+        ));
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_in_trace_span_runs_closure() {
+        let ctx = PluginContext::new("/work", "/work", "1.0.0");
+        assert_eq!(ctx.in_trace_span("sync", || 1), 1);
+        let ctx = ctx.with_trace_parent(crate::TraceParent::current());
+        assert_eq!(ctx.in_trace_span("sync", || 2), 2);
+    }
+}
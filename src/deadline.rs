@@ -0,0 +1,78 @@
+use std::time::{Duration, Instant};
+
+use crate::PluginError;
+
+/// Point in time by which a command must finish. The host computes it from
+/// the command's [`CommandSpec::timeout`](crate::CommandSpec::timeout) (or a
+/// user override) and passes it in the context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline {
+    start: Instant,
+    timeout: Duration,
+}
+
+impl Deadline {
+    /// A deadline `timeout` from now.
+    pub fn after(timeout: Duration) -> Self {
+        Self {
+            start: Instant::now(),
+            timeout,
+        }
+    }
+
+    /// The effective deadline for a command: a user-supplied override wins
+    /// over the command's declared default. None means no limit.
+    pub fn effective(declared: Option<Duration>, user_override: Option<Duration>) -> Option<Self> {
+        user_override.or(declared).map(Self::after)
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Time left before the deadline; zero once it has passed.
+    pub fn remaining(&self) -> Duration {
+        self.timeout.saturating_sub(self.start.elapsed())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.start.elapsed() >= self.timeout
+    }
+
+    /// Fail with [`PluginError::TimedOut`] once the deadline has passed.
+    pub fn check(&self) -> Result<(), PluginError> {
+        if self.is_expired() {
+            Err(PluginError::TimedOut(self.timeout))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deadline_expiry() {
+        let deadline = Deadline::after(Duration::from_secs(60));
+        assert!(!deadline.is_expired());
+        assert!(deadline.remaining() > Duration::from_secs(59));
+        assert!(deadline.check().is_ok());
+
+        let expired = Deadline::after(Duration::ZERO);
+        assert!(expired.is_expired());
+        assert_eq!(expired.remaining(), Duration::ZERO);
+        assert!(matches!(expired.check(), Err(PluginError::TimedOut(d)) if d == Duration::ZERO));
+    }
+
+    #[test]
+    fn test_effective_deadline_prefers_override() {
+        let declared = Some(Duration::from_secs(30));
+        let deadline = Deadline::effective(declared, Some(Duration::from_secs(5))).unwrap();
+        assert_eq!(deadline.timeout(), Duration::from_secs(5));
+        let deadline = Deadline::effective(declared, None).unwrap();
+        assert_eq!(deadline.timeout(), Duration::from_secs(30));
+        assert!(Deadline::effective(None, None).is_none());
+    }
+}
@@ -0,0 +1,239 @@
+use std::panic::{self, UnwindSafe};
+
+use crate::{Plugin, PluginContext, PluginError};
+
+/// Export a plugin type from a `cdylib` so the host can load it.
+///
+/// Emits the `_plugin_create_v2` constructor (matching
+/// [`PluginCreateV2`](crate::PluginCreateV2)), the older `_plugin_create`
+/// (matching [`PluginCreate`](crate::PluginCreate)) for hosts that predate
+/// it, the FFI-safe `_plugin_create_ffi` constructor (see
+/// [`ffi`](crate::ffi)) and the `_plugin_api_version` symbol. Panics raised
+/// while constructing the plugin are caught and reported to the host, and
+/// the plugin is wrapped in a [`PanicShield`](crate::PanicShield), so
+/// nothing unwinds across the library boundary.
+///
+/// A constructor that can fail, e.g. on a missing environment variable,
+/// is passed after `try` and returns a `Result`; its error reaches the
+/// host through `_plugin_create_v2`, while the older constructors return
+/// null.
+///
+/// ```ignore
+/// #[derive(Default)]
+/// struct MyPlugin;
+/// // impl Plugin for MyPlugin { ... }
+///
+/// meta_plugin_api::declare_plugin!(MyPlugin);
+/// // or, with an explicit constructor:
+/// meta_plugin_api::declare_plugin!(MyPlugin, MyPlugin::new);
+/// // or, with a fallible constructor returning anyhow::Result<MyPlugin>:
+/// meta_plugin_api::declare_plugin!(MyPlugin, try MyPlugin::from_env);
+/// ```
+#[macro_export]
+macro_rules! declare_plugin {
+    ($plugin_type:ty) => {
+        $crate::declare_plugin!(
+            $plugin_type,
+            <$plugin_type as ::std::default::Default>::default
+        );
+    };
+    ($plugin_type:ty, try $constructor:expr) => {
+        #[no_mangle]
+        #[allow(improper_ctypes_definitions)]
+        pub extern "C" fn _plugin_create_v2() -> $crate::PluginCreateResult {
+            $crate::__create_plugin(|| {
+                let plugin: $plugin_type = ($constructor)()?;
+                Ok(
+                    ::std::boxed::Box::new($crate::PanicShield::new(::std::boxed::Box::new(plugin)))
+                        as ::std::boxed::Box<dyn $crate::Plugin>,
+                )
+            })
+        }
+
+        #[no_mangle]
+        #[allow(improper_ctypes_definitions)]
+        pub extern "C" fn _plugin_create() -> *mut dyn $crate::Plugin {
+            _plugin_create_v2().into_raw_or_null()
+        }
+
+        #[no_mangle]
+        pub extern "C" fn _plugin_create_ffi() -> $crate::ffi::FfiPlugin {
+            $crate::ffi::create_ffi_plugin(|| {
+                let plugin: $plugin_type = ($constructor)()?;
+                Ok(::std::boxed::Box::new(plugin) as ::std::boxed::Box<dyn $crate::Plugin>)
+            })
+        }
+
+        #[no_mangle]
+        #[allow(non_upper_case_globals)]
+        pub static _plugin_api_version: u32 = $crate::PLUGIN_API_VERSION;
+    };
+    ($plugin_type:ty, $constructor:expr) => {
+        $crate::declare_plugin!(
+            $plugin_type,
+            try || ::std::result::Result::<_, ::std::convert::Infallible>::Ok(($constructor)())
+        );
+    };
+}
+
+/// Returned by `_plugin_create_v2`: exactly one of `plugin` and `error`
+/// is non-null. Both come from `Box::into_raw` in the plugin library and
+/// are owned by the host afterwards; [`into_result`](Self::into_result)
+/// takes them back.
+#[repr(C)]
+pub struct PluginCreateResult {
+    pub plugin: *mut dyn Plugin,
+    pub error: *mut String,
+}
+
+impl PluginCreateResult {
+    pub fn ok(plugin: Box<dyn Plugin>) -> Self {
+        Self {
+            plugin: Box::into_raw(plugin),
+            error: std::ptr::null_mut(),
+        }
+    }
+
+    pub fn err(message: impl Into<String>) -> Self {
+        Self {
+            plugin: null_plugin(),
+            error: Box::into_raw(Box::new(message.into())),
+        }
+    }
+
+    /// Take ownership of the plugin, or of the error as
+    /// [`PluginError::LoadError`].
+    ///
+    /// # Safety
+    /// `self` must come from a `_plugin_create_v2` export (or
+    /// [`ok`](Self::ok)/[`err`](Self::err)) and be consumed only once.
+    pub unsafe fn into_result(self) -> Result<Box<dyn Plugin>, PluginError> {
+        if !self.plugin.is_null() {
+            Ok(Box::from_raw(self.plugin))
+        } else if !self.error.is_null() {
+            Err(PluginError::LoadError(*Box::from_raw(self.error)))
+        } else {
+            Err(PluginError::LoadError(
+                "plugin constructor failed".to_string(),
+            ))
+        }
+    }
+
+    /// The `_plugin_create` shape: the plugin pointer, or null after
+    /// logging the error.
+    #[doc(hidden)]
+    pub fn into_raw_or_null(self) -> *mut dyn Plugin {
+        // SAFETY: called once, on a value just built by `create_plugin`.
+        match unsafe { self.into_result() } {
+            Ok(plugin) => Box::into_raw(plugin),
+            Err(e) => {
+                log::error!("{}", e);
+                null_plugin()
+            }
+        }
+    }
+}
+
+/// Run a plugin constructor, converting an error or a panic into
+/// [`PluginCreateResult::err`].
+#[doc(hidden)]
+pub fn create_plugin<F>(constructor: F) -> PluginCreateResult
+where
+    F: FnOnce() -> anyhow::Result<Box<dyn Plugin>> + UnwindSafe,
+{
+    match panic::catch_unwind(constructor) {
+        Ok(Ok(plugin)) => PluginCreateResult::ok(plugin),
+        Ok(Err(e)) => PluginCreateResult::err(format!("{:#}", e)),
+        Err(payload) => match PluginError::panicked("", &*payload) {
+            PluginError::Panicked { payload, .. } => {
+                PluginCreateResult::err(format!("plugin constructor panicked: {}", payload))
+            }
+            e => PluginCreateResult::err(e.to_string()),
+        },
+    }
+}
+
+fn null_plugin() -> *mut dyn Plugin {
+    std::ptr::null_mut::<Unconstructed>() as *mut dyn Plugin
+}
+
+/// Placeholder type used only to build a null `*mut dyn Plugin`.
+struct Unconstructed;
+
+impl Plugin for Unconstructed {
+    fn name(&self) -> &'static str {
+        unreachable!()
+    }
+    fn commands(&self) -> Vec<&'static str> {
+        unreachable!()
+    }
+    fn execute(
+        &self,
+        _command: &str,
+        _args: &[String],
+        _ctx: &PluginContext,
+    ) -> anyhow::Result<()> {
+        unreachable!()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct ExportedPlugin;
+    impl Plugin for ExportedPlugin {
+        fn name(&self) -> &'static str {
+            "exported"
+        }
+        fn commands(&self) -> Vec<&'static str> {
+            vec!["run"]
+        }
+        fn execute(
+            &self,
+            _command: &str,
+            _args: &[String],
+            _ctx: &PluginContext,
+        ) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    crate::declare_plugin!(ExportedPlugin);
+
+    #[test]
+    fn test_declare_plugin_exports_constructor() {
+        let raw = _plugin_create();
+        assert!(!raw.is_null());
+        let plugin = unsafe { Box::from_raw(raw) };
+        assert_eq!(plugin.name(), "exported");
+        assert_eq!(_plugin_api_version, crate::PLUGIN_API_VERSION);
+
+        let plugin = unsafe { _plugin_create_v2().into_result() }.unwrap();
+        assert_eq!(plugin.commands(), vec!["run"]);
+    }
+
+    #[test]
+    fn test_declare_plugin_exports_ffi_constructor() {
+        let plugin = unsafe { crate::ffi::FfiPluginProxy::from_raw(_plugin_create_ffi()) }.unwrap();
+        assert_eq!(plugin.name(), "exported");
+        assert_eq!(plugin.commands(), vec!["run"]);
+    }
+
+    #[test]
+    fn test_create_plugin_reports_errors() {
+        let result = create_plugin(|| panic!("constructor failed"));
+        let err = unsafe { result.into_result() }.err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Failed to load plugin: plugin constructor panicked: constructor failed"
+        );
+
+        let result = create_plugin(|| Err(anyhow::anyhow!("GITHUB_TOKEN is not set")));
+        assert!(result.into_raw_or_null().is_null());
+        let result = create_plugin(|| Err(anyhow::anyhow!("GITHUB_TOKEN is not set")));
+        let err = unsafe { result.into_result() }.err().unwrap();
+        assert!(err.to_string().ends_with("GITHUB_TOKEN is not set"));
+    }
+}
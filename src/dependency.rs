@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+use semver::{Version, VersionReq};
+
+use crate::PluginError;
+
+/// Another plugin this plugin needs at runtime.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginDependency {
+    pub name: &'static str,
+    pub version_req: VersionReq,
+}
+
+impl PluginDependency {
+    /// Depend on `name` matching a semver requirement such as `">=1.2, <2"`.
+    pub fn new(name: &'static str, version_req: &str) -> Result<Self, PluginError> {
+        let version_req = VersionReq::parse(version_req).map_err(|e| {
+            PluginError::config(format!("invalid version requirement for '{}': {}", name, e))
+        })?;
+        Ok(Self { name, version_req })
+    }
+
+    /// Depend on any version of `name`.
+    pub fn any(name: &'static str) -> Self {
+        Self {
+            name,
+            version_req: VersionReq::STAR,
+        }
+    }
+
+    pub fn is_satisfied_by(&self, version: &Version) -> bool {
+        self.version_req.matches(version)
+    }
+}
+
+/// Check `plugin`'s dependencies against the loaded plugins, given as a map
+/// of plugin name to version. Hosts call this for each plugin after loading
+/// everything and refuse to run commands from plugins that fail.
+pub fn resolve_dependencies(
+    plugin: &str,
+    dependencies: &[PluginDependency],
+    available: &HashMap<&str, Version>,
+) -> Result<(), PluginError> {
+    for dependency in dependencies {
+        let reason = match available.get(dependency.name) {
+            Some(version) if dependency.is_satisfied_by(version) => continue,
+            Some(version) => format!("found version {}", version),
+            None => "is not installed".to_string(),
+        };
+        return Err(PluginError::UnresolvedDependency {
+            plugin: plugin.to_string(),
+            dependency: dependency.name.to_string(),
+            requirement: dependency.version_req.to_string(),
+            reason,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_dependencies() {
+        let available = HashMap::from([
+            ("changelog", Version::new(1, 4, 0)),
+            ("git", Version::new(0, 9, 0)),
+        ]);
+        let deps = vec![
+            PluginDependency::new("changelog", "^1.2").unwrap(),
+            PluginDependency::any("git"),
+        ];
+        assert!(resolve_dependencies("release", &deps, &available).is_ok());
+
+        let deps = vec![PluginDependency::new("git", ">=1.0").unwrap()];
+        let err = resolve_dependencies("release", &deps, &available).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Plugin 'release' requires 'git' >=1.0, which found version 0.9.0"
+        );
+
+        let deps = vec![PluginDependency::any("jira")];
+        let err = resolve_dependencies("release", &deps, &available).unwrap_err();
+        assert!(err.to_string().ends_with("which is not installed"));
+    }
+
+    #[test]
+    fn test_invalid_requirement() {
+        assert!(matches!(
+            PluginDependency::new("git", "not a version"),
+            Err(PluginError::ConfigError(_))
+        ));
+    }
+}
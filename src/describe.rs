@@ -0,0 +1,293 @@
+//! A stable JSON description of what plugins provide, for IDE
+//! integrations and doc generators (`meta plugin describe --json`).
+//!
+//! The document layout is owned by this crate and versioned by
+//! [`DESCRIPTION_SCHEMA_VERSION`]: fields are only added within a
+//! version, so readers should ignore fields they do not know. With the
+//! `schema` feature, [`json_schema`] returns its JSON Schema.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ArgKind, ArgSpec, CommandSpec, Plugin, UpdateSource};
+
+/// Version of the [`Registry`] document layout.
+pub const DESCRIPTION_SCHEMA_VERSION: u32 = 1;
+
+/// Every plugin of a `meta` installation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Registry {
+    pub schema_version: u32,
+    pub plugins: Vec<PluginDescription>,
+}
+
+/// One plugin: identity, metadata, declared requirements and commands.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PluginDescription {
+    pub name: String,
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tagline: Option<String>,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub authors: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub homepage: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    #[serde(default)]
+    pub categories: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update: Option<UpdateSource>,
+    /// [`Capabilities`](crate::Capabilities) names, e.g. `"network"`
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    /// Host version requirement, e.g. `">=2.1"`
+    pub required_host: String,
+    /// [`Feature`](crate::Feature) names the plugin cannot run without
+    #[serde(default)]
+    pub required_features: Vec<String>,
+    #[serde(default)]
+    pub dependencies: Vec<DependencyDescription>,
+    #[serde(default)]
+    pub commands: Vec<CommandDescription>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DependencyDescription {
+    pub name: String,
+    pub version_req: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CommandDescription {
+    pub name: String,
+    #[serde(default)]
+    pub about: String,
+    /// Usage line without the `meta` prefix, e.g. `clone <url>`
+    pub usage: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub args: Vec<ArgDescription>,
+    #[serde(default)]
+    pub subcommands: Vec<CommandDescription>,
+    #[serde(default)]
+    pub hidden: bool,
+    #[serde(default)]
+    pub experimental: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<DeprecationDescription>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ArgDescription {
+    pub name: String,
+    /// `positional`, `flag` or `option`
+    pub kind: String,
+    #[serde(default)]
+    pub help: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub short: Option<char>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DeprecationDescription {
+    pub since: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+}
+
+/// Describe one plugin. Unlike [`docs`](crate::docs), hidden and
+/// experimental commands are included and marked as such.
+pub fn describe(plugin: &dyn Plugin) -> PluginDescription {
+    let metadata = plugin.metadata();
+    let owned = |items: Vec<&str>| items.into_iter().map(str::to_string).collect();
+    PluginDescription {
+        name: plugin.name().to_string(),
+        version: plugin.version().to_string(),
+        tagline: metadata.tagline.map(str::to_string),
+        description: plugin.description().to_string(),
+        authors: owned(metadata.authors),
+        homepage: metadata.homepage.map(str::to_string),
+        repository: metadata.repository.map(str::to_string),
+        license: metadata.license.map(str::to_string),
+        keywords: owned(metadata.keywords),
+        categories: owned(metadata.categories),
+        update: plugin.update_info(),
+        capabilities: owned(plugin.capabilities().names().collect()),
+        required_host: plugin.required_host().to_string(),
+        required_features: owned(
+            plugin
+                .requires_features()
+                .iter()
+                .map(|f| f.name())
+                .collect(),
+        ),
+        dependencies: plugin
+            .dependencies()
+            .into_iter()
+            .map(|d| DependencyDescription {
+                name: d.name.to_string(),
+                version_req: d.version_req.to_string(),
+            })
+            .collect(),
+        commands: plugin
+            .command_specs()
+            .iter()
+            .map(describe_command)
+            .collect(),
+    }
+}
+
+/// Describe every plugin, in the order given.
+pub fn registry<'a>(plugins: impl IntoIterator<Item = &'a dyn Plugin>) -> Registry {
+    Registry {
+        schema_version: DESCRIPTION_SCHEMA_VERSION,
+        plugins: plugins.into_iter().map(describe).collect(),
+    }
+}
+
+/// The [`Registry`] document as pretty-printed JSON.
+pub fn to_json<'a>(plugins: impl IntoIterator<Item = &'a dyn Plugin>) -> String {
+    serde_json::to_string_pretty(&registry(plugins)).expect("registry serializes")
+}
+
+/// JSON Schema of the [`Registry`] document.
+#[cfg(feature = "schema")]
+pub fn json_schema() -> schemars::Schema {
+    schemars::schema_for!(Registry)
+}
+
+fn describe_command(spec: &CommandSpec) -> CommandDescription {
+    CommandDescription {
+        name: spec.name.to_string(),
+        about: spec.about.to_string(),
+        usage: spec.usage_line(),
+        aliases: spec.aliases.iter().map(|a| a.to_string()).collect(),
+        args: spec.args.iter().map(describe_arg).collect(),
+        subcommands: spec.subcommands.iter().map(describe_command).collect(),
+        hidden: spec.hidden,
+        experimental: spec.experimental,
+        deprecated: spec.deprecated.as_ref().map(|d| DeprecationDescription {
+            since: d.since.to_string(),
+            note: d.note.map(str::to_string),
+            replacement: d.replacement.map(str::to_string),
+        }),
+        timeout_ms: spec.timeout.map(|t| t.as_millis() as u64),
+    }
+}
+
+fn describe_arg(arg: &ArgSpec) -> ArgDescription {
+    let kind = match arg.kind {
+        ArgKind::Positional => "positional",
+        ArgKind::Flag => "flag",
+        ArgKind::Option => "option",
+    };
+    ArgDescription {
+        name: arg.name.to_string(),
+        kind: kind.to_string(),
+        help: arg.help.to_string(),
+        short: arg.short,
+        required: arg.required,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Capabilities, PluginContext, PluginMetadata};
+
+    struct Git;
+
+    impl Plugin for Git {
+        fn name(&self) -> &'static str {
+            "git"
+        }
+        fn commands(&self) -> Vec<&'static str> {
+            vec!["clone"]
+        }
+        fn version(&self) -> &'static str {
+            "1.3.0"
+        }
+        fn metadata(&self) -> PluginMetadata {
+            PluginMetadata::new().license("MIT")
+        }
+        fn update_info(&self) -> Option<UpdateSource> {
+            Some(UpdateSource::crates_io("meta-git"))
+        }
+        fn capabilities(&self) -> Capabilities {
+            Capabilities::NETWORK
+        }
+        fn command_specs(&self) -> Vec<CommandSpec> {
+            vec![CommandSpec::new("clone")
+                .about("Clone every repo")
+                .arg(ArgSpec::positional("url").required())
+                .arg(ArgSpec::flag("shallow").short('s'))
+                .subcommand(CommandSpec::new("debug").hidden(true))]
+        }
+        fn execute(
+            &self,
+            _command: &str,
+            _args: &[String],
+            _ctx: &PluginContext,
+        ) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_registry_document() {
+        let json: serde_json::Value =
+            serde_json::from_str(&to_json([&Git as &dyn Plugin])).unwrap();
+        assert_eq!(json["schema_version"], DESCRIPTION_SCHEMA_VERSION);
+        let plugin = &json["plugins"][0];
+        assert_eq!(plugin["name"], "git");
+        assert_eq!(plugin["license"], "MIT");
+        assert!(plugin.get("homepage").is_none());
+        assert_eq!(plugin["capabilities"], serde_json::json!(["network"]));
+        assert_eq!(plugin["required_host"], "*");
+        assert_eq!(plugin["update"]["kind"], "crates_io");
+
+        let clone = &plugin["commands"][0];
+        assert_eq!(clone["usage"], "clone <url> [--shallow]");
+        assert_eq!(
+            clone["args"][1],
+            serde_json::json!({
+                "name": "shallow",
+                "kind": "flag",
+                "help": "",
+                "short": "s",
+                "required": false
+            })
+        );
+        assert_eq!(clone["subcommands"][0]["hidden"], true);
+
+        let parsed: Registry = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, registry([&Git as &dyn Plugin]));
+    }
+
+    #[cfg(feature = "schema")]
+    #[test]
+    fn test_json_schema() {
+        let schema = serde_json::to_value(json_schema()).unwrap();
+        assert_eq!(schema["title"], "Registry");
+        assert!(schema["properties"]["plugins"].is_object());
+    }
+}
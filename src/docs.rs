@@ -0,0 +1,240 @@
+//! Reference documentation rendered from a plugin's
+//! [`command_specs`](crate::Plugin::command_specs), for
+//! `meta docs generate`. Hidden and experimental commands are left out.
+
+use std::fmt::Write as _;
+
+use crate::{CommandSpec, Plugin};
+
+/// A Markdown page for `plugin`: its description, then one section per
+/// command with usage, aliases, deprecation notice and an argument table.
+pub fn markdown(plugin: &dyn Plugin) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# {}\n", plugin.name());
+    if !plugin.description().is_empty() {
+        let _ = writeln!(out, "{}\n", plugin.description());
+    }
+    let _ = writeln!(out, "Version {}", plugin.version());
+    for (path, spec) in documented(plugin) {
+        let _ = writeln!(out, "\n## `{}`\n", path);
+        if !spec.about.is_empty() {
+            let _ = writeln!(out, "{}\n", spec.about);
+        }
+        let _ = writeln!(out, "```text\n{}\n```", usage(&path, &spec));
+        if let Some(warning) = spec.deprecation_warning() {
+            let warning = warning.trim_start_matches("warning: ");
+            let _ = writeln!(out, "\n> **Deprecated:** {}", warning);
+        }
+        if !spec.aliases.is_empty() {
+            let aliases: Vec<String> = spec.aliases.iter().map(|a| format!("`{}`", a)).collect();
+            let _ = writeln!(out, "\nAliases: {}", aliases.join(", "));
+        }
+        if !spec.args.is_empty() {
+            let _ = writeln!(out, "\n| Argument | Description |\n| --- | --- |");
+            for arg in &spec.args {
+                let _ = writeln!(
+                    out,
+                    "| `{}` | {} |",
+                    arg.usage(),
+                    arg.help.replace('|', "\\|")
+                );
+            }
+        }
+    }
+    out
+}
+
+/// A section 1 man page (roff) named `meta-<plugin>`.
+pub fn man_page(plugin: &dyn Plugin) -> String {
+    let name = format!("meta-{}", plugin.name());
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        ".TH {} 1 \"\" \"{} {}\" \"meta plugins\"",
+        roff(&name.to_uppercase()),
+        roff(plugin.name()),
+        roff(plugin.version())
+    );
+    let _ = writeln!(out, ".SH NAME");
+    match plugin.description() {
+        "" => {
+            let _ = writeln!(out, "{}", roff(&name));
+        }
+        description => {
+            let _ = writeln!(out, "{} \\- {}", roff(&name), roff(description));
+        }
+    }
+
+    let _ = writeln!(out, ".SH COMMANDS");
+    for (path, spec) in documented(plugin) {
+        let _ = writeln!(out, ".TP\n.B {}", roff(&usage(&path, &spec)));
+        if !spec.about.is_empty() {
+            let _ = writeln!(out, "{}", roff(spec.about));
+        }
+        if let Some(warning) = spec.deprecation_warning() {
+            let _ = writeln!(
+                out,
+                ".br\n{}",
+                roff(warning.trim_start_matches("warning: "))
+            );
+        }
+        if !spec.aliases.is_empty() {
+            let _ = writeln!(out, ".br\nAliases: {}", roff(&spec.aliases.join(", ")));
+        }
+        if !spec.args.is_empty() {
+            let _ = writeln!(out, ".RS");
+            for arg in &spec.args {
+                let _ = writeln!(out, ".TP\n.B {}\n{}", roff(&arg.usage()), roff(arg.help));
+            }
+            let _ = writeln!(out, ".RE");
+        }
+    }
+
+    let metadata = plugin.metadata();
+    if !metadata.authors.is_empty() {
+        let _ = writeln!(out, ".SH AUTHORS");
+        for author in &metadata.authors {
+            let _ = writeln!(out, "{}\n.br", roff(author));
+        }
+    }
+    let links: Vec<&str> = metadata
+        .homepage
+        .into_iter()
+        .chain(metadata.repository)
+        .collect();
+    if !links.is_empty() {
+        let _ = writeln!(out, ".SH SEE ALSO");
+        for link in links {
+            let _ = writeln!(out, "{}\n.br", roff(link));
+        }
+    }
+    out
+}
+
+/// Listed commands depth-first, each with its full command path.
+fn documented(plugin: &dyn Plugin) -> Vec<(String, CommandSpec)> {
+    fn walk(parent: &str, specs: Vec<CommandSpec>, out: &mut Vec<(String, CommandSpec)>) {
+        for mut spec in specs.into_iter().filter(CommandSpec::is_listed) {
+            let path = if parent.is_empty() {
+                spec.name.to_string()
+            } else {
+                format!("{} {}", parent, spec.name)
+            };
+            let subcommands = std::mem::take(&mut spec.subcommands);
+            out.push((path.clone(), spec));
+            walk(&path, subcommands, out);
+        }
+    }
+    let mut out = Vec::new();
+    walk("", plugin.command_specs(), &mut out);
+    out
+}
+
+/// `meta <path> <args>`, reusing an explicit usage string when given.
+fn usage(path: &str, spec: &CommandSpec) -> String {
+    let line = spec.usage_line();
+    let args = line.strip_prefix(spec.name).unwrap_or(&line).trim_start();
+    if args.is_empty() {
+        format!("meta {}", path)
+    } else {
+        format!("meta {} {}", path, args)
+    }
+}
+
+/// Escape text for roff: backslashes, hyphens, and a leading control
+/// character.
+fn roff(text: &str) -> String {
+    let escaped = text.replace('\\', "\\e").replace('-', "\\-");
+    if escaped.starts_with(['.', '\'']) {
+        format!("\\&{}", escaped)
+    } else {
+        escaped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArgSpec, Deprecation, PluginContext, PluginMetadata};
+
+    struct Release;
+
+    impl Plugin for Release {
+        fn name(&self) -> &'static str {
+            "release"
+        }
+        fn commands(&self) -> Vec<&'static str> {
+            vec!["release"]
+        }
+        fn version(&self) -> &'static str {
+            "2.1.0"
+        }
+        fn description(&self) -> &'static str {
+            "Cut releases across repos"
+        }
+        fn metadata(&self) -> PluginMetadata {
+            PluginMetadata::new()
+                .author("Ada <ada@example.com>")
+                .homepage("https://example.com/release")
+        }
+        fn command_specs(&self) -> Vec<CommandSpec> {
+            vec![
+                CommandSpec::new("release")
+                    .about("Manage releases")
+                    .subcommand(
+                        CommandSpec::new("prepare")
+                            .about("Bump versions")
+                            .arg(ArgSpec::positional("version").required().help("e.g. 1.2.0"))
+                            .arg(ArgSpec::flag("dry-run").help("Print the plan | exit")),
+                    )
+                    .subcommand(CommandSpec::new("internal").hidden(true)),
+                CommandSpec::new("bump")
+                    .alias("b")
+                    .deprecated(Deprecation::new("2.0").replacement("release prepare")),
+            ]
+        }
+        fn execute(
+            &self,
+            _command: &str,
+            _args: &[String],
+            _ctx: &PluginContext,
+        ) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_markdown() {
+        assert_eq!(
+            markdown(&Release),
+            "# release\n\n\
+             Cut releases across repos\n\n\
+             Version 2.1.0\n\
+             \n## `release`\n\nManage releases\n\n```text\nmeta release\n```\n\
+             \n## `release prepare`\n\nBump versions\n\n\
+             ```text\nmeta release prepare <version> [--dry-run]\n```\n\
+             \n| Argument | Description |\n| --- | --- |\n\
+             | `<version>` | e.g. 1.2.0 |\n\
+             | `[--dry-run]` | Print the plan \\| exit |\n\
+             \n## `bump`\n\n```text\nmeta bump\n```\n\
+             \n> **Deprecated:** 'bump' is deprecated since 2.0; use 'release prepare' instead\n\
+             \nAliases: `b`\n"
+        );
+    }
+
+    #[test]
+    fn test_man_page() {
+        let page = man_page(&Release);
+        assert!(page.starts_with(
+            ".TH META\\-RELEASE 1 \"\" \"release 2.1.0\" \"meta plugins\"\n\
+             .SH NAME\nmeta\\-release \\- Cut releases across repos\n.SH COMMANDS\n"
+        ));
+        assert!(page.contains(
+            ".TP\n.B meta release prepare <version> [\\-\\-dry\\-run]\nBump versions\n.RS\n"
+        ));
+        assert!(!page.contains("internal"));
+        assert!(page.contains(".SH AUTHORS\nAda <ada@example.com>\n.br\n"));
+        assert!(page.ends_with(".SH SEE ALSO\nhttps://example.com/release\n.br\n"));
+        assert_eq!(roff(".hidden"), "\\&.hidden");
+    }
+}
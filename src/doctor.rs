@@ -0,0 +1,147 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::PluginContext;
+
+/// How serious a [`Diagnostic`] is. Ordered, so the worst of a list is
+/// its maximum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Worth knowing, e.g. which version of a tool was found
+    Info,
+    /// The plugin works, but something will bite later
+    Warning,
+    /// Some commands of the plugin cannot work
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// One finding of [`Plugin::health_check`](crate::Plugin::health_check),
+/// shown by `meta doctor`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Stable kebab-case identifier, e.g. `git-not-found`, for docs and
+    /// scripts
+    pub code: String,
+    pub message: String,
+    /// What the user should do about it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remediation: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, code: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            code: code.into(),
+            message: message.into(),
+            remediation: None,
+        }
+    }
+
+    pub fn info(code: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(Severity::Info, code, message)
+    }
+
+    pub fn warning(code: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, code, message)
+    }
+
+    pub fn error(code: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, code, message)
+    }
+
+    pub fn remediation(mut self, remediation: impl Into<String>) -> Self {
+        self.remediation = Some(remediation.into());
+        self
+    }
+
+    /// An error unless `program` is on the context's `PATH`.
+    pub fn require_binary(ctx: &PluginContext, program: &str) -> Option<Self> {
+        match ctx.env().which(program) {
+            Some(_) => None,
+            None => Some(
+                Self::error(
+                    format!("{}-not-found", program),
+                    format!("'{}' was not found on PATH", program),
+                )
+                .remediation(format!("Install {} or add it to PATH", program)),
+            ),
+        }
+    }
+
+    /// An error unless the environment variable `var` is set and not
+    /// empty, e.g. an API token.
+    pub fn require_env(ctx: &PluginContext, var: &str) -> Option<Self> {
+        match ctx.env().get(var) {
+            Some(value) if !value.is_empty() => None,
+            _ => Some(
+                Self::error(
+                    format!("{}-missing", var.to_lowercase().replace('_', "-")),
+                    format!("{} is not set", var),
+                )
+                .remediation(format!("Export {} in your shell profile", var)),
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    /// `error[git-not-found]: 'git' was not found on PATH`, followed by
+    /// an indented `help:` line when there is a remediation.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]: {}", self.severity, self.code, self.message)?;
+        if let Some(remediation) = &self.remediation {
+            write!(f, "\n  help: {}", remediation)?;
+        }
+        Ok(())
+    }
+}
+
+/// The highest severity among `diagnostics`, or `None` when there are
+/// none. `meta doctor` fails when this is [`Severity::Error`].
+pub fn worst_severity(diagnostics: &[Diagnostic]) -> Option<Severity> {
+    diagnostics.iter().map(|d| d.severity).max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Env;
+
+    #[test]
+    fn test_prerequisite_checks() {
+        let ctx = PluginContext::new("/work", "/work", "1.0.0")
+            .with_env(Env::new().with_var("GITHUB_TOKEN", "").with_path([]));
+        let diagnostics: Vec<_> = [
+            Diagnostic::require_binary(&ctx, "git"),
+            Diagnostic::require_env(&ctx, "GITHUB_TOKEN"),
+            Some(Diagnostic::info("git-version", "git 2.45")),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        assert_eq!(diagnostics[0].code, "git-not-found");
+        assert_eq!(
+            diagnostics[1].to_string(),
+            "error[github-token-missing]: GITHUB_TOKEN is not set\n\
+             \x20 help: Export GITHUB_TOKEN in your shell profile"
+        );
+        assert_eq!(worst_severity(&diagnostics), Some(Severity::Error));
+        assert_eq!(worst_severity(&diagnostics[2..]), Some(Severity::Info));
+        assert_eq!(worst_severity(&[]), None);
+    }
+}
@@ -0,0 +1,128 @@
+use std::path::{Path, PathBuf};
+
+use crate::{Env, PluginError};
+
+/// What [`PluginContext::open_in_editor`](crate::PluginContext::open_in_editor)
+/// opens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditTarget {
+    /// An existing file, edited in place
+    Path(PathBuf),
+    /// Text edited in a temporary file called `name`, e.g.
+    /// `COMMIT_EDITMSG`, so editors pick the right syntax
+    Buffer { name: String, text: String },
+}
+
+impl EditTarget {
+    pub fn buffer(name: impl Into<String>, text: impl Into<String>) -> Self {
+        EditTarget::Buffer {
+            name: name.into(),
+            text: text.into(),
+        }
+    }
+}
+
+impl From<PathBuf> for EditTarget {
+    fn from(path: PathBuf) -> Self {
+        EditTarget::Path(path)
+    }
+}
+
+impl From<&Path> for EditTarget {
+    fn from(path: &Path) -> Self {
+        EditTarget::Path(path.to_path_buf())
+    }
+}
+
+/// The result of an edit. An unchanged buffer usually means the user
+/// wants to abort, as with an empty commit message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditedContent {
+    pub text: String,
+    pub changed: bool,
+    /// The file that was edited; for buffers, a temporary file that is
+    /// removed when the command ends
+    pub path: PathBuf,
+}
+
+/// The editor to run, split into program and arguments: `$VISUAL`, then
+/// `$EDITOR`, then `notepad` on Windows and `vi` elsewhere.
+pub fn editor_command(env: &Env) -> Vec<String> {
+    const FALLBACK: &str = if cfg!(windows) { "notepad" } else { "vi" };
+    let configured = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| env.get(var))
+        .map(split_command)
+        .find(|words| !words.is_empty());
+    configured.unwrap_or_else(|| vec![FALLBACK.to_string()])
+}
+
+/// Split an editor setting such as `code --wait` into words. Single and
+/// double quotes group words, so `"C:\Program Files\Vim\gvim.exe" -f`
+/// works; backslashes are kept as they are.
+pub(crate) fn split_command(command: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    for c in command.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => word.get_or_insert_with(String::new).push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    words
+}
+
+/// Run the editor on `path` and wait for it to exit.
+pub(crate) fn run_editor(env: &Env, path: &Path) -> Result<(), PluginError> {
+    let words = editor_command(env);
+    let failed = |message: String| PluginError::Editor {
+        editor: words.join(" "),
+        message,
+    };
+    // Resolve through PATH so `code` finds `code.cmd` on Windows.
+    let program = env
+        .which(&words[0])
+        .unwrap_or_else(|| PathBuf::from(&words[0]));
+    let status = env
+        .command(program)
+        .args(&words[1..])
+        .arg(path)
+        .status()
+        .map_err(|e| failed(e.to_string()))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(failed(format!("exited with {}", status)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_editor_command() {
+        let env = Env::new().with_var("EDITOR", "nano");
+        assert_eq!(editor_command(&env), ["nano"]);
+        let env = env.with_var("VISUAL", "code --wait");
+        assert_eq!(editor_command(&env), ["code", "--wait"]);
+        let env = Env::new().with_var("VISUAL", r#""C:\Program Files\Vim\gvim.exe" -f"#);
+        assert_eq!(
+            editor_command(&env),
+            [r"C:\Program Files\Vim\gvim.exe", "-f"]
+        );
+        let fallback = if cfg!(windows) { "notepad" } else { "vi" };
+        assert_eq!(
+            editor_command(&Env::new().with_var("EDITOR", " ")),
+            [fallback]
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Capabilities;
+
+/// Variables every plugin may see: locations, locale and terminal
+/// settings, but nothing that grants access to anything.
+const BASE_VARS: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "USERNAME",
+    "LOGNAME",
+    "SHELL",
+    "TERM",
+    "COLORTERM",
+    "NO_COLOR",
+    "LANG",
+    "LANGUAGE",
+    "TZ",
+    "TMPDIR",
+    "TEMP",
+    "TMP",
+    "PAGER",
+    "EDITOR",
+    "VISUAL",
+    "XDG_CONFIG_HOME",
+    "XDG_CACHE_HOME",
+    "XDG_DATA_HOME",
+    "XDG_STATE_HOME",
+    "SYSTEMROOT",
+    "USERPROFILE",
+    "APPDATA",
+    "LOCALAPPDATA",
+    "PATHEXT",
+    "META_OFFLINE",
+];
+
+/// Proxy and CA settings, for plugins granted [`Capabilities::NETWORK`].
+const NETWORK_VARS: &[&str] = &["HTTP_PROXY", "HTTPS_PROXY", "NO_PROXY", "SSL_CERT_FILE"];
+
+/// The environment a plugin command runs with, as decided by the host.
+///
+/// Plugins should read variables from
+/// [`PluginContext::env`](crate::PluginContext::env) rather than
+/// `std::env`, and start child processes through [`Env::command`], so the
+/// host can inject per-repo or per-profile variables and tests can run
+/// hermetically.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Env {
+    vars: BTreeMap<String, String>,
+}
+
+impl Env {
+    /// An empty environment.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The current process environment. Variables that are not valid
+    /// UTF-8 are skipped.
+    pub fn from_process() -> Self {
+        Self {
+            vars: std::env::vars_os()
+                .filter_map(|(k, v)| Some((k.into_string().ok()?, v.into_string().ok()?)))
+                .collect(),
+        }
+    }
+
+    pub fn with_var(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.vars.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn without_var(mut self, key: &str) -> Self {
+        self.vars.remove(key);
+        self
+    }
+
+    /// Replace `PATH`, e.g. with a sanitized list of directories.
+    pub fn with_path(self, dirs: impl IntoIterator<Item = PathBuf>) -> Self {
+        match std::env::join_paths(dirs) {
+            Ok(path) => self.with_var("PATH", path.to_string_lossy()),
+            // A directory containing the separator cannot be expressed.
+            Err(_) => self,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.vars.get(key).map(String::as_str)
+    }
+
+    /// All variables, sorted by name.
+    pub fn vars(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.vars.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Directories in `PATH`, in search order.
+    pub fn path(&self) -> Vec<PathBuf> {
+        self.get("PATH")
+            .map(|path| std::env::split_paths(path).collect())
+            .unwrap_or_default()
+    }
+
+    /// The first executable named `program` in [`path`](Self::path),
+    /// trying `program.exe` and `program.cmd` too on Windows.
+    pub fn which(&self, program: &str) -> Option<PathBuf> {
+        let names: &[String] = &[
+            program.to_string(),
+            #[cfg(windows)]
+            format!("{}.exe", program),
+            #[cfg(windows)]
+            format!("{}.cmd", program),
+        ];
+        self.path()
+            .into_iter()
+            .flat_map(|dir| names.iter().map(move |name| dir.join(name)))
+            .find(|candidate| candidate.is_file())
+    }
+
+    /// The part of this environment a plugin granted only `granted` may
+    /// see: locations, locale and terminal settings, proxy settings with
+    /// [`Capabilities::NETWORK`], and `META_SECRET_*` variables with
+    /// [`Capabilities::CREDENTIALS`]. Everything else, such as cloud
+    /// credentials or tokens in other variables, is left out.
+    pub fn restricted_to(&self, granted: Capabilities) -> Self {
+        let allowed = |key: &str| {
+            BASE_VARS.iter().any(|v| v.eq_ignore_ascii_case(key))
+                || key.starts_with("LC_")
+                || (granted.contains(Capabilities::NETWORK)
+                    && NETWORK_VARS.iter().any(|v| v.eq_ignore_ascii_case(key)))
+                || (granted.contains(Capabilities::CREDENTIALS) && key.starts_with("META_SECRET_"))
+        };
+        Self {
+            vars: self
+                .vars
+                .iter()
+                .filter(|(key, _)| allowed(key))
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+        }
+    }
+
+    /// A [`Command`] for `program` that sees exactly this environment.
+    pub fn command(&self, program: impl AsRef<std::ffi::OsStr>) -> Command {
+        let mut command = Command::new(program);
+        command.env_clear().envs(&self.vars);
+        command
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_accessors() {
+        let env = Env::new()
+            .with_var("HOME", "/home/ada")
+            .with_var("TOKEN", "secret")
+            .without_var("TOKEN")
+            .with_path([PathBuf::from("/usr/bin"), PathBuf::from("/bin")]);
+        assert_eq!(env.get("HOME"), Some("/home/ada"));
+        assert_eq!(env.get("TOKEN"), None);
+        assert_eq!(
+            env.path(),
+            vec![PathBuf::from("/usr/bin"), PathBuf::from("/bin")]
+        );
+        assert_eq!(env.which("meta-no-such-tool"), None);
+        let exe = std::env::current_exe().unwrap();
+        let found = Env::new()
+            .with_path([exe.parent().unwrap().to_path_buf()])
+            .which(exe.file_name().unwrap().to_str().unwrap());
+        assert_eq!(found, Some(exe));
+        let names: Vec<_> = env.vars().map(|(k, _)| k).collect();
+        assert_eq!(names, ["HOME", "PATH"]);
+        assert_eq!(
+            serde_json::from_str::<Env>(&serde_json::to_string(&env).unwrap()).unwrap(),
+            env
+        );
+    }
+
+    #[test]
+    fn test_restricted_to_capabilities() {
+        let env = Env::new()
+            .with_var("PATH", "/usr/bin")
+            .with_var("LC_ALL", "C")
+            .with_var("https_proxy", "http://proxy:3128")
+            .with_var("META_SECRET_GITHUB_TOKEN", "ghp")
+            .with_var("AWS_SECRET_ACCESS_KEY", "aws");
+        let names = |env: Env| env.vars().map(|(k, _)| k.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            names(env.restricted_to(Capabilities::empty())),
+            ["LC_ALL", "PATH"]
+        );
+        assert_eq!(
+            names(env.restricted_to(Capabilities::NETWORK | Capabilities::CREDENTIALS)),
+            ["LC_ALL", "META_SECRET_GITHUB_TOKEN", "PATH", "https_proxy"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command_is_hermetic() {
+        let env = Env::new().with_var("GREETING", "hi");
+        let output = env
+            .command("/bin/sh")
+            .args(["-c", "echo \"$GREETING:$HOME\""])
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "hi:\n");
+    }
+}
@@ -0,0 +1,204 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum PluginError {
+    #[error("Failed to load plugin: {0}")]
+    LoadError(String),
+    #[error("Command not found: {0}")]
+    CommandNotFound(String),
+    #[error("Plugin API version mismatch: host uses v{host}, plugin was built against v{plugin}")]
+    VersionMismatch { host: u32, plugin: u32 },
+    /// Exit the process with this code; see [`CommandOutcome::from_result`](crate::CommandOutcome::from_result)
+    #[error("Command exited with code {0}")]
+    ExitCode(i32),
+    #[error("Invalid arguments for '{command}': {reason}")]
+    InvalidArguments { command: String, reason: String },
+    #[error("Missing required argument {argument} for '{command}'\n\nUsage: meta {usage}")]
+    MissingArgument {
+        command: String,
+        /// Usage fragment of the missing argument, e.g. `<url>`
+        argument: String,
+        usage: String,
+    },
+    #[error("Command '{command}' failed: {source}")]
+    ExecutionFailed {
+        command: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("Incompatible host: {0}")]
+    IncompatibleHost(String),
+    #[error("Configuration error: {0}")]
+    ConfigError(String),
+    #[error("Operation cancelled")]
+    Cancelled,
+    #[error("Command timed out after {0:?}")]
+    TimedOut(std::time::Duration),
+    #[error("Plugin '{plugin}' requires '{dependency}' {requirement}, which {reason}")]
+    UnresolvedDependency {
+        plugin: String,
+        dependency: String,
+        requirement: String,
+        reason: String,
+    },
+    #[error("Command aborted by plugin '{plugin}': {reason}")]
+    HookAborted { plugin: String, reason: String },
+    /// A plugin method panicked; the panic was caught at the boundary
+    #[error("Plugin '{plugin}' panicked: {payload}")]
+    Panicked { plugin: String, payload: String },
+    #[error("Project '{name}' is not cloned at {}", path.display())]
+    RepoMissing {
+        name: String,
+        path: std::path::PathBuf,
+    },
+    /// Some repos of a multi-repo run failed; `failures` holds each
+    /// repo's name and error message
+    #[error("{} of {total} repos failed:{}", failures.len(), failure_lines(failures))]
+    ReposFailed {
+        total: usize,
+        failures: Vec<(String, String)>,
+    },
+    #[error(
+        "Command '{0}' is experimental; pass --experimental or set META_EXPERIMENTAL=1 to run it"
+    )]
+    Experimental(String),
+    #[error("Host does not provide {0}")]
+    Unavailable(String),
+    /// A [`Prompter`](crate::Prompter) question had no default to fall back on
+    #[error("Cannot ask '{0}' without an interactive terminal")]
+    NonInteractive(String),
+    /// A plugin artifact is unsigned, tampered with, or signed by an
+    /// untrusted key; see [`SignedManifest`](crate::SignedManifest)
+    #[error("Plugin signature invalid: {0}")]
+    SignatureInvalid(String),
+    /// The host's [`SandboxProfile`](crate::SandboxProfile) withholds
+    /// `capability`, named as in [`Capabilities`](crate::Capabilities)
+    #[error("Capability '{capability}' denied: {action}")]
+    CapabilityDenied { capability: String, action: String },
+    /// Another command holds the [`LockScope`](crate::LockScope) named here
+    #[error("Lock '{0}' is held by another command")]
+    LockContended(String),
+    /// A [`GitOps`](crate::GitOps) operation failed; `command` is the git
+    /// subcommand, e.g. `fetch`
+    #[error("git {command} failed: {message}")]
+    Git { command: String, message: String },
+    /// A server answered with a non-2xx status; see
+    /// [`HttpResponse::error_for_status`](crate::HttpResponse::error_for_status)
+    #[error("HTTP {status} from {url}")]
+    Http { status: u16, url: String },
+    /// No [`SecretsProvider`](crate::SecretsProvider) can persist secrets,
+    /// e.g. without a keychain
+    #[error("Cannot store secrets: {0}")]
+    SecretStoreUnavailable(String),
+    /// The secret store exists but refused, e.g. a locked keychain
+    #[error("Secret store '{backend}' failed: {message}")]
+    SecretStoreFailed { backend: String, message: String },
+    /// [`PluginContext::open_in_editor`](crate::PluginContext::open_in_editor)
+    /// could not run `editor`, or it exited unsuccessfully
+    #[error("Editor '{editor}' failed: {message}")]
+    Editor { editor: String, message: String },
+    /// The host runs in read-only mode; see
+    /// [`PluginContext::assert_writable`](crate::PluginContext::assert_writable)
+    #[error("Workspace is read-only; this command would modify it")]
+    ReadOnly,
+    /// A repo named on stdin or the command line is not in `.meta`
+    #[error("No project named '{0}' in this workspace")]
+    UnknownProject(String),
+    /// A task started through [`Spawner`](crate::Spawner) panicked
+    #[error("Task '{task}' panicked: {payload}")]
+    TaskPanicked { task: String, payload: String },
+    /// Sync plugin code tried to wait for async work on a thread of a
+    /// current-thread runtime, which would deadlock
+    #[error(
+        "Cannot block on async work inside a current-thread runtime; call execute from spawn_blocking"
+    )]
+    NestedRuntime,
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl PluginError {
+    pub fn invalid_arguments(command: impl Into<String>, reason: impl Into<String>) -> Self {
+        PluginError::InvalidArguments {
+            command: command.into(),
+            reason: reason.into(),
+        }
+    }
+
+    pub fn execution_failed(command: impl Into<String>, source: impl Into<anyhow::Error>) -> Self {
+        PluginError::ExecutionFailed {
+            command: command.into(),
+            source: source.into().into(),
+        }
+    }
+
+    pub fn config(message: impl Into<String>) -> Self {
+        PluginError::ConfigError(message.into())
+    }
+
+    /// Build [`PluginError::Panicked`] from a payload returned by
+    /// `catch_unwind`.
+    pub fn panicked(plugin: impl Into<String>, payload: &(dyn std::any::Any + Send)) -> Self {
+        PluginError::Panicked {
+            plugin: plugin.into(),
+            payload: panic_message(payload),
+        }
+    }
+
+    /// Find the first `PluginError` in an `anyhow` error chain, so hosts
+    /// can branch on the kind of failure returned by `execute`.
+    pub fn find(err: &anyhow::Error) -> Option<&PluginError> {
+        err.chain()
+            .find_map(|cause| cause.downcast_ref::<PluginError>())
+    }
+}
+
+fn failure_lines(failures: &[(String, String)]) -> String {
+    failures
+        .iter()
+        .map(|(repo, error)| format!("\n  {}: {}", repo, error))
+        .collect()
+}
+
+/// The message of a panic payload returned by `catch_unwind`.
+pub(crate) fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic payload".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_error_helpers() {
+        let err = PluginError::invalid_arguments("clone", "missing <url>");
+        assert_eq!(
+            err.to_string(),
+            "Invalid arguments for 'clone': missing <url>"
+        );
+
+        let err = PluginError::execution_failed("push", anyhow::anyhow!("remote rejected"));
+        assert_eq!(err.to_string(), "Command 'push' failed: remote rejected");
+        assert!(std::error::Error::source(&err).is_some());
+
+        let err: PluginError = std::io::Error::new(std::io::ErrorKind::NotFound, "gone").into();
+        assert!(matches!(err, PluginError::Io(_)));
+    }
+
+    #[test]
+    fn test_find_in_anyhow_chain() {
+        let result: anyhow::Result<()> =
+            Err(PluginError::config("bad key")).context("loading configuration");
+        let err = result.unwrap_err();
+        assert!(matches!(
+            PluginError::find(&err),
+            Some(PluginError::ConfigError(_))
+        ));
+        assert!(PluginError::find(&anyhow::anyhow!("plain")).is_none());
+    }
+}
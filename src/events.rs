@@ -0,0 +1,77 @@
+use crate::ProjectInfo;
+
+/// Multi-repo git operations performed by the meta host that plugins can
+/// react to via [`Plugin::on_repo_event`](crate::Plugin::on_repo_event).
+///
+/// An error returned for a `Pre*` event aborts the operation for that
+/// repo; errors for `Post*` events are reported as warnings only.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RepoEvent {
+    /// The repo was just cloned into the workspace
+    PostClone { repo: ProjectInfo },
+    /// The repo was just fetched/pulled
+    PostUpdate { repo: ProjectInfo },
+    /// A branch was just checked out
+    PostCheckout { repo: ProjectInfo, branch: String },
+    /// A push is about to happen
+    PrePush {
+        repo: ProjectInfo,
+        remote: String,
+        branch: Option<String>,
+    },
+    /// A push completed
+    PostPush {
+        repo: ProjectInfo,
+        remote: String,
+        branch: Option<String>,
+    },
+}
+
+impl RepoEvent {
+    /// Stable snake_case name of the event, e.g. `post_clone`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            RepoEvent::PostClone { .. } => "post_clone",
+            RepoEvent::PostUpdate { .. } => "post_update",
+            RepoEvent::PostCheckout { .. } => "post_checkout",
+            RepoEvent::PrePush { .. } => "pre_push",
+            RepoEvent::PostPush { .. } => "post_push",
+        }
+    }
+
+    /// The repo the event concerns.
+    pub fn repo(&self) -> &ProjectInfo {
+        match self {
+            RepoEvent::PostClone { repo }
+            | RepoEvent::PostUpdate { repo }
+            | RepoEvent::PostCheckout { repo, .. }
+            | RepoEvent::PrePush { repo, .. }
+            | RepoEvent::PostPush { repo, .. } => repo,
+        }
+    }
+
+    /// Whether a handler error should abort the operation.
+    pub fn is_pre(&self) -> bool {
+        matches!(self, RepoEvent::PrePush { .. })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_accessors() {
+        let repo = ProjectInfo::new("api", "api", "git@x:api.git");
+        let event = RepoEvent::PrePush {
+            repo: repo.clone(),
+            remote: "origin".to_string(),
+            branch: Some("main".to_string()),
+        };
+        assert_eq!(event.name(), "pre_push");
+        assert_eq!(event.repo(), &repo);
+        assert!(event.is_pre());
+        assert!(!RepoEvent::PostClone { repo }.is_pre());
+    }
+}
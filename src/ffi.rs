@@ -0,0 +1,925 @@
+//! FFI-safe plugin interface.
+//!
+//! `*mut dyn Plugin` relies on the Rust ABI, which is only stable when host
+//! and plugin are built by the same compiler. This module defines a
+//! `#[repr(C)]` vtable that can be exchanged between libraries built with
+//! different toolchains:
+//!
+//! * the plugin side wraps a `Box<dyn Plugin>` with [`FfiPlugin::new`]
+//!   (done automatically by [`declare_plugin!`](crate::declare_plugin), which
+//!   exports `_plugin_create_ffi`);
+//! * the host side turns the returned [`FfiPlugin`] back into a
+//!   `dyn Plugin` with [`FfiPluginProxy::from_raw`].
+//!
+//! Besides commands, help and the load hooks, the vtable carries the
+//! plugin's [`required_host`](Plugin::required_host) and
+//! [`requires_features`](Plugin::requires_features), so the host can
+//! refuse an incompatible plugin; the rest of the [`Plugin`] trait keeps
+//! its defaults. Only the core of [`PluginContext`] (workspace root, cwd,
+//! host version, projects, the read-only and dry-run flags, the granted
+//! capabilities, the cancellation flag and the output sink) crosses this
+//! boundary, along with the host's sandbox profile at load time.
+
+use std::ffi::c_void;
+use std::mem::ManuallyDrop;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+use crate::error::panic_message;
+use crate::{
+    check_compatibility, CancellationToken, Capabilities, ExecutionMode, Feature, HelpBody,
+    HelpMode, HostFeatures, HostInfo, OutputSink, OutputStream, Plugin, PluginContext, PluginError,
+    ProjectInfo, SandboxProfile,
+};
+
+/// Name of the FFI constructor symbol emitted by [`declare_plugin!`](crate::declare_plugin).
+pub const FFI_PLUGIN_CREATE_SYMBOL: &[u8] = b"_plugin_create_ffi";
+
+/// Signature of the `_plugin_create_ffi` symbol. A null `this` pointer
+/// means the plugin constructor failed or panicked, with the reason in
+/// [`FfiPlugin::error`].
+pub type FfiPluginCreate = unsafe extern "C" fn() -> FfiPlugin;
+
+/// Borrowed UTF-8 string.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct FfiStr {
+    pub ptr: *const u8,
+    pub len: usize,
+}
+
+impl FfiStr {
+    pub fn new(s: &str) -> Self {
+        Self {
+            ptr: s.as_ptr(),
+            len: s.len(),
+        }
+    }
+
+    /// # Safety
+    /// `ptr`/`len` must describe valid UTF-8 that outlives `'a`.
+    pub unsafe fn as_str<'a>(&self) -> &'a str {
+        std::str::from_utf8_unchecked(std::slice::from_raw_parts(self.ptr, self.len))
+    }
+}
+
+/// String owned by the plugin. The host must release it with
+/// [`FfiPluginVTable::free_string`].
+#[repr(C)]
+#[derive(Debug)]
+pub struct FfiString {
+    pub ptr: *mut u8,
+    pub len: usize,
+    pub cap: usize,
+}
+
+impl FfiString {
+    pub fn new(s: String) -> Self {
+        let mut s = ManuallyDrop::new(s);
+        Self {
+            ptr: s.as_mut_ptr(),
+            len: s.len(),
+            cap: s.capacity(),
+        }
+    }
+
+    /// # Safety
+    /// Must have been created by [`FfiString::new`] in the same library.
+    unsafe fn into_string(self) -> String {
+        String::from_raw_parts(self.ptr, self.len, self.cap)
+    }
+
+    fn as_ffi_str(&self) -> FfiStr {
+        FfiStr {
+            ptr: self.ptr,
+            len: self.len,
+        }
+    }
+}
+
+/// List of `'static` strings owned by the plugin. The host must release it
+/// with [`FfiPluginVTable::free_str_list`].
+#[repr(C)]
+#[derive(Debug)]
+pub struct FfiStrList {
+    pub ptr: *mut FfiStr,
+    pub len: usize,
+    pub cap: usize,
+}
+
+impl FfiStrList {
+    fn new(items: Vec<&'static str>) -> Self {
+        let mut items = ManuallyDrop::new(items.into_iter().map(FfiStr::new).collect::<Vec<_>>());
+        Self {
+            ptr: items.as_mut_ptr(),
+            len: items.len(),
+            cap: items.capacity(),
+        }
+    }
+}
+
+/// Outcome of a fallible call. `error` is only meaningful when `ok` is false.
+#[repr(C)]
+#[derive(Debug)]
+pub struct FfiResult {
+    pub ok: bool,
+    pub error: FfiString,
+}
+
+impl FfiResult {
+    fn from_result(result: anyhow::Result<()>) -> Self {
+        match result {
+            Ok(()) => Self {
+                ok: true,
+                error: FfiString::new(String::new()),
+            },
+            Err(e) => Self::error(format!("{:#}", e)),
+        }
+    }
+
+    fn error(message: String) -> Self {
+        Self {
+            ok: false,
+            error: FfiString::new(message),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FfiHelpMode {
+    Override,
+    Prepend,
+    Append,
+    None,
+}
+
+impl From<HelpMode> for FfiHelpMode {
+    fn from(mode: HelpMode) -> Self {
+        match mode {
+            HelpMode::Override => FfiHelpMode::Override,
+            HelpMode::Prepend => FfiHelpMode::Prepend,
+            HelpMode::Append => FfiHelpMode::Append,
+            HelpMode::None => FfiHelpMode::None,
+        }
+    }
+}
+
+impl From<FfiHelpMode> for HelpMode {
+    fn from(mode: FfiHelpMode) -> Self {
+        match mode {
+            FfiHelpMode::Override => HelpMode::Override,
+            FfiHelpMode::Prepend => HelpMode::Prepend,
+            FfiHelpMode::Append => HelpMode::Append,
+            FfiHelpMode::None => HelpMode::None,
+        }
+    }
+}
+
+/// Result of `get_help_output`. `mode`, `text` and `markdown` are only
+/// meaningful when `present` is true.
+#[repr(C)]
+#[derive(Debug)]
+pub struct FfiHelp {
+    pub present: bool,
+    pub mode: FfiHelpMode,
+    pub text: FfiString,
+    /// `text` is Markdown; see [`HelpBody::Markdown`]
+    pub markdown: bool,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct FfiProject {
+    pub name: FfiStr,
+    pub path: FfiStr,
+    pub repo: FfiStr,
+}
+
+/// Borrowed view of a [`PluginContext`], valid for the duration of one call.
+#[repr(C)]
+#[derive(Debug)]
+pub struct FfiContext {
+    pub workspace_root: FfiStr,
+    pub cwd: FfiStr,
+    pub host_version: FfiStr,
+    pub projects: *const FfiProject,
+    pub projects_len: usize,
+    /// Host's cancellation flag; null when the host does not support it
+    pub cancelled: *const AtomicBool,
+    /// See [`PluginContext::is_read_only`]
+    pub read_only: bool,
+    /// See [`PluginContext::is_dry_run`]
+    pub dry_run: bool,
+    /// [`Capabilities::bits`] of
+    /// [`PluginContext::granted_capabilities`]
+    pub granted: u32,
+    /// Opaque handle passed back to `write_output`
+    pub output: *const c_void,
+    /// Writes `len` bytes to the host's stream 1 (stdout) or 2 (stderr);
+    /// `None` when the host has no output sink
+    pub write_output: Option<
+        unsafe extern "C" fn(
+            output: *const c_void,
+            stream: u32,
+            ptr: *const u8,
+            len: usize,
+        ) -> bool,
+    >,
+}
+
+/// Plugin-side [`OutputSink`] calling back into the host.
+struct FfiOutputSink {
+    output: *const c_void,
+    write: unsafe extern "C" fn(*const c_void, u32, *const u8, usize) -> bool,
+}
+
+// SAFETY: the host's sink is `Send + Sync`; `to_context` requires the
+// handle to stay valid while the context is in use.
+unsafe impl Send for FfiOutputSink {}
+unsafe impl Sync for FfiOutputSink {}
+
+impl OutputSink for FfiOutputSink {
+    fn write(&self, stream: OutputStream, bytes: &[u8]) -> std::io::Result<()> {
+        let stream = match stream {
+            OutputStream::Stdout => 1,
+            OutputStream::Stderr => 2,
+        };
+        if unsafe { (self.write)(self.output, stream, bytes.as_ptr(), bytes.len()) } {
+            Ok(())
+        } else {
+            Err(std::io::Error::other("host failed to write plugin output"))
+        }
+    }
+}
+
+/// Host side of [`FfiContext::write_output`]; `output` points at the
+/// host context's `Arc<dyn OutputSink>`.
+unsafe extern "C" fn host_write_output(
+    output: *const c_void,
+    stream: u32,
+    ptr: *const u8,
+    len: usize,
+) -> bool {
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        let sink = &*(output as *const Arc<dyn OutputSink>);
+        let stream = if stream == 2 {
+            OutputStream::Stderr
+        } else {
+            OutputStream::Stdout
+        };
+        sink.write(stream, std::slice::from_raw_parts(ptr, len))
+            .is_ok()
+    }))
+    .unwrap_or(false)
+}
+
+impl FfiContext {
+    /// Rebuild an owned [`PluginContext`] on the plugin side.
+    ///
+    /// # Safety
+    /// All pointers must be valid as described by [`FfiContext`].
+    pub unsafe fn to_context(&self) -> PluginContext {
+        let projects = if self.projects_len == 0 {
+            Vec::new()
+        } else {
+            std::slice::from_raw_parts(self.projects, self.projects_len)
+                .iter()
+                .map(|p| ProjectInfo::new(p.name.as_str(), p.path.as_str(), p.repo.as_str()))
+                .collect()
+        };
+        let ctx = PluginContext::new(
+            self.workspace_root.as_str(),
+            self.cwd.as_str(),
+            self.host_version.as_str(),
+        )
+        .with_projects(projects)
+        .with_read_only(self.read_only)
+        .with_granted_capabilities(Capabilities::from_bits_truncate(self.granted))
+        .with_execution_mode(if self.dry_run {
+            ExecutionMode::DryRun
+        } else {
+            ExecutionMode::Normal
+        });
+        let ctx = match self.write_output {
+            Some(write) if !self.output.is_null() => ctx.with_output(Arc::new(FfiOutputSink {
+                output: self.output,
+                write,
+            })),
+            _ => ctx,
+        };
+        if self.cancelled.is_null() {
+            ctx
+        } else {
+            ctx.with_cancellation(CancellationToken::from_ffi(self.cancelled))
+        }
+    }
+}
+
+/// Function table exported by a plugin. Every function receives the
+/// plugin's opaque `this` pointer.
+#[repr(C)]
+pub struct FfiPluginVTable {
+    /// [`PLUGIN_API_VERSION`](crate::PLUGIN_API_VERSION) the plugin was built against
+    pub api_version: u32,
+    pub name: unsafe extern "C" fn(this: *const c_void) -> FfiStr,
+    pub commands: unsafe extern "C" fn(this: *const c_void) -> FfiStrList,
+    pub execute: unsafe extern "C" fn(
+        this: *const c_void,
+        command: FfiStr,
+        args: *const FfiStr,
+        args_len: usize,
+        ctx: *const FfiContext,
+    ) -> FfiResult,
+    pub get_help_output:
+        unsafe extern "C" fn(this: *const c_void, args: *const FfiStr, args_len: usize) -> FfiHelp,
+    /// `features` are [`Feature::name`]s; names the plugin does not know
+    /// are skipped. `sandbox` is the host's [`SandboxProfile`] as JSON.
+    pub on_load: unsafe extern "C" fn(
+        this: *mut c_void,
+        host_version: FfiStr,
+        features: *const FfiStr,
+        features_len: usize,
+        sandbox: FfiStr,
+    ) -> FfiResult,
+    pub on_unload: unsafe extern "C" fn(this: *mut c_void),
+    pub free_string: unsafe extern "C" fn(s: FfiString),
+    pub free_str_list: unsafe extern "C" fn(list: FfiStrList),
+    pub drop: unsafe extern "C" fn(this: *mut c_void),
+    /// [`Plugin::required_host`] as a version requirement string
+    pub required_host: unsafe extern "C" fn(this: *const c_void) -> FfiString,
+    /// [`Plugin::requires_features`] as [`Feature::name`]s
+    pub requires_features: unsafe extern "C" fn(this: *const c_void) -> FfiStrList,
+}
+
+/// A plugin instance together with its function table.
+#[repr(C)]
+pub struct FfiPlugin {
+    pub this: *mut c_void,
+    pub vtable: *const FfiPluginVTable,
+    /// Why construction failed when `this` is null, empty otherwise;
+    /// released with [`FfiPluginVTable::free_string`]
+    pub error: FfiString,
+}
+
+impl FfiPlugin {
+    /// Wrap a plugin for export across the FFI boundary.
+    pub fn new(plugin: Box<dyn Plugin>) -> Self {
+        Self {
+            this: Box::into_raw(Box::new(plugin)) as *mut c_void,
+            vtable: &VTABLE,
+            error: FfiString::new(String::new()),
+        }
+    }
+
+    /// An instance signalling that construction failed.
+    pub fn null() -> Self {
+        Self::failed(String::new())
+    }
+
+    /// An instance signalling that construction failed because of `error`.
+    pub fn failed(error: String) -> Self {
+        Self {
+            this: std::ptr::null_mut(),
+            vtable: &VTABLE,
+            error: FfiString::new(error),
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.this.is_null() || self.vtable.is_null()
+    }
+}
+
+/// Run a plugin constructor for `_plugin_create_ffi`, converting an
+/// error or a panic into [`FfiPlugin::failed`].
+#[doc(hidden)]
+pub fn create_ffi_plugin<F>(constructor: F) -> FfiPlugin
+where
+    F: FnOnce() -> anyhow::Result<Box<dyn Plugin>> + panic::UnwindSafe,
+{
+    match panic::catch_unwind(constructor) {
+        Ok(Ok(plugin)) => FfiPlugin::new(plugin),
+        Ok(Err(e)) => FfiPlugin::failed(format!("{:#}", e)),
+        Err(payload) => FfiPlugin::failed(format!(
+            "plugin constructor panicked: {}",
+            panic_message(&*payload)
+        )),
+    }
+}
+
+static VTABLE: FfiPluginVTable = FfiPluginVTable {
+    api_version: crate::PLUGIN_API_VERSION,
+    name: shim_name,
+    commands: shim_commands,
+    execute: shim_execute,
+    get_help_output: shim_get_help_output,
+    on_load: shim_on_load,
+    on_unload: shim_on_unload,
+    free_string: shim_free_string,
+    free_str_list: shim_free_str_list,
+    drop: shim_drop,
+    required_host: shim_required_host,
+    requires_features: shim_requires_features,
+};
+
+unsafe fn plugin_ref<'a>(this: *const c_void) -> &'a dyn Plugin {
+    (*(this as *const Box<dyn Plugin>)).as_ref()
+}
+
+unsafe fn plugin_mut<'a>(this: *mut c_void) -> &'a mut dyn Plugin {
+    (*(this as *mut Box<dyn Plugin>)).as_mut()
+}
+
+unsafe fn ffi_args(args: *const FfiStr, args_len: usize) -> Vec<String> {
+    if args_len == 0 {
+        return Vec::new();
+    }
+    std::slice::from_raw_parts(args, args_len)
+        .iter()
+        .map(|a| a.as_str().to_string())
+        .collect()
+}
+
+unsafe extern "C" fn shim_name(this: *const c_void) -> FfiStr {
+    panic::catch_unwind(AssertUnwindSafe(|| FfiStr::new(plugin_ref(this).name())))
+        .unwrap_or_else(|_| FfiStr::new(""))
+}
+
+unsafe extern "C" fn shim_commands(this: *const c_void) -> FfiStrList {
+    let commands =
+        panic::catch_unwind(AssertUnwindSafe(|| plugin_ref(this).commands())).unwrap_or_default();
+    FfiStrList::new(commands)
+}
+
+unsafe extern "C" fn shim_required_host(this: *const c_void) -> FfiString {
+    let required = panic::catch_unwind(AssertUnwindSafe(|| plugin_ref(this).required_host()))
+        .unwrap_or(semver::VersionReq::STAR);
+    FfiString::new(required.to_string())
+}
+
+unsafe extern "C" fn shim_requires_features(this: *const c_void) -> FfiStrList {
+    let features = panic::catch_unwind(AssertUnwindSafe(|| {
+        plugin_ref(this)
+            .requires_features()
+            .iter()
+            .map(Feature::name)
+            .collect()
+    }))
+    .unwrap_or_default();
+    FfiStrList::new(features)
+}
+
+unsafe extern "C" fn shim_execute(
+    this: *const c_void,
+    command: FfiStr,
+    args: *const FfiStr,
+    args_len: usize,
+    ctx: *const FfiContext,
+) -> FfiResult {
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        let args = ffi_args(args, args_len);
+        let ctx = (*ctx).to_context();
+        FfiResult::from_result(plugin_ref(this).execute(command.as_str(), &args, &ctx))
+    }))
+    .unwrap_or_else(|_| FfiResult::error("plugin panicked during execute".to_string()))
+}
+
+unsafe extern "C" fn shim_get_help_output(
+    this: *const c_void,
+    args: *const FfiStr,
+    args_len: usize,
+) -> FfiHelp {
+    let help = panic::catch_unwind(AssertUnwindSafe(|| {
+        plugin_ref(this).get_help_output(&ffi_args(args, args_len))
+    }))
+    .unwrap_or(None);
+    match help {
+        Some((mode, body)) => FfiHelp {
+            present: true,
+            mode: mode.into(),
+            markdown: body.is_markdown(),
+            text: FfiString::new(body.as_str().to_string()),
+        },
+        None => FfiHelp {
+            present: false,
+            mode: FfiHelpMode::None,
+            text: FfiString::new(String::new()),
+            markdown: false,
+        },
+    }
+}
+
+unsafe extern "C" fn shim_on_load(
+    this: *mut c_void,
+    host_version: FfiStr,
+    features: *const FfiStr,
+    features_len: usize,
+    sandbox: FfiStr,
+) -> FfiResult {
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        let result = (|| {
+            let host = HostInfo {
+                version: semver::Version::parse(host_version.as_str())?,
+                features: ffi_args(features, features_len)
+                    .iter()
+                    .filter_map(|name| Feature::from_name(name))
+                    .collect(),
+                sandbox: serde_json::from_str::<SandboxProfile>(sandbox.as_str())?,
+            };
+            plugin_mut(this).on_load(&host)
+        })();
+        FfiResult::from_result(result)
+    }))
+    .unwrap_or_else(|_| FfiResult::error("plugin panicked during on_load".to_string()))
+}
+
+unsafe extern "C" fn shim_on_unload(this: *mut c_void) {
+    let _ = panic::catch_unwind(AssertUnwindSafe(|| plugin_mut(this).on_unload()));
+}
+
+unsafe extern "C" fn shim_free_string(s: FfiString) {
+    drop(s.into_string());
+}
+
+unsafe extern "C" fn shim_free_str_list(list: FfiStrList) {
+    drop(Vec::from_raw_parts(list.ptr, list.len, list.cap));
+}
+
+unsafe extern "C" fn shim_drop(this: *mut c_void) {
+    let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+        drop(Box::from_raw(this as *mut Box<dyn Plugin>))
+    }));
+}
+
+/// Host-side adapter presenting an [`FfiPlugin`] as a `dyn Plugin`.
+///
+/// The strings returned by `name()` and `commands()` point into the plugin
+/// library, so the proxy must be dropped before the library is unloaded.
+pub struct FfiPluginProxy {
+    raw: FfiPlugin,
+    name: &'static str,
+}
+
+impl FfiPluginProxy {
+    /// Take ownership of a plugin returned by `_plugin_create_ffi`.
+    ///
+    /// # Safety
+    /// `raw` must come from a `_plugin_create_ffi` export (or
+    /// [`FfiPlugin::new`]) and the library that produced it must stay loaded
+    /// for as long as the proxy exists.
+    pub unsafe fn from_raw(mut raw: FfiPlugin) -> Result<Self, PluginError> {
+        if raw.vtable.is_null() {
+            return Err(PluginError::LoadError(
+                "plugin constructor failed".to_string(),
+            ));
+        }
+        let vtable = &*raw.vtable;
+        // An empty string owns no allocation, so the placeholder left
+        // behind needs no freeing
+        let error = std::mem::replace(&mut raw.error, FfiString::new(String::new()));
+        let message = error.as_ffi_str().as_str().to_string();
+        (vtable.free_string)(error);
+        if raw.this.is_null() {
+            return Err(PluginError::LoadError(if message.is_empty() {
+                "plugin constructor failed".to_string()
+            } else {
+                message
+            }));
+        }
+        if let Err(e) = check_compatibility(crate::PLUGIN_API_VERSION, vtable.api_version) {
+            (vtable.drop)(raw.this);
+            return Err(e);
+        }
+        let name = (vtable.name)(raw.this).as_str();
+        Ok(Self { raw, name })
+    }
+
+    fn vtable(&self) -> &FfiPluginVTable {
+        unsafe { &*self.raw.vtable }
+    }
+
+    fn take_result(&self, result: FfiResult) -> anyhow::Result<()> {
+        if result.ok {
+            unsafe { (self.vtable().free_string)(result.error) };
+            Ok(())
+        } else {
+            let message = unsafe { result.error.as_ffi_str().as_str().to_string() };
+            unsafe { (self.vtable().free_string)(result.error) };
+            Err(anyhow::anyhow!(message))
+        }
+    }
+}
+
+impl Plugin for FfiPluginProxy {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn commands(&self) -> Vec<&'static str> {
+        unsafe {
+            let list = (self.vtable().commands)(self.raw.this);
+            let commands = if list.len == 0 {
+                Vec::new()
+            } else {
+                std::slice::from_raw_parts(list.ptr, list.len)
+                    .iter()
+                    .map(|c| c.as_str())
+                    .collect()
+            };
+            (self.vtable().free_str_list)(list);
+            commands
+        }
+    }
+
+    fn required_host(&self) -> semver::VersionReq {
+        let required = unsafe { (self.vtable().required_host)(self.raw.this) };
+        let text = unsafe { required.as_ffi_str().as_str().to_string() };
+        unsafe { (self.vtable().free_string)(required) };
+        semver::VersionReq::parse(&text).unwrap_or_else(|e| {
+            log::warn!("{}: invalid required host {:?}: {}", self.name, text, e);
+            semver::VersionReq::STAR
+        })
+    }
+
+    fn requires_features(&self) -> HostFeatures {
+        unsafe {
+            let list = (self.vtable().requires_features)(self.raw.this);
+            let features = if list.len == 0 {
+                HostFeatures::empty()
+            } else {
+                std::slice::from_raw_parts(list.ptr, list.len)
+                    .iter()
+                    .filter_map(|name| Feature::from_name(name.as_str()))
+                    .collect()
+            };
+            (self.vtable().free_str_list)(list);
+            features
+        }
+    }
+
+    fn execute(&self, command: &str, args: &[String], ctx: &PluginContext) -> anyhow::Result<()> {
+        let workspace_root = ctx.workspace_root().to_string_lossy();
+        let cwd = ctx.cwd().to_string_lossy();
+        let paths: Vec<String> = ctx
+            .projects()
+            .iter()
+            .map(|p| p.path.to_string_lossy().into_owned())
+            .collect();
+        let projects: Vec<FfiProject> = ctx
+            .projects()
+            .iter()
+            .zip(&paths)
+            .map(|(p, path)| FfiProject {
+                name: FfiStr::new(&p.name),
+                path: FfiStr::new(path),
+                repo: FfiStr::new(&p.repo),
+            })
+            .collect();
+        let ffi_ctx = FfiContext {
+            workspace_root: FfiStr::new(&workspace_root),
+            cwd: FfiStr::new(&cwd),
+            host_version: FfiStr::new(ctx.host_version()),
+            projects: projects.as_ptr(),
+            projects_len: projects.len(),
+            cancelled: ctx.cancellation().as_ffi(),
+            read_only: ctx.is_read_only(),
+            dry_run: ctx.is_dry_run(),
+            granted: ctx.granted_capabilities().bits(),
+            output: ctx.output_sink() as *const Arc<dyn OutputSink> as *const c_void,
+            write_output: Some(host_write_output),
+        };
+        let args: Vec<FfiStr> = args.iter().map(|a| FfiStr::new(a)).collect();
+        let result = unsafe {
+            (self.vtable().execute)(
+                self.raw.this,
+                FfiStr::new(command),
+                args.as_ptr(),
+                args.len(),
+                &ffi_ctx,
+            )
+        };
+        self.take_result(result)
+    }
+
+    fn get_help_output(&self, args: &[String]) -> Option<(HelpMode, HelpBody)> {
+        let args: Vec<FfiStr> = args.iter().map(|a| FfiStr::new(a)).collect();
+        unsafe {
+            let help = (self.vtable().get_help_output)(self.raw.this, args.as_ptr(), args.len());
+            let result = help.present.then(|| {
+                let text = help.text.as_ffi_str().as_str().to_string();
+                let body = if help.markdown {
+                    HelpBody::Markdown(text)
+                } else {
+                    HelpBody::Text(text)
+                };
+                (help.mode.into(), body)
+            });
+            (self.vtable().free_string)(help.text);
+            result
+        }
+    }
+
+    fn on_load(&mut self, host: &HostInfo) -> anyhow::Result<()> {
+        let version = host.version.to_string();
+        let features: Vec<FfiStr> = host
+            .features
+            .iter()
+            .map(|f| FfiStr::new(f.name()))
+            .collect();
+        let sandbox = serde_json::to_string(&host.sandbox)?;
+        let result = unsafe {
+            (self.vtable().on_load)(
+                self.raw.this,
+                FfiStr::new(&version),
+                features.as_ptr(),
+                features.len(),
+                FfiStr::new(&sandbox),
+            )
+        };
+        self.take_result(result)
+    }
+
+    fn on_unload(&mut self) {
+        unsafe { (self.vtable().on_unload)(self.raw.this) }
+    }
+}
+
+impl Drop for FfiPluginProxy {
+    fn drop(&mut self) {
+        unsafe { (self.vtable().drop)(self.raw.this) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    struct EchoPlugin {
+        dropped: Arc<AtomicBool>,
+    }
+
+    impl Plugin for EchoPlugin {
+        fn name(&self) -> &'static str {
+            "echo"
+        }
+        fn commands(&self) -> Vec<&'static str> {
+            vec!["echo", "mode", "fail"]
+        }
+        fn execute(
+            &self,
+            command: &str,
+            args: &[String],
+            ctx: &PluginContext,
+        ) -> anyhow::Result<()> {
+            match command {
+                "echo" => {
+                    assert_eq!(args, ["a", "b"]);
+                    assert_eq!(ctx.host_version(), "9.9.9");
+                    assert_eq!(ctx.project("api").unwrap().repo, "git@x:api.git");
+                    ctx.cancellation().check()?;
+                    write!(ctx.stdout(), "echo {}", args.join(" "))?;
+                    Ok(())
+                }
+                "mode" => {
+                    write!(
+                        ctx.stdout(),
+                        "read_only={} dry_run={} granted={:?}",
+                        ctx.is_read_only(),
+                        ctx.is_dry_run(),
+                        ctx.granted_capabilities()
+                    )?;
+                    Ok(())
+                }
+                _ => Err(anyhow::anyhow!("failed in {}", ctx.cwd().display())),
+            }
+        }
+        fn get_help_output(&self, args: &[String]) -> Option<(HelpMode, HelpBody)> {
+            args.is_empty()
+                .then(|| (HelpMode::Prepend, HelpBody::markdown("**echo** help")))
+        }
+        fn required_host(&self) -> semver::VersionReq {
+            semver::VersionReq::parse(">=9").unwrap()
+        }
+        fn on_load(&mut self, host: &HostInfo) -> anyhow::Result<()> {
+            if !host.sandbox.is_unrestricted() {
+                anyhow::bail!("sandbox allows {:?}", host.sandbox.capabilities());
+            }
+            Ok(())
+        }
+        fn requires_features(&self) -> HostFeatures {
+            HostFeatures::HOOKS | HostFeatures::PROGRESS
+        }
+    }
+
+    impl Drop for EchoPlugin {
+        fn drop(&mut self) {
+            self.dropped.store(true, Ordering::SeqCst);
+        }
+    }
+
+    fn proxy(dropped: &Arc<AtomicBool>) -> FfiPluginProxy {
+        let raw = FfiPlugin::new(Box::new(EchoPlugin {
+            dropped: dropped.clone(),
+        }));
+        unsafe { FfiPluginProxy::from_raw(raw).unwrap() }
+    }
+
+    #[test]
+    fn test_ffi_round_trip() {
+        let dropped = Arc::new(AtomicBool::new(false));
+        let plugin = proxy(&dropped);
+        assert_eq!(plugin.name(), "echo");
+        assert_eq!(plugin.commands(), vec!["echo", "mode", "fail"]);
+        assert_eq!(plugin.required_host().to_string(), ">=9");
+        assert_eq!(
+            plugin.requires_features(),
+            HostFeatures::HOOKS | HostFeatures::PROGRESS
+        );
+
+        let captured = Arc::new(crate::CapturedOutput::new());
+        let ctx = PluginContext::new("/ws", "/ws/api", "9.9.9")
+            .with_projects(vec![ProjectInfo::new("api", "api", "git@x:api.git")])
+            .with_output(captured.clone());
+        let args = vec!["a".to_string(), "b".to_string()];
+        assert!(plugin.execute("echo", &args, &ctx).is_ok());
+        assert_eq!(captured.stdout(), "echo a b");
+        let err = plugin.execute("fail", &[], &ctx).unwrap_err();
+        assert_eq!(err.to_string(), "failed in /ws/api");
+
+        let modes = Arc::new(crate::CapturedOutput::new());
+        let restricted = ctx
+            .clone()
+            .with_output(modes.clone())
+            .with_read_only(true)
+            .with_execution_mode(crate::ExecutionMode::DryRun)
+            .with_granted_capabilities(Capabilities::NETWORK);
+        plugin.execute("mode", &[], &restricted).unwrap();
+        assert_eq!(
+            modes.stdout(),
+            format!(
+                "read_only=true dry_run=true granted={:?}",
+                Capabilities::NETWORK
+            )
+        );
+
+        let mut plugin = plugin;
+        let host = HostInfo::new(semver::Version::new(9, 9, 9));
+        plugin.on_load(&host).unwrap();
+        let sandboxed = host.with_sandbox(SandboxProfile::denied());
+        let err = plugin.on_load(&sandboxed).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("sandbox allows {:?}", Capabilities::empty())
+        );
+
+        assert_eq!(
+            plugin.get_help_output(&[]),
+            Some((HelpMode::Prepend, HelpBody::markdown("**echo** help")))
+        );
+        assert_eq!(plugin.get_help_output(&args), None);
+
+        ctx.cancellation().cancel();
+        let err = plugin.execute("echo", &args, &ctx).unwrap_err();
+        assert_eq!(err.to_string(), "Operation cancelled");
+
+        drop(plugin);
+        assert!(dropped.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_ffi_rejects_null_and_version_mismatch() {
+        let err = unsafe { FfiPluginProxy::from_raw(FfiPlugin::null()) }
+            .err()
+            .unwrap();
+        assert!(matches!(err, PluginError::LoadError(_)));
+
+        let raw = create_ffi_plugin(|| Err(anyhow::anyhow!("missing config")));
+        let err = unsafe { FfiPluginProxy::from_raw(raw) }.err().unwrap();
+        assert!(matches!(err, PluginError::LoadError(m) if m == "missing config"));
+        let raw = create_ffi_plugin(|| panic!("boom"));
+        let err = unsafe { FfiPluginProxy::from_raw(raw) }.err().unwrap();
+        assert!(
+            matches!(err, PluginError::LoadError(m) if m == "plugin constructor panicked: boom")
+        );
+
+        static OLD_VTABLE: FfiPluginVTable = FfiPluginVTable {
+            api_version: 0,
+            ..VTABLE
+        };
+        let dropped = Arc::new(AtomicBool::new(false));
+        let mut raw = FfiPlugin::new(Box::new(EchoPlugin {
+            dropped: dropped.clone(),
+        }));
+        raw.vtable = &OLD_VTABLE;
+        let err = unsafe { FfiPluginProxy::from_raw(raw) }.err().unwrap();
+        assert!(matches!(
+            err,
+            PluginError::VersionMismatch { plugin: 0, .. }
+        ));
+        assert!(dropped.load(Ordering::SeqCst));
+    }
+}
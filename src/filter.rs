@@ -0,0 +1,182 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{Env, RepoHandle};
+
+/// The subset of repos the user restricted a command to, e.g. with
+/// `--include-only`, `--exclude`, `--tag` or `--changed-since`.
+///
+/// Globs match repo names and support `*` and `?`. A repo is selected when
+/// it matches an include glob (or there are none), has one of the tags (or
+/// none are given), matches no exclude glob, and has changes since
+/// `changed_since` if set.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RepoFilter {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub tags: Vec<String>,
+    /// Git ref to compare each repo's working tree against
+    pub changed_since: Option<String>,
+}
+
+impl RepoFilter {
+    /// A filter that selects every repo.
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    pub fn include(mut self, glob: impl Into<String>) -> Self {
+        self.include.push(glob.into());
+        self
+    }
+
+    pub fn exclude(mut self, glob: impl Into<String>) -> Self {
+        self.exclude.push(glob.into());
+        self
+    }
+
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    pub fn changed_since(mut self, git_ref: impl Into<String>) -> Self {
+        self.changed_since = Some(git_ref.into());
+        self
+    }
+
+    pub fn is_all(&self) -> bool {
+        *self == Self::all()
+    }
+
+    /// Check names and tags only, without running git.
+    pub fn matches_static(&self, repo: &RepoHandle) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|g| glob_match(g, &repo.name)))
+            && (self.tags.is_empty() || self.tags.iter().any(|t| repo.has_tag(t)))
+            && !self.exclude.iter().any(|g| glob_match(g, &repo.name))
+    }
+
+    /// Full check, running `git diff` in the repo when `changed_since` is
+    /// set. Repos git cannot compare (not cloned, unknown ref) count as
+    /// changed, so a bad ref never silently skips work.
+    pub fn matches(&self, repo: &RepoHandle, env: &Env) -> bool {
+        if !self.matches_static(repo) {
+            return false;
+        }
+        let Some(git_ref) = &self.changed_since else {
+            return true;
+        };
+        let unchanged = env
+            .command("git")
+            .arg("-C")
+            .arg(&repo.path)
+            .args([
+                "diff",
+                "--quiet",
+                "--end-of-options",
+                git_ref.as_str(),
+                "--",
+            ])
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success());
+        !unchanged
+    }
+}
+
+/// Match `text` against a glob where `*` is any run of characters and `?`
+/// any single character.
+fn glob_match(glob: &str, text: &str) -> bool {
+    let glob: Vec<char> = glob.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut g, mut t) = (0, 0);
+    // Position of the last `*` and the text index it is matched up to.
+    let mut star = None;
+    while t < text.len() {
+        match glob.get(g) {
+            Some('*') => {
+                star = Some((g, t));
+                g += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                g += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_g, star_t)) => {
+                    g = star_g + 1;
+                    t = star_t + 1;
+                    star = Some((star_g, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    glob[g..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProjectInfo;
+    use std::path::Path;
+
+    fn repo(name: &str, tags: &[&str]) -> RepoHandle {
+        let project = ProjectInfo::new(name, name, "").with_tags(tags.iter().copied());
+        RepoHandle::new(Path::new("/work"), &project)
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("api-*", "api-gateway"));
+        assert!(glob_match("*-svc", "billing-svc"));
+        assert!(glob_match("a?i", "api"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("api-*", "web"));
+        assert!(!glob_match("a*c", "abcd"));
+    }
+
+    #[test]
+    fn test_filter_selection() {
+        let filter = RepoFilter::all().include("api-*").exclude("*-legacy");
+        assert!(filter.matches_static(&repo("api-users", &[])));
+        assert!(!filter.matches_static(&repo("api-legacy", &[])));
+        assert!(!filter.matches_static(&repo("web", &[])));
+
+        let filter = RepoFilter::all().tag("backend");
+        assert!(filter.matches_static(&repo("billing", &["backend"])));
+        assert!(!filter.matches_static(&repo("web", &["frontend"])));
+        assert!(RepoFilter::all().is_all());
+
+        // Not cloned, so git cannot tell: selected.
+        let filter = RepoFilter::all().changed_since("main");
+        assert!(filter.matches(&repo("meta-no-such-repo", &[]), &Env::from_process()));
+    }
+
+    #[test]
+    fn test_changed_since_ref_is_not_an_option() {
+        let env = Env::from_process();
+        if env.which("git").is_none() {
+            return;
+        }
+        let root =
+            std::env::temp_dir().join(format!("meta_plugin_api-filter-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("api")).unwrap();
+        let status = env
+            .command("git")
+            .args(["init", "--quiet"])
+            .current_dir(root.join("api"))
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let marker = root.join("written");
+        let filter = RepoFilter::all().changed_since(format!("--output={}", marker.display()));
+        let api = RepoHandle::new(&root, &ProjectInfo::new("api", "api", ""));
+        assert!(filter.matches(&api, &env));
+        assert!(!marker.exists());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
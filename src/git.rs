@@ -0,0 +1,228 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::{Env, PluginError};
+
+/// One changed path from [`GitOps::status_porcelain`], as in
+/// `git status --porcelain`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitStatusEntry {
+    /// Staged state, e.g. `M`, `A`, `R`, or ` ` for unchanged; `?` for
+    /// untracked paths
+    pub index: char,
+    /// Unstaged state, in the same codes
+    pub worktree: char,
+    pub path: PathBuf,
+    /// Where a renamed or copied path came from
+    pub original: Option<PathBuf>,
+}
+
+impl GitStatusEntry {
+    pub fn is_untracked(&self) -> bool {
+        self.index == '?'
+    }
+}
+
+/// Git operations done the host's way, through
+/// [`PluginContext::git`](crate::PluginContext::git), so credentials, SSH
+/// configuration and retries are handled in one place rather than by
+/// each plugin's own `git` invocations.
+///
+/// `repo` is the checkout directory, e.g. from
+/// [`RepoHandle::run_in`](crate::RepoHandle::run_in). Failures are
+/// [`PluginError::Git`].
+pub trait GitOps: Send + Sync {
+    /// Clone `url` into `dest`, which must not exist yet.
+    fn clone(&self, url: &str, dest: &Path) -> Result<(), PluginError>;
+
+    /// Fetch from `remote`, or from the default remote when `None`.
+    fn fetch(&self, repo: &Path, remote: Option<&str>) -> Result<(), PluginError>;
+
+    /// The checked-out branch, None on a detached `HEAD`.
+    fn current_branch(&self, repo: &Path) -> Result<Option<String>, PluginError>;
+
+    /// Changed and untracked paths; empty when the checkout is clean.
+    fn status_porcelain(&self, repo: &Path) -> Result<Vec<GitStatusEntry>, PluginError>;
+
+    /// The full object id `rev` names, e.g. for `HEAD` or `origin/main`.
+    fn rev_parse(&self, repo: &Path, rev: &str) -> Result<String, PluginError>;
+}
+
+/// [`GitOps`] by running the `git` program with a given environment. This
+/// is what plugins get unless the host installs its own.
+#[derive(Debug, Clone)]
+pub struct GitCli {
+    env: Arc<Env>,
+}
+
+impl GitCli {
+    pub fn new(env: impl Into<Arc<Env>>) -> Self {
+        Self { env: env.into() }
+    }
+
+    /// Run `git -C repo args...`, returning stdout, or the trimmed stderr
+    /// if git failed.
+    fn run(
+        &self,
+        repo: Option<&Path>,
+        args: &[&str],
+    ) -> Result<Result<String, String>, PluginError> {
+        let mut command = self.env.command("git");
+        if let Some(repo) = repo {
+            command.arg("-C").arg(repo);
+        }
+        let output = command.args(args).output()?;
+        if output.status.success() {
+            Ok(Ok(String::from_utf8_lossy(&output.stdout).into_owned()))
+        } else {
+            Ok(Err(String::from_utf8_lossy(&output.stderr)
+                .trim()
+                .to_string()))
+        }
+    }
+
+    fn run_ok(&self, repo: Option<&Path>, args: &[&str]) -> Result<String, PluginError> {
+        self.run(repo, args)?.map_err(|message| PluginError::Git {
+            command: args[0].to_string(),
+            message,
+        })
+    }
+}
+
+impl GitOps for GitCli {
+    fn clone(&self, url: &str, dest: &Path) -> Result<(), PluginError> {
+        let dest = dest.to_string_lossy();
+        self.run_ok(None, &["clone", "--", url, &dest]).map(drop)
+    }
+
+    fn fetch(&self, repo: &Path, remote: Option<&str>) -> Result<(), PluginError> {
+        let mut args = vec!["fetch"];
+        if let Some(remote) = remote {
+            args.extend(["--end-of-options", remote]);
+        }
+        self.run_ok(Some(repo), &args).map(drop)
+    }
+
+    fn current_branch(&self, repo: &Path) -> Result<Option<String>, PluginError> {
+        // `--quiet` makes a detached HEAD fail without a message.
+        match self.run(Some(repo), &["symbolic-ref", "--quiet", "--short", "HEAD"])? {
+            Ok(branch) => Ok(Some(branch.trim().to_string())),
+            Err(message) if message.is_empty() => Ok(None),
+            Err(message) => Err(PluginError::Git {
+                command: "symbolic-ref".to_string(),
+                message,
+            }),
+        }
+    }
+
+    fn status_porcelain(&self, repo: &Path) -> Result<Vec<GitStatusEntry>, PluginError> {
+        let output = self.run_ok(Some(repo), &["status", "--porcelain=v1", "-z"])?;
+        Ok(parse_porcelain_z(&output))
+    }
+
+    fn rev_parse(&self, repo: &Path, rev: &str) -> Result<String, PluginError> {
+        let output = self.run_ok(
+            Some(repo),
+            &["rev-parse", "--verify", "--end-of-options", rev],
+        )?;
+        Ok(output.trim().to_string())
+    }
+}
+
+/// [`GitOps`] for a read-only run: queries go to `inner`, while
+/// [`clone`](GitOps::clone) and [`fetch`](GitOps::fetch) fail with
+/// [`PluginError::ReadOnly`] as
+/// [`assert_writable`](crate::PluginContext::assert_writable) would.
+pub(crate) struct ReadOnlyGit(pub(crate) Arc<dyn GitOps>);
+
+impl GitOps for ReadOnlyGit {
+    fn clone(&self, _url: &str, _dest: &Path) -> Result<(), PluginError> {
+        Err(PluginError::ReadOnly)
+    }
+
+    fn fetch(&self, _repo: &Path, _remote: Option<&str>) -> Result<(), PluginError> {
+        Err(PluginError::ReadOnly)
+    }
+
+    fn current_branch(&self, repo: &Path) -> Result<Option<String>, PluginError> {
+        self.0.current_branch(repo)
+    }
+
+    fn status_porcelain(&self, repo: &Path) -> Result<Vec<GitStatusEntry>, PluginError> {
+        self.0.status_porcelain(repo)
+    }
+
+    fn rev_parse(&self, repo: &Path, rev: &str) -> Result<String, PluginError> {
+        self.0.rev_parse(repo, rev)
+    }
+}
+
+/// Parse `git status --porcelain=v1 -z`, where a rename's original path
+/// follows it as a separate NUL-terminated field.
+fn parse_porcelain_z(output: &str) -> Vec<GitStatusEntry> {
+    let mut fields = output.split('\0').filter(|f| !f.is_empty());
+    let mut entries = Vec::new();
+    while let Some(field) = fields.next() {
+        let mut chars = field.chars();
+        let (Some(index), Some(worktree)) = (chars.next(), chars.next()) else {
+            continue;
+        };
+        let original = if matches!(index, 'R' | 'C') {
+            fields.next().map(PathBuf::from)
+        } else {
+            None
+        };
+        entries.push(GitStatusEntry {
+            index,
+            worktree,
+            path: PathBuf::from(field.get(3..).unwrap_or_default()),
+            original,
+        });
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_porcelain_z() {
+        let entries = parse_porcelain_z(" M src/lib.rs\0R  new.rs\0old.rs\0?? notes.txt\0");
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].worktree, 'M');
+        assert_eq!(entries[0].path, Path::new("src/lib.rs"));
+        assert_eq!(entries[1].path, Path::new("new.rs"));
+        assert_eq!(entries[1].original.as_deref(), Some(Path::new("old.rs")));
+        assert!(entries[2].is_untracked());
+        assert!(parse_porcelain_z("").is_empty());
+    }
+
+    #[test]
+    fn test_git_cli() {
+        let env = Env::from_process();
+        if env.which("git").is_none() {
+            return;
+        }
+        let dir = std::env::temp_dir().join(format!("meta_plugin_api-git-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let git = GitCli::new(env);
+        git.run_ok(Some(&dir), &["init", "--quiet"]).unwrap();
+        std::fs::write(dir.join("notes.txt"), "hi").unwrap();
+
+        assert!(git.current_branch(&dir).unwrap().is_some());
+        let status = git.status_porcelain(&dir).unwrap();
+        assert_eq!(status[0].path, Path::new("notes.txt"));
+        assert!(matches!(
+            git.rev_parse(&dir, "HEAD"),
+            Err(PluginError::Git { command, .. }) if command == "rev-parse"
+        ));
+        // A remote that looks like an option is still taken as a remote
+        let marker = dir.join("fetched");
+        let remote = format!("--upload-pack=touch {}", marker.display());
+        assert!(git.fetch(&dir, Some(&remote)).is_err());
+        assert!(!marker.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
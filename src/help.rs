@@ -0,0 +1,182 @@
+use crate::HelpMode;
+
+/// Help text as a plugin wrote it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HelpBody {
+    Text(String),
+    /// CommonMark, styled for the terminal by `render_terminal` (feature
+    /// `markdown`) and shown as source otherwise
+    Markdown(String),
+}
+
+impl HelpBody {
+    pub fn markdown(text: impl Into<String>) -> Self {
+        HelpBody::Markdown(text.into())
+    }
+
+    /// The text as written: plain text, or Markdown source.
+    pub fn as_str(&self) -> &str {
+        match self {
+            HelpBody::Text(text) | HelpBody::Markdown(text) => text,
+        }
+    }
+
+    pub fn is_markdown(&self) -> bool {
+        matches!(self, HelpBody::Markdown(_))
+    }
+
+    /// Text ready for the terminal, with Markdown rendered by
+    /// [`render_markdown`](crate::render_markdown).
+    #[cfg(feature = "markdown")]
+    pub fn render_terminal(&self, color: bool) -> String {
+        match self {
+            HelpBody::Text(text) => text.clone(),
+            HelpBody::Markdown(text) => crate::render_markdown(text, color),
+        }
+    }
+}
+
+impl From<String> for HelpBody {
+    fn from(text: String) -> Self {
+        HelpBody::Text(text)
+    }
+}
+
+impl From<&str> for HelpBody {
+    fn from(text: &str) -> Self {
+        HelpBody::Text(text.to_string())
+    }
+}
+
+/// A titled block of help text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HelpSection {
+    /// Heading such as "Plugin commands"; untitled sections render body only
+    pub title: Option<String>,
+    pub body: HelpBody,
+}
+
+/// Structured help output that the host can merge across plugins.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HelpOutput {
+    pub sections: Vec<HelpSection>,
+}
+
+impl HelpOutput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A single untitled section holding `text`.
+    pub fn from_text(text: impl Into<String>) -> Self {
+        Self::from_body(HelpBody::Text(text.into()))
+    }
+
+    /// A single untitled section holding `body`.
+    pub fn from_body(body: HelpBody) -> Self {
+        Self {
+            sections: vec![HelpSection { title: None, body }],
+        }
+    }
+
+    /// Add a titled section.
+    pub fn section(mut self, title: impl Into<String>, body: impl Into<HelpBody>) -> Self {
+        self.sections.push(HelpSection {
+            title: Some(title.into()),
+            body: body.into(),
+        });
+        self
+    }
+
+    /// Render sections separated by blank lines, titles followed by a colon.
+    /// Markdown bodies are shown as source.
+    pub fn render(&self) -> String {
+        self.render_with(|body| body.as_str().to_string())
+    }
+
+    /// Like [`render`](Self::render), with Markdown bodies rendered for
+    /// the terminal.
+    #[cfg(feature = "markdown")]
+    pub fn render_terminal(&self, color: bool) -> String {
+        self.render_with(|body| body.render_terminal(color))
+    }
+
+    fn render_with(&self, body: impl Fn(&HelpBody) -> String) -> String {
+        self.sections
+            .iter()
+            .map(|section| {
+                let text = body(&section.body);
+                match &section.title {
+                    Some(title) => format!("{}:\n{}", title, text.trim_end()),
+                    None => text.trim_end().to_string(),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+/// Combine the host's own help with plugin contributions.
+///
+/// `contributions` must already be sorted with
+/// [`plugin_order`](crate::plugin_order). The first
+/// [`HelpMode::Override`] contribution replaces `system_help`; `Prepend`
+/// contributions are rendered before it and `Append` contributions after
+/// it, each group in the given order. `HelpMode::None` entries are ignored.
+pub fn merge_help(system_help: &str, contributions: &[(HelpMode, HelpOutput)]) -> String {
+    let body = contributions
+        .iter()
+        .find(|(mode, _)| *mode == HelpMode::Override)
+        .map(|(_, output)| output.render())
+        .unwrap_or_else(|| system_help.trim_end().to_string());
+    let rendered = |wanted: HelpMode| {
+        contributions
+            .iter()
+            .filter(move |(mode, _)| *mode == wanted)
+            .map(|(_, output)| output.render())
+    };
+    rendered(HelpMode::Prepend)
+        .chain(std::iter::once(body))
+        .chain(rendered(HelpMode::Append))
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_sections() {
+        let output =
+            HelpOutput::from_text("Intro\n").section("Plugin commands", "  sync  Sync repos");
+        assert_eq!(
+            output.render(),
+            "Intro\n\nPlugin commands:\n  sync  Sync repos"
+        );
+    }
+
+    #[test]
+    fn test_merge_help_orders_contributions() {
+        let merged = merge_help(
+            "SYSTEM",
+            &[
+                (HelpMode::Append, HelpOutput::from_text("after-a")),
+                (HelpMode::Prepend, HelpOutput::from_text("before")),
+                (HelpMode::None, HelpOutput::from_text("ignored")),
+                (HelpMode::Append, HelpOutput::from_text("after-b")),
+            ],
+        );
+        assert_eq!(merged, "before\n\nSYSTEM\n\nafter-a\n\nafter-b");
+
+        let merged = merge_help(
+            "SYSTEM",
+            &[
+                (HelpMode::Override, HelpOutput::from_text("custom")),
+                (HelpMode::Override, HelpOutput::from_text("second")),
+            ],
+        );
+        assert_eq!(merged, "custom");
+    }
+}
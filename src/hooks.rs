@@ -0,0 +1,163 @@
+use crate::{CommandOutcome, Plugin, PluginContext, PluginError};
+
+/// A command about to run (or that just ran), as seen by hook plugins.
+#[derive(Debug, Clone, Copy)]
+pub struct CommandInvocation<'a> {
+    /// Plugin that owns the command, or `"meta"` for built-ins
+    pub plugin: &'a str,
+    pub command: &'a str,
+    pub args: &'a [String],
+}
+
+/// Result of a [`Plugin::before_command`] hook.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookDecision {
+    Continue,
+    /// Veto the command with a reason shown to the user
+    Abort(String),
+}
+
+/// Run `before_command` on every plugin in host order.
+///
+/// Ordering model: hooks run in the order of `plugins`, which the host
+/// sorts with [`plugin_order`](crate::plugin_order). The first plugin returning [`HookDecision::Abort`] stops the
+/// chain; later plugins are not consulted and the command does not run.
+pub fn run_before_hooks(
+    plugins: &[&dyn Plugin],
+    invocation: &CommandInvocation,
+    ctx: &PluginContext,
+) -> Result<(), PluginError> {
+    for plugin in plugins {
+        if let HookDecision::Abort(reason) = plugin.before_command(invocation, ctx) {
+            return Err(PluginError::HookAborted {
+                plugin: plugin.name().to_string(),
+                reason,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Run `after_command` on every plugin in reverse host order, so the
+/// outermost `before_command` hook sees the command finish last. Only
+/// called when the command actually ran.
+pub fn run_after_hooks(
+    plugins: &[&dyn Plugin],
+    invocation: &CommandInvocation,
+    outcome: &CommandOutcome,
+    ctx: &PluginContext,
+) {
+    for plugin in plugins.iter().rev() {
+        plugin.after_command(invocation, outcome, ctx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct Policy {
+        name: &'static str,
+        block: Option<&'static str>,
+        log: &'static Mutex<Vec<String>>,
+    }
+
+    impl Plugin for Policy {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+        fn commands(&self) -> Vec<&'static str> {
+            vec![]
+        }
+        fn execute(
+            &self,
+            _command: &str,
+            _args: &[String],
+            _ctx: &PluginContext,
+        ) -> anyhow::Result<()> {
+            Ok(())
+        }
+        fn before_command(
+            &self,
+            invocation: &CommandInvocation,
+            _ctx: &PluginContext,
+        ) -> HookDecision {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("before:{}", self.name));
+            match self.block {
+                Some(command) if command == invocation.command => {
+                    HookDecision::Abort("not on Fridays".to_string())
+                }
+                _ => HookDecision::Continue,
+            }
+        }
+        fn after_command(
+            &self,
+            _invocation: &CommandInvocation,
+            outcome: &CommandOutcome,
+            _ctx: &PluginContext,
+        ) {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("after:{}:{}", self.name, outcome.exit_code));
+        }
+    }
+
+    #[test]
+    fn test_hook_ordering_and_abort() {
+        static LOG: Mutex<Vec<String>> = Mutex::new(Vec::new());
+        let first = Policy {
+            name: "first",
+            block: None,
+            log: &LOG,
+        };
+        let second = Policy {
+            name: "second",
+            block: Some("push"),
+            log: &LOG,
+        };
+        let third = Policy {
+            name: "third",
+            block: None,
+            log: &LOG,
+        };
+        let plugins: Vec<&dyn Plugin> = vec![&first, &second, &third];
+        let ctx = PluginContext::new("/ws", "/ws", "1.0.0");
+
+        let status = CommandInvocation {
+            plugin: "git",
+            command: "status",
+            args: &[],
+        };
+        run_before_hooks(&plugins, &status, &ctx).unwrap();
+        run_after_hooks(&plugins, &status, &CommandOutcome::success(), &ctx);
+        assert_eq!(
+            *LOG.lock().unwrap(),
+            vec![
+                "before:first",
+                "before:second",
+                "before:third",
+                "after:third:0",
+                "after:second:0",
+                "after:first:0",
+            ]
+        );
+
+        LOG.lock().unwrap().clear();
+        let push = CommandInvocation {
+            plugin: "git",
+            command: "push",
+            args: &[],
+        };
+        let err = run_before_hooks(&plugins, &push, &ctx).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Command aborted by plugin 'second': not on Fridays"
+        );
+        assert_eq!(*LOG.lock().unwrap(), vec!["before:first", "before:second"]);
+    }
+}
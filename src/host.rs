@@ -0,0 +1,357 @@
+use std::fmt;
+use std::ops::{BitOr, BitOrAssign};
+
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{CommandOutcome, Plugin, PluginError, PluginValue, SandboxProfile};
+
+/// Services the host offers back to plugins, such as running another
+/// plugin's command without re-entering the `meta` binary.
+pub trait PluginHost: Send + Sync {
+    /// Run `command` of the plugin named `plugin` with a fresh context
+    /// derived from the caller's workspace. Fails with
+    /// [`PluginError::CommandNotFound`](crate::PluginError::CommandNotFound)
+    /// when no loaded plugin provides the command.
+    fn invoke(
+        &self,
+        plugin: &str,
+        command: &str,
+        args: &[String],
+    ) -> anyhow::Result<CommandOutcome>;
+
+    /// Like [`invoke`](Self::invoke), but through the callee's
+    /// [`Plugin::execute_for_value`], returning its result. Hosts that
+    /// predate this fail with
+    /// [`PluginError::Unavailable`](crate::PluginError::Unavailable).
+    fn invoke_for_value(
+        &self,
+        _plugin: &str,
+        _command: &str,
+        _args: &[String],
+    ) -> anyhow::Result<PluginValue> {
+        Err(PluginError::Unavailable("command values".to_string()).into())
+    }
+}
+
+/// Optional host capabilities a plugin can check for in
+/// [`Plugin::on_load`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Feature {
+    /// Drives [`AsyncPlugin`](crate::AsyncPlugin)s on a shared runtime
+    Async,
+    /// Loads WASM plugins
+    Wasm,
+    /// Loads subprocess plugins
+    Subprocess,
+    /// Runs `before_command`/`after_command` hooks
+    Hooks,
+    /// Delivers [`RepoEvent`](crate::RepoEvent)s
+    RepoEvents,
+    /// Provides [`PluginContext::invoke`](crate::PluginContext::invoke)
+    PluginInvoke,
+    /// Routes [`PluginContext::stdout`](crate::PluginContext::stdout) and
+    /// `stderr` through its own [`OutputSink`](crate::OutputSink)
+    StructuredOutput,
+    /// Displays [`PluginContext::progress`](crate::PluginContext::progress)
+    /// updates
+    Progress,
+    /// Answers [`PluginContext::prompter`](crate::PluginContext::prompter)
+    /// questions interactively
+    Prompts,
+    /// Hands the terminal to [`TuiPlugin`](crate::TuiPlugin)s
+    Tui,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 10] = [
+        Feature::Async,
+        Feature::Wasm,
+        Feature::Subprocess,
+        Feature::Hooks,
+        Feature::RepoEvents,
+        Feature::PluginInvoke,
+        Feature::StructuredOutput,
+        Feature::Progress,
+        Feature::Prompts,
+        Feature::Tui,
+    ];
+
+    /// Stable snake_case name, used where features cross a process or
+    /// FFI boundary.
+    pub fn name(self) -> &'static str {
+        match self {
+            Feature::Async => "async",
+            Feature::Wasm => "wasm",
+            Feature::Subprocess => "subprocess",
+            Feature::Hooks => "hooks",
+            Feature::RepoEvents => "repo_events",
+            Feature::PluginInvoke => "plugin_invoke",
+            Feature::StructuredOutput => "structured_output",
+            Feature::Progress => "progress",
+            Feature::Prompts => "prompts",
+            Feature::Tui => "tui",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.name() == name)
+    }
+}
+
+/// A set of [`Feature`]s, one bit each: what a host advertises through
+/// [`HostInfo::feature_set`] and what a plugin demands through
+/// [`Plugin::requires_features`].
+///
+/// Serializes as a list of [`Feature::name`]s; unknown names are skipped.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct HostFeatures(u32);
+
+impl HostFeatures {
+    pub const ASYNC: Self = Self::of(Feature::Async);
+    pub const WASM: Self = Self::of(Feature::Wasm);
+    pub const SUBPROCESS: Self = Self::of(Feature::Subprocess);
+    pub const HOOKS: Self = Self::of(Feature::Hooks);
+    pub const REPO_EVENTS: Self = Self::of(Feature::RepoEvents);
+    pub const PLUGIN_INVOKE: Self = Self::of(Feature::PluginInvoke);
+    pub const STRUCTURED_OUTPUT: Self = Self::of(Feature::StructuredOutput);
+    pub const PROGRESS: Self = Self::of(Feature::Progress);
+    pub const PROMPTS: Self = Self::of(Feature::Prompts);
+    pub const TUI: Self = Self::of(Feature::Tui);
+
+    pub const fn of(feature: Feature) -> Self {
+        Self(1 << feature as u32)
+    }
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Features in `self` that `other` lacks.
+    pub const fn difference(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+    pub fn iter(self) -> impl Iterator<Item = Feature> {
+        Feature::ALL
+            .into_iter()
+            .filter(move |f| self.contains(Self::of(*f)))
+    }
+}
+
+impl From<Feature> for HostFeatures {
+    fn from(feature: Feature) -> Self {
+        Self::of(feature)
+    }
+}
+
+impl FromIterator<Feature> for HostFeatures {
+    fn from_iter<I: IntoIterator<Item = Feature>>(features: I) -> Self {
+        features
+            .into_iter()
+            .fold(Self::empty(), |set, f| set.union(Self::of(f)))
+    }
+}
+
+impl BitOr for HostFeatures {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        self.union(other)
+    }
+}
+
+impl BitOrAssign for HostFeatures {
+    fn bitor_assign(&mut self, other: Self) {
+        *self = self.union(other);
+    }
+}
+
+impl fmt::Debug for HostFeatures {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set()
+            .entries(self.iter().map(Feature::name))
+            .finish()
+    }
+}
+
+impl fmt::Display for HostFeatures {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<_> = self.iter().map(Feature::name).collect();
+        f.write_str(&names.join(", "))
+    }
+}
+
+impl Serialize for HostFeatures {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<'de> Deserialize<'de> for HostFeatures {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let names = Vec::<String>::deserialize(deserializer)?;
+        Ok(names.iter().filter_map(|n| Feature::from_name(n)).collect())
+    }
+}
+
+/// What the plugin is running in, handed to [`Plugin::on_load`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HostInfo {
+    /// Version of the `meta` host
+    pub version: Version,
+    #[serde(default)]
+    pub features: Vec<Feature>,
+    /// Restrictions the plugin runs under; unrestricted unless the host
+    /// sandboxes it
+    #[serde(default, skip_serializing_if = "SandboxProfile::is_unrestricted")]
+    pub sandbox: SandboxProfile,
+}
+
+impl HostInfo {
+    pub fn new(version: Version) -> Self {
+        Self {
+            version,
+            features: Vec::new(),
+            sandbox: SandboxProfile::default(),
+        }
+    }
+
+    pub fn with_feature(mut self, feature: Feature) -> Self {
+        if !self.has(feature) {
+            self.features.push(feature);
+        }
+        self
+    }
+
+    pub fn with_sandbox(mut self, sandbox: SandboxProfile) -> Self {
+        self.sandbox = sandbox;
+        self
+    }
+
+    pub fn has(&self, feature: Feature) -> bool {
+        self.features.contains(&feature)
+    }
+
+    /// The advertised features as a set.
+    pub fn feature_set(&self) -> HostFeatures {
+        self.features.iter().copied().collect()
+    }
+
+    /// Fail with [`PluginError::IncompatibleHost`] unless this host
+    /// satisfies [`Plugin::required_host`] and offers every one of
+    /// [`Plugin::requires_features`]. Hosts call this before `on_load`.
+    pub fn check_required(&self, plugin: &dyn Plugin) -> Result<(), PluginError> {
+        let required: VersionReq = plugin.required_host();
+        if !required.matches(&self.version) {
+            return Err(PluginError::IncompatibleHost(format!(
+                "plugin '{}' requires meta {}, this is {}",
+                plugin.name(),
+                required,
+                self.version
+            )));
+        }
+        let missing = plugin.requires_features().difference(self.feature_set());
+        if !missing.is_empty() {
+            return Err(PluginError::IncompatibleHost(format!(
+                "plugin '{}' requires host features this meta {} lacks: {}",
+                plugin.name(),
+                self.version,
+                missing
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PluginContext;
+
+    struct Modern;
+
+    impl Plugin for Modern {
+        fn name(&self) -> &'static str {
+            "modern"
+        }
+        fn commands(&self) -> Vec<&'static str> {
+            vec![]
+        }
+        fn execute(
+            &self,
+            _command: &str,
+            _args: &[String],
+            _ctx: &PluginContext,
+        ) -> anyhow::Result<()> {
+            Ok(())
+        }
+        fn required_host(&self) -> VersionReq {
+            VersionReq::parse(">=2.1").unwrap()
+        }
+        fn requires_features(&self) -> HostFeatures {
+            HostFeatures::PROGRESS | HostFeatures::PROMPTS
+        }
+    }
+
+    #[test]
+    fn test_check_required_host() {
+        assert!(HostInfo::new(Version::new(2, 3, 0))
+            .with_feature(Feature::Progress)
+            .with_feature(Feature::Prompts)
+            .check_required(&Modern)
+            .is_ok());
+        let err = HostInfo::new(Version::new(2, 3, 0))
+            .with_feature(Feature::Prompts)
+            .check_required(&Modern)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Incompatible host: plugin 'modern' requires host features this meta 2.3.0 lacks: progress"
+        );
+        let err = HostInfo::new(Version::new(2, 0, 9))
+            .check_required(&Modern)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Incompatible host: plugin 'modern' requires meta >=2.1, this is 2.0.9"
+        );
+    }
+
+    #[test]
+    fn test_features() {
+        let host = HostInfo::new(Version::new(1, 0, 0))
+            .with_feature(Feature::Hooks)
+            .with_feature(Feature::Hooks);
+        assert_eq!(host.features, vec![Feature::Hooks]);
+        assert!(!host.has(Feature::Wasm));
+        for feature in Feature::ALL {
+            assert_eq!(Feature::from_name(feature.name()), Some(feature));
+        }
+        assert_eq!(host.feature_set(), HostFeatures::HOOKS);
+        let set = HostFeatures::ASYNC | HostFeatures::STRUCTURED_OUTPUT;
+        let json = serde_json::to_string(&set).unwrap();
+        assert_eq!(json, r#"["async","structured_output"]"#);
+        let parsed: HostFeatures =
+            serde_json::from_str(r#"["structured_output","teleport","async"]"#).unwrap();
+        assert_eq!(parsed, set);
+        assert_eq!(
+            serde_json::to_string(&host).unwrap(),
+            "{\"version\":\"1.0.0\",\"features\":[\"hooks\"]}"
+        );
+    }
+}
@@ -0,0 +1,214 @@
+use std::fmt;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::PluginError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HttpMethod {
+    Get,
+    Head,
+    Post,
+    Put,
+    Patch,
+    Delete,
+}
+
+impl HttpMethod {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HttpMethod::Get => "GET",
+            HttpMethod::Head => "HEAD",
+            HttpMethod::Post => "POST",
+            HttpMethod::Put => "PUT",
+            HttpMethod::Patch => "PATCH",
+            HttpMethod::Delete => "DELETE",
+        }
+    }
+}
+
+impl fmt::Display for HttpMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A request for the host's [`HttpClient`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRequest {
+    pub method: HttpMethod,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    /// Overrides the host's default timeout
+    pub timeout: Option<Duration>,
+}
+
+impl HttpRequest {
+    pub fn new(method: HttpMethod, url: impl Into<String>) -> Self {
+        Self {
+            method,
+            url: url.into(),
+            headers: Vec::new(),
+            body: Vec::new(),
+            timeout: None,
+        }
+    }
+
+    pub fn get(url: impl Into<String>) -> Self {
+        Self::new(HttpMethod::Get, url)
+    }
+
+    pub fn post(url: impl Into<String>) -> Self {
+        Self::new(HttpMethod::Post, url)
+    }
+
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Send `Authorization: Bearer <token>`.
+    pub fn bearer_token(self, token: &str) -> Self {
+        self.header("Authorization", format!("Bearer {}", token))
+    }
+
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Send `value` as a JSON body.
+    pub fn json<T: Serialize>(self, value: &T) -> Result<Self, serde_json::Error> {
+        let body = serde_json::to_vec(value)?;
+        Ok(self.header("Content-Type", "application/json").body(body))
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+/// What the host's [`HttpClient`] got back, after following redirects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn new(status: u16, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// The first header called `name`, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The body as text, with invalid UTF-8 replaced.
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        serde_json::from_slice(&self.body)
+    }
+
+    /// Turn a non-2xx status into [`PluginError::Http`].
+    pub fn error_for_status(self, url: &str) -> Result<Self, PluginError> {
+        if self.is_success() {
+            Ok(self)
+        } else {
+            Err(PluginError::Http {
+                status: self.status,
+                url: url.to_string(),
+            })
+        }
+    }
+}
+
+/// The host's configured HTTP client, shared through
+/// [`PluginContext::http`](crate::PluginContext::http) so plugins reuse
+/// its proxies, TLS roots and user agent instead of linking their own
+/// HTTP stack.
+///
+/// Only transport failures are errors; any status the server answered
+/// with is returned as a response.
+pub trait HttpClient: Send + Sync {
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse, PluginError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    struct Recorder(Mutex<Vec<HttpRequest>>);
+
+    impl HttpClient for Recorder {
+        fn send(&self, request: HttpRequest) -> Result<HttpResponse, PluginError> {
+            let status = if request.url.ends_with("/missing") {
+                404
+            } else {
+                200
+            };
+            self.0.lock().unwrap().push(request);
+            Ok(HttpResponse::new(status, r#"{"stars":3}"#).with_header("Content-Type", "json"))
+        }
+    }
+
+    #[test]
+    fn test_request_and_response() {
+        let client = Recorder(Mutex::new(Vec::new()));
+        let request = HttpRequest::post("https://api.example.com/repos")
+            .bearer_token("ghp")
+            .json(&serde_json::json!({"name": "api"}))
+            .unwrap();
+        let response = client.send(request).unwrap();
+        assert!(response.is_success());
+        assert_eq!(response.header("content-type"), Some("json"));
+        let body: serde_json::Value = response.json().unwrap();
+        assert_eq!(body["stars"], 3);
+
+        let sent = client.0.lock().unwrap().remove(0);
+        assert_eq!(sent.method.to_string(), "POST");
+        assert_eq!(
+            sent.headers[0],
+            ("Authorization".to_string(), "Bearer ghp".to_string())
+        );
+        assert_eq!(sent.body, br#"{"name":"api"}"#);
+
+        let url = "https://api.example.com/missing";
+        let err = client
+            .send(HttpRequest::get(url))
+            .unwrap()
+            .error_for_status(url)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "HTTP 404 from https://api.example.com/missing"
+        );
+    }
+}
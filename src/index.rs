@@ -0,0 +1,154 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{state, Plugin, PluginError, UpdateSource};
+
+/// Version of the [`IndexEntry`] layout. Fields are only added within a
+/// version, so readers should ignore fields they do not know.
+pub const INDEX_FORMAT_VERSION: u32 = 1;
+
+/// What `meta plugin search` knows about a plugin, stored next to its
+/// artifact as `<artifact>.index.json` (see [`path_for`](Self::path_for))
+/// when the plugin is built or published. A registry is built by reading
+/// these files, without loading or running any plugin.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct IndexEntry {
+    pub format_version: u32,
+    pub name: String,
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tagline: Option<String>,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub authors: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub homepage: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    #[serde(default)]
+    pub categories: Vec<String>,
+    /// Top-level command names
+    #[serde(default)]
+    pub commands: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update: Option<UpdateSource>,
+}
+
+impl IndexEntry {
+    /// The entry for `plugin`, as a build script or `meta plugin publish`
+    /// would write it.
+    pub fn from_plugin(plugin: &dyn Plugin) -> Self {
+        let metadata = plugin.metadata();
+        let owned = |items: Vec<&str>| items.into_iter().map(str::to_string).collect();
+        Self {
+            format_version: INDEX_FORMAT_VERSION,
+            name: plugin.name().to_string(),
+            version: plugin.version().to_string(),
+            tagline: metadata.tagline.map(str::to_string),
+            description: plugin.description().to_string(),
+            authors: owned(metadata.authors),
+            homepage: metadata.homepage.map(str::to_string),
+            repository: metadata.repository.map(str::to_string),
+            license: metadata.license.map(str::to_string),
+            keywords: owned(metadata.keywords),
+            categories: owned(metadata.categories),
+            commands: owned(plugin.commands()),
+            update: plugin.update_info(),
+        }
+    }
+
+    /// Where the entry for `artifact` lives.
+    pub fn path_for(artifact: &Path) -> PathBuf {
+        let mut name = artifact.file_name().unwrap_or_default().to_os_string();
+        name.push(".index.json");
+        artifact.with_file_name(name)
+    }
+
+    /// Read the entry stored next to `artifact`.
+    pub fn load_for(artifact: &Path) -> Result<Self, PluginError> {
+        let bytes = std::fs::read(Self::path_for(artifact))?;
+        Ok(serde_json::from_slice(&bytes).map_err(io::Error::from)?)
+    }
+
+    /// Store this entry next to `artifact`.
+    pub fn write_for(&self, artifact: &Path) -> Result<(), PluginError> {
+        let json = serde_json::to_vec_pretty(self).expect("index entry serializes");
+        state::write_atomic(&Self::path_for(artifact), &json)
+    }
+
+    /// Whether `query` appears, ignoring case, in the name, tagline,
+    /// keywords or categories.
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        std::iter::once(&self.name)
+            .chain(&self.tagline)
+            .chain(&self.keywords)
+            .chain(&self.categories)
+            .any(|field| field.to_lowercase().contains(&query))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PluginContext, PluginMetadata};
+
+    struct Jira;
+
+    impl Plugin for Jira {
+        fn name(&self) -> &'static str {
+            "jira"
+        }
+        fn commands(&self) -> Vec<&'static str> {
+            vec!["issues"]
+        }
+        fn metadata(&self) -> PluginMetadata {
+            PluginMetadata::new()
+                .tagline("Jira issues for every repo")
+                .keyword("tickets")
+                .category("project-management")
+        }
+        fn execute(
+            &self,
+            _command: &str,
+            _args: &[String],
+            _ctx: &PluginContext,
+        ) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_entry_round_trips_next_to_artifact() {
+        let dir =
+            std::env::temp_dir().join(format!("meta_plugin_api-index-{}", std::process::id()));
+        let artifact = dir.join("libmeta_jira.so");
+        let entry = IndexEntry::from_plugin(&Jira);
+        assert_eq!(entry.commands, ["issues"]);
+        assert_eq!(
+            IndexEntry::path_for(&artifact),
+            dir.join("libmeta_jira.so.index.json")
+        );
+
+        entry.write_for(&artifact).unwrap();
+        assert_eq!(IndexEntry::load_for(&artifact).unwrap(), entry);
+        assert!(matches!(
+            IndexEntry::load_for(&dir.join("missing.so")),
+            Err(PluginError::Io(_))
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(entry.matches("TICKETS"));
+        assert!(entry.matches("management"));
+        assert!(entry.matches("every repo"));
+        assert!(!entry.matches("git"));
+    }
+}
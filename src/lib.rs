@@ -1,20 +1,214 @@
 use std::any::Any;
-use thiserror::Error;
-
-#[derive(Debug, Error)]
-pub enum PluginError {
-    #[error("Failed to load plugin: {0}")]
-    LoadError(String),
-    #[error("Command not found: {0}")]
-    CommandNotFound(String),
+use std::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "async")]
+mod async_plugin;
+mod audit;
+mod cache;
+mod cancel;
+mod capabilities;
+#[cfg(feature = "clap")]
+mod clap_plugin;
+mod command;
+mod command_tree;
+mod completion;
+mod config;
+mod context;
+mod deadline;
+mod declare;
+mod dependency;
+pub mod describe;
+pub mod docs;
+mod doctor;
+mod editor;
+mod env;
+mod error;
+mod events;
+pub mod ffi;
+mod filter;
+mod git;
+mod help;
+mod hooks;
+mod host;
+mod http;
+mod index;
+#[cfg(feature = "loader")]
+pub mod loader;
+mod locale;
+mod lock;
+#[cfg(feature = "markdown")]
+mod markdown;
+mod meta_file;
+mod metadata;
+mod network;
+mod notify;
+mod output;
+mod pager;
+mod permission;
+mod plan;
+mod progress;
+mod prompt;
+pub mod protocol;
+mod rate_limit;
+mod redact;
+#[cfg(feature = "registry")]
+pub mod registry;
+mod repo;
+mod sandbox;
+mod secrets;
+mod shield;
+mod signal;
+mod signature;
+mod spawn;
+pub mod state;
+mod stdin;
+pub mod style;
+pub mod subprocess;
+mod telemetry;
+mod temp;
+mod terminal;
+#[cfg(any(test, feature = "testkit"))]
+pub mod testkit;
+#[cfg(feature = "instrumentation")]
+mod timing;
+#[cfg(feature = "tracing")]
+mod trace;
+mod tui;
+mod undo;
+mod update;
+mod user;
+mod value;
+pub mod wasm;
+
+#[cfg(feature = "async")]
+pub use async_plugin::{block_on_execute, AsyncPlugin};
+pub use audit::{AuditEntry, AuditEvent, AuditLog, AuditSink};
+pub use cache::{Cache, DEFAULT_CACHE_LIMIT};
+pub use cancel::CancellationToken;
+pub use capabilities::Capabilities;
+#[cfg(feature = "clap")]
+pub use clap_plugin::{clap_command_specs, execute_clap, ClapPlugin};
+pub use command::{ArgKind, ArgSpec, CommandOutcome, CommandSpec, Deprecation, EXPERIMENTAL_ENV};
+pub use command_tree::{CommandRoute, CommandTree};
+pub use completion::{CompletionItem, Shell};
+pub use config::{ConfigLayer, PluginConfig, ResolvedValue};
+pub use context::{
+    ContextSnapshot, ExecutionMode, OutputFormat, PluginContext, ProjectInfo, Verbosity,
+};
+pub use deadline::Deadline;
+#[doc(hidden)]
+pub use declare::create_plugin as __create_plugin;
+pub use declare::PluginCreateResult;
+pub use dependency::{resolve_dependencies, PluginDependency};
+pub use doctor::{worst_severity, Diagnostic, Severity};
+pub use editor::{editor_command, EditTarget, EditedContent};
+pub use env::Env;
+pub use error::PluginError;
+pub use events::RepoEvent;
+pub use filter::RepoFilter;
+pub use git::{GitCli, GitOps, GitStatusEntry};
+pub use help::{merge_help, HelpBody, HelpOutput, HelpSection};
+pub use hooks::{run_after_hooks, run_before_hooks, CommandInvocation, HookDecision};
+pub use host::{Feature, HostFeatures, HostInfo, PluginHost};
+pub use http::{HttpClient, HttpMethod, HttpRequest, HttpResponse};
+pub use index::{IndexEntry, INDEX_FORMAT_VERSION};
+pub use locale::{localized_command_specs, localized_help_output, Locale, LocalizedHelp};
+pub use lock::{default_lock_dir, LockGuard, LockScope, DEFAULT_LOCK_WAIT};
+#[cfg(feature = "markdown")]
+pub use markdown::render_markdown;
+pub use meta_file::{MetaFile, META_FILE};
+pub use metadata::PluginMetadata;
+pub use network::{NetworkSettings, OFFLINE_ENV};
+pub use notify::{Notification, Notifier, NotifyLevel};
+pub use output::{
+    render_diff, Align, Borders, CapturedOutput, Column, DiffOptions, OutputSink, OutputStream,
+    OutputWriter, ScopedWriter, StdioSink, Table,
+};
+pub use pager::{pager_command, Pager, PAGER_ENV};
+pub use permission::{prompt_for_grant, CapabilityRequest, FixedGrant, Grant, PermissionBroker};
+pub use plan::{ExecutionPlan, PlanStep};
+pub use progress::{
+    MultiProgress, NdjsonProgressSink, ProgressBar, ProgressEvent, ProgressReporter, ProgressSink,
+    Spinner, TaskId,
+};
+pub use prompt::{fuzzy_score, NonInteractivePrompter, Prompt, Prompter};
+pub use rate_limit::{RateLimiter, RateLimiters, RatePolicy};
+pub use redact::{RedactingSink, Redactor, MIN_REDACT_LEN, REDACTED};
+pub use repo::{RepoHandle, RepoResults};
+pub use sandbox::{NetworkPolicy, SandboxProfile, SubprocessPolicy};
+pub use secrets::{secret_env_var, EnvSecrets, Secret, SecretStore, SecretsChain, SecretsProvider};
+pub use shield::PanicShield;
+pub use signal::{deliver_signal, Signal, SignalResponse};
+#[cfg(feature = "signing")]
+pub use signature::TrustedKeys;
+pub use signature::{SignedManifest, SIGNATURE_CONTEXT};
+#[cfg(feature = "async")]
+pub use spawn::AsyncTaskHandle;
+pub use spawn::{Spawner, TaskHandle, TaskSet};
+pub use stdin::{PipedStdin, StdinReader};
+pub use style::{Color, Role, Style, Styled, Styler, Theme};
+pub use telemetry::{
+    Telemetry, TelemetryEvent, TelemetryOutcome, TelemetrySink, TELEMETRY_OPT_OUT_ENV,
+};
+pub use temp::TempSpace;
+pub use terminal::{ColorChoice, TerminalInfo};
+#[cfg(feature = "instrumentation")]
+pub use timing::{CommandTiming, SpanTiming, Timer, TimingReport, Timings};
+#[cfg(feature = "tracing")]
+pub use trace::TraceParent;
+pub use tui::{execute_tui, TerminalHandle, TerminalHost, TuiPlugin, TuiRequirements};
+pub use undo::{UndoAction, UndoEntry, UndoJournal, UndoRecord};
+pub use update::{newest_update, parse_version, UpdateSource};
+pub use user::{CiIdentity, UserIdentity};
+pub use value::PluginValue;
+
+/// Version of the plugin interface defined by this crate. Exported by
+/// [`declare_plugin!`] as the `_plugin_api_version` symbol.
+pub const PLUGIN_API_VERSION: u32 = 1;
+
+/// Name of the constructor symbol emitted by [`declare_plugin!`].
+pub const PLUGIN_CREATE_SYMBOL: &[u8] = b"_plugin_create";
+
+/// Name of the constructor symbol for [`PluginCreateV2`], emitted by
+/// [`declare_plugin!`] alongside [`PLUGIN_CREATE_SYMBOL`]. Hosts try it
+/// first and fall back to the older symbol for plugins built before it.
+pub const PLUGIN_CREATE_V2_SYMBOL: &[u8] = b"_plugin_create_v2";
+
+/// Name of the API version symbol emitted by [`declare_plugin!`].
+pub const PLUGIN_API_VERSION_SYMBOL: &[u8] = b"_plugin_api_version";
+
+/// Check that a plugin built against API version `plugin` can be driven
+/// by a host built against version `host`. Hosts should call this with the
+/// value of the plugin's `_plugin_api_version` symbol before calling
+/// `_plugin_create`.
+pub fn check_compatibility(host: u32, plugin: u32) -> Result<(), PluginError> {
+    if host == plugin {
+        Ok(())
+    } else {
+        Err(PluginError::VersionMismatch { host, plugin })
+    }
+}
+
+/// The host's plugin order: higher [`Plugin::priority`] first, ties broken
+/// by name. Hosts must sort with this before merging help or running hooks
+/// so output does not depend on load order.
+pub fn plugin_order(a: &dyn Plugin, b: &dyn Plugin) -> Ordering {
+    b.priority()
+        .cmp(&a.priority())
+        .then_with(|| a.name().cmp(b.name()))
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum HelpMode {
     /// Plugin help completely replaces system help
     Override,
     /// Plugin help is prepended before system help
     Prepend,
+    /// Plugin help is appended after system help
+    Append,
     /// Plugin does not customize help
     None,
 }
@@ -22,23 +216,328 @@ pub enum HelpMode {
 pub trait Plugin: Any {
     fn name(&self) -> &'static str;
     fn commands(&self) -> Vec<&'static str>;
-    fn execute(&self, command: &str, args: &[String]) -> anyhow::Result<()>;
+
+    /// Plugin version, usually `env!("CARGO_PKG_VERSION")`.
+    fn version(&self) -> &'static str {
+        "0.0.0"
+    }
+
+    /// One-line summary shown by `meta plugin list`.
+    fn description(&self) -> &'static str {
+        ""
+    }
+
+    /// Authors, links and keywords for listings and registries.
+    fn metadata(&self) -> PluginMetadata {
+        PluginMetadata::default()
+    }
+
+    /// Where newer versions of this plugin are published, for `meta
+    /// plugin upgrade`. None if the plugin is updated some other way,
+    /// e.g. along with the host.
+    fn update_info(&self) -> Option<UpdateSource> {
+        None
+    }
+
+    /// Structured metadata for each command. The default adapts
+    /// `commands()` into specs carrying only a name. Commands with
+    /// [`subcommands`](CommandSpec::subcommands) are routed through
+    /// [`CommandTree`], and `execute` receives the full path, e.g.
+    /// `"release prepare"`.
+    fn command_specs(&self) -> Vec<CommandSpec> {
+        self.commands().into_iter().map(CommandSpec::new).collect()
+    }
+
+    /// Map a command-line token to the command it names, following
+    /// [`CommandSpec::aliases`], so `meta st` can dispatch to `status`.
+    /// The host calls `execute` with the resolved name.
+    fn resolve_command(&self, token: &str) -> Option<&'static str> {
+        self.command_specs()
+            .into_iter()
+            .find(|spec| spec.matches(token))
+            .map(|spec| spec.name)
+    }
+
+    /// Check `args` before [`execute`](Self::execute) runs, so usage
+    /// errors are reported uniformly by the host. The default checks the
+    /// required arguments of the command's [`CommandSpec`], looked up
+    /// through [`CommandTree`]; commands without a spec always pass.
+    fn validate(&self, command: &str, args: &[String]) -> Result<(), PluginError> {
+        let path: Vec<&str> = command.split_whitespace().collect();
+        match CommandTree::new(self.command_specs()).find(&path) {
+            Some(spec) => spec.validate(args),
+            None => Ok(()),
+        }
+    }
+
+    /// Run a command. `ctx` carries the workspace information the host has
+    /// already discovered (root, projects, cwd, host version).
+    /// Return `Err(PluginError::ExitCode(n).into())` to exit with a
+    /// specific code.
+    fn execute(&self, command: &str, args: &[String], ctx: &PluginContext) -> anyhow::Result<()>;
+
+    /// Run a command and return its result as JSON, used when the user
+    /// passed `--json` (see [`PluginContext::output_format`]). The default
+    /// delegates to `execute` and returns `null`.
+    fn execute_structured(
+        &self,
+        command: &str,
+        args: &[String],
+        ctx: &PluginContext,
+    ) -> anyhow::Result<serde_json::Value> {
+        self.execute(command, args, ctx)?;
+        Ok(serde_json::Value::Null)
+    }
+
+    /// Run a command for its result, when another plugin or the host
+    /// consumes it programmatically instead of showing it to the user,
+    /// e.g. a dashboard collecting `status` from every plugin. The default
+    /// converts [`execute_structured`](Self::execute_structured)'s JSON.
+    fn execute_for_value(
+        &self,
+        command: &str,
+        args: &[String],
+        ctx: &PluginContext,
+    ) -> anyhow::Result<PluginValue> {
+        self.execute_structured(command, args, ctx)
+            .map(PluginValue::from)
+    }
 
     /// Provide custom help output.
     /// Return Some((HelpMode, help text)) to customize help,
-    /// or None to fallback to system help.
-    fn get_help_output(&self, _args: &[String]) -> Option<(HelpMode, String)> {
+    /// or None to fallback to system help. Return
+    /// [`HelpBody::Markdown`] for styled help instead of writing ANSI
+    /// escapes.
+    fn get_help_output(&self, _args: &[String]) -> Option<(HelpMode, HelpBody)> {
         None
     }
+
+    /// Provide sectioned help output for the host to merge with
+    /// [`merge_help`]. The default wraps `get_help_output` in a single
+    /// untitled section.
+    fn help_output(&self, args: &[String]) -> Option<(HelpMode, HelpOutput)> {
+        self.get_help_output(args)
+            .map(|(mode, body)| (mode, HelpOutput::from_body(body)))
+    }
+
+    /// Translations of this plugin's command summaries, argument help and
+    /// help text for `locale`, usually [`PluginContext::locale`]. Use
+    /// [`Locale::fallbacks`] to pick the closest catalog. The host applies
+    /// them with [`localized_command_specs`] and [`localized_help_output`].
+    fn localized_help(&self, _locale: &Locale) -> Option<LocalizedHelp> {
+        None
+    }
+
+    /// Completion script fragment for `shell`, aggregated by
+    /// `meta completions`. Return None to contribute nothing.
+    fn completions(&self, _shell: Shell) -> Option<String> {
+        None
+    }
+
+    /// Candidate values for the argument at `arg_index` (0-based, after the
+    /// command name) of `command`, given the partially typed `prefix`.
+    /// Called by the host's completion entry point at tab time, so `ctx`
+    /// can be used to offer dynamic values such as repo or branch names.
+    fn complete(
+        &self,
+        _command: &str,
+        _arg_index: usize,
+        _prefix: &str,
+        _ctx: &PluginContext,
+    ) -> Vec<CompletionItem> {
+        Vec::new()
+    }
+
+    /// Describe what `command` would do without doing any of it, for the
+    /// host to show on `--dry-run` or before asking for confirmation.
+    /// None (the default) if the command cannot be previewed.
+    fn plan(
+        &self,
+        _command: &str,
+        _args: &[String],
+        _ctx: &PluginContext,
+    ) -> anyhow::Result<Option<ExecutionPlan>> {
+        Ok(None)
+    }
+
+    /// Reverse a change recorded with [`UndoAction::Plugin`], for `meta
+    /// undo`. `data` is what the plugin recorded. The default fails, so
+    /// only plugins that record such actions need to implement it.
+    fn undo(&self, _data: &serde_json::Value, _ctx: &PluginContext) -> anyhow::Result<()> {
+        anyhow::bail!("plugin '{}' does not support undo", self.name())
+    }
+
+    /// Whether `command` honors [`PluginContext::is_dry_run`]. The host
+    /// warns before running a command that would ignore `--dry-run`.
+    fn supports_dry_run(&self, _command: &str) -> bool {
+        false
+    }
+
+    /// Position in the host's plugin order; see [`plugin_order`].
+    fn priority(&self) -> i32 {
+        0
+    }
+
+    /// Access this plugin needs (network, workspace writes, processes,
+    /// credentials), shown by the host at install time. Declare
+    /// everything any command may do; the default declares nothing.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::empty()
+    }
+
+    /// Other plugins this plugin needs; checked by the host with
+    /// [`resolve_dependencies`] before any command runs.
+    fn dependencies(&self) -> Vec<PluginDependency> {
+        Vec::new()
+    }
+
+    /// Host versions this plugin runs on, checked with
+    /// [`HostInfo::check_required`] before `on_load`, so a plugin relying
+    /// on newer host behavior can refuse older hosts instead of failing on
+    /// first use. Missing [`Feature`]s are better caught with
+    /// [`requires_features`](Self::requires_features).
+    fn required_host(&self) -> semver::VersionReq {
+        semver::VersionReq::STAR
+    }
+
+    /// Host [`Feature`]s this plugin cannot work without, checked with
+    /// [`HostInfo::check_required`] alongside
+    /// [`required_host`](Self::required_host). Features that are merely
+    /// nice to have belong in `on_load` checks with [`HostInfo::has`], so
+    /// the plugin can degrade on hosts without them.
+    fn requires_features(&self) -> HostFeatures {
+        HostFeatures::empty()
+    }
+
+    /// Key of this plugin's section in `.meta`, delivered through
+    /// [`PluginContext::config`]. Defaults to the plugin name.
+    fn config_namespace(&self) -> &'static str {
+        self.name()
+    }
+
+    /// JSON schema of this plugin's config section, so the host can
+    /// validate `.meta`/`.metarc` before running anything.
+    #[cfg(feature = "schema")]
+    fn config_schema(&self) -> Option<schemars::Schema> {
+        None
+    }
+
+    /// Validate this plugin's config section. The host calls this before
+    /// executing any command and reports errors as diagnostics; use
+    /// [`PluginConfig::unknown_keys`] to catch misspelled keys.
+    fn validate_config(&self, _config: &PluginConfig) -> Result<(), PluginError> {
+        Ok(())
+    }
+
+    /// Observe (and optionally veto) any command before it runs, including
+    /// other plugins' commands and built-ins. See [`run_before_hooks`] for
+    /// the ordering model.
+    fn before_command(
+        &self,
+        _invocation: &CommandInvocation,
+        _ctx: &PluginContext,
+    ) -> HookDecision {
+        HookDecision::Continue
+    }
+
+    /// Observe a command after it ran. See [`run_after_hooks`].
+    fn after_command(
+        &self,
+        _invocation: &CommandInvocation,
+        _outcome: &CommandOutcome,
+        _ctx: &PluginContext,
+    ) {
+    }
+
+    /// React to a signal the user or system sent `meta`, e.g. flush a
+    /// journal on `Terminate` or reopen logs on `Hangup`. Called through
+    /// [`deliver_signal`] on the host's signal-watching thread (never
+    /// inside the signal handler), possibly while a command is running.
+    /// Return [`SignalResponse::Handled`] to keep the command running;
+    /// otherwise the host cancels it via the [`CancellationToken`].
+    fn on_signal(&self, _signal: Signal) -> SignalResponse {
+        SignalResponse::Default
+    }
+
+    /// React to a git operation the host performed on a repo, e.g. install
+    /// git hooks after `PostClone`. Errors on `Pre*` events abort the
+    /// operation for that repo.
+    fn on_repo_event(&self, _event: &RepoEvent, _ctx: &PluginContext) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Verify this plugin's prerequisites for `meta doctor`: binaries on
+    /// `PATH`, tokens, valid config. Return nothing when all is well.
+    /// [`Diagnostic::require_binary`] and [`Diagnostic::require_env`]
+    /// cover the common checks.
+    fn health_check(&self, _ctx: &PluginContext) -> Vec<Diagnostic> {
+        Vec::new()
+    }
+
+    /// Bring the state directory written by version `from_version` of
+    /// this plugin up to the current format. The host calls this through
+    /// [`state::migrate_if_needed`] before the first command after an
+    /// upgrade (or downgrade), and records the new version only if it
+    /// succeeds, so a failed migration is retried next time.
+    fn migrate_state(
+        &self,
+        _from_version: &str,
+        _state_dir: &std::path::Path,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Called once by the host right after the plugin is constructed,
+    /// before any other method (including `name()` and `commands()`)
+    /// except `required_host` and `requires_features`, which the host
+    /// checks first. `host` says which host version and [`Feature`]s the
+    /// plugin is running with. Returning an error aborts loading and the
+    /// plugin is dropped without `on_unload` being called.
+    fn on_load(&mut self, _host: &HostInfo) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Install the host's logger so `log` macros inside the plugin reach
+    /// the host and respect `meta -v`. `log`'s global state is per-library,
+    /// so the host must call this after `on_load`. The default body is
+    /// compiled into the plugin crate and therefore sets the plugin's own
+    /// copy of the `log` globals; plugins rarely need to override it.
+    fn set_logger(&self, logger: &'static dyn log::Log, level: log::LevelFilter) {
+        let _ = log::set_logger(logger);
+        log::set_max_level(level);
+    }
+
+    /// Called once by the host before the plugin is dropped, after the
+    /// last call to any other method. Release threads, connections and
+    /// temp files here.
+    ///
+    /// A plugin loaded from a shared library may have its code unmapped
+    /// right after it is dropped (e.g. on hot reload), so nothing it
+    /// started may outlive this call: join spawned threads and stop
+    /// anything that would call back into the plugin.
+    fn on_unload(&mut self) {}
 }
 
-pub type PluginCreate = unsafe fn() -> *mut dyn Plugin;
+/// Signature of the `_plugin_create` symbol. Returns null if the
+/// plugin constructor panicked.
+#[allow(improper_ctypes_definitions)]
+pub type PluginCreate = unsafe extern "C" fn() -> *mut dyn Plugin;
+
+/// Signature of the `_plugin_create_v2` symbol, which reports why
+/// construction failed instead of returning null.
+#[allow(improper_ctypes_definitions)]
+pub type PluginCreateV2 = unsafe extern "C" fn() -> PluginCreateResult;
 
 #[cfg(test)]
+#[allow(unused_imports)]
 mod tests {
     use super::*;
     use anyhow::{anyhow, Result};
 
+    fn test_context() -> PluginContext {
+        PluginContext::new("/workspace", "/workspace", "0.0.0-test")
+    }
+
     struct MockSuccessPlugin;
     impl Plugin for MockSuccessPlugin {
         fn name(&self) -> &'static str {
@@ -47,7 +546,7 @@ mod tests {
         fn commands(&self) -> Vec<&'static str> {
             vec!["success_cmd"]
         }
-        fn execute(&self, command: &str, _args: &[String]) -> Result<()> {
+        fn execute(&self, command: &str, _args: &[String], _ctx: &PluginContext) -> Result<()> {
             if command == "success_cmd" {
                 Ok(())
             } else {
@@ -64,7 +563,7 @@ mod tests {
         fn commands(&self) -> Vec<&'static str> {
             vec!["fail_cmd"]
         }
-        fn execute(&self, _command: &str, _args: &[String]) -> Result<()> {
+        fn execute(&self, _command: &str, _args: &[String], _ctx: &PluginContext) -> Result<()> {
             Err(anyhow!("Simulated plugin failure"))
         }
     }
@@ -84,10 +583,206 @@ mod tests {
         assert_eq!(format!("{}", err), "Failed to load plugin: dlopen failed");
     }
 
+    #[test]
+    fn test_check_compatibility() {
+        assert!(check_compatibility(PLUGIN_API_VERSION, PLUGIN_API_VERSION).is_ok());
+        let err = check_compatibility(2, 1).unwrap_err();
+        assert!(matches!(
+            err,
+            PluginError::VersionMismatch { host: 2, plugin: 1 }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Plugin API version mismatch: host uses v2, plugin was built against v1"
+        );
+    }
+
+    #[test]
+    fn test_plugin_order() {
+        struct Ranked(&'static str, i32);
+        impl Plugin for Ranked {
+            fn name(&self) -> &'static str {
+                self.0
+            }
+            fn commands(&self) -> Vec<&'static str> {
+                vec![]
+            }
+            fn execute(
+                &self,
+                _command: &str,
+                _args: &[String],
+                _ctx: &PluginContext,
+            ) -> Result<()> {
+                Ok(())
+            }
+            fn priority(&self) -> i32 {
+                self.1
+            }
+        }
+
+        let plugins = [Ranked("git", 0), Ranked("audit", 10), Ranked("build", 0)];
+        let mut sorted: Vec<&dyn Plugin> = plugins.iter().map(|p| p as &dyn Plugin).collect();
+        sorted.sort_by(|a, b| plugin_order(*a, *b));
+        let names: Vec<_> = sorted.iter().map(|p| p.name()).collect();
+        assert_eq!(names, ["audit", "build", "git"]);
+    }
+
+    #[test]
+    fn test_default_metadata() {
+        assert_eq!(MockSuccessPlugin.version(), "0.0.0");
+        assert_eq!(MockSuccessPlugin.description(), "");
+        assert_eq!(MockSuccessPlugin.metadata(), PluginMetadata::default());
+        assert!(!MockSuccessPlugin.supports_dry_run("success_cmd"));
+        assert!(MockSuccessPlugin.capabilities().is_empty());
+    }
+
+    #[test]
+    fn test_default_command_specs_adapt_commands() {
+        let specs = MockSuccessPlugin.command_specs();
+        assert_eq!(specs, vec![CommandSpec::new("success_cmd")]);
+    }
+
+    struct MockAliasPlugin;
+    impl Plugin for MockAliasPlugin {
+        fn name(&self) -> &'static str {
+            "mock_alias"
+        }
+        fn commands(&self) -> Vec<&'static str> {
+            vec!["status", "checkout"]
+        }
+        fn command_specs(&self) -> Vec<CommandSpec> {
+            vec![
+                CommandSpec::new("status").alias("st"),
+                CommandSpec::new("checkout").alias("co").alias("switch"),
+            ]
+        }
+        fn execute(&self, _command: &str, _args: &[String], _ctx: &PluginContext) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_resolve_command_follows_aliases() {
+        assert_eq!(MockAliasPlugin.resolve_command("st"), Some("status"));
+        assert_eq!(MockAliasPlugin.resolve_command("switch"), Some("checkout"));
+        assert_eq!(
+            MockAliasPlugin.resolve_command("checkout"),
+            Some("checkout")
+        );
+        assert_eq!(MockAliasPlugin.resolve_command("stat"), None);
+        assert_eq!(
+            MockSuccessPlugin.resolve_command("success_cmd"),
+            Some("success_cmd")
+        );
+    }
+
+    struct MockCompletionPlugin;
+    impl Plugin for MockCompletionPlugin {
+        fn name(&self) -> &'static str {
+            "mock_completion"
+        }
+        fn commands(&self) -> Vec<&'static str> {
+            vec!["checkout"]
+        }
+        fn execute(&self, _command: &str, _args: &[String], _ctx: &PluginContext) -> Result<()> {
+            Ok(())
+        }
+        fn completions(&self, shell: Shell) -> Option<String> {
+            (shell == Shell::Bash).then(|| "complete -F _meta_checkout meta".to_string())
+        }
+        fn complete(
+            &self,
+            _command: &str,
+            arg_index: usize,
+            prefix: &str,
+            ctx: &PluginContext,
+        ) -> Vec<CompletionItem> {
+            if arg_index != 0 {
+                return Vec::new();
+            }
+            ctx.projects()
+                .iter()
+                .filter(|p| p.name.starts_with(prefix))
+                .map(|p| CompletionItem::new(&p.name).description(&p.repo))
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_plugin_completions() {
+        assert!(MockCompletionPlugin.completions(Shell::Bash).is_some());
+        assert!(MockCompletionPlugin.completions(Shell::Fish).is_none());
+        let ctx = test_context().with_projects(vec![
+            ProjectInfo::new("repo1", "repo1", "git@example.com:repo1"),
+            ProjectInfo::new("repo2", "libs/repo2", "git@example.com:repo2"),
+        ]);
+        let values: Vec<_> = MockCompletionPlugin
+            .complete("checkout", 0, "", &ctx)
+            .into_iter()
+            .map(|item| item.value)
+            .collect();
+        assert_eq!(values, vec!["repo1", "repo2"]);
+        assert!(MockCompletionPlugin
+            .complete("checkout", 0, "x", &ctx)
+            .is_empty());
+        assert!(MockSuccessPlugin
+            .complete("success_cmd", 0, "", &ctx)
+            .is_empty());
+    }
+
+    struct RecordingLogger(std::sync::Mutex<Vec<String>>);
+    impl log::Log for RecordingLogger {
+        fn enabled(&self, _metadata: &log::Metadata) -> bool {
+            true
+        }
+        fn log(&self, record: &log::Record) {
+            self.0.lock().unwrap().push(record.args().to_string());
+        }
+        fn flush(&self) {}
+    }
+
+    #[test]
+    fn test_set_logger_installs_host_logger() {
+        static LOGGER: RecordingLogger = RecordingLogger(std::sync::Mutex::new(Vec::new()));
+        MockSuccessPlugin.set_logger(&LOGGER, log::LevelFilter::Info);
+        log::info!("from plugin");
+        log::debug!("filtered out");
+        assert_eq!(*LOGGER.0.lock().unwrap(), vec!["from plugin"]);
+    }
+
+    struct MockConfigPlugin;
+    impl Plugin for MockConfigPlugin {
+        fn name(&self) -> &'static str {
+            "mock_config"
+        }
+        fn commands(&self) -> Vec<&'static str> {
+            vec![]
+        }
+        fn execute(&self, _command: &str, _args: &[String], _ctx: &PluginContext) -> Result<()> {
+            Ok(())
+        }
+        fn validate_config(&self, config: &PluginConfig) -> Result<(), PluginError> {
+            match config.unknown_keys(&["branch"]).first() {
+                Some(key) => Err(PluginError::config(format!("unknown key '{}'", key))),
+                None => Ok(()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_config_hook() {
+        let good = PluginConfig::new("mock_config", serde_json::json!({ "branch": "main" }));
+        let typo = PluginConfig::new("mock_config", serde_json::json!({ "brnch": "main" }));
+        assert_eq!(MockConfigPlugin.config_namespace(), "mock_config");
+        assert!(MockConfigPlugin.validate_config(&good).is_ok());
+        assert!(MockConfigPlugin.validate_config(&typo).is_err());
+        assert!(MockSuccessPlugin.validate_config(&typo).is_ok());
+    }
+
     #[test]
     fn test_plugin_execute_success() {
         let plugin = MockSuccessPlugin;
-        let result = plugin.execute("success_cmd", &[]);
+        let result = plugin.execute("success_cmd", &[], &test_context());
         assert!(result.is_ok());
     }
     
@@ -95,19 +790,72 @@ mod tests {
     pub use crate::HelpMode;
     pub use crate::PluginError;
 
+    #[test]
+    fn test_execute_structured_defaults_to_execute() {
+        let ctx = test_context().with_output_format(OutputFormat::Json);
+        let value = MockSuccessPlugin
+            .execute_structured("success_cmd", &[], &ctx)
+            .unwrap();
+        assert_eq!(value, serde_json::Value::Null);
+        assert!(MockFailPlugin
+            .execute_structured("fail_cmd", &[], &ctx)
+            .is_err());
+    }
+
     #[test]
     fn test_plugin_execute_command_not_found() {
         let plugin = MockSuccessPlugin;
-        let result = plugin.execute("unknown_cmd", &[]);
+        let result = plugin.execute("unknown_cmd", &[], &test_context());
         assert!(result.is_err());
     }
 
     #[test]
     fn test_plugin_execute_failure() {
         let plugin = MockFailPlugin;
-        let result = plugin.execute("fail_cmd", &[]);
+        let result = plugin.execute("fail_cmd", &[], &test_context());
         assert!(result.is_err());
         let err_msg = format!("{}", result.unwrap_err());
         assert!(err_msg.contains("Simulated plugin failure"));
     }
-}
\ No newline at end of file
+
+    struct MockLifecyclePlugin {
+        loaded: bool,
+        unloaded: bool,
+    }
+    impl Plugin for MockLifecyclePlugin {
+        fn name(&self) -> &'static str {
+            "mock_lifecycle"
+        }
+        fn commands(&self) -> Vec<&'static str> {
+            vec![]
+        }
+        fn execute(&self, _command: &str, _args: &[String], _ctx: &PluginContext) -> Result<()> {
+            Ok(())
+        }
+        fn on_load(&mut self, _host: &HostInfo) -> Result<()> {
+            self.loaded = true;
+            Ok(())
+        }
+        fn on_unload(&mut self) {
+            self.unloaded = true;
+        }
+    }
+
+    #[test]
+    fn test_plugin_lifecycle_hooks() {
+        let mut plugin = MockLifecyclePlugin {
+            loaded: false,
+            unloaded: false,
+        };
+        let host = HostInfo::new(semver::Version::new(1, 0, 0));
+        plugin.on_load(&host).unwrap();
+        assert!(plugin.loaded);
+        plugin.on_unload();
+        assert!(plugin.unloaded);
+
+        // Default hooks are no-ops
+        let mut plugin = MockSuccessPlugin;
+        assert!(plugin.on_load(&host).is_ok());
+        plugin.on_unload();
+    }
+}
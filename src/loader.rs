@@ -0,0 +1,544 @@
+//! Loading `cdylib` plugins exported with [`declare_plugin!`](crate::declare_plugin).
+//!
+//! Every call into a [`LoadedPlugin`] goes through a [`PanicShield`], and
+//! `declare_plugin!` shields the plugin on the library side as well, so a
+//! panicking plugin surfaces as [`PluginError::Panicked`] instead of
+//! aborting the host.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use libloading::Library;
+use semver::VersionReq;
+
+use crate::ffi::{FfiPluginCreate, FfiPluginProxy, FFI_PLUGIN_CREATE_SYMBOL};
+use crate::{
+    check_compatibility, Capabilities, CommandInvocation, CommandOutcome, CommandSpec,
+    CompletionItem, Diagnostic, ExecutionPlan, HelpBody, HelpMode, HelpOutput, HookDecision,
+    HostFeatures, HostInfo, Locale, LocalizedHelp, PanicShield, Plugin, PluginConfig,
+    PluginContext, PluginCreate, PluginCreateV2, PluginDependency, PluginError, PluginMetadata,
+    PluginValue, RepoEvent, Shell, Signal, SignalResponse, UpdateSource, PLUGIN_API_VERSION,
+    PLUGIN_API_VERSION_SYMBOL, PLUGIN_CREATE_SYMBOL, PLUGIN_CREATE_V2_SYMBOL,
+};
+
+/// Opens plugin libraries.
+#[derive(Debug)]
+pub struct PluginLoader;
+
+impl PluginLoader {
+    /// Open the library at `path`, check its API version, construct the
+    /// plugin, check its [`required_host`](Plugin::required_host) against
+    /// `host` and run its `on_load`.
+    pub fn load(path: impl AsRef<Path>, host: &HostInfo) -> Result<LoadedPlugin, PluginError> {
+        let path = path.as_ref();
+        let load_error =
+            |e: libloading::Error| PluginError::LoadError(format!("{}: {}", path.display(), e));
+        // SAFETY: loading a library runs its initializers; plugins are
+        // trusted code chosen by the user.
+        let library = unsafe { Library::new(path) }.map_err(load_error)?;
+        let version = unsafe {
+            let symbol = library
+                .get::<*const u32>(PLUGIN_API_VERSION_SYMBOL)
+                .map_err(load_error)?;
+            **symbol
+        };
+        check_compatibility(PLUGIN_API_VERSION, version)?;
+
+        let plugin = Self::create(&library).map_err(|e| match e {
+            PluginError::LoadError(reason) => {
+                PluginError::LoadError(format!("{}: {}", path.display(), reason))
+            }
+            e => e,
+        })?;
+        LoadedPlugin::new(plugin, library, path.to_path_buf(), host)
+    }
+
+    /// Construct the plugin through `_plugin_create_v2`, or
+    /// `_plugin_create` if built before that existed. These pass
+    /// `dyn Plugin` across the Rust ABI, so host and plugin must come from
+    /// the same compiler. Libraries exporting only the ABI-stable
+    /// `_plugin_create_ffi` are loaded through it, with the reduced trait
+    /// described in [`ffi`](crate::ffi).
+    fn create(library: &Library) -> Result<Box<dyn Plugin>, PluginError> {
+        // SAFETY: all symbols are emitted by `declare_plugin!` with these
+        // signatures, and their pointers come from `Box::into_raw`.
+        unsafe {
+            if let Ok(create) = library.get::<PluginCreateV2>(PLUGIN_CREATE_V2_SYMBOL) {
+                return create().into_result();
+            }
+            let create = match library.get::<PluginCreate>(PLUGIN_CREATE_SYMBOL) {
+                Ok(create) => create,
+                Err(e) => match library.get::<FfiPluginCreate>(FFI_PLUGIN_CREATE_SYMBOL) {
+                    Ok(create) => return Self::create_ffi(*create),
+                    Err(_) => return Err(PluginError::LoadError(e.to_string())),
+                },
+            };
+            let raw = create();
+            if raw.is_null() {
+                return Err(PluginError::LoadError(
+                    "plugin constructor panicked".to_string(),
+                ));
+            }
+            Ok(Box::from_raw(raw))
+        }
+    }
+
+    /// # Safety
+    /// `create` must be a `_plugin_create_ffi` export of a library that
+    /// stays loaded for as long as the plugin exists.
+    unsafe fn create_ffi(create: FfiPluginCreate) -> Result<Box<dyn Plugin>, PluginError> {
+        Ok(Box::new(FfiPluginProxy::from_raw(create())?))
+    }
+
+    /// Unload `plugin` and load its library again, e.g. after a rebuild.
+    ///
+    /// The old instance is unloaded first because most platforms hand back
+    /// the already-mapped library while the same path is still open. A
+    /// library that stays mapped after closing (Rust cdylibs with
+    /// thread-local destructors can) keeps serving old code; hosts that hit
+    /// this copy the rebuilt file to a fresh path and [`load`](Self::load)
+    /// that instead.
+    pub fn reload(plugin: LoadedPlugin, host: &HostInfo) -> Result<LoadedPlugin, PluginError> {
+        let path = plugin.path().to_path_buf();
+        plugin.unload()?;
+        Self::load(path, host)
+    }
+}
+
+/// Source of [`LoadedPlugin::generation`].
+static GENERATION: AtomicU64 = AtomicU64::new(1);
+
+/// A plugin together with the library its code lives in. The plugin is
+/// always dropped (after `on_unload`) before the library is closed.
+///
+/// Values the plugin handed out that borrow from the library, such as the
+/// `&'static str`s in [`commands`](Plugin::commands), must not be used
+/// after the plugin is unloaded.
+pub struct LoadedPlugin {
+    plugin: Option<PanicShield<dyn Plugin>>,
+    /// Owned copy of the plugin's name, valid after the library is closed
+    name: &'static str,
+    path: PathBuf,
+    generation: u64,
+    library: Option<Library>,
+}
+
+impl LoadedPlugin {
+    fn new(
+        plugin: Box<dyn Plugin>,
+        library: Library,
+        path: PathBuf,
+        host: &HostInfo,
+    ) -> Result<Self, PluginError> {
+        let mut plugin = PanicShield::new(plugin);
+        host.check_required(&plugin)?;
+        if let Err(e) = plugin.on_load(host) {
+            // A plugin that failed to load is dropped without on_unload.
+            return Err(PluginError::LoadError(format!(
+                "{}: {:#}",
+                path.display(),
+                e
+            )));
+        }
+        let name = plugin.name().to_string();
+        Ok(Self {
+            plugin: Some(plugin),
+            name: Box::leak(name.into_boxed_str()),
+            path,
+            generation: GENERATION.fetch_add(1, Ordering::Relaxed),
+            library: Some(library),
+        })
+    }
+
+    /// Process-wide load counter, unique to this load. A host that reloads
+    /// a plugin can compare generations to detect state left over from a
+    /// previous build.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Unload now instead of on drop: run `on_unload`, drop the plugin,
+    /// then close the library, reporting failures that dropping would
+    /// only log.
+    pub fn unload(mut self) -> Result<(), PluginError> {
+        self.shutdown()
+    }
+
+    fn shutdown(&mut self) -> Result<(), PluginError> {
+        let Some(mut plugin) = self.plugin.take() else {
+            return Ok(());
+        };
+        plugin.on_unload();
+        let dropped = panic::catch_unwind(AssertUnwindSafe(|| drop(plugin)))
+            .map_err(|payload| PluginError::panicked(self.name, &*payload));
+        // Close the library even if the drop panicked; its code is no
+        // longer reachable either way.
+        let closed = match self.library.take() {
+            Some(library) => library.close().map_err(|e| {
+                PluginError::LoadError(format!("{}: failed to close: {}", self.path.display(), e))
+            }),
+            None => Ok(()),
+        };
+        dropped.and(closed)
+    }
+
+    /// The library this plugin was loaded from.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn plugin(&self) -> &PanicShield<dyn Plugin> {
+        self.plugin.as_ref().expect("plugin is present until drop")
+    }
+
+    fn plugin_mut(&mut self) -> &mut PanicShield<dyn Plugin> {
+        self.plugin.as_mut().expect("plugin is present until drop")
+    }
+}
+
+impl Plugin for LoadedPlugin {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn commands(&self) -> Vec<&'static str> {
+        self.plugin().commands()
+    }
+
+    fn version(&self) -> &'static str {
+        self.plugin().version()
+    }
+
+    fn description(&self) -> &'static str {
+        self.plugin().description()
+    }
+
+    fn metadata(&self) -> PluginMetadata {
+        self.plugin().metadata()
+    }
+
+    fn update_info(&self) -> Option<UpdateSource> {
+        self.plugin().update_info()
+    }
+
+    fn command_specs(&self) -> Vec<CommandSpec> {
+        self.plugin().command_specs()
+    }
+
+    fn resolve_command(&self, token: &str) -> Option<&'static str> {
+        self.plugin().resolve_command(token)
+    }
+
+    fn validate(&self, command: &str, args: &[String]) -> Result<(), PluginError> {
+        self.plugin().validate(command, args)
+    }
+
+    fn execute(&self, command: &str, args: &[String], ctx: &PluginContext) -> anyhow::Result<()> {
+        self.plugin().execute(command, args, ctx)
+    }
+
+    fn execute_structured(
+        &self,
+        command: &str,
+        args: &[String],
+        ctx: &PluginContext,
+    ) -> anyhow::Result<serde_json::Value> {
+        self.plugin().execute_structured(command, args, ctx)
+    }
+
+    fn execute_for_value(
+        &self,
+        command: &str,
+        args: &[String],
+        ctx: &PluginContext,
+    ) -> anyhow::Result<PluginValue> {
+        self.plugin().execute_for_value(command, args, ctx)
+    }
+
+    fn undo(&self, data: &serde_json::Value, ctx: &PluginContext) -> anyhow::Result<()> {
+        self.plugin().undo(data, ctx)
+    }
+
+    fn plan(
+        &self,
+        command: &str,
+        args: &[String],
+        ctx: &PluginContext,
+    ) -> anyhow::Result<Option<ExecutionPlan>> {
+        self.plugin().plan(command, args, ctx)
+    }
+
+    fn get_help_output(&self, args: &[String]) -> Option<(HelpMode, HelpBody)> {
+        self.plugin().get_help_output(args)
+    }
+
+    fn help_output(&self, args: &[String]) -> Option<(HelpMode, HelpOutput)> {
+        self.plugin().help_output(args)
+    }
+
+    fn localized_help(&self, locale: &Locale) -> Option<LocalizedHelp> {
+        self.plugin().localized_help(locale)
+    }
+
+    fn completions(&self, shell: Shell) -> Option<String> {
+        self.plugin().completions(shell)
+    }
+
+    fn complete(
+        &self,
+        command: &str,
+        arg_index: usize,
+        prefix: &str,
+        ctx: &PluginContext,
+    ) -> Vec<CompletionItem> {
+        self.plugin().complete(command, arg_index, prefix, ctx)
+    }
+
+    fn supports_dry_run(&self, command: &str) -> bool {
+        self.plugin().supports_dry_run(command)
+    }
+
+    fn priority(&self) -> i32 {
+        self.plugin().priority()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.plugin().capabilities()
+    }
+
+    fn dependencies(&self) -> Vec<PluginDependency> {
+        self.plugin().dependencies()
+    }
+
+    fn config_namespace(&self) -> &'static str {
+        self.plugin().config_namespace()
+    }
+
+    #[cfg(feature = "schema")]
+    fn config_schema(&self) -> Option<schemars::Schema> {
+        self.plugin().config_schema()
+    }
+
+    fn validate_config(&self, config: &PluginConfig) -> Result<(), PluginError> {
+        self.plugin().validate_config(config)
+    }
+
+    fn before_command(&self, invocation: &CommandInvocation, ctx: &PluginContext) -> HookDecision {
+        self.plugin().before_command(invocation, ctx)
+    }
+
+    fn after_command(
+        &self,
+        invocation: &CommandInvocation,
+        outcome: &CommandOutcome,
+        ctx: &PluginContext,
+    ) {
+        self.plugin().after_command(invocation, outcome, ctx)
+    }
+
+    fn on_signal(&self, signal: Signal) -> SignalResponse {
+        self.plugin().on_signal(signal)
+    }
+
+    fn on_repo_event(&self, event: &RepoEvent, ctx: &PluginContext) -> anyhow::Result<()> {
+        self.plugin().on_repo_event(event, ctx)
+    }
+
+    fn health_check(&self, ctx: &PluginContext) -> Vec<Diagnostic> {
+        self.plugin().health_check(ctx)
+    }
+
+    fn required_host(&self) -> VersionReq {
+        self.plugin().required_host()
+    }
+
+    fn requires_features(&self) -> HostFeatures {
+        self.plugin().requires_features()
+    }
+
+    fn migrate_state(&self, from_version: &str, state_dir: &Path) -> anyhow::Result<()> {
+        self.plugin().migrate_state(from_version, state_dir)
+    }
+
+    fn on_load(&mut self, host: &HostInfo) -> anyhow::Result<()> {
+        self.plugin_mut().on_load(host)
+    }
+
+    fn set_logger(&self, logger: &'static dyn log::Log, level: log::LevelFilter) {
+        self.plugin().set_logger(logger, level)
+    }
+
+    fn on_unload(&mut self) {
+        self.plugin_mut().on_unload()
+    }
+}
+
+impl Drop for LoadedPlugin {
+    fn drop(&mut self) {
+        if let Err(e) = self.shutdown() {
+            log::error!("{}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct Counted {
+        unloads: Arc<AtomicUsize>,
+        fail_load: bool,
+    }
+
+    impl Plugin for Counted {
+        fn name(&self) -> &'static str {
+            "counted"
+        }
+        fn commands(&self) -> Vec<&'static str> {
+            vec!["count"]
+        }
+        fn execute(
+            &self,
+            _command: &str,
+            _args: &[String],
+            _ctx: &PluginContext,
+        ) -> anyhow::Result<()> {
+            panic!("count exploded")
+        }
+        fn required_host(&self) -> VersionReq {
+            VersionReq::parse(">=1.2").unwrap()
+        }
+        fn on_load(&mut self, _host: &HostInfo) -> anyhow::Result<()> {
+            if self.fail_load {
+                anyhow::bail!("missing credentials");
+            }
+            Ok(())
+        }
+        fn on_unload(&mut self) {
+            self.unloads.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// The test binary itself stands in for a plugin library.
+    #[cfg(unix)]
+    fn load(fail_load: bool, unloads: &Arc<AtomicUsize>) -> Result<LoadedPlugin, PluginError> {
+        load_on(
+            &HostInfo::new(semver::Version::new(1, 4, 0)),
+            fail_load,
+            unloads,
+        )
+    }
+
+    #[cfg(unix)]
+    fn load_on(
+        host: &HostInfo,
+        fail_load: bool,
+        unloads: &Arc<AtomicUsize>,
+    ) -> Result<LoadedPlugin, PluginError> {
+        let plugin = Box::new(Counted {
+            unloads: unloads.clone(),
+            fail_load,
+        });
+        let library = libloading::os::unix::Library::this().into();
+        LoadedPlugin::new(plugin, library, PathBuf::from("libcounted.so"), host)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_loaded_plugin_lifecycle() {
+        let unloads = Arc::new(AtomicUsize::new(0));
+        let loaded = load(false, &unloads).unwrap();
+        assert_eq!(loaded.name(), "counted");
+        assert_eq!(loaded.path(), Path::new("libcounted.so"));
+        let ctx = PluginContext::new("/work", "/work", "1.0.0");
+        let err = loaded.execute("count", &[], &ctx).unwrap_err();
+        assert!(matches!(
+            PluginError::find(&err),
+            Some(PluginError::Panicked { .. })
+        ));
+        drop(loaded);
+        assert_eq!(unloads.load(Ordering::SeqCst), 1);
+
+        let err = load(true, &unloads).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Failed to load plugin: libcounted.so: missing credentials"
+        );
+        assert_eq!(unloads.load(Ordering::SeqCst), 1);
+
+        // Refused before on_load, so never unloaded either.
+        let old_host = HostInfo::new(semver::Version::new(1, 1, 0));
+        let err = load_on(&old_host, false, &unloads).err().unwrap();
+        assert!(matches!(err, PluginError::IncompatibleHost(_)));
+        assert_eq!(unloads.load(Ordering::SeqCst), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unload_and_generations() {
+        let unloads = Arc::new(AtomicUsize::new(0));
+        let first = load(false, &unloads).unwrap();
+        let generation = first.generation();
+        first.unload().unwrap();
+        assert_eq!(unloads.load(Ordering::SeqCst), 1);
+
+        let second = load(false, &unloads).unwrap();
+        assert!(second.generation() > generation);
+        drop(second);
+        assert_eq!(unloads.load(Ordering::SeqCst), 2);
+    }
+
+    extern "C" fn create_counted_ffi() -> crate::ffi::FfiPlugin {
+        crate::ffi::FfiPlugin::new(Box::new(Counted {
+            unloads: Arc::new(AtomicUsize::new(0)),
+            fail_load: false,
+        }))
+    }
+
+    extern "C" fn create_failed_ffi() -> crate::ffi::FfiPlugin {
+        crate::ffi::FfiPlugin::null()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_ffi_constructor_path() {
+        let plugin = unsafe { PluginLoader::create_ffi(create_counted_ffi) }.unwrap();
+        let library = libloading::os::unix::Library::this().into();
+        let host = HostInfo::new(semver::Version::new(1, 4, 0));
+        let loaded =
+            LoadedPlugin::new(plugin, library, PathBuf::from("libcounted.so"), &host).unwrap();
+        assert_eq!(loaded.name(), "counted");
+        assert_eq!(loaded.commands(), vec!["count"]);
+        // The FFI shim catches the panic on the plugin side.
+        let ctx = PluginContext::new("/work", "/work", "1.0.0");
+        let err = loaded.execute("count", &[], &ctx).unwrap_err();
+        assert_eq!(err.to_string(), "plugin panicked during execute");
+        loaded.unload().unwrap();
+
+        // required_host crosses the vtable, so an old host is refused
+        let plugin = unsafe { PluginLoader::create_ffi(create_counted_ffi) }.unwrap();
+        let library = libloading::os::unix::Library::this().into();
+        let old_host = HostInfo::new(semver::Version::new(1, 0, 0));
+        let err = LoadedPlugin::new(plugin, library, PathBuf::from("libcounted.so"), &old_host)
+            .err()
+            .unwrap();
+        assert!(matches!(err, PluginError::IncompatibleHost(_)));
+
+        let err = unsafe { PluginLoader::create_ffi(create_failed_ffi) }
+            .err()
+            .unwrap();
+        assert!(matches!(err, PluginError::LoadError(_)));
+    }
+
+    #[test]
+    fn test_load_missing_library() {
+        let host = HostInfo::new(semver::Version::new(1, 0, 0));
+        let err = PluginLoader::load("/nonexistent/libmeta_missing.so", &host)
+            .err()
+            .unwrap();
+        assert!(matches!(err, PluginError::LoadError(m) if m.starts_with("/nonexistent")));
+    }
+}
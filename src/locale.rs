@@ -0,0 +1,258 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{CommandSpec, Env, HelpMode, HelpOutput, Plugin};
+
+/// The user's language as a BCP 47-style tag such as `pt-BR`. Defaults
+/// to `en`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Locale(String);
+
+impl Locale {
+    /// Normalize `tag`, accepting POSIX forms like `pt_BR.UTF-8`.
+    pub fn new(tag: &str) -> Self {
+        let tag = tag.split(['.', '@']).next().unwrap_or_default();
+        let mut parts = tag.split(['_', '-']).filter(|p| !p.is_empty());
+        let language = match parts.next() {
+            Some(language) if language != "C" && language != "POSIX" => {
+                language.to_ascii_lowercase()
+            }
+            _ => return Self::default(),
+        };
+        let normalized = std::iter::once(language)
+            .chain(parts.map(|p| match p.len() {
+                2 => p.to_ascii_uppercase(),
+                _ => p.to_string(),
+            }))
+            .collect::<Vec<_>>()
+            .join("-");
+        Self(normalized)
+    }
+
+    /// The locale from `LC_ALL`, `LC_MESSAGES` or `LANG`, in that order,
+    /// as the host detects it at startup.
+    pub fn from_env(env: &Env) -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .find_map(|var| env.get(var).filter(|v| !v.is_empty()))
+            .map(Self::new)
+            .unwrap_or_default()
+    }
+
+    pub fn tag(&self) -> &str {
+        &self.0
+    }
+
+    /// The primary language subtag, e.g. `pt` for `pt-BR`.
+    pub fn language(&self) -> &str {
+        self.0.split('-').next().unwrap_or_default()
+    }
+
+    /// Tags to try when looking up a translation, most specific first:
+    /// `pt-BR`, then `pt`.
+    pub fn fallbacks(&self) -> Vec<&str> {
+        let mut tags: Vec<&str> = self
+            .0
+            .match_indices('-')
+            .map(|(i, _)| &self.0[..i])
+            .collect();
+        tags.push(&self.0);
+        tags.reverse();
+        tags
+    }
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Self("en".to_string())
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Translated help strings returned by
+/// [`Plugin::localized_help`](crate::Plugin::localized_help). Anything
+/// left out falls back to the plugin's own strings.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LocalizedHelp {
+    /// Command summaries, keyed by command path such as `"release prepare"`
+    pub about: BTreeMap<&'static str, &'static str>,
+    /// Argument help, keyed by command path and argument name
+    pub args: BTreeMap<(&'static str, &'static str), &'static str>,
+    /// Replacements for [`Plugin::get_help_output`] text, keyed by the help
+    /// topic: `""` for the top level, otherwise the command path
+    pub help: BTreeMap<&'static str, &'static str>,
+}
+
+impl LocalizedHelp {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn about(mut self, command: &'static str, text: &'static str) -> Self {
+        self.about.insert(command, text);
+        self
+    }
+
+    pub fn arg(mut self, command: &'static str, arg: &'static str, text: &'static str) -> Self {
+        self.args.insert((command, arg), text);
+        self
+    }
+
+    pub fn help(mut self, topic: &'static str, text: &'static str) -> Self {
+        self.help.insert(topic, text);
+        self
+    }
+
+    /// Replace summaries and argument help in `specs`, subcommands
+    /// included.
+    pub fn apply(&self, specs: &mut [CommandSpec]) {
+        self.apply_under("", specs);
+    }
+
+    fn apply_under(&self, parent: &str, specs: &mut [CommandSpec]) {
+        for spec in specs {
+            let path = if parent.is_empty() {
+                spec.name.to_string()
+            } else {
+                format!("{} {}", parent, spec.name)
+            };
+            if let Some(about) = self.about.get(path.as_str()) {
+                spec.about = about;
+            }
+            for arg in &mut spec.args {
+                if let Some(help) = self.args.get(&(path.as_str(), arg.name)) {
+                    arg.help = help;
+                }
+            }
+            self.apply_under(&path, &mut spec.subcommands);
+        }
+    }
+
+    /// The translated help text for `args`, as passed to
+    /// [`Plugin::get_help_output`].
+    pub fn help_text(&self, args: &[String]) -> Option<&'static str> {
+        self.help.get(args.join(" ").as_str()).copied()
+    }
+}
+
+/// `plugin`'s command specs with its translations for `locale` applied.
+pub fn localized_command_specs(plugin: &dyn Plugin, locale: &Locale) -> Vec<CommandSpec> {
+    let mut specs = plugin.command_specs();
+    if let Some(localized) = plugin.localized_help(locale) {
+        localized.apply(&mut specs);
+    }
+    specs
+}
+
+/// `plugin`'s help for `args`, with the text translated for `locale` when
+/// the plugin provides a translation. The mode is always the plugin's own.
+pub fn localized_help_output(
+    plugin: &dyn Plugin,
+    args: &[String],
+    locale: &Locale,
+) -> Option<(HelpMode, HelpOutput)> {
+    let (mode, output) = plugin.help_output(args)?;
+    let translated = plugin
+        .localized_help(locale)
+        .and_then(|localized| localized.help_text(args));
+    match translated {
+        Some(text) => Some((mode, HelpOutput::from_text(text))),
+        None => Some((mode, output)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArgSpec, HelpBody, PluginContext};
+
+    #[test]
+    fn test_locale_normalization() {
+        assert_eq!(Locale::new("pt_BR.UTF-8").tag(), "pt-BR");
+        assert_eq!(Locale::new("de").tag(), "de");
+        assert_eq!(Locale::new("zh-hant-tw").tag(), "zh-hant-TW");
+        assert_eq!(Locale::new("C.UTF-8"), Locale::default());
+        assert_eq!(Locale::new("pt-BR").fallbacks(), vec!["pt-BR", "pt"]);
+        assert_eq!(Locale::new("pt-BR").language(), "pt");
+
+        let env = Env::new()
+            .with_var("LANG", "fr_FR.UTF-8")
+            .with_var("LC_MESSAGES", "de_DE");
+        assert_eq!(Locale::from_env(&env).tag(), "de-DE");
+        assert_eq!(Locale::from_env(&Env::new()), Locale::default());
+    }
+
+    struct Translated;
+
+    impl Plugin for Translated {
+        fn name(&self) -> &'static str {
+            "git"
+        }
+        fn commands(&self) -> Vec<&'static str> {
+            vec!["clone"]
+        }
+        fn command_specs(&self) -> Vec<CommandSpec> {
+            vec![CommandSpec::new("clone")
+                .about("Clone all repos")
+                .arg(ArgSpec::flag("shallow").help("Depth 1"))
+                .subcommand(CommandSpec::new("missing").about("Clone missing repos"))]
+        }
+        fn execute(
+            &self,
+            _command: &str,
+            _args: &[String],
+            _ctx: &PluginContext,
+        ) -> anyhow::Result<()> {
+            Ok(())
+        }
+        fn get_help_output(&self, _args: &[String]) -> Option<(HelpMode, HelpBody)> {
+            Some((HelpMode::Append, "Git commands".into()))
+        }
+        fn localized_help(&self, locale: &Locale) -> Option<LocalizedHelp> {
+            (locale.language() == "pt").then(|| {
+                LocalizedHelp::new()
+                    .about("clone", "Clonar todos os repositórios")
+                    .arg("clone", "shallow", "Profundidade 1")
+                    .about("clone missing", "Clonar os que faltam")
+                    .help("", "Comandos git")
+            })
+        }
+    }
+
+    #[test]
+    fn test_localized_specs_and_help() {
+        let pt = Locale::new("pt_BR");
+        let specs = localized_command_specs(&Translated, &pt);
+        assert_eq!(specs[0].about, "Clonar todos os repositórios");
+        assert_eq!(specs[0].args[0].help, "Profundidade 1");
+        assert_eq!(specs[0].subcommands[0].about, "Clonar os que faltam");
+        assert_eq!(
+            localized_help_output(&Translated, &[], &pt)
+                .unwrap()
+                .1
+                .render(),
+            "Comandos git"
+        );
+
+        let en = Locale::default();
+        assert_eq!(
+            localized_command_specs(&Translated, &en)[0].about,
+            "Clone all repos"
+        );
+        assert_eq!(
+            localized_help_output(&Translated, &[], &en)
+                .unwrap()
+                .1
+                .render(),
+            "Git commands"
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use std::fmt;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::{state, CancellationToken, PluginError};
+
+/// How long [`PluginContext::lock`](crate::PluginContext::lock) waits for
+/// a lock held elsewhere before failing with
+/// [`PluginError::LockContended`].
+pub const DEFAULT_LOCK_WAIT: Duration = Duration::from_secs(30);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// What a [`LockGuard`] protects. Every plugin and every `meta` process
+/// working on the same workspace agrees on these, so a plugin rewriting
+/// `.meta` and another adding a project take turns.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LockScope {
+    /// The `.meta` file and anything else shared by the whole workspace
+    Workspace,
+    /// One repo, by project name
+    Repo(String),
+    /// Anything else, named by convention between the plugins sharing it,
+    /// e.g. `release-notes`
+    Named(String),
+}
+
+impl LockScope {
+    pub fn repo(name: impl Into<String>) -> Self {
+        LockScope::Repo(name.into())
+    }
+
+    pub fn named(name: impl Into<String>) -> Self {
+        LockScope::Named(name.into())
+    }
+
+    /// Lock file name, hashed so any project or lock name is a valid file
+    /// name and `Repo("x")` and `Named("x")` never collide.
+    fn file_name(&self) -> String {
+        match self {
+            LockScope::Workspace => "workspace.lock".to_string(),
+            LockScope::Repo(name) => {
+                format!("repo-{:016x}.lock", state::stable_hash(name.as_bytes()))
+            }
+            LockScope::Named(name) => {
+                format!("named-{:016x}.lock", state::stable_hash(name.as_bytes()))
+            }
+        }
+    }
+}
+
+impl fmt::Display for LockScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockScope::Workspace => f.write_str("workspace"),
+            LockScope::Repo(name) => write!(f, "repo:{}", name),
+            LockScope::Named(name) => f.write_str(name),
+        }
+    }
+}
+
+/// An exclusive lock on a [`LockScope`], released when dropped. Backed by
+/// an OS file lock, so it also excludes other processes and is released
+/// if the holder crashes.
+#[derive(Debug)]
+pub struct LockGuard {
+    scope: LockScope,
+    _file: File,
+}
+
+impl LockGuard {
+    /// Take the lock for `scope` among those kept in `dir`, failing with
+    /// [`PluginError::LockContended`] at once if it is held.
+    pub fn try_acquire(dir: &Path, scope: LockScope) -> Result<Self, PluginError> {
+        fs::create_dir_all(dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(dir.join(scope.file_name()))?;
+        match file.try_lock() {
+            Ok(()) => Ok(Self { scope, _file: file }),
+            Err(TryLockError::WouldBlock) => Err(PluginError::LockContended(scope.to_string())),
+            Err(TryLockError::Error(e)) => Err(e.into()),
+        }
+    }
+
+    /// Like [`try_acquire`](Self::try_acquire), retrying for up to `wait`
+    /// and giving up early with [`PluginError::Cancelled`] if `cancel` is
+    /// set.
+    pub fn acquire(
+        dir: &Path,
+        scope: LockScope,
+        wait: Duration,
+        cancel: &CancellationToken,
+    ) -> Result<Self, PluginError> {
+        let started = Instant::now();
+        loop {
+            match Self::try_acquire(dir, scope.clone()) {
+                Err(PluginError::LockContended(_)) if started.elapsed() < wait => {
+                    cancel.check()?;
+                    std::thread::sleep(POLL_INTERVAL.min(wait - started.elapsed()));
+                }
+                result => return result,
+            }
+        }
+    }
+
+    pub fn scope(&self) -> &LockScope {
+        &self.scope
+    }
+}
+
+/// Where the locks for the workspace at `workspace_root` live when the
+/// host does not choose: a per-workspace directory under the system temp
+/// directory, so lock files never show up in the workspace itself.
+pub fn default_lock_dir(workspace_root: &Path) -> PathBuf {
+    state::workspace_scoped_dir(&std::env::temp_dir().join("meta"), "locks", workspace_root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_excludes_until_dropped() {
+        let dir = default_lock_dir(Path::new(&format!("/work-{}", std::process::id())));
+        let guard = LockGuard::try_acquire(&dir, LockScope::Workspace).unwrap();
+        assert_eq!(guard.scope(), &LockScope::Workspace);
+
+        let err = LockGuard::acquire(
+            &dir,
+            LockScope::Workspace,
+            Duration::from_millis(60),
+            &CancellationToken::new(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Lock 'workspace' is held by another command"
+        );
+        let other = LockGuard::try_acquire(&dir, LockScope::repo("workspace")).unwrap();
+
+        drop(guard);
+        LockGuard::try_acquire(&dir, LockScope::Workspace).unwrap();
+        drop(other);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
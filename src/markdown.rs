@@ -0,0 +1,138 @@
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+
+const BOLD: &str = "\x1b[1m";
+const NOT_BOLD: &str = "\x1b[22m";
+const ITALIC: &str = "\x1b[3m";
+const NOT_ITALIC: &str = "\x1b[23m";
+const CODE: &str = "\x1b[36m";
+const NOT_CODE: &str = "\x1b[39m";
+
+/// Render CommonMark for a terminal: headings and `**strong**` in bold,
+/// `*emphasis*` in italics, code in cyan, code blocks indented, list
+/// items bulleted and links followed by their URL. Without `color` the
+/// same layout is produced with no escape codes.
+pub fn render_markdown(markdown: &str, color: bool) -> String {
+    let style = |code: &'static str| if color { code } else { "" };
+    let mut out = String::new();
+    // Next number of each open list; None for bullet lists
+    let mut lists: Vec<Option<u64>> = Vec::new();
+    let mut in_code_block = false;
+    let mut link_urls = Vec::new();
+
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Start(Tag::Heading { .. }) | Event::Start(Tag::Strong) => {
+                out.push_str(style(BOLD))
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                out.push_str(style(NOT_BOLD));
+                out.push_str("\n\n");
+            }
+            Event::End(TagEnd::Strong) => out.push_str(style(NOT_BOLD)),
+            Event::Start(Tag::Emphasis) => out.push_str(style(ITALIC)),
+            Event::End(TagEnd::Emphasis) => out.push_str(style(NOT_ITALIC)),
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(TagEnd::CodeBlock) => {
+                in_code_block = false;
+                out.push('\n');
+            }
+            Event::Start(Tag::List(start)) => {
+                if lists.is_empty() && !out.is_empty() && !out.ends_with("\n\n") {
+                    out.push('\n');
+                }
+                lists.push(start);
+            }
+            Event::End(TagEnd::List(_)) => {
+                lists.pop();
+                if lists.is_empty() {
+                    out.push('\n');
+                }
+            }
+            Event::Start(Tag::Item) => {
+                if !out.is_empty() && !out.ends_with('\n') {
+                    out.push('\n');
+                }
+                out.push_str(&"  ".repeat(lists.len().saturating_sub(1)));
+                match lists.last_mut() {
+                    Some(Some(number)) => {
+                        out.push_str(&format!("{}. ", number));
+                        *number += 1;
+                    }
+                    _ => out.push_str("- "),
+                }
+            }
+            Event::End(TagEnd::Item) if !out.ends_with('\n') => out.push('\n'),
+            Event::End(TagEnd::Paragraph) => {
+                out.push_str(if lists.is_empty() { "\n\n" } else { "\n" })
+            }
+            Event::Start(Tag::Link { dest_url, .. }) => link_urls.push(dest_url),
+            Event::End(TagEnd::Link) => {
+                if let Some(url) = link_urls.pop() {
+                    out.push_str(&format!(" ({})", url));
+                }
+            }
+            Event::Text(text) if in_code_block => {
+                for line in text.lines() {
+                    out.push_str(&format!("    {}{}{}\n", style(CODE), line, style(NOT_CODE)));
+                }
+            }
+            Event::Text(text) => out.push_str(&text),
+            Event::Code(code) => {
+                out.push_str(style(CODE));
+                out.push_str(&code);
+                out.push_str(style(NOT_CODE));
+            }
+            Event::SoftBreak | Event::HardBreak => out.push('\n'),
+            Event::Rule => out.push_str("---\n\n"),
+            _ => {}
+        }
+    }
+    let mut out = out.trim_end().to_string();
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HELP: &str = "\
+# Release
+
+Cut a **release** of *every* repo with `meta release`.
+
+- prepare
+- publish
+
+1. Tag
+2. Push
+
+```
+meta release prepare 1.2.0
+```
+
+See [the guide](https://example.com).
+";
+
+    #[test]
+    fn test_render_plain() {
+        assert_eq!(
+            render_markdown(HELP, false),
+            "Release\n\n\
+             Cut a release of every repo with meta release.\n\n\
+             - prepare\n- publish\n\n\
+             1. Tag\n2. Push\n\n\
+             \x20   meta release prepare 1.2.0\n\n\
+             See the guide (https://example.com).\n"
+        );
+    }
+
+    #[test]
+    fn test_render_color() {
+        let rendered = render_markdown(HELP, true);
+        assert!(rendered.starts_with("\x1b[1mRelease\x1b[22m\n\n"));
+        assert!(rendered.contains("a \x1b[1mrelease\x1b[22m of \x1b[3mevery\x1b[23m"));
+        assert!(rendered.contains("with \x1b[36mmeta release\x1b[39m."));
+        assert!(rendered.contains("    \x1b[36mmeta release prepare 1.2.0\x1b[39m\n"));
+    }
+}
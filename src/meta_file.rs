@@ -0,0 +1,572 @@
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::{state, PluginError, ProjectInfo};
+
+/// Name of the workspace file listing projects, in the workspace root.
+pub const META_FILE: &str = ".meta";
+
+const INDENT: &str = "  ";
+
+/// The `.meta` file, edited in place: changes touch only the text they
+/// concern, so comments, key order and formatting elsewhere survive.
+/// Comments (`//` and `/* */`) and trailing commas are accepted.
+///
+/// Plugins usually edit through
+/// [`PluginContext::edit_meta`](crate::PluginContext::edit_meta), which
+/// holds the workspace lock while the file is read and written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetaFile {
+    path: PathBuf,
+    text: String,
+    modified: bool,
+}
+
+impl MetaFile {
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, PluginError> {
+        let path = path.into();
+        let text = std::fs::read_to_string(&path)?;
+        Self::parse(path, text)
+    }
+
+    /// A file at `path` with contents `text`, which must be a JSON object.
+    pub fn parse(path: impl Into<PathBuf>, text: impl Into<String>) -> Result<Self, PluginError> {
+        let file = Self {
+            path: path.into(),
+            text: text.into(),
+            modified: false,
+        };
+        file.root()?;
+        Ok(file)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Whether anything changed since the file was loaded or saved.
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    /// The file as JSON, without comments.
+    pub fn value(&self) -> Result<Value, PluginError> {
+        let root = self.root()?;
+        Ok(root.to_value(&self.text))
+    }
+
+    /// Names in the `projects` map, in file order.
+    pub fn project_names(&self) -> Result<Vec<String>, PluginError> {
+        let root = self.root()?;
+        Ok(root
+            .member("projects")
+            .map(|projects| {
+                projects
+                    .value
+                    .members
+                    .iter()
+                    .map(|m| m.key.clone())
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// Add `project` to the `projects` map, as a bare URL when its path is
+    /// its name and it has no tags. Returns false, changing nothing, if a
+    /// project of that name is already listed.
+    pub fn add_project(&mut self, project: &ProjectInfo) -> Result<bool, PluginError> {
+        if self.project_names()?.contains(&project.name) {
+            return Ok(false);
+        }
+        let value = if project.path == Path::new(&project.name) && project.tags.is_empty() {
+            Value::from(project.repo.clone())
+        } else {
+            let mut entry = serde_json::json!({
+                "repo": project.repo,
+                "path": project.path,
+            });
+            if !project.tags.is_empty() {
+                entry["tags"] = Value::from(project.tags.clone());
+            }
+            entry
+        };
+        // Project names may contain dots, so the key is not split
+        self.set_keys(&["projects", &project.name], value)?;
+        Ok(true)
+    }
+
+    /// Remove the project called `name`, with its line and any comment
+    /// on that line. Returns whether it was listed.
+    pub fn remove_project(&mut self, name: &str) -> Result<bool, PluginError> {
+        let root = self.root()?;
+        let Some(projects) = root.member("projects") else {
+            return Ok(false);
+        };
+        let Some(index) = projects.value.members.iter().position(|m| m.key == name) else {
+            return Ok(false);
+        };
+        self.remove_member(&projects.value, index);
+        Ok(true)
+    }
+
+    /// Set the field at the dot-separated `path`, e.g. `git.remote`,
+    /// creating objects along the way. An existing value is replaced in
+    /// place; a new key goes after the object's last member.
+    pub fn set_field(&mut self, path: &str, value: impl Into<Value>) -> Result<(), PluginError> {
+        let keys: Vec<&str> = path.split('.').collect();
+        self.set_keys(&keys, value.into())
+    }
+
+    /// [`set_field`](Self::set_field) with the path already split into
+    /// keys, which are taken literally.
+    fn set_keys(&mut self, keys: &[&str], value: Value) -> Result<(), PluginError> {
+        let root = self.root()?;
+        let mut node = &root;
+        for (i, key) in keys.iter().enumerate() {
+            if !node.is_object {
+                return Err(PluginError::config(format!(
+                    "{}: '{}' is not an object",
+                    META_FILE,
+                    keys[..i].join(".")
+                )));
+            }
+            match node.member(key) {
+                Some(member) if i + 1 == keys.len() => {
+                    let rendered = render(&value, &self.indent_at(member.key_start));
+                    self.splice(member.value.start..member.value.end, &rendered);
+                    return Ok(());
+                }
+                Some(member) => node = &member.value,
+                None => {
+                    let nested = keys[i + 1..]
+                        .iter()
+                        .rev()
+                        .fold(value, |inner, key| serde_json::json!({ *key: inner }));
+                    self.insert_member(node, key, &nested);
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the file back, atomically.
+    pub fn save(&mut self) -> Result<(), PluginError> {
+        state::write_atomic(&self.path, self.text.as_bytes())?;
+        self.modified = false;
+        Ok(())
+    }
+
+    fn root(&self) -> Result<Node, PluginError> {
+        let mut parser = Parser {
+            text: &self.text,
+            pos: 0,
+        };
+        parser.skip_trivia()?;
+        let root = parser.value()?;
+        parser.skip_trivia()?;
+        if parser.pos != self.text.len() {
+            return Err(parser.error("unexpected text after the top-level object"));
+        }
+        if !root.is_object {
+            return Err(PluginError::config(format!(
+                "{}: expected a JSON object",
+                META_FILE
+            )));
+        }
+        Ok(root)
+    }
+
+    fn insert_member(&mut self, object: &Node, key: &str, value: &Value) {
+        let key = Value::from(key).to_string();
+        match object.members.last() {
+            Some(last) => {
+                let indent = self.indent_at(last.key_start);
+                let line = format!("\n{}{}: {}", indent, key, render(value, &indent));
+                let after_value = skip_trivia(&self.text, last.value.end);
+                if self.text[after_value..].starts_with(',') {
+                    let end = self.line_end(after_value + 1);
+                    self.splice(end..end, &format!("{},", line));
+                } else {
+                    let end = self.line_end(last.value.end);
+                    self.splice(end..end, &line);
+                    self.splice(last.value.end..last.value.end, ",");
+                }
+            }
+            None => {
+                let outer = self.indent_at(object.start);
+                let inner = format!("{}{}", outer, INDENT);
+                let member = format!("\n{}{}: {}", inner, key, render(value, &inner));
+                if self.text[object.start + 1..object.end - 1]
+                    .trim()
+                    .is_empty()
+                {
+                    self.splice(
+                        object.start..object.end,
+                        &format!("{{{}\n{}}}", member, outer),
+                    );
+                } else {
+                    self.splice(object.start + 1..object.start + 1, &member);
+                }
+            }
+        }
+    }
+
+    fn remove_member(&mut self, object: &Node, index: usize) {
+        let member = &object.members[index];
+        let line_start = self.line_start(member.key_start);
+        let own_line = self.text[line_start..member.key_start].trim().is_empty();
+        let begin = if own_line {
+            line_start
+        } else {
+            member.key_start
+        };
+        let after_value = skip_trivia(&self.text, member.value.end);
+        let has_comma = self.text[after_value..].starts_with(',');
+        let mut end = self.line_end(if has_comma {
+            after_value + 1
+        } else {
+            member.value.end
+        });
+        if own_line {
+            if self.text[end..].starts_with("\r\n") {
+                end += 2;
+            } else if self.text[end..].starts_with('\n') {
+                end += 1;
+            }
+        }
+        self.splice(begin..end, "");
+        if !has_comma && index > 0 {
+            // The previous member's comma now precedes nothing.
+            let comma = skip_trivia(&self.text, object.members[index - 1].value.end);
+            self.splice(comma..comma + 1, "");
+        }
+    }
+
+    fn splice(&mut self, range: std::ops::Range<usize>, with: &str) {
+        self.text.replace_range(range, with);
+        self.modified = true;
+    }
+
+    fn line_start(&self, pos: usize) -> usize {
+        self.text[..pos].rfind('\n').map_or(0, |i| i + 1)
+    }
+
+    /// Past spaces and a `//` comment following `pos` on its line.
+    fn line_end(&self, pos: usize) -> usize {
+        let rest = &self.text[pos..];
+        let trimmed = rest.trim_start_matches([' ', '\t']);
+        let pos = pos + rest.len() - trimmed.len();
+        if trimmed.starts_with("//") {
+            pos + trimmed.find(['\r', '\n']).unwrap_or(trimmed.len())
+        } else {
+            pos
+        }
+    }
+
+    /// Leading whitespace of the line containing `pos`.
+    fn indent_at(&self, pos: usize) -> String {
+        let line = &self.text[self.line_start(pos)..];
+        line[..line.len() - line.trim_start_matches([' ', '\t']).len()].to_string()
+    }
+}
+
+/// `value` as pretty JSON, continuation lines indented by `indent`.
+fn render(value: &Value, indent: &str) -> String {
+    let pretty = serde_json::to_string_pretty(value).expect("JSON value serializes");
+    pretty.replace('\n', &format!("\n{}", indent))
+}
+
+/// Past whitespace and comments from `pos`, or `pos` on an unterminated
+/// comment.
+fn skip_trivia(text: &str, pos: usize) -> usize {
+    let mut parser = Parser { text, pos };
+    match parser.skip_trivia() {
+        Ok(()) => parser.pos,
+        Err(_) => pos,
+    }
+}
+
+/// A parsed value and where it sits in the text.
+#[derive(Debug)]
+struct Node {
+    start: usize,
+    end: usize,
+    is_object: bool,
+    members: Vec<Member>,
+    elements: Vec<Node>,
+}
+
+#[derive(Debug)]
+struct Member {
+    key: String,
+    key_start: usize,
+    value: Node,
+}
+
+impl Node {
+    fn member(&self, key: &str) -> Option<&Member> {
+        self.members.iter().find(|m| m.key == key)
+    }
+
+    fn to_value(&self, text: &str) -> Value {
+        if self.is_object {
+            Value::Object(
+                self.members
+                    .iter()
+                    .map(|m| (m.key.clone(), m.value.to_value(text)))
+                    .collect(),
+            )
+        } else if text[self.start..].starts_with('[') {
+            Value::Array(self.elements.iter().map(|e| e.to_value(text)).collect())
+        } else {
+            // Scalars were validated while parsing.
+            serde_json::from_str(&text[self.start..self.end]).unwrap_or(Value::Null)
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    fn error(&self, message: &str) -> PluginError {
+        let line = self.text[..self.pos].matches('\n').count() + 1;
+        PluginError::config(format!("{} line {}: {}", META_FILE, line, message))
+    }
+
+    fn skip_trivia(&mut self) -> Result<(), PluginError> {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            if trimmed.starts_with("//") {
+                self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else if let Some(comment) = trimmed.strip_prefix("/*") {
+                let end = comment
+                    .find("*/")
+                    .ok_or_else(|| self.error("unterminated comment"))?;
+                self.pos += end + 4;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn eat(&mut self, c: char) -> Result<(), PluginError> {
+        if self.rest().starts_with(c) {
+            self.pos += c.len_utf8();
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", c)))
+        }
+    }
+
+    fn value(&mut self) -> Result<Node, PluginError> {
+        let start = self.pos;
+        let mut node = Node {
+            start,
+            end: start,
+            is_object: false,
+            members: Vec::new(),
+            elements: Vec::new(),
+        };
+        match self.rest().chars().next() {
+            Some('{') => {
+                node.is_object = true;
+                self.pos += 1;
+                while !self.at_close('}')? {
+                    let key_start = self.pos;
+                    let key = self.string()?;
+                    self.skip_trivia()?;
+                    self.eat(':')?;
+                    self.skip_trivia()?;
+                    let value = self.value()?;
+                    node.members.push(Member {
+                        key,
+                        key_start,
+                        value,
+                    });
+                    if self.separator('}')? {
+                        break;
+                    }
+                }
+            }
+            Some('[') => {
+                self.pos += 1;
+                while !self.at_close(']')? {
+                    node.elements.push(self.value()?);
+                    if self.separator(']')? {
+                        break;
+                    }
+                }
+            }
+            Some('"') => {
+                self.string()?;
+            }
+            Some(_) => {
+                let len = self
+                    .rest()
+                    .find(|c: char| c.is_whitespace() || ",:]}/".contains(c))
+                    .unwrap_or(self.rest().len());
+                serde_json::from_str::<Value>(&self.rest()[..len])
+                    .map_err(|_| self.error("invalid value"))?;
+                self.pos += len;
+            }
+            None => return Err(self.error("unexpected end of file")),
+        }
+        node.end = self.pos;
+        Ok(node)
+    }
+
+    /// Before an element of an object or array: consume `close` and
+    /// return true if there are no more elements.
+    fn at_close(&mut self, close: char) -> Result<bool, PluginError> {
+        self.skip_trivia()?;
+        let closed = self.rest().starts_with(close);
+        if closed {
+            self.pos += 1;
+        }
+        Ok(closed)
+    }
+
+    /// After an element: consume a comma, or `close` and return true.
+    fn separator(&mut self, close: char) -> Result<bool, PluginError> {
+        self.skip_trivia()?;
+        if self.rest().starts_with(',') {
+            self.pos += 1;
+            return Ok(false);
+        }
+        self.eat(close)?;
+        Ok(true)
+    }
+
+    fn string(&mut self) -> Result<String, PluginError> {
+        let start = self.pos;
+        self.eat('"')?;
+        let mut escaped = false;
+        let len = self
+            .rest()
+            .char_indices()
+            .find(|&(_, c)| {
+                let closing = c == '"' && !escaped;
+                escaped = c == '\\' && !escaped;
+                closing
+            })
+            .map(|(i, _)| i)
+            .ok_or_else(|| self.error("unterminated string"))?;
+        self.pos += len + 1;
+        serde_json::from_str(&self.text[start..self.pos]).map_err(|_| self.error("invalid string"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const META: &str = r#"{
+  // Services
+  "projects": {
+    "api": "git@example.com:org/api.git", // the backend
+    "web": "git@example.com:org/web.git"
+  },
+  /* plugin settings */
+  "git": { "remote": "origin" }
+}
+"#;
+
+    fn meta() -> MetaFile {
+        MetaFile::parse("/work/.meta", META).unwrap()
+    }
+
+    #[test]
+    fn test_add_and_remove_keep_comments() {
+        let mut meta = meta();
+        assert_eq!(meta.project_names().unwrap(), ["api", "web"]);
+        assert!(meta
+            .add_project(&ProjectInfo::new(
+                "docs",
+                "docs",
+                "git@example.com:org/docs.git"
+            ))
+            .unwrap());
+        assert!(!meta
+            .add_project(&ProjectInfo::new("api", "api", "x"))
+            .unwrap());
+        assert!(meta.remove_project("api").unwrap());
+        assert!(!meta.remove_project("api").unwrap());
+        assert!(meta.is_modified());
+        assert_eq!(
+            meta.text(),
+            r#"{
+  // Services
+  "projects": {
+    "web": "git@example.com:org/web.git",
+    "docs": "git@example.com:org/docs.git"
+  },
+  /* plugin settings */
+  "git": { "remote": "origin" }
+}
+"#
+        );
+
+        assert!(meta.remove_project("docs").unwrap());
+        assert!(meta.remove_project("web").unwrap());
+        assert_eq!(meta.value().unwrap()["projects"], serde_json::json!({}));
+        meta.add_project(&ProjectInfo::new("cli", "tools/cli", "u").with_tags(["rust"]))
+            .unwrap();
+        assert_eq!(
+            meta.value().unwrap()["projects"]["cli"],
+            serde_json::json!({"repo": "u", "path": "tools/cli", "tags": ["rust"]})
+        );
+
+        // Dots in a name are part of the key, not a path
+        assert!(meta
+            .add_project(&ProjectInfo::new("my.repo", "my.repo", "m"))
+            .unwrap());
+        assert!(meta
+            .add_project(&ProjectInfo::new("cli.docs", "cli.docs", "d"))
+            .unwrap());
+        assert_eq!(
+            meta.project_names().unwrap(),
+            ["cli", "my.repo", "cli.docs"]
+        );
+        assert_eq!(meta.value().unwrap()["projects"]["my.repo"], "m");
+        assert!(meta.remove_project("my.repo").unwrap());
+    }
+
+    #[test]
+    fn test_set_field() {
+        let mut meta = meta();
+        meta.set_field("git.remote", "upstream").unwrap();
+        assert!(meta.text().contains(r#""git": { "remote": "upstream" }"#));
+        meta.set_field("release.sign", true).unwrap();
+        assert!(meta.text().ends_with(
+            "  \"git\": { \"remote\": \"upstream\" },\n  \"release\": {\n    \"sign\": true\n  }\n}\n"
+        ));
+        assert!(meta.text().contains("/* plugin settings */"));
+        assert!(matches!(
+            meta.set_field("git.remote.url", "x"),
+            Err(PluginError::ConfigError(_))
+        ));
+
+        let trailing = MetaFile::parse(".meta", "{\"a\": [1, 2,], // c\n}").unwrap();
+        assert_eq!(trailing.value().unwrap(), serde_json::json!({"a": [1, 2]}));
+        let err = MetaFile::parse(".meta", "{\n  \"a\": nope\n}").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Configuration error: .meta line 2: invalid value"
+        );
+    }
+}
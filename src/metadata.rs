@@ -0,0 +1,87 @@
+use serde::Serialize;
+
+/// Descriptive information about a plugin for `meta plugin list` and
+/// plugin registries. Everything is optional.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct PluginMetadata {
+    /// One line for search results, e.g. `"Jira issues for every repo"`
+    pub tagline: Option<&'static str>,
+    pub authors: Vec<&'static str>,
+    pub homepage: Option<&'static str>,
+    pub repository: Option<&'static str>,
+    /// SPDX license expression, e.g. `"MIT OR Apache-2.0"`
+    pub license: Option<&'static str>,
+    /// Search terms for registries
+    pub keywords: Vec<&'static str>,
+    /// Broad groupings for browsing, e.g. `"git"` or `"release"`
+    pub categories: Vec<&'static str>,
+}
+
+impl PluginMetadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn tagline(mut self, tagline: &'static str) -> Self {
+        self.tagline = Some(tagline);
+        self
+    }
+
+    pub fn author(mut self, author: &'static str) -> Self {
+        self.authors.push(author);
+        self
+    }
+
+    pub fn homepage(mut self, url: &'static str) -> Self {
+        self.homepage = Some(url);
+        self
+    }
+
+    pub fn repository(mut self, url: &'static str) -> Self {
+        self.repository = Some(url);
+        self
+    }
+
+    pub fn license(mut self, license: &'static str) -> Self {
+        self.license = Some(license);
+        self
+    }
+
+    pub fn keyword(mut self, keyword: &'static str) -> Self {
+        self.keywords.push(keyword);
+        self
+    }
+
+    pub fn category(mut self, category: &'static str) -> Self {
+        self.categories.push(category);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata_serializes() {
+        let metadata = PluginMetadata::new()
+            .author("Ada <ada@example.com>")
+            .repository("https://github.com/example/meta-release")
+            .license("MIT")
+            .keyword("release")
+            .category("vcs");
+        assert_eq!(
+            serde_json::to_value(&metadata).unwrap(),
+            serde_json::json!({
+                "tagline": null,
+                "authors": ["Ada <ada@example.com>"],
+                "homepage": null,
+                "repository": "https://github.com/example/meta-release",
+                "license": "MIT",
+                "keywords": ["release"],
+                "categories": ["vcs"]
+            })
+        );
+    }
+}
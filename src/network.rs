@@ -0,0 +1,123 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Env;
+
+/// Set to any non-empty value other than `0` to tell plugins not to use
+/// the network (`meta --offline`).
+pub const OFFLINE_ENV: &str = "META_OFFLINE";
+
+/// How the host reaches the network, from
+/// [`PluginContext::network`](crate::PluginContext::network). Plugins
+/// making their own connections use this instead of reading proxy
+/// variables themselves, so corporate proxies and CA bundles configured
+/// once in the host apply everywhere.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkSettings {
+    /// Proxy URL for `http://` requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_proxy: Option<String>,
+    /// Proxy URL for `https://` requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub https_proxy: Option<String>,
+    /// Hosts that bypass the proxy: exact names, domain suffixes such as
+    /// `.corp.example.com`, or `*` for all
+    #[serde(default)]
+    pub no_proxy: Vec<String>,
+    /// PEM file of extra trusted certificates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_bundle: Option<PathBuf>,
+    /// Whether the user asked for no network access at all
+    #[serde(default)]
+    pub offline: bool,
+}
+
+impl NetworkSettings {
+    /// Settings from the conventional variables in `env`: `HTTP_PROXY`,
+    /// `HTTPS_PROXY`, `NO_PROXY` (or their lowercase forms),
+    /// `SSL_CERT_FILE` and [`OFFLINE_ENV`].
+    pub fn from_env(env: &Env) -> Self {
+        let var = |name: &str| {
+            env.get(name)
+                .or_else(|| env.get(&name.to_lowercase()))
+                .filter(|v| !v.is_empty())
+        };
+        Self {
+            http_proxy: var("HTTP_PROXY").map(str::to_string),
+            https_proxy: var("HTTPS_PROXY").map(str::to_string),
+            no_proxy: var("NO_PROXY")
+                .map(|list| {
+                    list.split(',')
+                        .map(str::trim)
+                        .filter(|h| !h.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            ca_bundle: var("SSL_CERT_FILE").map(PathBuf::from),
+            offline: matches!(env.get(OFFLINE_ENV), Some(v) if !v.is_empty() && v != "0"),
+        }
+    }
+
+    /// The proxy to use for `url`, None for a direct connection.
+    pub fn proxy_for(&self, url: &str) -> Option<&str> {
+        let (scheme, rest) = url.split_once("://")?;
+        let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+        let host = authority.rsplit('@').next().unwrap_or_default();
+        let host = match host.rsplit_once(':') {
+            Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+            _ => host,
+        };
+        if self.bypasses_proxy(host) {
+            return None;
+        }
+        match scheme.to_ascii_lowercase().as_str() {
+            "https" => self.https_proxy.as_deref(),
+            "http" => self.http_proxy.as_deref(),
+            _ => None,
+        }
+    }
+
+    fn bypasses_proxy(&self, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+        self.no_proxy.iter().any(|entry| {
+            let entry = entry.to_ascii_lowercase();
+            let domain = entry.trim_start_matches('.');
+            entry == "*"
+                || host == domain
+                || host
+                    .strip_suffix(domain)
+                    .is_some_and(|prefix| prefix.ends_with('.'))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_env_and_proxy_for() {
+        let env = Env::new()
+            .with_var("https_proxy", "http://proxy.corp:3128")
+            .with_var("NO_PROXY", "localhost, .corp.example.com")
+            .with_var("SSL_CERT_FILE", "/etc/corp-ca.pem")
+            .with_var(OFFLINE_ENV, "0");
+        let settings = NetworkSettings::from_env(&env);
+        assert_eq!(settings.ca_bundle, Some(PathBuf::from("/etc/corp-ca.pem")));
+        assert!(!settings.offline);
+
+        assert_eq!(
+            settings.proxy_for("https://api.github.com/repos"),
+            Some("http://proxy.corp:3128")
+        );
+        assert_eq!(settings.proxy_for("http://api.github.com"), None);
+        assert_eq!(settings.proxy_for("https://localhost:8080/x"), None);
+        assert_eq!(settings.proxy_for("https://git.corp.example.com"), None);
+        assert_eq!(
+            settings.proxy_for("https://notcorp.example.com"),
+            Some("http://proxy.corp:3128")
+        );
+    }
+}
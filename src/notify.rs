@@ -0,0 +1,62 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// How urgent a [`Notification`] is; hosts may pick an icon or sound by
+/// it, or only show failures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifyLevel {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for NotifyLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            NotifyLevel::Info => "info",
+            NotifyLevel::Success => "success",
+            NotifyLevel::Warning => "warning",
+            NotifyLevel::Error => "error",
+        })
+    }
+}
+
+/// An alert for the user, from
+/// [`PluginContext::notify`](crate::PluginContext::notify).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Notification {
+    pub title: String,
+    #[serde(default)]
+    pub body: String,
+    pub level: NotifyLevel,
+}
+
+/// Host-side backend for notifications: a desktop notification, the
+/// terminal bell, or nothing in CI. Delivery is best effort.
+pub trait Notifier: Send + Sync {
+    fn notify(&self, notification: &Notification);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notification_serde() {
+        let notification = Notification {
+            title: "meta git pull".to_string(),
+            body: "12 repos updated".to_string(),
+            level: NotifyLevel::Success,
+        };
+        let json = serde_json::to_value(&notification).unwrap();
+        assert_eq!(json["level"], "success");
+        assert_eq!(
+            serde_json::from_value::<Notification>(json).unwrap(),
+            notification
+        );
+        assert_eq!(NotifyLevel::Warning.to_string(), "warning");
+    }
+}
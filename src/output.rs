@@ -0,0 +1,188 @@
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+mod diff;
+mod table;
+
+pub use diff::{render_diff, DiffOptions};
+pub use table::{Align, Borders, Column, Table};
+
+/// Which of the user's output streams a write is meant for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Host-side destination of plugin output. The host decides whether it
+/// reaches the terminal, a log file, a per-repo prefixer or nowhere.
+pub trait OutputSink: Send + Sync {
+    fn write(&self, stream: OutputStream, bytes: &[u8]) -> io::Result<()>;
+
+    /// One complete line (including its `\n`) from a [`ScopedWriter`]
+    /// labeled `label`. The default renders `[label] line` in a single
+    /// write, so lines from parallel repos never interleave mid-line.
+    fn write_scoped(&self, label: &str, stream: OutputStream, line: &[u8]) -> io::Result<()> {
+        let mut prefixed = format!("[{}] ", label).into_bytes();
+        prefixed.extend_from_slice(line);
+        self.write(stream, &prefixed)
+    }
+}
+
+/// Writes straight to the process's stdout and stderr; what plugins get
+/// unless the host installs its own sink.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdioSink;
+
+impl OutputSink for StdioSink {
+    fn write(&self, stream: OutputStream, bytes: &[u8]) -> io::Result<()> {
+        match stream {
+            OutputStream::Stdout => io::stdout().write_all(bytes),
+            OutputStream::Stderr => io::stderr().write_all(bytes),
+        }
+    }
+}
+
+/// Keeps everything written to it, e.g. for JSON mode or tests.
+#[derive(Debug, Default)]
+pub struct CapturedOutput {
+    stdout: Mutex<Vec<u8>>,
+    stderr: Mutex<Vec<u8>>,
+}
+
+impl CapturedOutput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn stdout(&self) -> String {
+        String::from_utf8_lossy(&self.stdout.lock().unwrap()).into_owned()
+    }
+
+    pub fn stderr(&self) -> String {
+        String::from_utf8_lossy(&self.stderr.lock().unwrap()).into_owned()
+    }
+}
+
+impl OutputSink for CapturedOutput {
+    fn write(&self, stream: OutputStream, bytes: &[u8]) -> io::Result<()> {
+        let buffer = match stream {
+            OutputStream::Stdout => &self.stdout,
+            OutputStream::Stderr => &self.stderr,
+        };
+        buffer.lock().unwrap().extend_from_slice(bytes);
+        Ok(())
+    }
+}
+
+/// [`Write`] handle returned by
+/// [`PluginContext::stdout`](crate::PluginContext::stdout) and
+/// [`stderr`](crate::PluginContext::stderr). Each `write` goes to the sink
+/// immediately.
+#[derive(Clone)]
+pub struct OutputWriter {
+    sink: Arc<dyn OutputSink>,
+    stream: OutputStream,
+}
+
+impl OutputWriter {
+    pub fn new(sink: Arc<dyn OutputSink>, stream: OutputStream) -> Self {
+        Self { sink, stream }
+    }
+}
+
+impl Write for OutputWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.sink.write(self.stream, buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Line-buffered [`Write`] handle for one labeled stream, typically one
+/// repo of a multi-repo command, returned by
+/// [`PluginContext::scoped_stdout`](crate::PluginContext::scoped_stdout).
+/// Complete lines go to [`OutputSink::write_scoped`]; a trailing partial
+/// line is written on `flush` or drop.
+pub struct ScopedWriter {
+    sink: Arc<dyn OutputSink>,
+    stream: OutputStream,
+    label: String,
+    pending: Vec<u8>,
+}
+
+impl ScopedWriter {
+    pub fn new(sink: Arc<dyn OutputSink>, stream: OutputStream, label: impl Into<String>) -> Self {
+        Self {
+            sink,
+            stream,
+            label: label.into(),
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+}
+
+impl Write for ScopedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        while let Some(end) = self.pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            self.sink.write_scoped(&self.label, self.stream, &line)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let mut line = std::mem::take(&mut self.pending);
+        line.push(b'\n');
+        self.sink.write_scoped(&self.label, self.stream, &line)
+    }
+}
+
+impl Drop for ScopedWriter {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_writers_reach_the_sink() {
+        let captured = Arc::new(CapturedOutput::new());
+        let mut stdout = OutputWriter::new(captured.clone(), OutputStream::Stdout);
+        let mut stderr = OutputWriter::new(captured.clone(), OutputStream::Stderr);
+        writeln!(stdout, "pulled {} repos", 3).unwrap();
+        write!(stderr, "warning").unwrap();
+        assert_eq!(captured.stdout(), "pulled 3 repos\n");
+        assert_eq!(captured.stderr(), "warning");
+    }
+
+    #[test]
+    fn test_scoped_writer_prefixes_whole_lines() {
+        let captured = Arc::new(CapturedOutput::new());
+        let mut api = ScopedWriter::new(captured.clone(), OutputStream::Stdout, "api");
+        let mut web = ScopedWriter::new(captured.clone(), OutputStream::Stdout, "web");
+        write!(api, "fetching").unwrap();
+        writeln!(web, "up to date").unwrap();
+        writeln!(api, " origin\npruned").unwrap();
+        write!(web, "no newline").unwrap();
+        drop(web);
+        assert_eq!(
+            captured.stdout(),
+            "[web] up to date\n[api] fetching origin\n[api] pruned\n[web] no newline\n"
+        );
+    }
+}
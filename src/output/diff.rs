@@ -0,0 +1,343 @@
+use std::ops::Range;
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const CYAN: &str = "\x1b[36m";
+const REVERSE: &str = "\x1b[7m";
+const NOT_REVERSE: &str = "\x1b[27m";
+const RESET: &str = "\x1b[0m";
+
+/// How [`render_diff`] lays out a diff. The default has 3 lines of
+/// context, no file header, no color and no width limit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffOptions {
+    /// Unchanged lines shown around each change
+    pub context: usize,
+    /// Names for the `---`/`+++` header, e.g. `a/.meta` and `b/.meta`
+    pub labels: Option<(String, String)>,
+    /// Red deletions and green additions, with the changed part of
+    /// modified lines in reverse video
+    pub color: bool,
+    /// Longer lines are cut to this many characters, ending in `…`
+    pub width: Option<usize>,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            context: 3,
+            labels: None,
+            color: false,
+            width: None,
+        }
+    }
+}
+
+impl DiffOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn context(mut self, lines: usize) -> Self {
+        self.context = lines;
+        self
+    }
+
+    pub fn labels(mut self, old: impl Into<String>, new: impl Into<String>) -> Self {
+        self.labels = Some((old.into(), new.into()));
+        self
+    }
+
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = Some(width);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// A unified diff from `old` to `new`, empty if they have the same
+/// lines. Meant for previews of files a person would read, up to a few
+/// thousand lines; the comparison is quadratic in the changed region.
+///
+/// ```
+/// use meta_plugin_api::{render_diff, DiffOptions};
+///
+/// let diff = render_diff("version = 1.2.0\n", "version = 1.3.0\n", &DiffOptions::new());
+/// assert_eq!(diff, "@@ -1 +1 @@\n-version = 1.2.0\n+version = 1.3.0\n");
+/// ```
+pub fn render_diff(old: &str, new: &str, options: &DiffOptions) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let ops = diff_lines(&old, &new);
+    let hunks = hunks(&ops, options.context);
+    if hunks.is_empty() {
+        return String::new();
+    }
+
+    let mut out = String::new();
+    if let Some((old_label, new_label)) = &options.labels {
+        out.push_str(&format!("--- {}\n+++ {}\n", old_label, new_label));
+    }
+    for hunk in hunks {
+        let header = format!(
+            "@@ -{} +{} @@",
+            range(&ops, &hunk, true),
+            range(&ops, &hunk, false)
+        );
+        let ops = &ops[hunk];
+        push_line(&mut out, &header, None, options, CYAN);
+
+        let mut i = 0;
+        while i < ops.len() {
+            match ops[i] {
+                Op::Equal(o, _) => {
+                    push_line(&mut out, &format!(" {}", old[o]), None, options, "");
+                    i += 1;
+                }
+                _ => {
+                    let end = ops[i..]
+                        .iter()
+                        .position(|op| matches!(op, Op::Equal(..)))
+                        .map_or(ops.len(), |n| i + n);
+                    let deleted: Vec<&str> = ops[i..end]
+                        .iter()
+                        .filter_map(|op| match op {
+                            Op::Delete(o) => Some(old[*o]),
+                            _ => None,
+                        })
+                        .collect();
+                    let inserted: Vec<&str> = ops[i..end]
+                        .iter()
+                        .filter_map(|op| match op {
+                            Op::Insert(n) => Some(new[*n]),
+                            _ => None,
+                        })
+                        .collect();
+                    push_changes(&mut out, &deleted, &inserted, options);
+                    i = end;
+                }
+            }
+        }
+    }
+    out
+}
+
+/// A run of changed lines: all deletions, then all additions, with the
+/// k-th deleted and k-th added line highlighted against each other.
+fn push_changes(out: &mut String, deleted: &[&str], inserted: &[&str], options: &DiffOptions) {
+    let changed = |k: usize| match (deleted.get(k), inserted.get(k)) {
+        (Some(old), Some(new)) => Some(changed_chars(old, new)),
+        _ => None,
+    };
+    for (k, line) in deleted.iter().enumerate() {
+        let highlight = changed(k).map(|(old, _)| old);
+        push_line(out, &format!("-{}", line), highlight, options, RED);
+    }
+    for (k, line) in inserted.iter().enumerate() {
+        let highlight = changed(k).map(|(_, new)| new);
+        push_line(out, &format!("+{}", line), highlight, options, GREEN);
+    }
+}
+
+/// Write one line, cut to the width, with `highlight` (a char range of
+/// the text after the marker) in reverse video.
+fn push_line(
+    out: &mut String,
+    line: &str,
+    highlight: Option<Range<usize>>,
+    options: &DiffOptions,
+    color: &str,
+) {
+    let mut chars: Vec<char> = line.chars().collect();
+    let mut cut = false;
+    if let Some(width) = options.width {
+        if chars.len() > width {
+            chars.truncate(width.saturating_sub(1));
+            cut = true;
+        }
+    }
+    if !options.color || color.is_empty() {
+        out.extend(&chars);
+    } else {
+        out.push_str(color);
+        // Shift past the marker and clip to what is left after cutting
+        let highlight = highlight
+            .map(|r| (r.start + 1).min(chars.len())..(r.end + 1).min(chars.len()))
+            .filter(|r| !r.is_empty());
+        match highlight {
+            Some(r) => {
+                out.extend(&chars[..r.start]);
+                out.push_str(REVERSE);
+                out.extend(&chars[r.clone()]);
+                out.push_str(NOT_REVERSE);
+                out.extend(&chars[r.end..]);
+            }
+            None => out.extend(&chars),
+        }
+    }
+    if cut {
+        out.push('…');
+    }
+    if options.color && !color.is_empty() {
+        out.push_str(RESET);
+    }
+    out.push('\n');
+}
+
+/// The char ranges of `old` and `new` between their common prefix and
+/// common suffix.
+fn changed_chars(old: &str, new: &str) -> (Range<usize>, Range<usize>) {
+    let old: Vec<char> = old.chars().collect();
+    let new: Vec<char> = new.chars().collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    (prefix..old.len() - suffix, prefix..new.len() - suffix)
+}
+
+/// `start,count` of the lines `ops[hunk]` covers on one side, in
+/// unified diff notation.
+fn range(ops: &[Op], hunk: &Range<usize>, old_side: bool) -> String {
+    let on_side = |op: &&Op| match op {
+        Op::Equal(..) => true,
+        Op::Delete(_) => old_side,
+        Op::Insert(_) => !old_side,
+    };
+    let before = ops[..hunk.start].iter().filter(on_side).count();
+    match ops[hunk.clone()].iter().filter(on_side).count() {
+        // An empty side is numbered by the line before it
+        0 => format!("{},0", before),
+        1 => (before + 1).to_string(),
+        count => format!("{},{}", before + 1, count),
+    }
+}
+
+/// Ranges of `ops` to show: each change with `context` lines around it,
+/// merged when the context would touch.
+fn hunks(ops: &[Op], context: usize) -> Vec<Range<usize>> {
+    let mut hunks: Vec<Range<usize>> = Vec::new();
+    for (i, op) in ops.iter().enumerate() {
+        if matches!(op, Op::Equal(..)) {
+            continue;
+        }
+        let start = i.saturating_sub(context);
+        let end = (i + context + 1).min(ops.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.end => last.end = end,
+            _ => hunks.push(start..end),
+        }
+    }
+    hunks
+}
+
+/// Line edit script from `old` to `new`, deletions before insertions in
+/// each changed run.
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<Op> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+
+    // lcs[i][j]: longest common subsequence of a[i..] and b[j..]
+    let mut lcs = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops: Vec<Op> = (0..prefix).map(|i| Op::Equal(i, i)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            ops.push(Op::Equal(prefix + i, prefix + j));
+            i += 1;
+            j += 1;
+        } else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push(Op::Delete(prefix + i));
+            i += 1;
+        } else {
+            ops.push(Op::Insert(prefix + j));
+            j += 1;
+        }
+    }
+    let (old_end, new_end) = (old.len() - suffix, new.len() - suffix);
+    ops.extend((0..suffix).map(|k| Op::Equal(old_end + k, new_end + k)));
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+
+    #[test]
+    fn test_unified_hunks_with_context() {
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\nj\nk\n";
+        let diff = render_diff(
+            OLD,
+            new,
+            &DiffOptions::new().context(1).labels("a/x", "b/x"),
+        );
+        assert_eq!(
+            diff,
+            "--- a/x\n+++ b/x\n\
+             @@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n\
+             @@ -8,3 +8,3 @@\n h\n-i\n j\n+k\n"
+        );
+        assert_eq!(render_diff(OLD, OLD, &DiffOptions::new()), "");
+        assert_eq!(
+            render_diff("", "new\n", &DiffOptions::new()),
+            "@@ -0,0 +1 @@\n+new\n"
+        );
+        assert_eq!(
+            render_diff("a\nb\n", "a\nnew\nb\n", &DiffOptions::new().context(0)),
+            "@@ -1,0 +2 @@\n+new\n"
+        );
+    }
+
+    #[test]
+    fn test_color_highlights_changed_part_within_width() {
+        let options = DiffOptions::new().color(true).width(12);
+        let diff = render_diff("version = \"1.2.0\"\n", "version = \"1.3.0\"\n", &options);
+        assert_eq!(
+            diff,
+            "\x1b[36m@@ -1 +1 @@\x1b[0m\n\
+             \x1b[31m-version = …\x1b[0m\n\
+             \x1b[32m+version = …\x1b[0m\n"
+        );
+
+        let wide = render_diff(
+            "name: api\n",
+            "name: web\n",
+            &DiffOptions::new().color(true),
+        );
+        assert!(wide.contains("\x1b[31m-name: \x1b[7mapi\x1b[27m\x1b[0m\n"));
+        assert!(wide.contains("\x1b[32m+name: \x1b[7mweb\x1b[27m\x1b[0m\n"));
+    }
+}
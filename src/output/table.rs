@@ -0,0 +1,281 @@
+use crate::TerminalInfo;
+
+/// How cells of a [`Column`] line up.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Align {
+    #[default]
+    Left,
+    Right,
+    Center,
+}
+
+/// Frame drawn around a [`Table`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Borders {
+    /// Columns separated by two spaces, for pipes and `grep`
+    #[default]
+    None,
+    /// Box-drawing lines, for terminals
+    Box,
+}
+
+impl Borders {
+    /// [`Box`](Borders::Box) on a terminal, [`None`](Borders::None)
+    /// otherwise.
+    pub fn for_terminal(terminal: &TerminalInfo) -> Self {
+        if terminal.is_tty {
+            Borders::Box
+        } else {
+            Borders::None
+        }
+    }
+}
+
+/// One column of a [`Table`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Column {
+    pub title: String,
+    pub align: Align,
+    /// Longer cells are cut to this many characters, ending in `…`
+    pub max_width: Option<usize>,
+}
+
+impl Column {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            align: Align::Left,
+            max_width: None,
+        }
+    }
+
+    pub fn align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub fn max_width(mut self, width: usize) -> Self {
+        self.max_width = Some(width);
+        self
+    }
+}
+
+/// Tabular output that looks the same in every plugin, e.g. one row per
+/// repo. Widths count characters, so wide CJK text and emoji may still
+/// push a column out of line.
+///
+/// ```
+/// use meta_plugin_api::{Align, Borders, Column, Table};
+///
+/// let table = Table::new()
+///     .column(Column::new("Repo"))
+///     .column(Column::new("Ahead").align(Align::Right))
+///     .row(["api", "2"])
+///     .row(["web", "10"]);
+/// assert_eq!(
+///     table.render(Borders::None, None),
+///     "Repo  Ahead\napi       2\nweb      10\n"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Table {
+    columns: Vec<Column>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn column(mut self, column: Column) -> Self {
+        self.columns.push(column);
+        self
+    }
+
+    /// Add a row. Missing cells are left empty and extra cells dropped.
+    pub fn row<S: Into<String>>(mut self, cells: impl IntoIterator<Item = S>) -> Self {
+        self.push_row(cells);
+        self
+    }
+
+    pub fn push_row<S: Into<String>>(&mut self, cells: impl IntoIterator<Item = S>) {
+        let mut row: Vec<String> = cells.into_iter().map(Into::into).collect();
+        row.resize(self.columns.len(), String::new());
+        self.rows.push(row);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// The table with `borders`, its widest columns shrunk until each
+    /// line fits in `max_width` characters (if given and possible).
+    pub fn render(&self, borders: Borders, max_width: Option<usize>) -> String {
+        let widths = self.widths(borders, max_width);
+        let mut out = String::new();
+        if borders == Borders::Box {
+            rule(&mut out, &widths, ['┌', '┬', '┐']);
+        }
+        let titles: Vec<&str> = self.columns.iter().map(|c| c.title.as_str()).collect();
+        self.line(&mut out, &titles, &widths, borders);
+        if borders == Borders::Box {
+            rule(&mut out, &widths, ['├', '┼', '┤']);
+        }
+        for row in &self.rows {
+            let cells: Vec<&str> = row.iter().map(String::as_str).collect();
+            self.line(&mut out, &cells, &widths, borders);
+        }
+        if borders == Borders::Box {
+            rule(&mut out, &widths, ['└', '┴', '┘']);
+        }
+        out
+    }
+
+    /// The table as it should look on `terminal`: boxed and fitted to the
+    /// window on a TTY, plain and full width in a pipe.
+    pub fn render_for(&self, terminal: &TerminalInfo) -> String {
+        let max_width = terminal.is_tty.then_some(terminal.width).flatten();
+        self.render(Borders::for_terminal(terminal), max_width.map(usize::from))
+    }
+
+    fn widths(&self, borders: Borders, max_width: Option<usize>) -> Vec<usize> {
+        let mut widths: Vec<usize> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                let widest = self
+                    .rows
+                    .iter()
+                    .map(|row| char_len(&row[i]))
+                    .chain([char_len(&column.title)])
+                    .max()
+                    .unwrap_or(0);
+                column.max_width.map_or(widest, |max| widest.min(max))
+            })
+            .collect();
+        if let Some(max_width) = max_width {
+            let frame = match borders {
+                Borders::None => 2 * widths.len().saturating_sub(1),
+                Borders::Box => 3 * widths.len() + 1,
+            };
+            while frame + widths.iter().sum::<usize>() > max_width {
+                let widest = widths.iter_mut().max().filter(|w| **w > 1);
+                match widest {
+                    Some(width) => *width -= 1,
+                    None => break,
+                }
+            }
+        }
+        widths
+    }
+
+    fn line(&self, out: &mut String, cells: &[&str], widths: &[usize], borders: Borders) {
+        let padded: Vec<String> = self
+            .columns
+            .iter()
+            .zip(cells)
+            .zip(widths)
+            .map(|((column, cell), &width)| pad(&truncate(cell, width), width, column.align))
+            .collect();
+        match borders {
+            Borders::None => out.push_str(padded.join("  ").trim_end()),
+            Borders::Box => {
+                out.push_str("│ ");
+                out.push_str(&padded.join(" │ "));
+                out.push_str(" │");
+            }
+        }
+        out.push('\n');
+    }
+}
+
+fn rule(out: &mut String, widths: &[usize], [left, middle, right]: [char; 3]) {
+    out.push(left);
+    for (i, width) in widths.iter().enumerate() {
+        if i > 0 {
+            out.push(middle);
+        }
+        out.extend(std::iter::repeat_n('─', width + 2));
+    }
+    out.push(right);
+    out.push('\n');
+}
+
+fn char_len(text: &str) -> usize {
+    text.chars().count()
+}
+
+/// `text` cut to `width` characters, the last one replaced by `…` if
+/// anything was cut.
+pub(crate) fn truncate(text: &str, width: usize) -> String {
+    if char_len(text) <= width {
+        return text.to_string();
+    }
+    let mut cut: String = text.chars().take(width.saturating_sub(1)).collect();
+    if width > 0 {
+        cut.push('…');
+    }
+    cut
+}
+
+fn pad(text: &str, width: usize, align: Align) -> String {
+    let space = width.saturating_sub(char_len(text));
+    let (left, right) = match align {
+        Align::Left => (0, space),
+        Align::Right => (space, 0),
+        Align::Center => (space / 2, space - space / 2),
+    };
+    format!("{}{}{}", " ".repeat(left), text, " ".repeat(right))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repos() -> Table {
+        Table::new()
+            .column(Column::new("Repo").max_width(12))
+            .column(Column::new("Branch"))
+            .column(Column::new("Ahead").align(Align::Right))
+            .row(["api", "main", "0"])
+            .row(["frontend-monorepo-legacy", "feature/login", "12"])
+            .row(["web"])
+    }
+
+    #[test]
+    fn test_plain_table_truncates_long_cells() {
+        assert_eq!(
+            repos().render(Borders::None, None),
+            "Repo          Branch         Ahead\n\
+             api           main               0\n\
+             frontend-mo…  feature/login     12\n\
+             web\n"
+        );
+    }
+
+    #[test]
+    fn test_boxed_table_fits_terminal_width() {
+        let terminal = TerminalInfo {
+            is_tty: true,
+            width: Some(30),
+            ..TerminalInfo::default()
+        };
+        let rendered = repos().render_for(&terminal);
+        assert!(rendered.lines().all(|line| char_len(line) <= 30));
+        assert_eq!(
+            rendered.lines().nth(1),
+            Some("│ Repo     │ Branch  │ Ahead │")
+        );
+        assert_eq!(
+            rendered.lines().nth(4),
+            Some("│ fronten… │ featur… │    12 │")
+        );
+        assert!(rendered.starts_with("┌──────────┬"));
+        assert!(rendered.ends_with("┘\n"));
+        assert!(repos()
+            .render_for(&TerminalInfo::default())
+            .contains("frontend-mo…"));
+    }
+}
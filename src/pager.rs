@@ -0,0 +1,215 @@
+use std::io::{self, Write};
+use std::process::{Child, ChildStdin, Stdio};
+
+use crate::editor::split_command;
+use crate::{Env, OutputWriter, Redactor};
+
+/// The user's pager, e.g. `less -R`. Set but empty, or `cat`, means no
+/// paging.
+pub const PAGER_ENV: &str = "PAGER";
+
+/// The pager to run, split into program and arguments: `$PAGER`, else
+/// `more` on Windows and `less -R` elsewhere. `None` when the user
+/// turned paging off.
+pub fn pager_command(env: &Env) -> Option<Vec<String>> {
+    let words = match env.get(PAGER_ENV) {
+        Some(configured) => split_command(configured),
+        None if cfg!(windows) => vec!["more".to_string()],
+        None => vec!["less".to_string(), "-R".to_string()],
+    };
+    match words.first().map(String::as_str) {
+        None | Some("cat") => None,
+        Some(_) => Some(words),
+    }
+}
+
+/// [`Write`] handle for long output, from
+/// [`PluginContext::pager`](crate::PluginContext::pager). On a terminal it
+/// feeds the user's pager, otherwise it writes to stdout directly. Call
+/// [`finish`](Self::finish) when done to wait for the user to leave the
+/// pager; dropping the handle does the same.
+pub struct Pager {
+    target: Target,
+}
+
+enum Target {
+    Direct(OutputWriter),
+    Piped {
+        child: Child,
+        stdin: Option<ChildStdin>,
+        redactor: Redactor,
+        /// Bytes after the last newline, held back so a secret split
+        /// across writes is still masked
+        partial: Vec<u8>,
+    },
+}
+
+impl Pager {
+    pub(crate) fn direct(stdout: OutputWriter) -> Self {
+        Self {
+            target: Target::Direct(stdout),
+        }
+    }
+
+    /// Start the pager from `env`, or write to `stdout` if paging is off
+    /// or the pager cannot be started.
+    pub(crate) fn spawn(env: &Env, redactor: Redactor, stdout: OutputWriter) -> Self {
+        let Some(words) = pager_command(env) else {
+            return Self::direct(stdout);
+        };
+        let program = env
+            .which(&words[0])
+            .unwrap_or_else(|| words[0].clone().into());
+        let mut command = env.command(program);
+        command.args(&words[1..]).stdin(Stdio::piped());
+        // As git does: quit if it fits one screen, keep colors, and leave
+        // the output on screen after quitting
+        if env.get("LESS").is_none() {
+            command.env("LESS", "FRX");
+        }
+        match command.spawn() {
+            Ok(mut child) => Self {
+                target: Target::Piped {
+                    stdin: child.stdin.take(),
+                    child,
+                    redactor,
+                    partial: Vec::new(),
+                },
+            },
+            Err(_) => Self::direct(stdout),
+        }
+    }
+
+    /// Whether output goes through a pager rather than straight to stdout.
+    pub fn is_paging(&self) -> bool {
+        matches!(self.target, Target::Piped { .. })
+    }
+
+    /// Write what is buffered and wait for the pager to exit.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.close()
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        self.flush()?;
+        if let Target::Piped { child, stdin, .. } = &mut self.target {
+            // Closing its input tells the pager there is no more to come
+            drop(stdin.take());
+            child.wait()?;
+        }
+        Ok(())
+    }
+}
+
+/// Send `bytes` to the pager. A user quitting the pager early is not an
+/// error, so the rest of the output is dropped quietly.
+fn send(stdin: &mut Option<ChildStdin>, bytes: &[u8]) -> io::Result<()> {
+    let Some(pipe) = stdin else {
+        return Ok(());
+    };
+    match pipe.write_all(bytes) {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {
+            *stdin = None;
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+impl Write for Pager {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.target {
+            Target::Direct(stdout) => stdout.write(buf),
+            Target::Piped {
+                stdin,
+                redactor,
+                partial,
+                ..
+            } => {
+                partial.extend_from_slice(buf);
+                if let Some(end) = partial.iter().rposition(|&b| b == b'\n') {
+                    let lines: Vec<u8> = partial.drain(..=end).collect();
+                    send(stdin, &redactor.redact_bytes(&lines))?;
+                }
+                Ok(buf.len())
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.target {
+            Target::Direct(stdout) => stdout.flush(),
+            Target::Piped {
+                stdin,
+                redactor,
+                partial,
+                ..
+            } => {
+                let rest = std::mem::take(partial);
+                send(stdin, &redactor.redact_bytes(&rest))?;
+                match stdin {
+                    Some(pipe) => pipe.flush(),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Pager {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CapturedOutput, OutputStream};
+
+    #[test]
+    fn test_pager_command() {
+        let fallback: &[&str] = if cfg!(windows) {
+            &["more"]
+        } else {
+            &["less", "-R"]
+        };
+        assert_eq!(pager_command(&Env::new()).unwrap(), fallback);
+        let env = Env::new().with_var(PAGER_ENV, "most -s");
+        assert_eq!(pager_command(&env).unwrap(), ["most", "-s"]);
+        assert_eq!(pager_command(&Env::new().with_var(PAGER_ENV, "")), None);
+        assert_eq!(pager_command(&Env::new().with_var(PAGER_ENV, "cat")), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_piped_output_is_redacted_and_waited_for() {
+        let out =
+            std::env::temp_dir().join(format!("meta_plugin_api-pager-{}", std::process::id()));
+        let env = Env::from_process()
+            .with_var(PAGER_ENV, format!("sh -c 'cat > \"$0\"' {}", out.display()));
+        let redactor = Redactor::new();
+        redactor.add("hunter22");
+        let sink = std::sync::Arc::new(CapturedOutput::new());
+        let stdout = OutputWriter::new(sink.clone(), OutputStream::Stdout);
+        let mut pager = Pager::spawn(&env, redactor.clone(), stdout.clone());
+        assert!(pager.is_paging());
+        write!(pager, "token: hunter").unwrap();
+        writeln!(pager, "22").unwrap();
+        write!(pager, "no newline").unwrap();
+        pager.finish().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&out).unwrap(),
+            "token: ***\nno newline"
+        );
+        std::fs::remove_file(&out).unwrap();
+        assert!(sink.stdout().is_empty());
+
+        let missing = Env::from_process().with_var(PAGER_ENV, "meta-no-such-pager");
+        let mut pager = Pager::spawn(&missing, redactor, stdout);
+        assert!(!pager.is_paging());
+        writeln!(pager, "plain").unwrap();
+        drop(pager);
+        assert_eq!(sink.stdout(), "plain\n");
+    }
+}
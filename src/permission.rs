@@ -0,0 +1,115 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Capabilities, PluginError, Prompter};
+
+/// A plugin asking, mid-command, for capabilities it was not granted at
+/// install time, through
+/// [`PluginContext::request_capability`](crate::PluginContext::request_capability).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapabilityRequest {
+    pub capability: Capabilities,
+    /// Shown to the user, e.g. `fetch issue titles from jira.example.com`
+    pub reason: String,
+}
+
+/// The answer to a [`CapabilityRequest`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Grant {
+    /// For this command only
+    Once,
+    /// For this and later commands; the host remembers it
+    Always,
+    Denied,
+}
+
+impl Grant {
+    pub fn is_granted(self) -> bool {
+        self != Grant::Denied
+    }
+
+    /// Ok unless denied, otherwise [`PluginError::CapabilityDenied`] for
+    /// `request`.
+    pub fn check(self, request: &CapabilityRequest) -> Result<(), PluginError> {
+        if self.is_granted() {
+            Ok(())
+        } else {
+            Err(PluginError::CapabilityDenied {
+                capability: request.capability.to_string(),
+                action: request.reason.clone(),
+            })
+        }
+    }
+}
+
+impl fmt::Display for Grant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Grant::Once => "allowed once",
+            Grant::Always => "always allowed",
+            Grant::Denied => "denied",
+        })
+    }
+}
+
+/// Host-side decision maker for [`CapabilityRequest`]s, e.g. a terminal
+/// dialog that also records `Always` grants in the user's config.
+pub trait PermissionBroker: Send + Sync {
+    fn request(&self, request: &CapabilityRequest) -> Grant;
+}
+
+/// Answers every request the same way, for runs where nobody can be
+/// asked: `FixedGrant(Grant::Denied)` in CI, `FixedGrant(Grant::Once)`
+/// under `--yes`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedGrant(pub Grant);
+
+impl PermissionBroker for FixedGrant {
+    fn request(&self, _request: &CapabilityRequest) -> Grant {
+        self.0
+    }
+}
+
+/// Ask through `prompter`, defaulting to [`Grant::Denied`]. This is what
+/// the context does when the host installed no broker, so a
+/// [`NonInteractivePrompter`](crate::NonInteractivePrompter) denies.
+pub fn prompt_for_grant(prompter: &dyn Prompter, request: &CapabilityRequest) -> Grant {
+    let message = format!("Allow {} to {}?", request.capability, request.reason);
+    let choices = [Grant::Once, Grant::Always, Grant::Denied];
+    let labels = ["Allow once", "Always allow", "Deny"];
+    match prompter.select(&message, &labels, Some(2)) {
+        Ok(i) => choices.get(i).copied().unwrap_or(Grant::Denied),
+        Err(_) => Grant::Denied,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NonInteractivePrompter;
+
+    fn network() -> CapabilityRequest {
+        CapabilityRequest {
+            capability: Capabilities::NETWORK,
+            reason: "fetch issue titles".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_non_interactive_denies() {
+        let grant = prompt_for_grant(&NonInteractivePrompter, &network());
+        assert_eq!(grant, Grant::Denied);
+        let err = grant.check(&network()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Capability 'network' denied: fetch issue titles"
+        );
+        assert!(FixedGrant(Grant::Once).request(&network()).is_granted());
+        assert_eq!(
+            serde_json::to_value(network()).unwrap(),
+            serde_json::json!({"capability": ["network"], "reason": "fetch issue titles"})
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// What a command would do, from [`Plugin::plan`](crate::Plugin::plan):
+/// the host shows it for `--dry-run` or before asking the user to
+/// confirm, then runs the command with `execute` as usual.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionPlan {
+    pub steps: Vec<PlanStep>,
+}
+
+/// One action of an [`ExecutionPlan`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanStep {
+    /// Project the step acts on; None for workspace-wide steps
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+    /// What happens, e.g. `delete branch feature/login`
+    pub action: String,
+    /// Whether the step loses data or cannot easily be undone, e.g. a
+    /// force push; hosts ask before running plans with such steps
+    #[serde(default)]
+    pub destructive: bool,
+}
+
+impl PlanStep {
+    pub fn new(action: impl Into<String>) -> Self {
+        Self {
+            repo: None,
+            action: action.into(),
+            destructive: false,
+        }
+    }
+
+    pub fn repo(mut self, repo: impl Into<String>) -> Self {
+        self.repo = Some(repo.into());
+        self
+    }
+
+    pub fn destructive(mut self) -> Self {
+        self.destructive = true;
+        self
+    }
+}
+
+impl ExecutionPlan {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn step(mut self, step: PlanStep) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    pub fn push(&mut self, step: PlanStep) {
+        self.steps.push(step);
+    }
+
+    /// Whether there is nothing to do.
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn is_destructive(&self) -> bool {
+        self.steps.iter().any(|s| s.destructive)
+    }
+
+    pub fn destructive_steps(&self) -> impl Iterator<Item = &PlanStep> {
+        self.steps.iter().filter(|s| s.destructive)
+    }
+}
+
+impl fmt::Display for ExecutionPlan {
+    /// One line per step, e.g. `api: delete branch old (destructive)`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.steps {
+            if let Some(repo) = &step.repo {
+                write!(f, "{}: ", repo)?;
+            }
+            f.write_str(&step.action)?;
+            if step.destructive {
+                f.write_str(" (destructive)")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_render_and_serde() {
+        let plan = ExecutionPlan::new()
+            .step(PlanStep::new("push main to origin").repo("api"))
+            .step(PlanStep::new("delete branch old").repo("web").destructive())
+            .step(PlanStep::new("update .meta"));
+        assert!(plan.is_destructive());
+        assert_eq!(plan.destructive_steps().count(), 1);
+        assert_eq!(
+            plan.to_string(),
+            "api: push main to origin\nweb: delete branch old (destructive)\nupdate .meta\n"
+        );
+
+        let json = serde_json::to_value(&plan).unwrap();
+        assert_eq!(
+            json["steps"][2],
+            serde_json::json!({"action": "update .meta", "destructive": false})
+        );
+        assert_eq!(serde_json::from_value::<ExecutionPlan>(json).unwrap(), plan);
+        assert!(!ExecutionPlan::new().is_destructive());
+    }
+}
@@ -0,0 +1,458 @@
+use std::fmt;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{OutputSink, OutputStream};
+
+/// Identifies one task started through a [`ProgressReporter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TaskId(u64);
+
+/// A progress event emitted by a plugin. Serializes to the NDJSON shape
+/// `{"event":"started","task":1,...}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    Started {
+        task: TaskId,
+        label: String,
+        /// Number of units of work, if known
+        total: Option<u64>,
+        /// Group this task belongs to, e.g. one repo of a
+        /// [`MultiProgress`]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        parent: Option<TaskId>,
+    },
+    Updated {
+        task: TaskId,
+        current: u64,
+        message: Option<String>,
+    },
+    Finished {
+        task: TaskId,
+        success: bool,
+    },
+}
+
+/// Host-side receiver of progress events, e.g. a progress bar renderer.
+pub trait ProgressSink: Send + Sync {
+    fn event(&self, event: ProgressEvent);
+}
+
+struct NullSink;
+
+impl ProgressSink for NullSink {
+    fn event(&self, _event: ProgressEvent) {}
+}
+
+/// Writes each event as one JSON line.
+pub struct NdjsonProgressSink<W> {
+    writer: Mutex<W>,
+}
+
+impl<W: Write + Send> NdjsonProgressSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+        }
+    }
+}
+
+impl<W: Write + Send> ProgressSink for NdjsonProgressSink<W> {
+    fn event(&self, event: ProgressEvent) {
+        if let (Ok(line), Ok(mut writer)) = (serde_json::to_string(&event), self.writer.lock()) {
+            let _ = writeln!(writer, "{}", line);
+        }
+    }
+}
+
+/// Handle plugins use to report progress instead of printing it.
+#[derive(Clone)]
+pub struct ProgressReporter {
+    sink: Arc<dyn ProgressSink>,
+    next_task: Arc<AtomicU64>,
+}
+
+impl ProgressReporter {
+    pub fn new(sink: Arc<dyn ProgressSink>) -> Self {
+        Self {
+            sink,
+            next_task: Arc::new(AtomicU64::new(1)),
+        }
+    }
+
+    /// A reporter that discards all events.
+    pub fn disabled() -> Self {
+        Self::new(Arc::new(NullSink))
+    }
+
+    pub fn start_task(&self, label: impl Into<String>, total: Option<u64>) -> TaskId {
+        self.start(label.into(), total, None)
+    }
+
+    /// Like [`start_task`](Self::start_task), as part of the group
+    /// `parent`.
+    pub fn start_subtask(
+        &self,
+        parent: TaskId,
+        label: impl Into<String>,
+        total: Option<u64>,
+    ) -> TaskId {
+        self.start(label.into(), total, Some(parent))
+    }
+
+    fn start(&self, label: String, total: Option<u64>, parent: Option<TaskId>) -> TaskId {
+        let task = TaskId(self.next_task.fetch_add(1, Ordering::Relaxed));
+        self.sink.event(ProgressEvent::Started {
+            task,
+            label,
+            total,
+            parent,
+        });
+        task
+    }
+
+    pub fn update(&self, task: TaskId, current: u64, message: Option<&str>) {
+        self.sink.event(ProgressEvent::Updated {
+            task,
+            current,
+            message: message.map(str::to_string),
+        });
+    }
+
+    pub fn finish(&self, task: TaskId, success: bool) {
+        self.sink.event(ProgressEvent::Finished { task, success });
+    }
+}
+
+impl Default for ProgressReporter {
+    fn default() -> Self {
+        Self::disabled()
+    }
+}
+
+impl fmt::Debug for ProgressReporter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProgressReporter").finish_non_exhaustive()
+    }
+}
+
+/// Where [`Spinner`]s and [`ProgressBar`]s go: the host's renderer on a
+/// terminal, plain lines on stderr in CI logs and pipes.
+#[derive(Clone)]
+pub(crate) enum ProgressDisplay {
+    Renderer(ProgressReporter),
+    Log(Arc<dyn OutputSink>),
+}
+
+/// State shared by the progress handles.
+struct Task {
+    display: ProgressDisplay,
+    /// Set with [`ProgressDisplay::Renderer`]
+    id: Option<TaskId>,
+    label: String,
+    current: AtomicU64,
+    finished: AtomicBool,
+    /// Set when this task fails, so its [`MultiProgress`] fails too
+    group_failed: Option<Arc<AtomicBool>>,
+}
+
+impl Task {
+    fn start(
+        display: ProgressDisplay,
+        label: String,
+        total: Option<u64>,
+        parent: Option<TaskId>,
+        group_failed: Option<Arc<AtomicBool>>,
+    ) -> Self {
+        let id = match &display {
+            ProgressDisplay::Renderer(reporter) => {
+                Some(reporter.start(label.clone(), total, parent))
+            }
+            ProgressDisplay::Log(_) => None,
+        };
+        let task = Self {
+            display,
+            id,
+            label,
+            current: AtomicU64::new(0),
+            finished: AtomicBool::new(false),
+            group_failed,
+        };
+        task.log(&format!("{}...", task.label));
+        task
+    }
+
+    fn update(&self, current: u64, message: Option<&str>) {
+        self.current.store(current, Ordering::Relaxed);
+        match (&self.display, self.id) {
+            (ProgressDisplay::Renderer(reporter), Some(id)) => {
+                reporter.update(id, current, message)
+            }
+            _ => {
+                if let Some(message) = message {
+                    self.log(&format!("{}: {}", self.label, message));
+                }
+            }
+        }
+    }
+
+    fn finish(&self, success: bool) {
+        if self.finished.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        if !success {
+            if let Some(failed) = &self.group_failed {
+                failed.store(true, Ordering::Relaxed);
+            }
+        }
+        match (&self.display, self.id) {
+            (ProgressDisplay::Renderer(reporter), Some(id)) => reporter.finish(id, success),
+            _ => self.log(&format!(
+                "{}: {}",
+                self.label,
+                if success { "done" } else { "failed" }
+            )),
+        }
+    }
+
+    fn log(&self, line: &str) {
+        if let ProgressDisplay::Log(sink) = &self.display {
+            let _ = sink.write(OutputStream::Stderr, format!("{}\n", line).as_bytes());
+        }
+    }
+}
+
+/// Activity without a known amount of work, from
+/// [`PluginContext::spinner`](crate::PluginContext::spinner). Outside a
+/// terminal it prints a line when it starts, for each message and when
+/// it ends. Dropping it unfinished reports a failure, as happens when
+/// `?` returns early.
+pub struct Spinner {
+    task: Task,
+}
+
+impl Spinner {
+    pub(crate) fn start(display: ProgressDisplay, label: impl Into<String>) -> Self {
+        Self {
+            task: Task::start(display, label.into(), None, None, None),
+        }
+    }
+
+    pub fn set_message(&self, message: &str) {
+        self.task.update(0, Some(message));
+    }
+
+    pub fn finish(self) {
+        self.task.finish(true);
+    }
+
+    pub fn fail(self) {
+        self.task.finish(false);
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        self.task.finish(false);
+    }
+}
+
+/// Progress through `total` units of work, from
+/// [`PluginContext::progress_bar`](crate::PluginContext::progress_bar)
+/// or [`MultiProgress::bar`]. Logs like a [`Spinner`] outside a
+/// terminal, where positions alone are not printed.
+pub struct ProgressBar {
+    task: Task,
+}
+
+impl ProgressBar {
+    pub(crate) fn start(display: ProgressDisplay, label: impl Into<String>, total: u64) -> Self {
+        Self {
+            task: Task::start(display, label.into(), Some(total), None, None),
+        }
+    }
+
+    pub fn position(&self) -> u64 {
+        self.task.current.load(Ordering::Relaxed)
+    }
+
+    pub fn set_position(&self, position: u64) {
+        self.task.update(position, None);
+    }
+
+    pub fn inc(&self, delta: u64) {
+        self.set_position(self.position() + delta);
+    }
+
+    pub fn set_message(&self, message: &str) {
+        self.task.update(self.position(), Some(message));
+    }
+
+    pub fn finish(self) {
+        self.task.finish(true);
+    }
+
+    pub fn fail(self) {
+        self.task.finish(false);
+    }
+}
+
+impl Drop for ProgressBar {
+    fn drop(&mut self) {
+        self.task.finish(false);
+    }
+}
+
+/// A group of spinners and bars shown together, typically one per repo,
+/// from [`PluginContext::multi_progress`](crate::PluginContext::multi_progress).
+/// The group fails if any of its members did.
+pub struct MultiProgress {
+    task: Task,
+    failed: Arc<AtomicBool>,
+}
+
+impl MultiProgress {
+    pub(crate) fn start(display: ProgressDisplay, label: impl Into<String>) -> Self {
+        Self {
+            task: Task::start(display, label.into(), None, None, None),
+            failed: Arc::default(),
+        }
+    }
+
+    pub fn spinner(&self, label: impl Into<String>) -> Spinner {
+        Spinner {
+            task: self.member(label.into(), None),
+        }
+    }
+
+    pub fn bar(&self, label: impl Into<String>, total: u64) -> ProgressBar {
+        ProgressBar {
+            task: self.member(label.into(), Some(total)),
+        }
+    }
+
+    /// End the group, successfully unless a member failed.
+    pub fn finish(self) {
+        self.task.finish(!self.failed.load(Ordering::Relaxed));
+    }
+
+    fn member(&self, label: String, total: Option<u64>) -> Task {
+        Task::start(
+            self.task.display.clone(),
+            label,
+            total,
+            self.task.id,
+            Some(self.failed.clone()),
+        )
+    }
+}
+
+impl Drop for MultiProgress {
+    fn drop(&mut self) {
+        self.task.finish(!self.failed.load(Ordering::Relaxed));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<ProgressEvent>>);
+
+    impl ProgressSink for Recorder {
+        fn event(&self, event: ProgressEvent) {
+            self.0.lock().unwrap().push(event);
+        }
+    }
+
+    #[test]
+    fn test_reporter_emits_events() {
+        let recorder = Arc::new(Recorder::default());
+        let reporter = ProgressReporter::new(recorder.clone());
+        let clone = reporter.start_task("clone", Some(2));
+        let fetch = reporter.start_task("fetch", None);
+        assert_ne!(clone, fetch);
+        reporter.update(clone, 1, Some("api"));
+        reporter.finish(clone, true);
+
+        let events = recorder.0.lock().unwrap();
+        assert_eq!(events.len(), 4);
+        assert_eq!(
+            events[2],
+            ProgressEvent::Updated {
+                task: clone,
+                current: 1,
+                message: Some("api".to_string())
+            }
+        );
+    }
+
+    #[test]
+    fn test_handles_render_through_the_reporter() {
+        let recorder = Arc::new(Recorder::default());
+        let display = ProgressDisplay::Renderer(ProgressReporter::new(recorder.clone()));
+        let multi = MultiProgress::start(display, "clone");
+        let api = multi.bar("api", 2);
+        api.inc(1);
+        api.finish();
+        drop(multi.spinner("web"));
+        multi.finish();
+
+        let events = recorder.0.lock().unwrap();
+        let group = TaskId(1);
+        assert_eq!(
+            events[1],
+            ProgressEvent::Started {
+                task: TaskId(2),
+                label: "api".to_string(),
+                total: Some(2),
+                parent: Some(group),
+            }
+        );
+        assert_eq!(
+            events.last(),
+            Some(&ProgressEvent::Finished {
+                task: group,
+                success: false
+            })
+        );
+        assert_eq!(events.len(), 7);
+    }
+
+    #[test]
+    fn test_handles_log_lines_without_a_terminal() {
+        let captured = Arc::new(crate::CapturedOutput::new());
+        let display = ProgressDisplay::Log(captured.clone());
+        let spinner = Spinner::start(display.clone(), "fetch");
+        spinner.set_message("api");
+        spinner.finish();
+        let bar = ProgressBar::start(display, "pull", 3);
+        bar.inc(2);
+        bar.fail();
+        assert_eq!(
+            captured.stderr(),
+            "fetch...\nfetch: api\nfetch: done\npull...\npull: failed\n"
+        );
+        assert_eq!(captured.stdout(), "");
+    }
+
+    #[test]
+    fn test_ndjson_sink() {
+        let sink = NdjsonProgressSink::new(Vec::new());
+        sink.event(ProgressEvent::Finished {
+            task: TaskId(3),
+            success: false,
+        });
+        let output = String::from_utf8(sink.writer.into_inner().unwrap()).unwrap();
+        assert_eq!(
+            output,
+            "{\"event\":\"finished\",\"task\":3,\"success\":false}\n"
+        );
+    }
+}
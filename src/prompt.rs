@@ -0,0 +1,325 @@
+use std::io::BufRead;
+
+use crate::{PluginContext, PluginError, RepoFilter, RepoHandle};
+
+/// Asks the user questions on the plugin's behalf. Plugins prompt through
+/// [`PluginContext::prompter`](crate::PluginContext::prompter) instead of
+/// reading stdin, so the host decides whether a terminal is available.
+///
+/// Every method returns [`PluginError::NonInteractive`] when the question
+/// cannot be answered.
+pub trait Prompter: Send + Sync {
+    /// Yes/no question; `default` is the answer on a bare Enter.
+    fn confirm(&self, message: &str, default: bool) -> Result<bool, PluginError>;
+
+    /// Free-form text.
+    fn input(&self, message: &str, default: Option<&str>) -> Result<String, PluginError>;
+
+    /// Pick one of `items`, returning its index.
+    fn select(
+        &self,
+        message: &str,
+        items: &[&str],
+        default: Option<usize>,
+    ) -> Result<usize, PluginError>;
+
+    /// Pick any number of `items`, returning their indices in order.
+    /// `defaults` marks the initially selected items.
+    fn multi_select(
+        &self,
+        message: &str,
+        items: &[&str],
+        defaults: &[bool],
+    ) -> Result<Vec<usize>, PluginError>;
+
+    /// Like [`multi_select`](Self::multi_select) for long lists, where
+    /// typing narrows the items down, e.g. with [`fuzzy_score`]. The
+    /// default shows the plain list.
+    fn fuzzy_multi_select(
+        &self,
+        message: &str,
+        items: &[&str],
+        defaults: &[bool],
+    ) -> Result<Vec<usize>, PluginError> {
+        self.multi_select(message, items, defaults)
+    }
+}
+
+/// How well `query` matches `candidate`, None if it does not: the
+/// characters of `query` must appear in order, ignoring case. Runs of
+/// consecutive characters, and matches at the start or after a separator
+/// (`-`, `_`, `/`, `.` or a space), score higher.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<u32> {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    let candidate: Vec<char> = candidate.chars().flat_map(char::to_lowercase).collect();
+    let base = |j: usize| {
+        let boundary = j == 0 || matches!(candidate[j - 1], '-' | '_' | '/' | '.' | ' ');
+        if boundary {
+            4
+        } else {
+            1
+        }
+    };
+    // best[j]: top score with the current query character at `j`
+    let mut best: Vec<Option<u32>> = vec![Some(0); candidate.len() + 1];
+    for (i, &q) in query.iter().enumerate() {
+        let mut next = vec![None; candidate.len() + 1];
+        for (j, &c) in candidate.iter().enumerate() {
+            if c != q {
+                continue;
+            }
+            next[j + 1] = if i == 0 {
+                Some(base(j))
+            } else {
+                (1..=j)
+                    .filter_map(|k| {
+                        let bonus = if k == j { 4 } else { 0 };
+                        best[k].map(|score| score + bonus)
+                    })
+                    .max()
+                    .map(|score| score + base(j))
+            };
+        }
+        best = next;
+    }
+    if query.is_empty() {
+        return Some(0);
+    }
+    best.into_iter().flatten().max()
+}
+
+/// Common questions built on the context's [`Prompter`], from
+/// [`PluginContext::prompt`].
+pub struct Prompt<'a> {
+    ctx: &'a PluginContext,
+}
+
+impl<'a> Prompt<'a> {
+    pub(crate) fn new(ctx: &'a PluginContext) -> Self {
+        Self { ctx }
+    }
+
+    /// Let the user pick the repos to act on, in workspace order, with
+    /// those matching `default_filter` preselected. Answered without
+    /// asking when possible:
+    ///
+    /// - repo filter flags such as `--include-only` already chose, so
+    ///   [`selected_repos`](PluginContext::selected_repos) is returned;
+    /// - names are piped in, one per line (blank lines and `#` comments
+    ///   are skipped), and those repos are returned in that order;
+    /// - there is no terminal, so the repos matching `default_filter`
+    ///   are returned.
+    ///
+    /// Otherwise the prompter asks, with fuzzy search if it has it.
+    pub fn select_repos(
+        &self,
+        default_filter: &RepoFilter,
+    ) -> Result<Vec<RepoHandle>, PluginError> {
+        let ctx = self.ctx;
+        if !ctx.repo_filter().is_all() {
+            return Ok(ctx.selected_repos());
+        }
+        let repos = ctx.repos();
+        if let Some(stdin) = ctx.stdin() {
+            let mut chosen: Vec<RepoHandle> = Vec::new();
+            for line in stdin.lines() {
+                let line = line?;
+                let name = line.trim();
+                if name.is_empty() || name.starts_with('#') || chosen.iter().any(|r| r.name == name)
+                {
+                    continue;
+                }
+                let repo = repos
+                    .iter()
+                    .find(|r| r.name == name)
+                    .ok_or_else(|| PluginError::UnknownProject(name.to_string()))?;
+                chosen.push(repo.clone());
+            }
+            return Ok(chosen);
+        }
+        let defaults: Vec<bool> = repos
+            .iter()
+            .map(|repo| default_filter.matches(repo, ctx.env()))
+            .collect();
+        let picked = if ctx.terminal().is_tty {
+            let names: Vec<&str> = repos.iter().map(|r| r.name.as_str()).collect();
+            ctx.prompter()
+                .fuzzy_multi_select("Select repos", &names, &defaults)?
+        } else {
+            (0..repos.len()).filter(|&i| defaults[i]).collect()
+        };
+        Ok(picked
+            .into_iter()
+            .filter_map(|i| repos.get(i).cloned())
+            .collect())
+    }
+}
+
+/// Answers every question with its default, for CI and piped runs. This
+/// is what plugins get unless the host installs an interactive prompter.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NonInteractivePrompter;
+
+impl Prompter for NonInteractivePrompter {
+    fn confirm(&self, _message: &str, default: bool) -> Result<bool, PluginError> {
+        Ok(default)
+    }
+
+    fn input(&self, message: &str, default: Option<&str>) -> Result<String, PluginError> {
+        default
+            .map(str::to_string)
+            .ok_or_else(|| PluginError::NonInteractive(message.to_string()))
+    }
+
+    fn select(
+        &self,
+        message: &str,
+        items: &[&str],
+        default: Option<usize>,
+    ) -> Result<usize, PluginError> {
+        default
+            .filter(|&i| i < items.len())
+            .ok_or_else(|| PluginError::NonInteractive(message.to_string()))
+    }
+
+    fn multi_select(
+        &self,
+        _message: &str,
+        items: &[&str],
+        defaults: &[bool],
+    ) -> Result<Vec<usize>, PluginError> {
+        Ok((0..items.len())
+            .filter(|&i| defaults.get(i).copied().unwrap_or(false))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PipedStdin, ProjectInfo, TerminalInfo};
+    use std::sync::Arc;
+
+    /// Picks whatever was not preselected.
+    struct Inverting;
+
+    impl Prompter for Inverting {
+        fn confirm(&self, _message: &str, default: bool) -> Result<bool, PluginError> {
+            Ok(!default)
+        }
+        fn input(&self, _message: &str, _default: Option<&str>) -> Result<String, PluginError> {
+            Ok(String::new())
+        }
+        fn select(
+            &self,
+            _message: &str,
+            _items: &[&str],
+            _default: Option<usize>,
+        ) -> Result<usize, PluginError> {
+            Ok(0)
+        }
+        fn multi_select(
+            &self,
+            _message: &str,
+            items: &[&str],
+            defaults: &[bool],
+        ) -> Result<Vec<usize>, PluginError> {
+            Ok((0..items.len()).filter(|&i| !defaults[i]).collect())
+        }
+    }
+
+    fn workspace() -> PluginContext {
+        PluginContext::new("/work", "/work", "1.0.0").with_projects(
+            ["api", "web", "cli"]
+                .into_iter()
+                .map(|name| ProjectInfo::new(name, name, format!("git@example.com:{}.git", name)))
+                .collect(),
+        )
+    }
+
+    fn names(repos: Vec<RepoHandle>) -> Vec<String> {
+        repos.into_iter().map(|r| r.name).collect()
+    }
+
+    #[test]
+    fn test_select_repos_falls_back_without_a_terminal() {
+        let defaults = RepoFilter::all().exclude("cli");
+        let ctx = workspace();
+        assert_eq!(
+            names(ctx.prompt().select_repos(&defaults).unwrap()),
+            ["api", "web"]
+        );
+
+        let ctx = workspace().with_repo_filter(RepoFilter::all().include("c*"));
+        assert_eq!(
+            names(ctx.prompt().select_repos(&defaults).unwrap()),
+            ["cli"]
+        );
+
+        let piped = |input: &'static str| {
+            workspace().with_stdin(PipedStdin::piped(std::io::Cursor::new(input)))
+        };
+        let ctx = piped("web\n\n# keep api last\napi\nweb\n");
+        assert_eq!(
+            names(ctx.prompt().select_repos(&defaults).unwrap()),
+            ["web", "api"]
+        );
+        assert!(matches!(
+            piped("docs\n").prompt().select_repos(&defaults),
+            Err(PluginError::UnknownProject(name)) if name == "docs"
+        ));
+    }
+
+    #[test]
+    fn test_select_repos_asks_on_a_terminal() {
+        let ctx = workspace()
+            .with_terminal(TerminalInfo {
+                is_tty: true,
+                ..TerminalInfo::default()
+            })
+            .with_prompter(Arc::new(Inverting));
+        let picked = ctx
+            .prompt()
+            .select_repos(&RepoFilter::all().include("api"))
+            .unwrap();
+        assert_eq!(names(picked), ["web", "cli"]);
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert_eq!(fuzzy_score("xyz", "frontend"), None);
+        assert!(fuzzy_score("fe", "frontend-app").is_some());
+        assert!(fuzzy_score("api", "payments-api") > fuzzy_score("api", "rapid-index"));
+        assert!(fuzzy_score("WEB", "web") > fuzzy_score("web", "w-e-b"));
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+    }
+
+    #[test]
+    fn test_non_interactive_uses_defaults() {
+        let prompter = NonInteractivePrompter;
+        assert!(prompter.confirm("Push?", true).unwrap());
+        assert_eq!(prompter.input("Tag", Some("v1")).unwrap(), "v1");
+        assert_eq!(
+            prompter
+                .select("Remote", &["origin", "fork"], Some(1))
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            prompter
+                .multi_select("Projects", &["api", "web", "cli"], &[true, false, true])
+                .unwrap(),
+            vec![0, 2]
+        );
+
+        let err = prompter.input("Commit message", None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Cannot ask 'Commit message' without an interactive terminal"
+        );
+        assert!(matches!(
+            prompter.select("Remote", &["origin"], Some(3)),
+            Err(PluginError::NonInteractive(_))
+        ));
+    }
+}
@@ -0,0 +1,111 @@
+//! Plain-data messages shared by the out-of-process plugin transports
+//! ([`wasm`](crate::wasm) and [`subprocess`](crate::subprocess)).
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ContextSnapshot, HelpBody, HelpMode, HostInfo, PluginError};
+
+/// Input of an execute call.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExecuteRequest {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    pub context: ContextSnapshot,
+}
+
+/// Outcome of a fallible call; `{}` means success.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallResult {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Set when the guest failed with [`PluginError::ExitCode`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+}
+
+impl CallResult {
+    pub fn from_result(result: &anyhow::Result<()>) -> Self {
+        match result {
+            Ok(()) => Self::default(),
+            Err(e) => Self {
+                error: Some(format!("{:#}", e)),
+                exit_code: match PluginError::find(e) {
+                    Some(PluginError::ExitCode(code)) => Some(*code),
+                    _ => None,
+                },
+            },
+        }
+    }
+
+    /// Convert back into the plugin's result, restoring an exit code as
+    /// [`PluginError::ExitCode`].
+    pub fn into_result(self) -> anyhow::Result<()> {
+        match (self.exit_code, self.error) {
+            (Some(code), _) => Err(PluginError::ExitCode(code).into()),
+            (None, Some(message)) => Err(anyhow::anyhow!(message)),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+/// Reply to a help request when the plugin customizes help.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HelpReply {
+    pub mode: HelpMode,
+    pub text: String,
+    /// `text` is Markdown; see [`HelpBody::Markdown`]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub markdown: bool,
+}
+
+impl HelpReply {
+    pub fn new(mode: HelpMode, body: HelpBody) -> Self {
+        let markdown = body.is_markdown();
+        let text = match body {
+            HelpBody::Text(text) | HelpBody::Markdown(text) => text,
+        };
+        Self {
+            mode,
+            text,
+            markdown,
+        }
+    }
+
+    pub fn into_parts(self) -> (HelpMode, HelpBody) {
+        let body = if self.markdown {
+            HelpBody::Markdown(self.text)
+        } else {
+            HelpBody::Text(self.text)
+        };
+        (self.mode, body)
+    }
+}
+
+/// Input of a WASM guest's `meta_on_load`: the [`HostInfo`] plus the
+/// host's log level, so the guest only sends records the host keeps.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoadRequest {
+    #[serde(flatten)]
+    pub host: HostInfo,
+    /// A [`log::LevelFilter`] name, e.g. `warn`; absent means `trace`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_level: Option<String>,
+}
+
+impl LoadRequest {
+    /// A request carrying the host's current [`log::max_level`].
+    pub fn new(host: HostInfo) -> Self {
+        Self {
+            host,
+            log_level: Some(log::max_level().to_string().to_ascii_lowercase()),
+        }
+    }
+
+    pub fn log_level(&self) -> log::LevelFilter {
+        self.log_level
+            .as_deref()
+            .and_then(|level| level.parse().ok())
+            .unwrap_or(log::LevelFilter::Trace)
+    }
+}
@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::{CancellationToken, PluginError};
+
+/// How fast requests to one backend may go, as a token bucket: up to
+/// `burst` requests at once, then `rate` requests every `per`. A zero
+/// `rate` never refills the bucket, and a zero `per` refills it at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RatePolicy {
+    pub rate: u32,
+    pub per: Duration,
+    pub burst: u32,
+}
+
+impl RatePolicy {
+    /// `rate` requests every `per`, all of which may be spent at once.
+    pub fn new(rate: u32, per: Duration) -> Self {
+        Self {
+            rate,
+            per,
+            burst: rate,
+        }
+    }
+
+    pub fn per_second(rate: u32) -> Self {
+        Self::new(rate, Duration::from_secs(1))
+    }
+
+    pub fn per_minute(rate: u32) -> Self {
+        Self::new(rate, Duration::from_secs(60))
+    }
+
+    /// E.g. `per_hour(5000)` for GitHub's authenticated REST API.
+    pub fn per_hour(rate: u32) -> Self {
+        Self::new(rate, Duration::from_secs(3600))
+    }
+
+    /// Cap how many requests may go out back to back.
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = burst;
+        self
+    }
+
+    /// Tokens regained per second.
+    fn refill_rate(&self) -> f64 {
+        if self.rate == 0 {
+            0.0
+        } else if self.per.is_zero() {
+            f64::INFINITY
+        } else {
+            self.rate as f64 / self.per.as_secs_f64()
+        }
+    }
+}
+
+struct Bucket {
+    policy: RatePolicy,
+    tokens: f64,
+    updated: Instant,
+    /// No tokens are handed out before this, see [`RateLimiter::pause`]
+    paused_until: Option<Instant>,
+}
+
+impl Bucket {
+    fn new(policy: RatePolicy, now: Instant) -> Self {
+        Self {
+            policy,
+            tokens: policy.burst as f64,
+            updated: now,
+            paused_until: None,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        let regained = match self.policy.refill_rate() {
+            rate if rate.is_infinite() => self.policy.burst as f64,
+            rate => elapsed * rate,
+        };
+        self.tokens = (self.tokens + regained).min(self.policy.burst as f64);
+        self.updated = now;
+    }
+
+    /// Take `n` tokens, or say how long until they are there; `None` if
+    /// they never will be.
+    fn take(&mut self, n: u32, now: Instant) -> Result<(), Option<Duration>> {
+        if let Some(until) = self.paused_until {
+            if now < until {
+                return Err(Some(until - now));
+            }
+            self.paused_until = None;
+            self.updated = now;
+        }
+        self.refill(now);
+        let n = n as f64;
+        if self.tokens >= n {
+            self.tokens -= n;
+            return Ok(());
+        }
+        let rate = self.policy.refill_rate();
+        if n > self.policy.burst as f64 || rate == 0.0 {
+            return Err(None);
+        }
+        let missing = n - self.tokens;
+        Err(Some(Duration::from_secs_f64(missing / rate)))
+    }
+}
+
+fn lock(bucket: &Mutex<Bucket>) -> MutexGuard<'_, Bucket> {
+    bucket.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Token buckets by key, owned by the host and shared by every plugin, so
+/// plugins talking to the same backend draw from one budget. Use the
+/// backend as the key, e.g. `api.github.com`.
+#[derive(Clone, Default)]
+pub struct RateLimiters {
+    buckets: Arc<Mutex<HashMap<String, Arc<Mutex<Bucket>>>>>,
+}
+
+impl RateLimiters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The limiter for `key`. The first policy given for a key is the one
+    /// enforced; later callers share it.
+    pub fn get(&self, key: &str, policy: RatePolicy) -> RateLimiter {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let bucket = buckets
+            .entry(key.to_string())
+            .or_insert_with(|| Arc::new(Mutex::new(Bucket::new(policy, Instant::now()))))
+            .clone();
+        RateLimiter {
+            key: key.to_string(),
+            bucket,
+            cancellation: CancellationToken::new(),
+        }
+    }
+}
+
+impl fmt::Debug for RateLimiters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        f.debug_struct("RateLimiters")
+            .field("keys", &buckets.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Handle to one shared token bucket, from
+/// [`PluginContext::rate_limiter`](crate::PluginContext::rate_limiter).
+/// Take a token before each request.
+#[derive(Clone)]
+pub struct RateLimiter {
+    key: String,
+    bucket: Arc<Mutex<Bucket>>,
+    cancellation: CancellationToken,
+}
+
+impl RateLimiter {
+    pub(crate) fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn policy(&self) -> RatePolicy {
+        lock(&self.bucket).policy
+    }
+
+    /// Take a token if one is available right now.
+    pub fn try_acquire(&self) -> bool {
+        lock(&self.bucket).take(1, Instant::now()).is_ok()
+    }
+
+    /// Wait for a token, failing with [`PluginError::Cancelled`] if the
+    /// command is cancelled first.
+    pub fn acquire(&self) -> Result<(), PluginError> {
+        self.acquire_n(1)
+    }
+
+    /// Like [`acquire`](Self::acquire), for requests that cost `n`
+    /// tokens, e.g. a GraphQL query. Fails with
+    /// [`PluginError::ConfigError`] if `n` exceeds the burst or the bucket
+    /// is empty and never refills.
+    pub fn acquire_n(&self, n: u32) -> Result<(), PluginError> {
+        loop {
+            self.cancellation.check()?;
+            let taken = lock(&self.bucket).take(n, Instant::now());
+            let wait = match taken {
+                Ok(()) => return Ok(()),
+                Err(Some(wait)) => wait,
+                Err(None) => {
+                    let policy = self.policy();
+                    return Err(PluginError::ConfigError(format!(
+                        "rate limit '{}' cannot grant {} tokens: burst is {} and {} are regained every {:?}",
+                        self.key, n, policy.burst, policy.rate, policy.per
+                    )));
+                }
+            };
+            // Wake up now and then to notice cancellation
+            std::thread::sleep(wait.min(Duration::from_millis(100)));
+        }
+    }
+
+    /// Hand out no tokens for `duration`, e.g. until the reset time a
+    /// server sent with a 429 or 403 response.
+    pub fn pause(&self, duration: Duration) {
+        let mut bucket = lock(&self.bucket);
+        let until = Instant::now() + duration;
+        bucket.paused_until = Some(bucket.paused_until.map_or(until, |old| old.max(until)));
+        bucket.tokens = 0.0;
+    }
+}
+
+impl fmt::Debug for RateLimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RateLimiter")
+            .field("key", &self.key)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_bursts_then_refills() {
+        let start = Instant::now();
+        let mut bucket = Bucket::new(RatePolicy::per_second(10).burst(2), start);
+        assert!(bucket.take(1, start).is_ok());
+        assert!(bucket.take(1, start).is_ok());
+        let wait = bucket.take(1, start).unwrap_err().unwrap();
+        assert!(wait > Duration::from_millis(99) && wait <= Duration::from_millis(100));
+
+        let later = start + Duration::from_millis(150);
+        assert!(bucket.take(1, later).is_ok());
+        assert!(bucket.take(1, later).is_err());
+        // Never more than the burst, however long it sat idle
+        let much_later = start + Duration::from_secs(60);
+        assert!(bucket.take(2, much_later).is_ok());
+        assert!(bucket.take(1, much_later).is_err());
+    }
+
+    #[test]
+    fn test_limiters_share_buckets_by_key() {
+        let limiters = RateLimiters::new();
+        let github = limiters.get("api.github.com", RatePolicy::per_hour(2));
+        let other_plugin = limiters.get("api.github.com", RatePolicy::per_second(100));
+        assert_eq!(other_plugin.policy(), RatePolicy::per_hour(2));
+        assert!(github.try_acquire());
+        assert!(other_plugin.try_acquire());
+        assert!(!github.try_acquire());
+        assert!(limiters.get("jira", RatePolicy::per_hour(2)).try_acquire());
+
+        let token = CancellationToken::new();
+        token.cancel();
+        assert!(matches!(
+            github.clone().with_cancellation(token).acquire(),
+            Err(PluginError::Cancelled)
+        ));
+    }
+
+    #[test]
+    fn test_unsatisfiable_requests_fail() {
+        let limiters = RateLimiters::new();
+        let limiter = limiters.get("small", RatePolicy::per_second(10).burst(2));
+        assert!(matches!(
+            limiter.acquire_n(3),
+            Err(PluginError::ConfigError(_))
+        ));
+        limiter.acquire_n(2).unwrap();
+
+        let never = limiters.get("never", RatePolicy::per_second(0).burst(1));
+        never.acquire().unwrap();
+        assert!(!never.try_acquire());
+        assert!(matches!(never.acquire(), Err(PluginError::ConfigError(_))));
+
+        let unlimited = limiters.get("unlimited", RatePolicy::new(1, Duration::ZERO));
+        for _ in 0..100 {
+            unlimited.acquire().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_pause_blocks_until_reset() {
+        let limiter = RateLimiters::new().get("gitlab", RatePolicy::per_second(1000));
+        limiter.pause(Duration::from_millis(30));
+        assert!(!limiter.try_acquire());
+        let start = Instant::now();
+        limiter.acquire().unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(20));
+    }
+}
@@ -0,0 +1,162 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::io;
+use std::sync::{Arc, RwLock};
+
+use crate::{OutputSink, OutputStream};
+
+/// What a registered value is replaced with.
+pub const REDACTED: &str = "***";
+
+/// Values shorter than this are not registered: masking every `a` or
+/// `42` in the output would make it unreadable without hiding anything.
+pub const MIN_REDACT_LEN: usize = 4;
+
+/// Sensitive values to mask wherever plugin output goes. The host
+/// creates one per command and applies it to its own logs; the context
+/// applies it to [`stdout`](crate::PluginContext::stdout),
+/// [`stderr`](crate::PluginContext::stderr) and the scoped writers.
+/// Clones share the registered values.
+///
+/// Masking works per write, so a value is only caught if a single write
+/// contains all of it; `write!` and `writeln!` pass each argument whole.
+#[derive(Clone, Default)]
+pub struct Redactor {
+    values: Arc<RwLock<Vec<String>>>,
+}
+
+impl Redactor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mask `value` from now on. Secrets read through the context are
+    /// added automatically.
+    pub fn add(&self, value: impl Into<String>) {
+        let value = value.into();
+        if value.len() < MIN_REDACT_LEN {
+            return;
+        }
+        let mut values = self.values.write().unwrap_or_else(|e| e.into_inner());
+        if !values.contains(&value) {
+            values.push(value);
+            // Longest first, so a value containing another is masked whole.
+            values.sort_by_key(|v| std::cmp::Reverse(v.len()));
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+
+    /// `text` with every registered value replaced by [`REDACTED`].
+    pub fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match self.redact_bytes(text.as_bytes()) {
+            Cow::Borrowed(_) => Cow::Borrowed(text),
+            Cow::Owned(bytes) => Cow::Owned(
+                String::from_utf8(bytes).expect("replacing UTF-8 with UTF-8 keeps it valid"),
+            ),
+        }
+    }
+
+    /// Like [`redact`](Self::redact), for raw output.
+    pub fn redact_bytes<'a>(&self, bytes: &'a [u8]) -> Cow<'a, [u8]> {
+        let mut out = Cow::Borrowed(bytes);
+        for value in self.read().iter() {
+            if let Some(replaced) = replace_all(&out, value.as_bytes()) {
+                out = Cow::Owned(replaced);
+            }
+        }
+        out
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, Vec<String>> {
+        self.values.read().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl fmt::Debug for Redactor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Redactor")
+            .field("values", &self.read().len())
+            .finish()
+    }
+}
+
+/// `haystack` with each `needle` replaced by [`REDACTED`], None if it
+/// does not occur.
+fn replace_all(haystack: &[u8], needle: &[u8]) -> Option<Vec<u8>> {
+    let first = haystack.windows(needle.len()).position(|w| w == needle)?;
+    let mut out = haystack[..first].to_vec();
+    let mut rest = &haystack[first..];
+    while !rest.is_empty() {
+        if rest.starts_with(needle) {
+            out.extend_from_slice(REDACTED.as_bytes());
+            rest = &rest[needle.len()..];
+        } else {
+            out.push(rest[0]);
+            rest = &rest[1..];
+        }
+    }
+    Some(out)
+}
+
+/// [`OutputSink`] that masks a [`Redactor`]'s values before passing
+/// writes on to `inner`.
+pub struct RedactingSink {
+    inner: Arc<dyn OutputSink>,
+    redactor: Redactor,
+}
+
+impl RedactingSink {
+    pub fn new(inner: Arc<dyn OutputSink>, redactor: Redactor) -> Self {
+        Self { inner, redactor }
+    }
+}
+
+impl OutputSink for RedactingSink {
+    fn write(&self, stream: OutputStream, bytes: &[u8]) -> io::Result<()> {
+        self.inner.write(stream, &self.redactor.redact_bytes(bytes))
+    }
+
+    fn write_scoped(&self, label: &str, stream: OutputStream, line: &[u8]) -> io::Result<()> {
+        self.inner
+            .write_scoped(label, stream, &self.redactor.redact_bytes(line))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CapturedOutput;
+
+    #[test]
+    fn test_redactor_masks_registered_values() {
+        let redactor = Redactor::new();
+        let shared = redactor.clone();
+        assert_eq!(redactor.redact("ghp_abc123"), "ghp_abc123");
+
+        shared.add("ghp_abc123");
+        shared.add("abc1");
+        shared.add("ab");
+        assert_eq!(
+            redactor.redact("token=ghp_abc123 and abc1, ab"),
+            "token=*** and ***, ab"
+        );
+        assert!(matches!(redactor.redact("nothing here"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_redacting_sink_masks_plain_and_scoped_writes() {
+        let captured = Arc::new(CapturedOutput::new());
+        let redactor = Redactor::new();
+        let sink = RedactingSink::new(captured.clone(), redactor.clone());
+        redactor.add("hunter22");
+        sink.write(OutputStream::Stdout, b"password: hunter22\n")
+            .unwrap();
+        sink.write_scoped("api", OutputStream::Stderr, b"using hunter22\n")
+            .unwrap();
+        assert_eq!(captured.stdout(), "password: ***\n");
+        assert_eq!(captured.stderr(), "[api] using ***\n");
+    }
+}
@@ -0,0 +1,165 @@
+//! Plugins compiled into the host binary instead of loaded at runtime.
+//!
+//! A statically linked plugin crate registers its type with
+//! [`register_plugin!`](crate::register_plugin); the host collects every
+//! registration in the final binary with [`registered_plugins`] and drives
+//! them through the same [`Plugin`] trait as dynamically loaded ones,
+//! each wrapped in a [`PanicShield`] like a loaded library would be.
+
+use crate::{plugin_order, PanicShield, Plugin};
+
+#[doc(hidden)]
+pub use inventory as __inventory;
+
+/// A plugin constructor submitted by [`register_plugin!`](crate::register_plugin).
+pub struct PluginRegistration {
+    constructor: fn() -> Box<dyn Plugin>,
+}
+
+impl PluginRegistration {
+    pub const fn new(constructor: fn() -> Box<dyn Plugin>) -> Self {
+        Self { constructor }
+    }
+
+    /// Construct the plugin behind a [`PanicShield`].
+    pub fn create(&self) -> Box<dyn Plugin> {
+        Box::new(PanicShield::new((self.constructor)()))
+    }
+}
+
+inventory::collect!(PluginRegistration);
+
+/// Construct every plugin registered in this binary, sorted with
+/// [`plugin_order`]. The host still calls `on_load` on each.
+pub fn registered_plugins() -> Vec<Box<dyn Plugin>> {
+    let mut plugins: Vec<_> = inventory::iter::<PluginRegistration>
+        .into_iter()
+        .map(PluginRegistration::create)
+        .collect();
+    plugins.sort_by(|a, b| plugin_order(a.as_ref(), b.as_ref()));
+    plugins
+}
+
+/// Register a plugin type compiled into the host; the static counterpart
+/// of [`declare_plugin!`](crate::declare_plugin). Requires the `registry`
+/// feature.
+///
+/// ```ignore
+/// meta_plugin_api::register_plugin!(MyPlugin);
+/// // or, with an explicit constructor:
+/// meta_plugin_api::register_plugin!(MyPlugin, MyPlugin::new);
+/// ```
+#[macro_export]
+macro_rules! register_plugin {
+    ($plugin_type:ty) => {
+        $crate::register_plugin!(
+            $plugin_type,
+            <$plugin_type as ::std::default::Default>::default
+        );
+    };
+    ($plugin_type:ty, $constructor:expr) => {
+        const _: () = {
+            fn construct() -> ::std::boxed::Box<dyn $crate::Plugin> {
+                let plugin: $plugin_type = ($constructor)();
+                ::std::boxed::Box::new(plugin)
+            }
+
+            $crate::registry::__inventory::submit! {
+                $crate::registry::PluginRegistration::new(construct)
+            }
+        };
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PluginContext, PluginError};
+
+    #[derive(Default)]
+    struct Builtin;
+
+    impl Plugin for Builtin {
+        fn name(&self) -> &'static str {
+            "builtin"
+        }
+        fn commands(&self) -> Vec<&'static str> {
+            vec!["status"]
+        }
+        fn execute(
+            &self,
+            _command: &str,
+            _args: &[String],
+            _ctx: &PluginContext,
+        ) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    struct Audit(i32);
+
+    impl Plugin for Audit {
+        fn name(&self) -> &'static str {
+            "audit"
+        }
+        fn commands(&self) -> Vec<&'static str> {
+            vec!["audit"]
+        }
+        fn execute(
+            &self,
+            _command: &str,
+            _args: &[String],
+            _ctx: &PluginContext,
+        ) -> anyhow::Result<()> {
+            Ok(())
+        }
+        fn priority(&self) -> i32 {
+            self.0
+        }
+    }
+
+    struct Volatile;
+
+    impl Plugin for Volatile {
+        fn name(&self) -> &'static str {
+            "volatile"
+        }
+        fn commands(&self) -> Vec<&'static str> {
+            vec!["boom"]
+        }
+        fn execute(
+            &self,
+            _command: &str,
+            _args: &[String],
+            _ctx: &PluginContext,
+        ) -> anyhow::Result<()> {
+            panic!("boom")
+        }
+        fn priority(&self) -> i32 {
+            -10
+        }
+    }
+
+    crate::register_plugin!(Builtin);
+    crate::register_plugin!(Audit, || Audit(-1));
+    crate::register_plugin!(Volatile, || Volatile);
+
+    #[test]
+    fn test_registered_plugins_are_discovered_in_order() {
+        let plugins = registered_plugins();
+        let names: Vec<_> = plugins.iter().map(|p| p.name()).collect();
+        assert_eq!(names, ["builtin", "audit", "volatile"]);
+        assert_eq!(plugins[0].commands(), vec!["status"]);
+    }
+
+    #[test]
+    fn test_registered_plugins_are_shielded() {
+        let plugins = registered_plugins();
+        let ctx = PluginContext::new("/work", "/work", "1.0.0");
+        let err = plugins[2].execute("boom", &[], &ctx).unwrap_err();
+        assert!(matches!(
+            PluginError::find(&err),
+            Some(PluginError::Panicked { plugin, .. }) if plugin == "volatile"
+        ));
+    }
+}
@@ -0,0 +1,241 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::{CancellationToken, PluginError, ProjectInfo};
+
+/// A project from `.meta` resolved against the workspace, as handed out by
+/// [`PluginContext::repos`](crate::PluginContext::repos).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoHandle {
+    pub name: String,
+    /// Absolute, normalized checkout directory
+    pub path: PathBuf,
+    /// Repository URL the project is cloned from
+    pub url: String,
+    pub tags: Vec<String>,
+}
+
+impl RepoHandle {
+    pub fn new(workspace_root: &Path, project: &ProjectInfo) -> Self {
+        Self {
+            name: project.name.clone(),
+            path: normalize(&workspace_root.join(&project.path)),
+            url: project.url().to_string(),
+            tags: project.tags.clone(),
+        }
+    }
+
+    /// Whether the project has been cloned.
+    pub fn exists(&self) -> bool {
+        self.path.is_dir()
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    /// Run `f` with the checkout directory. Fails with
+    /// [`PluginError::RepoMissing`] if it has not been cloned. The process
+    /// working directory is left alone; pass `dir` to
+    /// [`Command::current_dir`](std::process::Command::current_dir) and
+    /// join relative paths onto it.
+    pub fn run_in<R>(&self, f: impl FnOnce(&Path) -> anyhow::Result<R>) -> anyhow::Result<R> {
+        if !self.exists() {
+            return Err(PluginError::RepoMissing {
+                name: self.name.clone(),
+                path: self.path.clone(),
+            }
+            .into());
+        }
+        f(&self.path)
+    }
+}
+
+/// Per-repo results of
+/// [`PluginContext::for_each_repo_parallel`](crate::PluginContext::for_each_repo_parallel),
+/// in `.meta` order regardless of which repo finished first.
+#[derive(Debug)]
+pub struct RepoResults<R> {
+    results: Vec<(RepoHandle, anyhow::Result<R>)>,
+}
+
+impl<R> RepoResults<R> {
+    pub fn iter(&self) -> impl Iterator<Item = (&RepoHandle, &anyhow::Result<R>)> {
+        self.results.iter().map(|(repo, result)| (repo, result))
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = (&RepoHandle, &anyhow::Error)> {
+        self.results
+            .iter()
+            .filter_map(|(repo, result)| Some((repo, result.as_ref().err()?)))
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.failures().next().is_none()
+    }
+
+    /// All values in repo order, or [`PluginError::ReposFailed`] naming
+    /// every repo that failed.
+    pub fn into_result(self) -> anyhow::Result<Vec<R>> {
+        let total = self.results.len();
+        let mut values = Vec::with_capacity(total);
+        let mut failures = Vec::new();
+        for (repo, result) in self.results {
+            match result {
+                Ok(value) => values.push(value),
+                Err(e) => failures.push((repo.name, format!("{:#}", e))),
+            }
+        }
+        if failures.is_empty() {
+            Ok(values)
+        } else {
+            Err(PluginError::ReposFailed { total, failures }.into())
+        }
+    }
+}
+
+impl<R> IntoIterator for RepoResults<R> {
+    type Item = (RepoHandle, anyhow::Result<R>);
+    type IntoIter = std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.results.into_iter()
+    }
+}
+
+/// Run `f` on every repo with at most `concurrency` threads. Repos not yet
+/// started when `cancellation` is set fail with
+/// [`PluginError::Cancelled`], and a repo `f` panics on fails with
+/// [`PluginError::Panicked`] for `plugin` without stopping the others.
+pub(crate) fn for_each_parallel<R, F>(
+    repos: Vec<RepoHandle>,
+    concurrency: usize,
+    cancellation: &CancellationToken,
+    plugin: &str,
+    f: F,
+) -> RepoResults<R>
+where
+    R: Send,
+    F: Fn(&RepoHandle) -> anyhow::Result<R> + Sync,
+{
+    let slots: Vec<Mutex<Option<anyhow::Result<R>>>> =
+        repos.iter().map(|_| Mutex::new(None)).collect();
+    let next = AtomicUsize::new(0);
+    let worker = || loop {
+        let index = next.fetch_add(1, Ordering::Relaxed);
+        let Some(repo) = repos.get(index) else {
+            break;
+        };
+        let result = match cancellation.check() {
+            Ok(()) => panic::catch_unwind(AssertUnwindSafe(|| f(repo)))
+                .unwrap_or_else(|payload| Err(PluginError::panicked(plugin, &*payload).into())),
+            Err(e) => Err(e.into()),
+        };
+        *slots[index].lock().unwrap() = Some(result);
+    };
+    std::thread::scope(|scope| {
+        for _ in 0..concurrency.clamp(1, repos.len().max(1)) {
+            scope.spawn(worker);
+        }
+    });
+
+    let results = repos
+        .into_iter()
+        .zip(slots)
+        .map(|(repo, slot)| {
+            let result = slot
+                .into_inner()
+                .unwrap()
+                .expect("every repo is visited once");
+            (repo, result)
+        })
+        .collect();
+    RepoResults { results }
+}
+
+/// Resolve `.` and `..` lexically, so `/work/./api/../web` becomes
+/// `/work/web` without touching the filesystem.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push(component);
+                }
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paths_are_normalized() {
+        let project = ProjectInfo::new("web", "./libs/../web", "git@x:web.git").with_tags(["ui"]);
+        let repo = RepoHandle::new(Path::new("/work"), &project);
+        assert_eq!(repo.path, Path::new("/work/web"));
+        assert_eq!(repo.url, "git@x:web.git");
+        assert!(repo.has_tag("ui"));
+
+        let absolute = ProjectInfo::new("shared", "/opt/shared", "git@x:shared.git");
+        assert_eq!(
+            RepoHandle::new(Path::new("/work"), &absolute).path,
+            Path::new("/opt/shared")
+        );
+    }
+
+    #[test]
+    fn test_for_each_parallel_keeps_order_and_aggregates() {
+        let repos: Vec<_> = ["a", "b", "c", "d"]
+            .iter()
+            .map(|name| RepoHandle::new(Path::new("/work"), &ProjectInfo::new(*name, *name, "")))
+            .collect();
+        let token = CancellationToken::new();
+        let results = for_each_parallel(repos.clone(), 3, &token, "test", |repo| {
+            match repo.name.as_str() {
+                "b" | "d" => Err(anyhow::anyhow!("{} broke", repo.name)),
+                "c" => panic!("c exploded"),
+                name => Ok(name.to_uppercase()),
+            }
+        });
+        assert!(!results.is_success());
+        let failed: Vec<_> = results.failures().map(|(r, _)| r.name.as_str()).collect();
+        assert_eq!(failed, ["b", "c", "d"]);
+        let err = results.into_result().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "3 of 4 repos failed:\n  b: b broke\n  c: Plugin 'test' panicked: c exploded\n  d: d broke"
+        );
+
+        token.cancel();
+        let results = for_each_parallel(repos, 2, &token, "test", |_| Ok(()));
+        assert!(results.iter().all(|(_, r)| matches!(
+            r.as_ref().err().and_then(PluginError::find),
+            Some(PluginError::Cancelled)
+        )));
+    }
+
+    #[test]
+    fn test_run_in_requires_checkout() {
+        let dir = std::env::temp_dir();
+        let project = ProjectInfo::new("tmp", ".", "git@x:tmp.git");
+        let repo = RepoHandle::new(&dir, &project);
+        let seen = repo.run_in(|path| Ok(path.to_path_buf())).unwrap();
+        assert_eq!(seen, normalize(&dir));
+
+        let missing = RepoHandle::new(&dir, &ProjectInfo::new("gone", "meta-no-such-repo", ""));
+        let err = missing.run_in(|_| Ok(())).unwrap_err();
+        assert!(matches!(
+            PluginError::find(&err),
+            Some(PluginError::RepoMissing { name, .. }) if name == "gone"
+        ));
+    }
+}
@@ -0,0 +1,268 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Capabilities, PluginError};
+
+/// Which remote hosts a plugin may connect to.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NetworkPolicy {
+    #[default]
+    Allow,
+    Deny,
+    /// Only these host names
+    Hosts(Vec<String>),
+}
+
+/// Which external programs a plugin may spawn.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubprocessPolicy {
+    #[default]
+    Allow,
+    Deny,
+    /// Only these program names, e.g. `git`
+    Programs(Vec<String>),
+}
+
+/// What the host lets a plugin do, handed over in
+/// [`HostInfo::sandbox`](crate::HostInfo::sandbox) at load time. The
+/// default is unrestricted.
+///
+/// Hosts build a profile from what the user granted out of the plugin's
+/// [`capabilities`](crate::Plugin::capabilities); plugins check it
+/// before acting and fail with [`PluginError::CapabilityDenied`] rather
+/// than half-completing a command.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SandboxProfile {
+    /// Directories the plugin may write under; `None` means anywhere
+    #[serde(default)]
+    pub allowed_paths: Option<Vec<PathBuf>>,
+    #[serde(default)]
+    pub network: NetworkPolicy,
+    #[serde(default)]
+    pub subprocess: SubprocessPolicy,
+    /// Whether tokens and credential helpers may be read
+    #[serde(default = "allow")]
+    pub credentials: bool,
+}
+
+fn allow() -> bool {
+    true
+}
+
+impl SandboxProfile {
+    /// A profile with no restrictions.
+    pub fn unrestricted() -> Self {
+        Self {
+            allowed_paths: None,
+            network: NetworkPolicy::Allow,
+            subprocess: SubprocessPolicy::Allow,
+            credentials: true,
+        }
+    }
+
+    pub fn is_unrestricted(&self) -> bool {
+        *self == Self::unrestricted()
+    }
+
+    /// A profile granting nothing; widen it with the builder methods.
+    pub fn denied() -> Self {
+        Self {
+            allowed_paths: Some(Vec::new()),
+            network: NetworkPolicy::Deny,
+            subprocess: SubprocessPolicy::Deny,
+            credentials: false,
+        }
+    }
+
+    /// The widest profile that grants only `capabilities`, with writes
+    /// limited to `workspace` when [`Capabilities::WRITE_WORKSPACE`] is
+    /// among them.
+    pub fn from_capabilities(capabilities: Capabilities, workspace: impl Into<PathBuf>) -> Self {
+        let mut profile = Self::denied();
+        if capabilities.contains(Capabilities::WRITE_WORKSPACE) {
+            profile = profile.allow_path(workspace);
+        }
+        if capabilities.contains(Capabilities::NETWORK) {
+            profile.network = NetworkPolicy::Allow;
+        }
+        if capabilities.contains(Capabilities::SPAWN_PROCESSES) {
+            profile.subprocess = SubprocessPolicy::Allow;
+        }
+        profile.credentials = capabilities.contains(Capabilities::CREDENTIALS);
+        profile
+    }
+
+    pub fn allow_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.allowed_paths
+            .get_or_insert_with(Vec::new)
+            .push(path.into());
+        self
+    }
+
+    pub fn with_network(mut self, network: NetworkPolicy) -> Self {
+        self.network = network;
+        self
+    }
+
+    pub fn with_subprocess(mut self, subprocess: SubprocessPolicy) -> Self {
+        self.subprocess = subprocess;
+        self
+    }
+
+    pub fn with_credentials(mut self, credentials: bool) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    /// Capabilities this profile grants at least in part.
+    pub fn capabilities(&self) -> Capabilities {
+        let mut granted = Capabilities::empty();
+        if self.allowed_paths.as_ref().is_none_or(|p| !p.is_empty()) {
+            granted |= Capabilities::WRITE_WORKSPACE;
+        }
+        if self.network != NetworkPolicy::Deny {
+            granted |= Capabilities::NETWORK;
+        }
+        if self.subprocess != SubprocessPolicy::Deny {
+            granted |= Capabilities::SPAWN_PROCESSES;
+        }
+        if self.credentials {
+            granted |= Capabilities::CREDENTIALS;
+        }
+        granted
+    }
+
+    /// Fail unless writing to `path` is allowed. `path` is compared
+    /// lexically, so pass it absolute and without `..`.
+    pub fn check_write(&self, path: &Path) -> Result<(), PluginError> {
+        match &self.allowed_paths {
+            Some(allowed) if !allowed.iter().any(|dir| path.starts_with(dir)) => Err(denied(
+                Capabilities::WRITE_WORKSPACE,
+                format!("writing {}", path.display()),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check_network(&self, host: &str) -> Result<(), PluginError> {
+        let allowed = match &self.network {
+            NetworkPolicy::Allow => true,
+            NetworkPolicy::Deny => false,
+            NetworkPolicy::Hosts(hosts) => hosts.iter().any(|h| h.eq_ignore_ascii_case(host)),
+        };
+        if allowed {
+            Ok(())
+        } else {
+            Err(denied(
+                Capabilities::NETWORK,
+                format!("connecting to {}", host),
+            ))
+        }
+    }
+
+    /// Fail unless `program` (a name such as `git`, or a path whose file
+    /// name is checked) may be spawned.
+    pub fn check_spawn(&self, program: &str) -> Result<(), PluginError> {
+        let name = Path::new(program)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(program);
+        let allowed = match &self.subprocess {
+            SubprocessPolicy::Allow => true,
+            SubprocessPolicy::Deny => false,
+            SubprocessPolicy::Programs(programs) => programs.iter().any(|p| p == name),
+        };
+        if allowed {
+            Ok(())
+        } else {
+            Err(denied(
+                Capabilities::SPAWN_PROCESSES,
+                format!("running {}", program),
+            ))
+        }
+    }
+
+    pub fn check_credentials(&self) -> Result<(), PluginError> {
+        if self.credentials {
+            Ok(())
+        } else {
+            Err(denied(
+                Capabilities::CREDENTIALS,
+                "reading credentials".to_string(),
+            ))
+        }
+    }
+}
+
+impl Default for SandboxProfile {
+    fn default() -> Self {
+        Self::unrestricted()
+    }
+}
+
+fn denied(capability: Capabilities, action: String) -> PluginError {
+    PluginError::CapabilityDenied {
+        capability: capability.to_string(),
+        action,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_capabilities() {
+        let profile = SandboxProfile::from_capabilities(
+            Capabilities::WRITE_WORKSPACE | Capabilities::SPAWN_PROCESSES,
+            "/work",
+        );
+        assert!(profile
+            .check_write(Path::new("/work/api/Cargo.toml"))
+            .is_ok());
+        assert!(profile.check_spawn("/usr/bin/git").is_ok());
+        assert_eq!(
+            profile
+                .check_write(Path::new("/etc/passwd"))
+                .unwrap_err()
+                .to_string(),
+            "Capability 'write_workspace' denied: writing /etc/passwd"
+        );
+        assert!(matches!(
+            profile.check_network("github.com"),
+            Err(PluginError::CapabilityDenied { .. })
+        ));
+        assert!(profile.check_credentials().is_err());
+        assert_eq!(
+            profile.capabilities(),
+            Capabilities::WRITE_WORKSPACE | Capabilities::SPAWN_PROCESSES
+        );
+        assert_eq!(
+            SandboxProfile::unrestricted().capabilities(),
+            Capabilities::all()
+        );
+    }
+
+    #[test]
+    fn test_allow_lists_and_serde() {
+        let profile = SandboxProfile::denied()
+            .with_network(NetworkPolicy::Hosts(vec!["GitHub.com".to_string()]))
+            .with_subprocess(SubprocessPolicy::Programs(vec!["git".to_string()]));
+        assert!(profile.check_network("github.com").is_ok());
+        assert!(profile.check_network("example.com").is_err());
+        assert!(profile.check_spawn("npm").is_err());
+
+        let json = serde_json::to_string(&profile).unwrap();
+        assert_eq!(
+            serde_json::from_str::<SandboxProfile>(&json).unwrap(),
+            profile
+        );
+        assert_eq!(
+            serde_json::from_str::<SandboxProfile>("{}").unwrap(),
+            SandboxProfile::unrestricted()
+        );
+    }
+}
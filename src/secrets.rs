@@ -0,0 +1,286 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::{Env, PluginError, Redactor};
+
+/// A credential such as an API token. Its `Debug` output is redacted so
+/// it does not end up in logs by accident.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    /// The secret value, to put in a request header or similar.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(***)")
+    }
+}
+
+/// Where the host keeps credentials: environment variables, the OS
+/// keychain, a secrets file. Plugins ask for secrets by name through
+/// [`PluginContext::get_secret`](crate::PluginContext::get_secret)
+/// instead of reading ad-hoc variables, so the host can rotate and audit
+/// them in one place.
+///
+/// Names are lowercase with underscores, e.g. `github_token`.
+///
+/// Providers that can persist secrets, such as a keychain, also implement
+/// [`store_secret`](Self::store_secret) and
+/// [`delete_secret`](Self::delete_secret); the defaults fail with
+/// [`PluginError::SecretStoreUnavailable`].
+pub trait SecretsProvider: Send + Sync {
+    /// The secret called `name`, None if it is not configured.
+    fn get_secret(&self, name: &str) -> Result<Option<Secret>, PluginError>;
+
+    /// Save `secret` as `name`, replacing any previous value.
+    fn store_secret(&self, _name: &str, _secret: &Secret) -> Result<(), PluginError> {
+        Err(PluginError::SecretStoreUnavailable(
+            "this provider is read-only".to_string(),
+        ))
+    }
+
+    /// Forget `name`, returning whether it was stored.
+    fn delete_secret(&self, _name: &str) -> Result<bool, PluginError> {
+        Err(PluginError::SecretStoreUnavailable(
+            "this provider is read-only".to_string(),
+        ))
+    }
+}
+
+/// The plugin's view of the host's secrets, from
+/// [`PluginContext::secrets`](crate::PluginContext::secrets). Store tokens
+/// obtained at runtime, e.g. from an OAuth flow, here rather than in
+/// files of the plugin's own.
+#[derive(Clone)]
+pub struct SecretStore {
+    provider: Arc<dyn SecretsProvider>,
+    redactor: Option<Redactor>,
+}
+
+impl SecretStore {
+    pub fn new(provider: Arc<dyn SecretsProvider>) -> Self {
+        Self {
+            provider,
+            redactor: None,
+        }
+    }
+
+    /// Register every secret read or stored with `redactor`, so it never
+    /// shows up in output.
+    pub fn redacting(mut self, redactor: Redactor) -> Self {
+        self.redactor = Some(redactor);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Result<Option<Secret>, PluginError> {
+        let secret = self.provider.get_secret(name)?;
+        if let Some(secret) = &secret {
+            self.mask(secret);
+        }
+        Ok(secret)
+    }
+
+    /// Like [`get`](Self::get), failing with [`PluginError::Unavailable`]
+    /// when the secret is not configured.
+    pub fn require(&self, name: &str) -> Result<Secret, PluginError> {
+        self.get(name)?
+            .ok_or_else(|| PluginError::Unavailable(format!("the secret '{}'", name)))
+    }
+
+    pub fn store(&self, name: &str, secret: &Secret) -> Result<(), PluginError> {
+        self.mask(secret);
+        self.provider.store_secret(name, secret)
+    }
+
+    pub fn delete(&self, name: &str) -> Result<bool, PluginError> {
+        self.provider.delete_secret(name)
+    }
+
+    fn mask(&self, secret: &Secret) {
+        if let Some(redactor) = &self.redactor {
+            redactor.add(secret.expose());
+        }
+    }
+}
+
+impl fmt::Debug for SecretStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecretStore").finish_non_exhaustive()
+    }
+}
+
+/// The variable [`EnvSecrets`] reads first for `name`:
+/// `github_token` becomes `META_SECRET_GITHUB_TOKEN`.
+pub fn secret_env_var(name: &str) -> String {
+    format!("META_SECRET_{}", name.to_ascii_uppercase())
+}
+
+/// Reads secrets from an environment: [`secret_env_var`] of the name,
+/// then the upper-cased name itself (`GITHUB_TOKEN`). Empty values count
+/// as unset. This is what plugins get unless the host installs another
+/// provider.
+#[derive(Debug, Clone)]
+pub struct EnvSecrets {
+    env: Arc<Env>,
+}
+
+impl EnvSecrets {
+    pub fn new(env: impl Into<Arc<Env>>) -> Self {
+        Self { env: env.into() }
+    }
+}
+
+impl SecretsProvider for EnvSecrets {
+    fn get_secret(&self, name: &str) -> Result<Option<Secret>, PluginError> {
+        Ok([secret_env_var(name), name.to_ascii_uppercase()]
+            .iter()
+            .find_map(|var| self.env.get(var).filter(|v| !v.is_empty()))
+            .map(Secret::new))
+    }
+}
+
+/// Asks several providers in turn, e.g. the keychain before a secrets
+/// file. The first to have the secret wins; an error from any of them
+/// stops the search. Secrets are stored in the first provider that can
+/// store them, and deleted from all that can.
+#[derive(Clone, Default)]
+pub struct SecretsChain {
+    providers: Vec<Arc<dyn SecretsProvider>>,
+}
+
+impl SecretsChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, provider: Arc<dyn SecretsProvider>) -> Self {
+        self.providers.push(provider);
+        self
+    }
+}
+
+impl SecretsProvider for SecretsChain {
+    fn get_secret(&self, name: &str) -> Result<Option<Secret>, PluginError> {
+        for provider in &self.providers {
+            if let Some(secret) = provider.get_secret(name)? {
+                return Ok(Some(secret));
+            }
+        }
+        Ok(None)
+    }
+
+    fn store_secret(&self, name: &str, secret: &Secret) -> Result<(), PluginError> {
+        let mut last_error = None;
+        for provider in &self.providers {
+            match provider.store_secret(name, secret) {
+                Err(e @ PluginError::SecretStoreUnavailable(_)) => last_error = Some(e),
+                result => return result,
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            PluginError::SecretStoreUnavailable("no providers configured".to_string())
+        }))
+    }
+
+    fn delete_secret(&self, name: &str) -> Result<bool, PluginError> {
+        let mut deleted = false;
+        for provider in &self.providers {
+            match provider.delete_secret(name) {
+                Err(PluginError::SecretStoreUnavailable(_)) => {}
+                result => deleted |= result?,
+            }
+        }
+        Ok(deleted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use super::*;
+
+    struct Keychain(HashMap<&'static str, &'static str>);
+
+    impl SecretsProvider for Keychain {
+        fn get_secret(&self, name: &str) -> Result<Option<Secret>, PluginError> {
+            Ok(self.0.get(name).map(|v| Secret::new(*v)))
+        }
+    }
+
+    #[derive(Default)]
+    struct Writable(Mutex<HashMap<String, Secret>>);
+
+    impl SecretsProvider for Writable {
+        fn get_secret(&self, name: &str) -> Result<Option<Secret>, PluginError> {
+            Ok(self.0.lock().unwrap().get(name).cloned())
+        }
+        fn store_secret(&self, name: &str, secret: &Secret) -> Result<(), PluginError> {
+            self.0
+                .lock()
+                .unwrap()
+                .insert(name.to_string(), secret.clone());
+            Ok(())
+        }
+        fn delete_secret(&self, name: &str) -> Result<bool, PluginError> {
+            Ok(self.0.lock().unwrap().remove(name).is_some())
+        }
+    }
+
+    #[test]
+    fn test_store_through_chain() {
+        let env_only = SecretStore::new(Arc::new(EnvSecrets::new(Env::new())));
+        assert!(matches!(
+            env_only.store("jira_token", &Secret::new("t")),
+            Err(PluginError::SecretStoreUnavailable(_))
+        ));
+
+        let store = SecretStore::new(Arc::new(
+            SecretsChain::new()
+                .with(Arc::new(EnvSecrets::new(Env::new())))
+                .with(Arc::new(Writable::default())),
+        ));
+        store.store("jira_token", &Secret::new("oauth")).unwrap();
+        assert_eq!(store.require("jira_token").unwrap().expose(), "oauth");
+        assert!(store.delete("jira_token").unwrap());
+        assert!(!store.delete("jira_token").unwrap());
+        assert!(store.require("jira_token").is_err());
+    }
+
+    #[test]
+    fn test_env_and_chain() {
+        let env = Env::new()
+            .with_var("GITHUB_TOKEN", "plain")
+            .with_var("META_SECRET_JIRA_TOKEN", "scoped")
+            .with_var("JIRA_TOKEN", "ignored")
+            .with_var("META_SECRET_NPM_TOKEN", "");
+        let secrets = EnvSecrets::new(env);
+        let get = |name| secrets.get_secret(name).unwrap();
+        assert_eq!(get("github_token").unwrap().expose(), "plain");
+        assert_eq!(get("jira_token").unwrap().expose(), "scoped");
+        assert_eq!(get("npm_token"), None);
+
+        let chain = SecretsChain::new()
+            .with(Arc::new(Keychain(HashMap::from([("github_token", "kc")]))))
+            .with(Arc::new(secrets.clone()));
+        let get = |name| {
+            chain
+                .get_secret(name)
+                .unwrap()
+                .map(|s| s.expose().to_string())
+        };
+        assert_eq!(get("github_token").as_deref(), Some("kc"));
+        assert_eq!(get("jira_token").as_deref(), Some("scoped"));
+        assert_eq!(format!("{:?}", Secret::new("kc")), "Secret(***)");
+    }
+}
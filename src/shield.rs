@@ -0,0 +1,301 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+
+use semver::VersionReq;
+
+use crate::{
+    Capabilities, CommandInvocation, CommandOutcome, CommandSpec, CompletionItem, Diagnostic,
+    ExecutionPlan, HelpBody, HelpMode, HelpOutput, HookDecision, HostFeatures, HostInfo, Locale,
+    LocalizedHelp, Plugin, PluginConfig, PluginContext, PluginDependency, PluginError,
+    PluginMetadata, PluginValue, RepoEvent, Shell, Signal, SignalResponse, UpdateSource,
+};
+
+/// Wraps a plugin so a panic in any trait method becomes
+/// [`PluginError::Panicked`] (or a logged fallback for methods with no
+/// error channel) instead of unwinding into the caller.
+///
+/// A `cdylib` carries its own copy of std, and the host cannot catch its
+/// panics; the catch has to run in the plugin's code. That is why
+/// [`declare_plugin!`](crate::declare_plugin) wraps the exported plugin
+/// in a shield, monomorphized inside the plugin library. The loader and
+/// `registry::registered_plugins` add one on the host side as well, so
+/// hosts never need to wrap plugins themselves.
+pub struct PanicShield<P: ?Sized> {
+    plugin: Box<P>,
+}
+
+impl<P: Plugin + ?Sized> PanicShield<P> {
+    pub fn new(plugin: Box<P>) -> Self {
+        Self { plugin }
+    }
+
+    pub fn into_inner(self) -> Box<P> {
+        self.plugin
+    }
+
+    fn plugin_name(&self) -> &'static str {
+        panic::catch_unwind(AssertUnwindSafe(|| self.plugin.name())).unwrap_or("<unknown>")
+    }
+
+    fn guard<R>(&self, f: impl FnOnce(&P) -> R) -> Result<R, PluginError> {
+        panic::catch_unwind(AssertUnwindSafe(|| f(&self.plugin)))
+            .map_err(|payload| PluginError::panicked(self.plugin_name(), &*payload))
+    }
+
+    fn guard_mut<R>(&mut self, f: impl FnOnce(&mut P) -> R) -> Result<R, PluginError> {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| f(&mut self.plugin)));
+        result.map_err(|payload| PluginError::panicked(self.plugin_name(), &*payload))
+    }
+
+    /// Like [`guard`](Self::guard) for methods with no error channel: log
+    /// the panic and fall back to `default`.
+    fn guard_or<R>(&self, default: R, f: impl FnOnce(&P) -> R) -> R {
+        self.guard(f).unwrap_or_else(|e| {
+            log::error!("{}", e);
+            default
+        })
+    }
+}
+
+impl<P: Plugin + ?Sized> Plugin for PanicShield<P> {
+    fn name(&self) -> &'static str {
+        self.guard_or("<unknown>", |p| p.name())
+    }
+
+    fn commands(&self) -> Vec<&'static str> {
+        self.guard_or(Vec::new(), |p| p.commands())
+    }
+
+    fn version(&self) -> &'static str {
+        self.guard_or("0.0.0", |p| p.version())
+    }
+
+    fn description(&self) -> &'static str {
+        self.guard_or("", |p| p.description())
+    }
+
+    fn metadata(&self) -> PluginMetadata {
+        self.guard_or(PluginMetadata::default(), |p| p.metadata())
+    }
+
+    fn update_info(&self) -> Option<UpdateSource> {
+        self.guard_or(None, |p| p.update_info())
+    }
+
+    fn command_specs(&self) -> Vec<CommandSpec> {
+        self.guard_or(Vec::new(), |p| p.command_specs())
+    }
+
+    fn resolve_command(&self, token: &str) -> Option<&'static str> {
+        self.guard_or(None, |p| p.resolve_command(token))
+    }
+
+    fn validate(&self, command: &str, args: &[String]) -> Result<(), PluginError> {
+        self.guard(|p| p.validate(command, args))?
+    }
+
+    fn execute(&self, command: &str, args: &[String], ctx: &PluginContext) -> anyhow::Result<()> {
+        self.guard(|p| p.execute(command, args, ctx))?
+    }
+
+    fn execute_structured(
+        &self,
+        command: &str,
+        args: &[String],
+        ctx: &PluginContext,
+    ) -> anyhow::Result<serde_json::Value> {
+        self.guard(|p| p.execute_structured(command, args, ctx))?
+    }
+
+    fn execute_for_value(
+        &self,
+        command: &str,
+        args: &[String],
+        ctx: &PluginContext,
+    ) -> anyhow::Result<PluginValue> {
+        self.guard(|p| p.execute_for_value(command, args, ctx))?
+    }
+
+    fn undo(&self, data: &serde_json::Value, ctx: &PluginContext) -> anyhow::Result<()> {
+        self.guard(|p| p.undo(data, ctx))?
+    }
+
+    fn plan(
+        &self,
+        command: &str,
+        args: &[String],
+        ctx: &PluginContext,
+    ) -> anyhow::Result<Option<ExecutionPlan>> {
+        self.guard(|p| p.plan(command, args, ctx))?
+    }
+
+    fn get_help_output(&self, args: &[String]) -> Option<(HelpMode, HelpBody)> {
+        self.guard_or(None, |p| p.get_help_output(args))
+    }
+
+    fn help_output(&self, args: &[String]) -> Option<(HelpMode, HelpOutput)> {
+        self.guard_or(None, |p| p.help_output(args))
+    }
+
+    fn localized_help(&self, locale: &Locale) -> Option<LocalizedHelp> {
+        self.guard_or(None, |p| p.localized_help(locale))
+    }
+
+    fn completions(&self, shell: Shell) -> Option<String> {
+        self.guard_or(None, |p| p.completions(shell))
+    }
+
+    fn complete(
+        &self,
+        command: &str,
+        arg_index: usize,
+        prefix: &str,
+        ctx: &PluginContext,
+    ) -> Vec<CompletionItem> {
+        self.guard_or(Vec::new(), |p| p.complete(command, arg_index, prefix, ctx))
+    }
+
+    fn supports_dry_run(&self, command: &str) -> bool {
+        self.guard_or(false, |p| p.supports_dry_run(command))
+    }
+
+    fn priority(&self) -> i32 {
+        self.guard_or(0, |p| p.priority())
+    }
+
+    /// A plugin that panics here gets no benefit of the doubt: it is
+    /// reported as needing everything.
+    fn capabilities(&self) -> Capabilities {
+        self.guard_or(Capabilities::all(), |p| p.capabilities())
+    }
+
+    fn dependencies(&self) -> Vec<PluginDependency> {
+        self.guard_or(Vec::new(), |p| p.dependencies())
+    }
+
+    fn config_namespace(&self) -> &'static str {
+        self.guard_or(self.plugin_name(), |p| p.config_namespace())
+    }
+
+    #[cfg(feature = "schema")]
+    fn config_schema(&self) -> Option<schemars::Schema> {
+        self.guard_or(None, |p| p.config_schema())
+    }
+
+    fn validate_config(&self, config: &PluginConfig) -> Result<(), PluginError> {
+        self.guard(|p| p.validate_config(config))?
+    }
+
+    fn before_command(&self, invocation: &CommandInvocation, ctx: &PluginContext) -> HookDecision {
+        // A hook that panics cannot vouch for the command; stop it.
+        self.guard(|p| p.before_command(invocation, ctx))
+            .unwrap_or_else(|e| HookDecision::Abort(e.to_string()))
+    }
+
+    fn after_command(
+        &self,
+        invocation: &CommandInvocation,
+        outcome: &CommandOutcome,
+        ctx: &PluginContext,
+    ) {
+        self.guard_or((), |p| p.after_command(invocation, outcome, ctx))
+    }
+
+    fn on_signal(&self, signal: Signal) -> SignalResponse {
+        self.guard_or(SignalResponse::Default, |p| p.on_signal(signal))
+    }
+
+    fn on_repo_event(&self, event: &RepoEvent, ctx: &PluginContext) -> anyhow::Result<()> {
+        self.guard(|p| p.on_repo_event(event, ctx))?
+    }
+
+    fn health_check(&self, ctx: &PluginContext) -> Vec<Diagnostic> {
+        self.guard(|p| p.health_check(ctx))
+            .unwrap_or_else(|e| vec![Diagnostic::error("health-check-panicked", e.to_string())])
+    }
+
+    fn required_host(&self) -> VersionReq {
+        self.guard_or(VersionReq::STAR, |p| p.required_host())
+    }
+
+    fn requires_features(&self) -> HostFeatures {
+        self.guard_or(HostFeatures::empty(), |p| p.requires_features())
+    }
+
+    fn migrate_state(&self, from_version: &str, state_dir: &Path) -> anyhow::Result<()> {
+        self.guard(|p| p.migrate_state(from_version, state_dir))?
+    }
+
+    fn on_load(&mut self, host: &HostInfo) -> anyhow::Result<()> {
+        self.guard_mut(|p| p.on_load(host))?
+    }
+
+    fn set_logger(&self, logger: &'static dyn log::Log, level: log::LevelFilter) {
+        self.guard_or((), |p| p.set_logger(logger, level))
+    }
+
+    fn on_unload(&mut self) {
+        if let Err(e) = self.guard_mut(|p| p.on_unload()) {
+            log::error!("{}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Flaky;
+
+    impl Plugin for Flaky {
+        fn name(&self) -> &'static str {
+            "flaky"
+        }
+        fn commands(&self) -> Vec<&'static str> {
+            panic!("commands exploded")
+        }
+        fn execute(
+            &self,
+            command: &str,
+            _args: &[String],
+            _ctx: &PluginContext,
+        ) -> anyhow::Result<()> {
+            match command {
+                "ok" => Ok(()),
+                _ => panic!("{} exploded", command),
+            }
+        }
+        fn before_command(
+            &self,
+            _invocation: &CommandInvocation,
+            _ctx: &PluginContext,
+        ) -> HookDecision {
+            panic!("hook exploded")
+        }
+    }
+
+    #[test]
+    fn test_panics_are_contained() {
+        let shield = PanicShield::new(Box::new(Flaky));
+        let ctx = PluginContext::new("/work", "/work", "1.0.0");
+
+        assert!(shield.execute("ok", &[], &ctx).is_ok());
+        let err = shield.execute("sync", &[], &ctx).unwrap_err();
+        assert!(matches!(
+            PluginError::find(&err),
+            Some(PluginError::Panicked { plugin, payload })
+                if plugin == "flaky" && payload == "sync exploded"
+        ));
+        assert!(shield.commands().is_empty());
+
+        let invocation = CommandInvocation {
+            plugin: "git",
+            command: "push",
+            args: &[],
+        };
+        assert!(matches!(
+            shield.before_command(&invocation, &ctx),
+            HookDecision::Abort(reason) if reason.contains("hook exploded")
+        ));
+    }
+}
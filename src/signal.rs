@@ -0,0 +1,159 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{CancellationToken, Plugin};
+
+/// A process signal the host forwards to plugins through
+/// [`Plugin::on_signal`], named the same on every platform. Unix hosts
+/// map signal numbers with [`from_unix`](Self::from_unix), Windows hosts
+/// map console control events with
+/// [`from_console_event`](Self::from_console_event).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Signal {
+    /// `SIGINT`, or Ctrl-C on Windows
+    Interrupt,
+    /// `SIGTERM`, or a logoff or shutdown on Windows
+    Terminate,
+    /// `SIGHUP`, or the console window closing on Windows
+    Hangup,
+    /// `SIGQUIT`, or Ctrl-Break on Windows
+    Quit,
+}
+
+impl Signal {
+    /// The signal for Unix signal number `signo`. These four numbers are
+    /// the same on every Unix.
+    pub fn from_unix(signo: i32) -> Option<Self> {
+        match signo {
+            1 => Some(Signal::Hangup),
+            2 => Some(Signal::Interrupt),
+            3 => Some(Signal::Quit),
+            15 => Some(Signal::Terminate),
+            _ => None,
+        }
+    }
+
+    /// The signal for a Windows `CTRL_*_EVENT` passed to a console
+    /// control handler.
+    pub fn from_console_event(event: u32) -> Option<Self> {
+        match event {
+            0 => Some(Signal::Interrupt),
+            1 => Some(Signal::Quit),
+            2 => Some(Signal::Hangup),
+            5 | 6 => Some(Signal::Terminate),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Signal::Interrupt => "interrupt",
+            Signal::Terminate => "terminate",
+            Signal::Hangup => "hangup",
+            Signal::Quit => "quit",
+        }
+    }
+}
+
+/// What a plugin did with a [`Signal`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignalResponse {
+    /// Nothing special; the host cancels the running command as usual
+    #[default]
+    Default,
+    /// The plugin dealt with it (e.g. reopened its log on `Hangup`); the
+    /// command keeps running
+    Handled,
+}
+
+/// Forward `signal` to every plugin, then cancel `token` unless one of
+/// them answered [`SignalResponse::Handled`]. Every plugin is told, so
+/// each can flush journals or release locks, even after another handled
+/// the signal.
+pub fn deliver_signal<'a>(
+    plugins: impl IntoIterator<Item = &'a dyn Plugin>,
+    signal: Signal,
+    token: &CancellationToken,
+) -> SignalResponse {
+    let response = plugins
+        .into_iter()
+        .map(|plugin| plugin.on_signal(signal))
+        .fold(SignalResponse::Default, |acc, r| match r {
+            SignalResponse::Handled => SignalResponse::Handled,
+            SignalResponse::Default => acc,
+        });
+    if response == SignalResponse::Default {
+        token.cancel();
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::PluginContext;
+
+    struct Journal {
+        flushed: AtomicUsize,
+    }
+
+    impl Plugin for Journal {
+        fn name(&self) -> &'static str {
+            "journal"
+        }
+        fn commands(&self) -> Vec<&'static str> {
+            vec![]
+        }
+        fn execute(
+            &self,
+            _command: &str,
+            _args: &[String],
+            _ctx: &PluginContext,
+        ) -> anyhow::Result<()> {
+            Ok(())
+        }
+        fn on_signal(&self, signal: Signal) -> SignalResponse {
+            self.flushed.fetch_add(1, Ordering::SeqCst);
+            match signal {
+                Signal::Hangup => SignalResponse::Handled,
+                _ => SignalResponse::Default,
+            }
+        }
+    }
+
+    #[test]
+    fn test_deliver_signal() {
+        let journal = Journal {
+            flushed: AtomicUsize::new(0),
+        };
+        let plugins: [&dyn Plugin; 1] = [&journal];
+
+        let token = CancellationToken::new();
+        let response = deliver_signal(plugins, Signal::Hangup, &token);
+        assert_eq!(response, SignalResponse::Handled);
+        assert!(!token.is_cancelled());
+
+        assert_eq!(
+            deliver_signal(plugins, Signal::Terminate, &token),
+            SignalResponse::Default
+        );
+        assert!(token.is_cancelled());
+        assert_eq!(journal.flushed.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_platform_mapping() {
+        assert_eq!(Signal::from_unix(15), Some(Signal::Terminate));
+        assert_eq!(Signal::from_unix(9), None);
+        assert_eq!(Signal::from_console_event(0), Some(Signal::Interrupt));
+        assert_eq!(Signal::from_console_event(6), Some(Signal::Terminate));
+        assert_eq!(
+            serde_json::to_string(&Signal::Hangup).unwrap(),
+            "\"hangup\""
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "signing")]
+use crate::PluginError;
+
+/// Detached signature for a plugin artifact (shared library or `.wasm`),
+/// stored next to it as `<artifact>.manifest.json`; see
+/// [`path_for`](Self::path_for).
+///
+/// The signature is ed25519 over [`SIGNATURE_CONTEXT`] followed by the
+/// raw SHA-256 digest of the artifact. Verification (feature `signing`)
+/// is [`TrustedKeys::verify`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedManifest {
+    /// File name of the artifact, e.g. `libmeta_git.so`
+    pub artifact: String,
+    /// Lowercase hex SHA-256 of the artifact
+    pub sha256: String,
+    /// Name of the key that signed it, looked up in the host's trusted keys
+    pub signer: String,
+    /// Lowercase hex ed25519 signature
+    pub signature: String,
+}
+
+/// Domain separator prepended to the digest before signing, so a plugin
+/// signature cannot be replayed as a signature over anything else.
+pub const SIGNATURE_CONTEXT: &[u8] = b"meta-plugin-signature-v1\0";
+
+impl SignedManifest {
+    /// Where the manifest for `artifact` lives.
+    pub fn path_for(artifact: &Path) -> PathBuf {
+        let mut name = artifact.file_name().unwrap_or_default().to_os_string();
+        name.push(".manifest.json");
+        artifact.with_file_name(name)
+    }
+
+    /// Read the manifest stored next to `artifact`.
+    pub fn load_for(artifact: &Path) -> Result<Self, crate::PluginError> {
+        let path = Self::path_for(artifact);
+        let bytes = std::fs::read(&path).map_err(|e| {
+            crate::PluginError::SignatureInvalid(format!("{}: {}", path.display(), e))
+        })?;
+        serde_json::from_slice(&bytes)
+            .map_err(|e| crate::PluginError::SignatureInvalid(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Sign `contents` as the artifact named `artifact`.
+    #[cfg(feature = "signing")]
+    pub fn sign(
+        artifact: impl Into<String>,
+        contents: &[u8],
+        signer: impl Into<String>,
+        key: &ed25519_dalek::SigningKey,
+    ) -> Self {
+        use ed25519_dalek::Signer;
+
+        let digest = sha256(contents);
+        let signature = key.sign(&signed_message(&digest));
+        Self {
+            artifact: artifact.into(),
+            sha256: hex(&digest),
+            signer: signer.into(),
+            signature: hex(&signature.to_bytes()),
+        }
+    }
+}
+
+/// Public keys the host accepts plugin signatures from.
+#[cfg(feature = "signing")]
+#[derive(Debug, Clone, Default)]
+pub struct TrustedKeys {
+    keys: std::collections::BTreeMap<String, ed25519_dalek::VerifyingKey>,
+}
+
+#[cfg(feature = "signing")]
+impl TrustedKeys {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_key(mut self, signer: impl Into<String>, key: ed25519_dalek::VerifyingKey) -> Self {
+        self.keys.insert(signer.into(), key);
+        self
+    }
+
+    /// Trust `signer` with a key given as 64 hex characters, as found in
+    /// host configuration.
+    pub fn with_hex_key(self, signer: impl Into<String>, key: &str) -> Result<Self, PluginError> {
+        let signer = signer.into();
+        let invalid = || PluginError::SignatureInvalid(format!("bad public key for '{}'", signer));
+        let bytes: [u8; 32] = unhex(key)
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(invalid)?;
+        let key = ed25519_dalek::VerifyingKey::from_bytes(&bytes).map_err(|_| invalid())?;
+        Ok(self.with_key(signer, key))
+    }
+
+    /// Check that `contents` match `manifest` and that a trusted key
+    /// signed it.
+    pub fn verify(&self, manifest: &SignedManifest, contents: &[u8]) -> Result<(), PluginError> {
+        let invalid = |reason: &str| {
+            PluginError::SignatureInvalid(format!("{}: {}", manifest.artifact, reason))
+        };
+        let digest = sha256(contents);
+        if !hex(&digest).eq_ignore_ascii_case(&manifest.sha256) {
+            return Err(invalid("contents do not match the manifest hash"));
+        }
+        let key = self
+            .keys
+            .get(&manifest.signer)
+            .ok_or_else(|| invalid(&format!("signer '{}' is not trusted", manifest.signer)))?;
+        let signature: [u8; 64] = unhex(&manifest.signature)
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(|| invalid("malformed signature"))?;
+        key.verify_strict(
+            &signed_message(&digest),
+            &ed25519_dalek::Signature::from_bytes(&signature),
+        )
+        .map_err(|_| invalid("signature does not verify"))
+    }
+
+    /// Verify the artifact at `path` against the manifest stored next to
+    /// it. Call before loading the plugin.
+    pub fn verify_file(&self, path: &Path) -> Result<(), PluginError> {
+        let manifest = SignedManifest::load_for(path)?;
+        let contents = std::fs::read(path)?;
+        self.verify(&manifest, &contents)
+    }
+}
+
+#[cfg(feature = "signing")]
+fn sha256(contents: &[u8]) -> [u8; 32] {
+    use sha2::Digest;
+    sha2::Sha256::digest(contents).into()
+}
+
+#[cfg(feature = "signing")]
+fn signed_message(digest: &[u8; 32]) -> Vec<u8> {
+    [SIGNATURE_CONTEXT, digest.as_slice()].concat()
+}
+
+#[cfg(feature = "signing")]
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(feature = "signing")]
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_path() {
+        assert_eq!(
+            SignedManifest::path_for(Path::new("/plugins/libmeta_git.so")),
+            Path::new("/plugins/libmeta_git.so.manifest.json")
+        );
+    }
+
+    #[cfg(feature = "signing")]
+    #[test]
+    fn test_sign_and_verify() {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7; 32]);
+        let public = hex(key.verifying_key().as_bytes());
+        let keys = TrustedKeys::new()
+            .with_hex_key("release-team", &public)
+            .unwrap();
+
+        let manifest = SignedManifest::sign("plugin.wasm", b"\0asm", "release-team", &key);
+        assert!(keys.verify(&manifest, b"\0asm").is_ok());
+
+        let err = keys.verify(&manifest, b"\0asm tampered").unwrap_err();
+        assert!(err.to_string().contains("contents do not match"));
+
+        let forged = SignedManifest {
+            signer: "intern".to_string(),
+            ..manifest.clone()
+        };
+        assert!(keys
+            .verify(&forged, b"\0asm")
+            .unwrap_err()
+            .to_string()
+            .contains("not trusted"));
+
+        let other = ed25519_dalek::SigningKey::from_bytes(&[8; 32]);
+        let resigned = SignedManifest::sign("plugin.wasm", b"\0asm", "release-team", &other);
+        assert!(matches!(
+            keys.verify(&resigned, b"\0asm"),
+            Err(PluginError::SignatureInvalid(_))
+        ));
+    }
+}
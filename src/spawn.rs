@@ -0,0 +1,337 @@
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+use crate::error::panic_message;
+use crate::{CancellationToken, Diagnostic, PluginError};
+
+#[derive(Default)]
+struct Shared {
+    closed: AtomicBool,
+    running: Mutex<usize>,
+    idle: Condvar,
+    diagnostics: Mutex<Vec<Diagnostic>>,
+    #[cfg(feature = "async")]
+    aborts: Mutex<Vec<tokio::task::AbortHandle>>,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The tasks plugins started during one command, owned by the host. After
+/// the command returns, the host calls [`shutdown`](Self::shutdown) so no
+/// task outlives it and panics inside tasks are reported.
+#[derive(Clone, Default)]
+pub struct TaskSet {
+    shared: Arc<Shared>,
+}
+
+impl TaskSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tasks started and not yet finished.
+    pub fn running(&self) -> usize {
+        *lock(&self.shared.running)
+    }
+
+    /// One error per task that panicked so far.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        lock(&self.shared.diagnostics).clone()
+    }
+
+    /// Refuse new tasks, abort async ones and wait up to `timeout` for
+    /// blocking ones, which should be watching the cancellation token.
+    /// Returns the panics, plus a warning if tasks were still running.
+    pub fn shutdown(&self, timeout: Duration) -> Vec<Diagnostic> {
+        self.shared.closed.store(true, Ordering::SeqCst);
+        #[cfg(feature = "async")]
+        for abort in lock(&self.shared.aborts).drain(..) {
+            abort.abort();
+        }
+        let running = lock(&self.shared.running);
+        let (running, _) = self
+            .shared
+            .idle
+            .wait_timeout_while(running, timeout, |running| *running > 0)
+            .unwrap_or_else(|e| e.into_inner());
+        let mut diagnostics = std::mem::take(&mut *lock(&self.shared.diagnostics));
+        if *running > 0 {
+            diagnostics.push(
+                Diagnostic::warning(
+                    "task-still-running",
+                    format!("{} task(s) kept running after the command ended", *running),
+                )
+                .remediation("Stop background work when the cancellation token is set"),
+            );
+        }
+        diagnostics
+    }
+
+    /// Count a new task in, unless the set is shut down.
+    fn admit(&self) -> Result<Running, PluginError> {
+        if self.shared.closed.load(Ordering::SeqCst) {
+            return Err(PluginError::Cancelled);
+        }
+        *lock(&self.shared.running) += 1;
+        Ok(Running(self.shared.clone()))
+    }
+
+    fn record_panic(&self, task: &str, payload: &(dyn std::any::Any + Send)) -> PluginError {
+        let error = PluginError::TaskPanicked {
+            task: task.to_string(),
+            payload: panic_message(payload),
+        };
+        lock(&self.shared.diagnostics).push(Diagnostic::error("task-panicked", error.to_string()));
+        error
+    }
+}
+
+impl fmt::Debug for TaskSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaskSet")
+            .field("running", &self.running())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Counts a task as running until dropped at the end of it.
+struct Running(Arc<Shared>);
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        *lock(&self.0.running) -= 1;
+        self.0.idle.notify_all();
+    }
+}
+
+/// Starts background work tied to the current command, from
+/// [`PluginContext::spawner`](crate::PluginContext::spawner). Tasks see
+/// the command's cancellation token, are refused once it is cancelled or
+/// the command ended, and panics become [`PluginError::TaskPanicked`] and
+/// a diagnostic for the host instead of tearing down the process.
+#[derive(Clone)]
+pub struct Spawner {
+    tasks: TaskSet,
+    cancellation: CancellationToken,
+    #[cfg(feature = "async")]
+    runtime: Option<tokio::runtime::Handle>,
+}
+
+impl Spawner {
+    pub(crate) fn new(tasks: TaskSet, cancellation: CancellationToken) -> Self {
+        Self {
+            tasks,
+            cancellation,
+            #[cfg(feature = "async")]
+            runtime: None,
+        }
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) fn with_runtime(mut self, runtime: Option<tokio::runtime::Handle>) -> Self {
+        self.runtime = runtime;
+        self
+    }
+
+    /// Run `f` on its own thread, named after `name`. `f` gets the
+    /// cancellation token and should return soon after it is set.
+    pub fn spawn_blocking<F, R>(
+        &self,
+        name: impl Into<String>,
+        f: F,
+    ) -> Result<TaskHandle<R>, PluginError>
+    where
+        F: FnOnce(&CancellationToken) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        self.cancellation.check()?;
+        let running = self.tasks.admit()?;
+        let name = name.into();
+        let tasks = self.tasks.clone();
+        let token = self.cancellation.clone();
+        let thread = thread::Builder::new()
+            .name(format!("meta-task-{}", name))
+            .spawn(move || {
+                let _running = running;
+                panic::catch_unwind(AssertUnwindSafe(|| f(&token)))
+                    .map_err(|payload| tasks.record_panic(&name, &*payload))
+            })?;
+        Ok(TaskHandle { thread })
+    }
+
+    /// Run `future` on the host's runtime. It is aborted when the
+    /// command is cancelled or ends.
+    #[cfg(feature = "async")]
+    pub fn spawn<F>(
+        &self,
+        name: impl Into<String>,
+        future: F,
+    ) -> Result<AsyncTaskHandle<F::Output>, PluginError>
+    where
+        F: std::future::Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let runtime = self
+            .runtime
+            .as_ref()
+            .ok_or_else(|| PluginError::Unavailable("an async runtime".to_string()))?;
+        self.cancellation.check()?;
+        let running = self.tasks.admit()?;
+        let name = name.into();
+        let inner = runtime.spawn(future);
+        let abort = inner.abort_handle();
+        lock(&self.tasks.shared.aborts).push(abort.clone());
+        self.cancellation.on_cancel(move || abort.abort());
+
+        let tasks = self.tasks.clone();
+        let outer = runtime.spawn(async move {
+            let _running = running;
+            match inner.await {
+                Ok(output) => Ok(output),
+                Err(error) if error.is_panic() => {
+                    Err(tasks.record_panic(&name, &*error.into_panic()))
+                }
+                Err(_) => Err(PluginError::Cancelled),
+            }
+        });
+        Ok(AsyncTaskHandle { outer })
+    }
+}
+
+impl fmt::Debug for Spawner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Spawner")
+            .field("tasks", &self.tasks)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A task started with [`Spawner::spawn_blocking`].
+pub struct TaskHandle<R> {
+    thread: thread::JoinHandle<Result<R, PluginError>>,
+}
+
+impl<R> TaskHandle<R> {
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Wait for the task and take its result.
+    pub fn join(self) -> Result<R, PluginError> {
+        self.thread.join().unwrap_or_else(|payload| {
+            Err(PluginError::TaskPanicked {
+                task: "unknown".to_string(),
+                payload: panic_message(&*payload),
+            })
+        })
+    }
+}
+
+/// A task started with [`Spawner::spawn`]; await it for the result.
+/// Dropping it leaves the task running.
+#[cfg(feature = "async")]
+pub struct AsyncTaskHandle<R> {
+    outer: tokio::task::JoinHandle<Result<R, PluginError>>,
+}
+
+#[cfg(feature = "async")]
+impl<R> std::future::Future for AsyncTaskHandle<R> {
+    type Output = Result<R, PluginError>;
+
+    fn poll(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        std::pin::Pin::new(&mut self.outer)
+            .poll(cx)
+            .map(|joined| joined.unwrap_or(Err(PluginError::Cancelled)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocking_tasks_join_and_capture_panics() {
+        let tasks = TaskSet::new();
+        let token = CancellationToken::new();
+        let spawner = Spawner::new(tasks.clone(), token.clone());
+
+        let sum = spawner.spawn_blocking("sum", |_| 2 + 2).unwrap();
+        assert_eq!(sum.join().unwrap(), 4);
+
+        let broken = spawner
+            .spawn_blocking("index", |_| -> u32 { panic!("corrupt index") })
+            .unwrap();
+        assert_eq!(
+            broken.join().unwrap_err().to_string(),
+            "Task 'index' panicked: corrupt index"
+        );
+        assert_eq!(tasks.diagnostics()[0].code, "task-panicked");
+
+        let waiting = spawner
+            .spawn_blocking("watch", |token| {
+                while !token.is_cancelled() {
+                    thread::sleep(Duration::from_millis(1));
+                }
+            })
+            .unwrap();
+        token.cancel();
+        waiting.join().unwrap();
+        assert!(matches!(
+            spawner.spawn_blocking("late", |_| ()),
+            Err(PluginError::Cancelled)
+        ));
+        assert_eq!(tasks.shutdown(Duration::from_secs(1)).len(), 1);
+        assert_eq!(tasks.running(), 0);
+    }
+
+    #[test]
+    fn test_shutdown_reports_tasks_left_running() {
+        let tasks = TaskSet::new();
+        let spawner = Spawner::new(tasks.clone(), CancellationToken::new());
+        let (release, wait) = std::sync::mpsc::channel::<()>();
+        let stuck = spawner
+            .spawn_blocking("stuck", move |_| wait.recv().ok())
+            .unwrap();
+        let diagnostics = tasks.shutdown(Duration::from_millis(10));
+        assert_eq!(diagnostics[0].code, "task-still-running");
+        assert!(matches!(
+            spawner.spawn_blocking("after", |_| ()),
+            Err(PluginError::Cancelled)
+        ));
+        release.send(()).unwrap();
+        stuck.join().unwrap();
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_tasks_run_on_the_host_runtime() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let tasks = TaskSet::new();
+        let spawner = Spawner::new(tasks.clone(), CancellationToken::new())
+            .with_runtime(Some(runtime.handle().clone()));
+        let double = spawner.spawn("double", async { 21 * 2 }).unwrap();
+        let broken = spawner
+            .spawn("broken", async { panic!("bad response") })
+            .unwrap();
+        runtime.block_on(async {
+            assert_eq!(double.await.unwrap(), 42);
+            let err = broken.await.unwrap_err();
+            assert_eq!(err.to_string(), "Task 'broken' panicked: bad response");
+        });
+        assert!(matches!(
+            Spawner::new(TaskSet::new(), CancellationToken::new()).spawn("x", async {}),
+            Err(PluginError::Unavailable(_))
+        ));
+    }
+}
@@ -0,0 +1,235 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{Plugin, PluginError};
+
+/// Directory for `plugin`'s data about the workspace at `workspace_root`,
+/// under a host-chosen `base` (e.g. `~/.local/state/meta`). The workspace
+/// is identified by a stable hash of its path so that two checkouts of the
+/// same repos don't share state.
+pub fn workspace_scoped_dir(base: &Path, plugin: &str, workspace_root: &Path) -> PathBuf {
+    let hash = stable_hash(workspace_root.to_string_lossy().as_bytes());
+    let label: String = workspace_root
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    base.join(plugin).join(format!("{}-{:016x}", label, hash))
+}
+
+/// File in a plugin's state directory recording which plugin version
+/// wrote it; see [`migrate_if_needed`].
+pub const VERSION_STAMP_FILE: &str = ".meta-plugin-version";
+
+/// Version assumed for state written before the plugin stamped its
+/// directory: a directory with files but no stamp migrates from this.
+pub const UNSTAMPED_VERSION: &str = "0.0.0";
+
+/// The plugin version recorded in `state_dir`, None if there is none.
+pub fn read_version_stamp(state_dir: &Path) -> Result<Option<String>, PluginError> {
+    Ok(read_if_exists(&state_dir.join(VERSION_STAMP_FILE))?
+        .map(|bytes| String::from_utf8_lossy(&bytes).trim().to_string()))
+}
+
+pub fn write_version_stamp(state_dir: &Path, version: &str) -> Result<(), PluginError> {
+    write_atomic(&state_dir.join(VERSION_STAMP_FILE), version.as_bytes())
+}
+
+/// Run [`Plugin::migrate_state`] if `state_dir` was written by another
+/// version of `plugin`, then stamp it with the current version. An
+/// empty or missing directory is stamped without migrating. Returns
+/// whether a migration ran.
+pub fn migrate_if_needed(plugin: &dyn Plugin, state_dir: &Path) -> anyhow::Result<bool> {
+    let current = plugin.version();
+    let from = match read_version_stamp(state_dir)? {
+        Some(stamp) if stamp == current => return Ok(false),
+        Some(stamp) => Some(stamp),
+        None if has_entries(state_dir)? => Some(UNSTAMPED_VERSION.to_string()),
+        None => None,
+    };
+    if let Some(from) = &from {
+        plugin.migrate_state(from, state_dir)?;
+    }
+    write_version_stamp(state_dir, current)?;
+    Ok(from.is_some())
+}
+
+fn has_entries(dir: &Path) -> Result<bool, PluginError> {
+    match fs::read_dir(dir) {
+        Ok(mut entries) => Ok(entries.next().is_some()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// FNV-1a: stable across Rust releases, unlike DefaultHasher.
+pub(crate) fn stable_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Distinguishes the temp files of concurrent [`write_atomic`] calls in
+/// one process.
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Replace `path` with `contents` without ever exposing a partially
+/// written file: the data is written and synced to a sibling temp file
+/// which is then renamed over the target.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), PluginError> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(dir)?;
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+    let tmp = dir.join(format!(
+        ".{}.tmp-{}-{}",
+        file_name.to_string_lossy(),
+        std::process::id(),
+        TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let result = (|| {
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    Ok(result?)
+}
+
+/// Read `path`, returning None if it does not exist.
+pub fn read_if_exists(path: &Path) -> Result<Option<Vec<u8>>, PluginError> {
+    match fs::read(path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("meta_plugin_api-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_workspace_scoped_dir_is_stable_and_distinct() {
+        let base = Path::new("/state");
+        let a = workspace_scoped_dir(base, "release", Path::new("/home/me/work"));
+        let b = workspace_scoped_dir(base, "release", Path::new("/home/me/other/work"));
+        assert_eq!(
+            a,
+            workspace_scoped_dir(base, "release", Path::new("/home/me/work"))
+        );
+        assert_ne!(a, b);
+        assert!(a.starts_with("/state/release"));
+        assert!(a
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("work-"));
+    }
+
+    struct Versioned {
+        version: &'static str,
+        migrated: std::sync::Mutex<Vec<String>>,
+    }
+
+    impl Plugin for Versioned {
+        fn name(&self) -> &'static str {
+            "versioned"
+        }
+        fn commands(&self) -> Vec<&'static str> {
+            vec![]
+        }
+        fn version(&self) -> &'static str {
+            self.version
+        }
+        fn execute(
+            &self,
+            _command: &str,
+            _args: &[String],
+            _ctx: &crate::PluginContext,
+        ) -> anyhow::Result<()> {
+            Ok(())
+        }
+        fn migrate_state(&self, from_version: &str, _state_dir: &Path) -> anyhow::Result<()> {
+            self.migrated.lock().unwrap().push(from_version.to_string());
+            anyhow::ensure!(from_version != "0.1.0", "cannot migrate 0.1.0");
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_migrate_if_needed() {
+        let dir = temp_dir("migrate");
+        let plugin = |version| Versioned {
+            version,
+            migrated: Default::default(),
+        };
+
+        let v1 = plugin("1.0.0");
+        assert!(!migrate_if_needed(&v1, &dir).unwrap());
+        assert!(!migrate_if_needed(&v1, &dir).unwrap());
+        assert_eq!(read_version_stamp(&dir).unwrap().as_deref(), Some("1.0.0"));
+
+        let v2 = plugin("2.0.0");
+        assert!(migrate_if_needed(&v2, &dir).unwrap());
+        assert_eq!(*v2.migrated.lock().unwrap(), ["1.0.0"]);
+
+        fs::remove_file(dir.join(VERSION_STAMP_FILE)).unwrap();
+        fs::write(dir.join("cache.json"), "{}").unwrap();
+        assert!(migrate_if_needed(&v2, &dir).unwrap());
+        assert_eq!(v2.migrated.lock().unwrap()[1], UNSTAMPED_VERSION);
+
+        write_version_stamp(&dir, "0.1.0").unwrap();
+        assert!(migrate_if_needed(&v2, &dir).is_err());
+        assert_eq!(read_version_stamp(&dir).unwrap().as_deref(), Some("0.1.0"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_atomic_write_and_read() {
+        let dir = temp_dir("state");
+        let path = dir.join("nested").join("state.json");
+        assert_eq!(read_if_exists(&path).unwrap(), None);
+        write_atomic(&path, b"one").unwrap();
+        write_atomic(&path, b"two").unwrap();
+        assert_eq!(read_if_exists(&path).unwrap(), Some(b"two".to_vec()));
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+
+        // Threads writing the same file each get their own temp file
+        let contents: Vec<Vec<u8>> = (0..8).map(|i| vec![b'a' + i; 4096]).collect();
+        std::thread::scope(|scope| {
+            for bytes in &contents {
+                let path = &path;
+                scope.spawn(move || {
+                    for _ in 0..20 {
+                        write_atomic(path, bytes).unwrap();
+                    }
+                });
+            }
+        });
+        assert!(contents.contains(&fs::read(&path).unwrap()));
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
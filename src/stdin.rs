@@ -0,0 +1,116 @@
+use std::fmt;
+use std::io::{self, BufRead, BufReader, IsTerminal, Read};
+use std::sync::{Arc, Mutex};
+
+/// The host's stdin when input is piped in (`cat list.txt | meta bulk
+/// import`), shared by every clone of a [`PluginContext`](crate::PluginContext)
+/// but readable by one party only: the first [`take`](Self::take) gets the
+/// reader and later calls get `None`. A host that reads stdin itself must
+/// not hand it to plugins.
+///
+/// When stdin is a terminal nothing is handed out; plugins ask the user
+/// through the [`Prompter`](crate::Prompter) instead.
+#[derive(Clone)]
+pub struct PipedStdin {
+    reader: Arc<Mutex<Option<Box<dyn Read + Send>>>>,
+    piped: bool,
+}
+
+impl PipedStdin {
+    /// No piped input, the default.
+    pub fn none() -> Self {
+        Self {
+            reader: Arc::new(Mutex::new(None)),
+            piped: false,
+        }
+    }
+
+    /// Offer `reader` as piped input.
+    pub fn piped(reader: impl Read + Send + 'static) -> Self {
+        Self {
+            reader: Arc::new(Mutex::new(Some(Box::new(reader)))),
+            piped: true,
+        }
+    }
+
+    /// The process's stdin if it is not a terminal, otherwise
+    /// [`none`](Self::none).
+    pub fn from_process() -> Self {
+        if io::stdin().is_terminal() {
+            Self::none()
+        } else {
+            Self::piped(io::stdin())
+        }
+    }
+
+    /// Whether input was piped in, whether or not it has been taken.
+    pub fn is_piped(&self) -> bool {
+        self.piped
+    }
+
+    /// Claim the input. Only the first call returns it.
+    pub fn take(&self) -> Option<StdinReader> {
+        let reader = self
+            .reader
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()?;
+        Some(StdinReader {
+            inner: BufReader::new(reader),
+        })
+    }
+}
+
+impl Default for PipedStdin {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+impl fmt::Debug for PipedStdin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PipedStdin")
+            .field("piped", &self.piped)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Buffered piped input claimed with [`PipedStdin::take`].
+pub struct StdinReader {
+    inner: BufReader<Box<dyn Read + Send>>,
+}
+
+impl Read for StdinReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl BufRead for StdinReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.inner.consume(amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_piped_input_is_taken_once() {
+        let stdin = PipedStdin::piped(io::Cursor::new("api\nweb\n"));
+        let shared = stdin.clone();
+        let lines: Vec<String> = stdin.take().unwrap().lines().map(Result::unwrap).collect();
+        assert_eq!(lines, ["api", "web"]);
+        assert!(shared.take().is_none());
+        assert!(shared.is_piped());
+
+        let none = PipedStdin::default();
+        assert!(!none.is_piped());
+        assert!(none.take().is_none());
+    }
+}
@@ -0,0 +1,369 @@
+//! Semantic styles for plugin output. Plugins say what a piece of text
+//! is — a success, a repo name, a command — and the host's
+//! [`Theme`] and color settings decide how it looks:
+//!
+//! ```
+//! use meta_plugin_api::{style, PluginContext};
+//!
+//! let ctx = PluginContext::new("/work", "/work", "1.0.0");
+//! let styler = ctx.style();
+//! let line = format!(
+//!     "{} {}",
+//!     styler.paint(style::repo_name(), "api"),
+//!     styler.paint(style::success(), "up to date"),
+//! );
+//! // Not a terminal, so no escape codes
+//! assert_eq!(line, "api up to date");
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ColorChoice, Env, TerminalInfo};
+
+/// Disables color when set to anything but the empty string, unless the
+/// user asked for `--color always`. See <https://no-color.org>.
+pub const NO_COLOR_ENV: &str = "NO_COLOR";
+
+/// What a piece of text means, for the [`Theme`] to style.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Role {
+    Success,
+    Warning,
+    Error,
+    /// A project name, e.g. in `api: pulled`
+    RepoName,
+    /// A command the user could run, e.g. `meta git pull`
+    Command,
+    Path,
+    /// Secondary details such as timings
+    Muted,
+}
+
+impl Role {
+    pub const ALL: [Role; 7] = [
+        Role::Success,
+        Role::Warning,
+        Role::Error,
+        Role::RepoName,
+        Role::Command,
+        Role::Path,
+        Role::Muted,
+    ];
+}
+
+pub fn success() -> Role {
+    Role::Success
+}
+
+pub fn warning() -> Role {
+    Role::Warning
+}
+
+pub fn error() -> Role {
+    Role::Error
+}
+
+pub fn repo_name() -> Role {
+    Role::RepoName
+}
+
+pub fn command() -> Role {
+    Role::Command
+}
+
+pub fn path() -> Role {
+    Role::Path
+}
+
+pub fn muted() -> Role {
+    Role::Muted
+}
+
+/// A foreground color: one of the 8 basic terminal colors, which follow
+/// the user's terminal palette, or an entry of the 256-color palette.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    Fixed(u8),
+}
+
+impl Color {
+    fn write_sgr(self, out: &mut String) {
+        let basic = |n: u8| (30 + n).to_string();
+        out.push_str(&match self {
+            Color::Black => basic(0),
+            Color::Red => basic(1),
+            Color::Green => basic(2),
+            Color::Yellow => basic(3),
+            Color::Blue => basic(4),
+            Color::Magenta => basic(5),
+            Color::Cyan => basic(6),
+            Color::White => basic(7),
+            Color::Fixed(n) => format!("38;5;{}", n),
+        });
+    }
+}
+
+/// How one [`Role`] looks. The default is unstyled text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+impl Style {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn fg(mut self, color: Color) -> Self {
+        self.fg = Some(color);
+        self
+    }
+
+    pub fn bold(mut self) -> Self {
+        self.bold = true;
+        self
+    }
+
+    pub fn dim(mut self) -> Self {
+        self.dim = true;
+        self
+    }
+
+    pub fn italic(mut self) -> Self {
+        self.italic = true;
+        self
+    }
+
+    pub fn underline(mut self) -> Self {
+        self.underline = true;
+        self
+    }
+
+    /// The escape sequence turning this style on, empty for plain text.
+    fn prefix(&self) -> String {
+        let mut codes = Vec::new();
+        if self.bold {
+            codes.push("1".to_string());
+        }
+        if self.dim {
+            codes.push("2".to_string());
+        }
+        if self.italic {
+            codes.push("3".to_string());
+        }
+        if self.underline {
+            codes.push("4".to_string());
+        }
+        if let Some(color) = self.fg {
+            let mut code = String::new();
+            color.write_sgr(&mut code);
+            codes.push(code);
+        }
+        if codes.is_empty() {
+            String::new()
+        } else {
+            format!("\x1b[{}m", codes.join(";"))
+        }
+    }
+}
+
+/// Maps each [`Role`] to a [`Style`]. The host loads the user's theme
+/// from its config; roles the theme leaves out keep their default look.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "BTreeMap<Role, Style>", into = "BTreeMap<Role, Style>")]
+pub struct Theme {
+    styles: BTreeMap<Role, Style>,
+}
+
+impl Theme {
+    /// Every role unstyled, for users who want no emphasis at all.
+    pub fn plain() -> Self {
+        Self {
+            styles: Role::ALL
+                .into_iter()
+                .map(|role| (role, Style::default()))
+                .collect(),
+        }
+    }
+
+    pub fn with(mut self, role: Role, style: Style) -> Self {
+        self.styles.insert(role, style);
+        self
+    }
+
+    pub fn get(&self, role: Role) -> Style {
+        self.styles.get(&role).copied().unwrap_or_default()
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::plain()
+            .with(Role::Success, Style::new().fg(Color::Green))
+            .with(Role::Warning, Style::new().fg(Color::Yellow))
+            .with(Role::Error, Style::new().fg(Color::Red).bold())
+            .with(Role::RepoName, Style::new().fg(Color::Cyan).bold())
+            .with(Role::Command, Style::new().bold())
+            .with(Role::Path, Style::new().underline())
+            .with(Role::Muted, Style::new().dim())
+    }
+}
+
+impl From<BTreeMap<Role, Style>> for Theme {
+    /// A user theme: the given roles replace the defaults.
+    fn from(styles: BTreeMap<Role, Style>) -> Self {
+        styles
+            .into_iter()
+            .fold(Theme::default(), |theme, (role, style)| {
+                theme.with(role, style)
+            })
+    }
+}
+
+impl From<Theme> for BTreeMap<Role, Style> {
+    fn from(theme: Theme) -> Self {
+        theme.styles
+    }
+}
+
+/// Resolves [`Role`]s to escape codes, or to nothing when color is off,
+/// from [`PluginContext::style`](crate::PluginContext::style).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Styler {
+    theme: Theme,
+    color: bool,
+}
+
+impl Styler {
+    pub fn new(theme: Theme, color: bool) -> Self {
+        Self { theme, color }
+    }
+
+    /// Color as `terminal` calls for, except that `NO_COLOR` in `env`
+    /// turns [`ColorChoice::Auto`] off.
+    pub fn for_terminal(theme: Theme, terminal: &TerminalInfo, env: &Env) -> Self {
+        let no_color = env.get(NO_COLOR_ENV).is_some_and(|v| !v.is_empty());
+        let color = match terminal.color {
+            ColorChoice::Auto if no_color => false,
+            _ => terminal.use_color(),
+        };
+        Self::new(theme, color)
+    }
+
+    pub fn use_color(&self) -> bool {
+        self.color
+    }
+
+    /// `text` styled as `role`, for `format!` and `write!`.
+    pub fn paint<'a>(&self, role: Role, text: &'a str) -> Styled<'a> {
+        let style = if self.color {
+            self.theme.get(role)
+        } else {
+            Style::default()
+        };
+        Styled { style, text }
+    }
+}
+
+/// Text with a resolved [`Style`]; displays with escape codes only when
+/// the style is not plain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Styled<'a> {
+    style: Style,
+    text: &'a str,
+}
+
+impl fmt::Display for Styled<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let prefix = self.style.prefix();
+        if prefix.is_empty() {
+            f.write_str(self.text)
+        } else {
+            write!(f, "{}{}\x1b[0m", prefix, self.text)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tty(color: ColorChoice) -> TerminalInfo {
+        TerminalInfo {
+            is_tty: true,
+            color,
+            ..TerminalInfo::default()
+        }
+    }
+
+    #[test]
+    fn test_styler_respects_color_settings_and_no_color() {
+        let env = Env::new();
+        let styler = Styler::for_terminal(Theme::default(), &tty(ColorChoice::Auto), &env);
+        assert_eq!(
+            styler.paint(success(), "ok").to_string(),
+            "\x1b[32mok\x1b[0m"
+        );
+        assert_eq!(
+            styler.paint(repo_name(), "api").to_string(),
+            "\x1b[1;36mapi\x1b[0m"
+        );
+
+        let no_color = Env::new().with_var(NO_COLOR_ENV, "1");
+        let styler = Styler::for_terminal(Theme::default(), &tty(ColorChoice::Auto), &no_color);
+        assert_eq!(styler.paint(error(), "failed").to_string(), "failed");
+        let forced = Styler::for_terminal(Theme::default(), &tty(ColorChoice::Always), &no_color);
+        assert!(forced.use_color());
+        let empty = Env::new().with_var(NO_COLOR_ENV, "");
+        assert!(
+            Styler::for_terminal(Theme::default(), &tty(ColorChoice::Auto), &empty).use_color()
+        );
+        assert!(
+            !Styler::for_terminal(Theme::default(), &TerminalInfo::default(), &env).use_color()
+        );
+    }
+
+    #[test]
+    fn test_user_theme_overrides_defaults() {
+        let theme: Theme = serde_json::from_value(serde_json::json!({
+            "success": {"fg": {"fixed": 42}, "italic": true},
+            "command": {}
+        }))
+        .unwrap();
+        let styler = Styler::new(theme.clone(), true);
+        assert_eq!(
+            styler.paint(success(), "ok").to_string(),
+            "\x1b[3;38;5;42mok\x1b[0m"
+        );
+        assert_eq!(
+            styler.paint(command(), "meta git pull").to_string(),
+            "meta git pull"
+        );
+        assert_eq!(theme.get(Role::Warning), Style::new().fg(Color::Yellow));
+
+        let json = serde_json::to_value(&theme).unwrap();
+        assert_eq!(serde_json::from_value::<Theme>(json).unwrap(), theme);
+        let plain = serde_json::to_value(Theme::plain()).unwrap();
+        let plain: Theme = serde_json::from_value(plain).unwrap();
+        assert_eq!(plain.get(Role::Error), Style::default());
+    }
+}
@@ -0,0 +1,752 @@
+//! Plugins that run as a separate executable and talk to the host over
+//! stdio, so they can be written in any language.
+//!
+//! For every call the host starts the executable, writes one [`Request`]
+//! as a JSON line to its stdin and closes it, then reads JSON lines from
+//! its stdout until the final [`Response`]:
+//!
+//! ```text
+//! > {"type":"execute","command":"build","args":["--release"],"context":{...}}
+//! < {"type":"progress","event":{"event":"started","task":1,"label":"api","total":null}}
+//! < {"type":"output","text":"built api\n"}
+//! < {"type":"result"}
+//! ```
+//!
+//! | Request         | Final response                                      |
+//! |-----------------|-----------------------------------------------------|
+//! | `list_commands` | `commands`, with the plugin name and command names  |
+//! | `execute`       | `result` ([`CallResult`]; `{"type":"result"}` is success) |
+//! | `help_request`  | `help`, without a `help` field when not customized  |
+//!
+//! `progress` and `output` messages may precede any final response.
+//! stderr is shown to the user through the host's output, with secrets
+//! masked, and stdout lines that are not protocol messages are shown as
+//! plain output so simple scripts can print freely. Rust plugin
+//! executables implement their side with [`serve`].
+//!
+//! The host's [`HostInfo`] travels in the environment of every call:
+//! `META_HOST_VERSION` holds the version and `META_HOST_FEATURES` a
+//! comma-separated list of [`Feature::name`]s. A restricted
+//! [`SandboxProfile`](crate::SandboxProfile) is passed as JSON in
+//! `META_HOST_SANDBOX`.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Child, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::cancel::Watchdog;
+use crate::protocol::{CallResult, ExecuteRequest, HelpReply};
+use crate::{
+    Env, Feature, HelpBody, HelpMode, HostInfo, OutputSink, OutputStream, Plugin, PluginContext,
+    PluginError, ProgressEvent, ProgressReporter, ProgressSink, StdioSink, TaskId,
+};
+
+/// Environment variable carrying [`HostInfo::version`].
+pub const HOST_VERSION_ENV: &str = "META_HOST_VERSION";
+/// Environment variable carrying [`HostInfo::features`].
+pub const HOST_FEATURES_ENV: &str = "META_HOST_FEATURES";
+/// Environment variable carrying [`HostInfo::sandbox`], unset when
+/// unrestricted.
+pub const HOST_SANDBOX_ENV: &str = "META_HOST_SANDBOX";
+
+/// Message sent by the host on the plugin's stdin.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    ListCommands,
+    Execute(Box<ExecuteRequest>),
+    HelpRequest {
+        #[serde(default)]
+        args: Vec<String>,
+    },
+}
+
+/// Message written by the plugin on its stdout.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Response {
+    Commands {
+        name: String,
+        commands: Vec<String>,
+    },
+    Help {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        help: Option<HelpReply>,
+    },
+    /// Forwarded to the host's [`ProgressReporter`]; task ids are the
+    /// plugin's own and are remapped by the host
+    Progress {
+        event: ProgressEvent,
+    },
+    /// Text for the user's terminal, on stdout unless `stderr` is set
+    Output {
+        text: String,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        stderr: bool,
+    },
+    Result(CallResult),
+}
+
+/// An external executable driven through the [`Plugin`] trait.
+#[derive(Debug)]
+pub struct SubprocessPlugin {
+    program: PathBuf,
+    args: Vec<String>,
+    host: HostInfo,
+    name: &'static str,
+    commands: Vec<&'static str>,
+}
+
+impl SubprocessPlugin {
+    /// Run `program` once with `list_commands` to learn its name and
+    /// commands. `args` are passed on every invocation.
+    pub fn new(
+        program: impl Into<PathBuf>,
+        args: Vec<String>,
+        host: &HostInfo,
+    ) -> Result<Self, PluginError> {
+        let mut plugin = Self {
+            program: program.into(),
+            args,
+            host: host.clone(),
+            name: "",
+            commands: Vec::new(),
+        };
+        let response = plugin
+            .request(&Request::ListCommands, None)
+            .map_err(|e| PluginError::LoadError(format!("{:#}", e)))?;
+        let Response::Commands { name, commands } = response else {
+            return Err(PluginError::LoadError(format!(
+                "{}: expected a commands response",
+                plugin.program.display()
+            )));
+        };
+        // Plugin names are `&'static str`; each executable is loaded once
+        // per process, so leaking its name and commands is bounded.
+        plugin.name = Box::leak(name.into_boxed_str());
+        plugin.commands = commands
+            .into_iter()
+            .map(|c| &*Box::leak(c.into_boxed_str()))
+            .collect();
+        Ok(plugin)
+    }
+
+    /// Start the executable, send `request` and read up to its final
+    /// response, forwarding progress and output to `ctx` (or the process
+    /// stdio without one).
+    fn request(&self, request: &Request, ctx: Option<&PluginContext>) -> anyhow::Result<Response> {
+        let disabled = ProgressReporter::disabled();
+        let progress = ctx.map_or(&disabled, |ctx| ctx.progress());
+        let output: Arc<dyn OutputSink> = match ctx {
+            Some(ctx) => ctx.output_sink().clone(),
+            None => Arc::new(StdioSink),
+        };
+        let env = match ctx {
+            Some(ctx) => ctx.plugin_env(),
+            None if self.host.sandbox.is_unrestricted() => Env::from_process(),
+            None => Env::from_process().restricted_to(self.host.sandbox.capabilities()),
+        };
+        let mut command = env.command(&self.program);
+        command
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .env(HOST_VERSION_ENV, self.host.version.to_string())
+            .env(HOST_FEATURES_ENV, feature_list(&self.host.features));
+        if !self.host.sandbox.is_unrestricted() {
+            command.env(HOST_SANDBOX_ENV, serde_json::to_string(&self.host.sandbox)?);
+        }
+        if let Some(ctx) = ctx {
+            command.current_dir(ctx.cwd());
+        }
+        let mut child = command
+            .spawn()
+            .with_context(|| format!("failed to start {}", self.program.display()))?;
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        let stderr = forward_stderr(
+            child.stderr.take().expect("stderr is piped"),
+            output.clone(),
+        );
+        let child = RunningChild(Arc::new(Mutex::new(child)));
+        // Reading stdout blocks until the plugin replies, so a hung plugin
+        // is stopped by killing it
+        let _watchdog = ctx.map(|ctx| {
+            let child = child.0.clone();
+            Watchdog::start(
+                ctx.cancellation().clone(),
+                ctx.deadline().copied(),
+                move || {
+                    let _ = lock(&child).kill();
+                },
+            )
+        });
+
+        let mut line = serde_json::to_vec(request)?;
+        line.push(b'\n');
+        let written = stdin.write_all(&line);
+        drop(stdin);
+        if let Err(e) = written {
+            // A plugin that exits without reading still gets to reply.
+            if e.kind() != io::ErrorKind::BrokenPipe {
+                return Err(e.into());
+            }
+        }
+
+        let mut tasks = HashMap::new();
+        let mut reply = None;
+        for line in stdout.lines() {
+            let line = line?;
+            match serde_json::from_str::<Response>(&line) {
+                Ok(Response::Progress { event }) => forward_progress(progress, &mut tasks, event),
+                Ok(Response::Output { text, stderr }) => {
+                    let stream = if stderr {
+                        OutputStream::Stderr
+                    } else {
+                        OutputStream::Stdout
+                    };
+                    output.write(stream, text.as_bytes())?;
+                }
+                Ok(response) => {
+                    reply = Some(response);
+                    break;
+                }
+                Err(_) => output.write(OutputStream::Stdout, format!("{}\n", line).as_bytes())?,
+            }
+        }
+        if reply.is_none() {
+            if let Some(reason) =
+                ctx.and_then(|ctx| Watchdog::reason(ctx.cancellation(), ctx.deadline()))
+            {
+                return Err(reason.into());
+            }
+        }
+        let status = child.wait_or_kill(EXIT_GRACE)?;
+        let _ = stderr.join();
+        reply.ok_or_else(|| {
+            anyhow!(
+                "{} exited ({}) without replying",
+                self.program.display(),
+                status
+            )
+        })
+    }
+}
+
+impl Plugin for SubprocessPlugin {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn commands(&self) -> Vec<&'static str> {
+        self.commands.clone()
+    }
+
+    fn execute(&self, command: &str, args: &[String], ctx: &PluginContext) -> anyhow::Result<()> {
+        let request = Request::Execute(Box::new(ExecuteRequest {
+            command: command.to_string(),
+            args: args.to_vec(),
+            context: ctx.snapshot(),
+        }));
+        match self.request(&request, Some(ctx))? {
+            Response::Result(result) => result.into_result(),
+            other => Err(anyhow!("{}: unexpected response {:?}", self.name, other)),
+        }
+    }
+
+    fn get_help_output(&self, args: &[String]) -> Option<(HelpMode, HelpBody)> {
+        let request = Request::HelpRequest {
+            args: args.to_vec(),
+        };
+        match self.request(&request, None) {
+            Ok(Response::Help { help }) => help.map(HelpReply::into_parts),
+            Ok(other) => {
+                log::warn!("{}: unexpected help response {:?}", self.name, other);
+                None
+            }
+            Err(e) => {
+                log::warn!("{}: failed to get help: {:#}", self.name, e);
+                None
+            }
+        }
+    }
+
+    /// The executable runs its own `on_load` on every call; this only
+    /// records what to tell it.
+    fn on_load(&mut self, host: &HostInfo) -> anyhow::Result<()> {
+        self.host = host.clone();
+        Ok(())
+    }
+}
+
+/// How often [`RunningChild::wait_or_kill`] checks whether the plugin
+/// exited.
+const EXIT_POLL: Duration = Duration::from_millis(10);
+
+/// How long a plugin may keep running once its stdout is done before it
+/// is killed.
+const EXIT_GRACE: Duration = Duration::from_secs(2);
+
+/// The plugin process, shared with the watchdog that kills it. Dropping
+/// it kills the process if it is still running and reaps it, so no error
+/// path leaves a zombie behind.
+struct RunningChild(Arc<Mutex<Child>>);
+
+impl RunningChild {
+    /// Wait up to `grace` for the plugin to exit, then kill it. The lock
+    /// is not held while waiting, so the watchdog can still kill it.
+    fn wait_or_kill(&self, grace: Duration) -> io::Result<ExitStatus> {
+        let start = Instant::now();
+        loop {
+            let mut child = lock(&self.0);
+            if let Some(status) = child.try_wait()? {
+                return Ok(status);
+            }
+            if start.elapsed() >= grace {
+                let _ = child.kill();
+                return child.wait();
+            }
+            drop(child);
+            thread::sleep(EXIT_POLL);
+        }
+    }
+}
+
+impl Drop for RunningChild {
+    fn drop(&mut self) {
+        let mut child = lock(&self.0);
+        if let Ok(None) = child.try_wait() {
+            let _ = child.kill();
+        }
+        let _ = child.wait();
+    }
+}
+
+fn lock(child: &Mutex<Child>) -> MutexGuard<'_, Child> {
+    child.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Copy the plugin's stderr to `output` line by line, so the host's
+/// redaction sees whole lines.
+fn forward_stderr(
+    stderr: impl io::Read + Send + 'static,
+    output: Arc<dyn OutputSink>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut stderr = BufReader::new(stderr);
+        let mut line = Vec::new();
+        while matches!(stderr.read_until(b'\n', &mut line), Ok(n) if n > 0) {
+            if output.write(OutputStream::Stderr, &line).is_err() {
+                break;
+            }
+            line.clear();
+        }
+    })
+}
+
+fn feature_list(features: &[Feature]) -> String {
+    features
+        .iter()
+        .map(|f| f.name())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Read the [`HostInfo`] the host put in the environment.
+fn host_from_env() -> anyhow::Result<HostInfo> {
+    let version = std::env::var(HOST_VERSION_ENV).with_context(|| {
+        format!(
+            "{} is not set; run this plugin through meta",
+            HOST_VERSION_ENV
+        )
+    })?;
+    let mut host = HostInfo::new(
+        version
+            .parse()
+            .with_context(|| format!("invalid {}", HOST_VERSION_ENV))?,
+    );
+    for name in std::env::var(HOST_FEATURES_ENV)
+        .unwrap_or_default()
+        .split(',')
+    {
+        // Features newer than this crate are skipped.
+        if let Some(feature) = Feature::from_name(name) {
+            host = host.with_feature(feature);
+        }
+    }
+    if let Ok(sandbox) = std::env::var(HOST_SANDBOX_ENV) {
+        host = host.with_sandbox(
+            serde_json::from_str(&sandbox)
+                .with_context(|| format!("invalid {}", HOST_SANDBOX_ENV))?,
+        );
+    }
+    Ok(host)
+}
+
+fn forward_progress(
+    progress: &ProgressReporter,
+    tasks: &mut HashMap<TaskId, TaskId>,
+    event: ProgressEvent,
+) {
+    match event {
+        ProgressEvent::Started {
+            task,
+            label,
+            total,
+            parent,
+        } => {
+            let host_task = match parent.and_then(|parent| tasks.get(&parent)) {
+                Some(&host_parent) => progress.start_subtask(host_parent, label, total),
+                None => progress.start_task(label, total),
+            };
+            tasks.insert(task, host_task);
+        }
+        ProgressEvent::Updated {
+            task,
+            current,
+            message,
+        } => {
+            if let Some(&host_task) = tasks.get(&task) {
+                progress.update(host_task, current, message.as_deref());
+            }
+        }
+        ProgressEvent::Finished { task, success } => {
+            if let Some(host_task) = tasks.remove(&task) {
+                progress.finish(host_task, success);
+            }
+        }
+    }
+}
+
+type SharedWriter = Arc<Mutex<dyn Write + Send>>;
+
+/// Sends progress and output from [`serve`] to the host as `progress` and
+/// `output` messages.
+struct ResponseSink(SharedWriter);
+
+impl ResponseSink {
+    fn send(&self, response: &Response) -> io::Result<()> {
+        let mut line = serde_json::to_vec(response)?;
+        line.push(b'\n');
+        let mut writer = self.0.lock().unwrap();
+        writer.write_all(&line)?;
+        writer.flush()
+    }
+}
+
+impl OutputSink for ResponseSink {
+    fn write(&self, stream: OutputStream, bytes: &[u8]) -> io::Result<()> {
+        self.send(&Response::Output {
+            text: String::from_utf8_lossy(bytes).into_owned(),
+            stderr: stream == OutputStream::Stderr,
+        })
+    }
+}
+
+impl ProgressSink for ResponseSink {
+    fn event(&self, event: ProgressEvent) {
+        let _ = self.send(&Response::Progress { event });
+    }
+}
+
+/// Plugin side of the protocol: answer one request from stdin on stdout.
+/// The `main` of a Rust plugin executable is usually just
+/// `serve(&mut MyPlugin::default())`. Since stdout carries the protocol,
+/// output meant for the user must go through
+/// [`PluginContext::stdout`], which sends `output` messages, or to stderr.
+pub fn serve(plugin: &mut dyn Plugin) -> anyhow::Result<()> {
+    let host = host_from_env()?;
+    serve_io(
+        plugin,
+        &host,
+        io::stdin().lock(),
+        Arc::new(Mutex::new(io::stdout())),
+    )
+}
+
+/// [`serve`] over arbitrary streams, for a host described by `host`.
+pub fn serve_io(
+    plugin: &mut dyn Plugin,
+    host: &HostInfo,
+    mut input: impl BufRead,
+    output: SharedWriter,
+) -> anyhow::Result<()> {
+    let sink = Arc::new(ResponseSink(output));
+    let mut line = String::new();
+    input.read_line(&mut line)?;
+    let request = match serde_json::from_str::<Request>(&line) {
+        Ok(request) => request,
+        Err(e) => {
+            let result = Err(anyhow!("invalid request: {}", e));
+            sink.send(&Response::Result(CallResult::from_result(&result)))?;
+            return Ok(());
+        }
+    };
+
+    let loaded = host
+        .check_required(plugin)
+        .map_err(anyhow::Error::from)
+        .and_then(|()| plugin.on_load(host));
+    if loaded.is_err() {
+        sink.send(&Response::Result(CallResult::from_result(&loaded)))?;
+        return Ok(());
+    }
+    let response = match request {
+        Request::ListCommands => Response::Commands {
+            name: plugin.name().to_string(),
+            commands: plugin.commands().iter().map(|c| c.to_string()).collect(),
+        },
+        Request::Execute(request) => {
+            let ctx = request
+                .context
+                .into_context()
+                .with_progress(ProgressReporter::new(sink.clone()))
+                .with_output(sink.clone());
+            let result = plugin.execute(&request.command, &request.args, &ctx);
+            Response::Result(CallResult::from_result(&result))
+        }
+        Request::HelpRequest { args } => Response::Help {
+            help: plugin
+                .get_help_output(&args)
+                .map(|(mode, body)| HelpReply::new(mode, body)),
+        },
+    };
+    sink.send(&response)?;
+    plugin.on_unload();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<ProgressEvent>>);
+
+    impl ProgressSink for Recorder {
+        fn event(&self, event: ProgressEvent) {
+            self.0.lock().unwrap().push(event);
+        }
+    }
+
+    struct Builder;
+
+    impl Plugin for Builder {
+        fn name(&self) -> &'static str {
+            "builder"
+        }
+        fn commands(&self) -> Vec<&'static str> {
+            vec!["build"]
+        }
+        fn execute(
+            &self,
+            _command: &str,
+            args: &[String],
+            ctx: &PluginContext,
+        ) -> anyhow::Result<()> {
+            let task = ctx.progress().start_task("build", Some(1));
+            ctx.progress().finish(task, true);
+            writeln!(ctx.stdout(), "built")?;
+            if args.is_empty() {
+                Err(PluginError::ExitCode(2).into())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    fn serve_line(request: &str) -> Vec<Response> {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let host = HostInfo::new(semver::Version::new(1, 0, 0));
+        serve_io(&mut Builder, &host, request.as_bytes(), output.clone()).unwrap();
+        let output = output.lock().unwrap();
+        output
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_serve_answers_requests() {
+        assert_eq!(
+            serve_line("{\"type\":\"list_commands\"}\n"),
+            vec![Response::Commands {
+                name: "builder".to_string(),
+                commands: vec!["build".to_string()]
+            }]
+        );
+        assert_eq!(
+            serve_line("{\"type\":\"help_request\"}\n"),
+            vec![Response::Help { help: None }]
+        );
+
+        let request = serde_json::to_string(&Request::Execute(Box::new(ExecuteRequest {
+            command: "build".to_string(),
+            args: vec![],
+            context: PluginContext::new("/work", "/work", "1.0.0").snapshot(),
+        })))
+        .unwrap();
+        let responses = serve_line(&request);
+        assert_eq!(responses.len(), 4);
+        assert!(matches!(
+            responses[0],
+            Response::Progress {
+                event: ProgressEvent::Started { .. }
+            }
+        ));
+        assert_eq!(
+            responses[2],
+            Response::Output {
+                text: "built\n".to_string(),
+                stderr: false
+            }
+        );
+        assert!(matches!(
+            &responses[3],
+            Response::Result(CallResult {
+                exit_code: Some(2),
+                ..
+            })
+        ));
+
+        let responses = serve_line("not json\n");
+        assert!(matches!(&responses[0], Response::Result(r) if r.error.is_some()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_subprocess_plugin_speaks_protocol() {
+        let script = r#"
+            read request
+            [ "$META_HOST_VERSION" = 1.2.0 ] && [ "$META_HOST_FEATURES" = hooks,wasm ] || exit 9
+            case "$request" in
+              *list_commands*) echo '{"type":"commands","name":"shell","commands":["deploy"]}' ;;
+              *help_request*) echo '{"type":"help","help":{"mode":"append","text":"deploy help"}}' ;;
+              *) echo '{"type":"progress","event":{"event":"started","task":7,"label":"deploy","total":null}}'
+                 echo '{"type":"progress","event":{"event":"finished","task":7,"success":true}}'
+                 echo '{"type":"result","error":"failed","exit_code":4}' ;;
+            esac
+        "#;
+        let host = HostInfo::new(semver::Version::new(1, 2, 0))
+            .with_feature(Feature::Hooks)
+            .with_feature(Feature::Wasm);
+        let args = vec!["-c".to_string(), script.to_string()];
+        let plugin = SubprocessPlugin::new("/bin/sh", args, &host).unwrap();
+        assert_eq!(plugin.name(), "shell");
+        assert_eq!(plugin.commands(), vec!["deploy"]);
+        assert_eq!(
+            plugin.get_help_output(&[]),
+            Some((HelpMode::Append, "deploy help".into()))
+        );
+
+        let recorder = Arc::new(Recorder::default());
+        let ctx = PluginContext::new("/", "/", "1.0.0")
+            .with_progress(ProgressReporter::new(recorder.clone()));
+        let err = plugin.execute("deploy", &[], &ctx).unwrap_err();
+        assert!(matches!(
+            PluginError::find(&err),
+            Some(PluginError::ExitCode(4))
+        ));
+        let events = recorder.0.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(
+            events[1],
+            ProgressEvent::Finished { success: true, .. }
+        ));
+    }
+
+    #[cfg(unix)]
+    fn shell_plugin(on_execute: &str) -> SubprocessPlugin {
+        let script = format!(
+            r#"
+            read request
+            case "$request" in
+              *list_commands*) echo '{{"type":"commands","name":"shell","commands":["run"]}}' ;;
+              *) {on_execute} ;;
+            esac
+        "#
+        );
+        let args = vec!["-c".to_string(), script];
+        let host = HostInfo::new(semver::Version::new(1, 0, 0));
+        SubprocessPlugin::new("/bin/sh", args, &host).unwrap()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_subprocess_stderr_and_env_go_through_context() {
+        let plugin = shell_plugin(
+            r#"echo "token hunter22" >&2
+               [ -z "$META_SECRET_API" ] || echo leaked >&2
+               echo '{"type":"result"}'"#,
+        );
+        let captured = Arc::new(crate::CapturedOutput::new());
+        let ctx = PluginContext::new("/", "/", "1.0.0")
+            .with_env(Env::from_process().with_var("META_SECRET_API", "hunter22"))
+            .with_granted_capabilities(crate::Capabilities::empty())
+            .with_output(captured.clone());
+        ctx.redact("hunter22");
+        plugin.execute("run", &[], &ctx).unwrap();
+        assert_eq!(captured.stderr(), "token ***\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_subprocess_without_context_is_contained() {
+        // Help requests run without a context: the host's sandbox still
+        // limits the env (cargo sets CARGO_MANIFEST_DIR for tests), and a
+        // plugin that lingers after replying is killed
+        let script = r#"
+            read request
+            case "$request" in
+              *list_commands*) echo '{"type":"commands","name":"shell","commands":[]}' ;;
+              *) [ -z "$CARGO_MANIFEST_DIR" ] && echo '{"type":"help","help":{"mode":"append","text":"clean"}}'
+                 exec sleep 30 ;;
+            esac
+        "#;
+        let host = HostInfo::new(semver::Version::new(1, 0, 0))
+            .with_sandbox(crate::SandboxProfile::denied());
+        let args = vec!["-c".to_string(), script.to_string()];
+        let plugin = SubprocessPlugin::new("/bin/sh", args, &host).unwrap();
+        let start = std::time::Instant::now();
+        assert_eq!(
+            plugin.get_help_output(&[]),
+            Some((HelpMode::Append, "clean".into()))
+        );
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hung_subprocess_is_killed() {
+        let plugin = shell_plugin("exec sleep 30");
+        let ctx = PluginContext::new("/", "/", "1.0.0")
+            .with_deadline(crate::Deadline::after(Duration::from_millis(100)));
+        let err = plugin.execute("run", &[], &ctx).unwrap_err();
+        assert!(matches!(
+            PluginError::find(&err),
+            Some(PluginError::TimedOut(_))
+        ));
+
+        let token = crate::CancellationToken::new();
+        let ctx = PluginContext::new("/", "/", "1.0.0").with_cancellation(token.clone());
+        let cancel = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            token.cancel();
+        });
+        let err = plugin.execute("run", &[], &ctx).unwrap_err();
+        cancel.join().unwrap();
+        assert!(matches!(
+            PluginError::find(&err),
+            Some(PluginError::Cancelled)
+        ));
+    }
+}
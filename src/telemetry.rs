@@ -0,0 +1,214 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{CommandOutcome, Env, PluginError};
+
+/// Set to any non-empty value other than `0` to turn telemetry off for
+/// every plugin. `DO_NOT_TRACK=1` is honored too.
+pub const TELEMETRY_OPT_OUT_ENV: &str = "META_NO_TELEMETRY";
+
+/// How a command ended, without its error message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TelemetryOutcome {
+    Success,
+    Failure,
+    Cancelled,
+    TimedOut,
+}
+
+/// A usage event. Carries only plugin and command names, timing and
+/// outcome; never arguments, paths, messages or anything else the user
+/// typed. Serializes to `{"event":"command_finished","plugin":...}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum TelemetryEvent {
+    CommandStarted {
+        plugin: String,
+        command: String,
+    },
+    CommandFinished {
+        plugin: String,
+        command: String,
+        #[serde(with = "duration_ms")]
+        duration: Duration,
+        outcome: TelemetryOutcome,
+        exit_code: i32,
+    },
+}
+
+/// Host-side receiver of telemetry, e.g. a batching uploader.
+pub trait TelemetrySink: Send + Sync {
+    fn record(&self, event: &TelemetryEvent);
+}
+
+struct NullSink;
+
+impl TelemetrySink for NullSink {
+    fn record(&self, _event: &TelemetryEvent) {}
+}
+
+/// Handle plugins use to report usage. Discards events unless the host
+/// installed a sink, which it does not do when the user opted out.
+#[derive(Clone)]
+pub struct Telemetry {
+    sink: Option<Arc<dyn TelemetrySink>>,
+}
+
+impl Telemetry {
+    pub fn new(sink: Arc<dyn TelemetrySink>) -> Self {
+        Self { sink: Some(sink) }
+    }
+
+    pub fn disabled() -> Self {
+        Self { sink: None }
+    }
+
+    /// A handle sending to `sink`, or a disabled one if `env` opts out via
+    /// [`TELEMETRY_OPT_OUT_ENV`] or `DO_NOT_TRACK`.
+    pub fn from_env(env: &Env, sink: Arc<dyn TelemetrySink>) -> Self {
+        let opted_out = [TELEMETRY_OPT_OUT_ENV, "DO_NOT_TRACK"]
+            .iter()
+            .any(|var| matches!(env.get(var), Some(v) if !v.is_empty() && v != "0"));
+        if opted_out {
+            Self::disabled()
+        } else {
+            Self::new(sink)
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.sink.is_some()
+    }
+
+    pub fn record(&self, event: TelemetryEvent) {
+        self.sink.as_deref().unwrap_or(&NullSink).record(&event);
+    }
+
+    /// Run `f` between a `CommandStarted` and a `CommandFinished` event
+    /// carrying its duration and outcome.
+    pub fn track(
+        &self,
+        plugin: &str,
+        command: &str,
+        f: impl FnOnce() -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        if !self.is_enabled() {
+            return f();
+        }
+        self.record(TelemetryEvent::CommandStarted {
+            plugin: plugin.to_string(),
+            command: command.to_string(),
+        });
+        let started = Instant::now();
+        let result = f();
+        let outcome = match result.as_ref().map_err(PluginError::find) {
+            Ok(()) => TelemetryOutcome::Success,
+            Err(Some(PluginError::Cancelled)) => TelemetryOutcome::Cancelled,
+            Err(Some(PluginError::TimedOut(_))) => TelemetryOutcome::TimedOut,
+            Err(_) => TelemetryOutcome::Failure,
+        };
+        self.record(TelemetryEvent::CommandFinished {
+            plugin: plugin.to_string(),
+            command: command.to_string(),
+            duration: started.elapsed(),
+            outcome,
+            exit_code: CommandOutcome::from_result(&result).exit_code,
+        });
+        result
+    }
+}
+
+impl Default for Telemetry {
+    fn default() -> Self {
+        Self::disabled()
+    }
+}
+
+pub(crate) mod duration_ms {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_millis)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<TelemetryEvent>>);
+
+    impl TelemetrySink for Recorder {
+        fn record(&self, event: &TelemetryEvent) {
+            self.0.lock().unwrap().push(event.clone());
+        }
+    }
+
+    #[test]
+    fn test_track_records_outcome() {
+        let recorder = Arc::new(Recorder::default());
+        let telemetry = Telemetry::new(recorder.clone());
+        let _ = telemetry.track("git", "pull", || Err(PluginError::ExitCode(4).into()));
+        let _ = telemetry.track("git", "push", || Err(PluginError::Cancelled.into()));
+
+        let events = recorder.0.lock().unwrap();
+        assert_eq!(events.len(), 4);
+        assert_eq!(
+            events[0],
+            TelemetryEvent::CommandStarted {
+                plugin: "git".to_string(),
+                command: "pull".to_string()
+            }
+        );
+        assert!(matches!(
+            events[1],
+            TelemetryEvent::CommandFinished {
+                outcome: TelemetryOutcome::Failure,
+                exit_code: 4,
+                ..
+            }
+        ));
+        assert!(matches!(
+            events[3],
+            TelemetryEvent::CommandFinished {
+                outcome: TelemetryOutcome::Cancelled,
+                ..
+            }
+        ));
+
+        let json = serde_json::to_value(TelemetryEvent::CommandFinished {
+            plugin: "git".to_string(),
+            command: "pull".to_string(),
+            duration: Duration::from_millis(1500),
+            outcome: TelemetryOutcome::Success,
+            exit_code: 0,
+        })
+        .unwrap();
+        assert_eq!(json["event"], "command_finished");
+        assert_eq!(json["duration"], 1500);
+    }
+
+    #[test]
+    fn test_opt_out() {
+        let sink: Arc<dyn TelemetrySink> = Arc::new(Recorder::default());
+        assert!(Telemetry::from_env(&Env::new(), sink.clone()).is_enabled());
+        let env = Env::new().with_var(TELEMETRY_OPT_OUT_ENV, "0");
+        assert!(Telemetry::from_env(&env, sink.clone()).is_enabled());
+        let env = Env::new().with_var("DO_NOT_TRACK", "1");
+        assert!(!Telemetry::from_env(&env, sink).is_enabled());
+        assert!(!Telemetry::default().is_enabled());
+    }
+}
@@ -0,0 +1,185 @@
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::PluginError;
+
+/// A temporary directory for one plugin command, behind
+/// [`PluginContext::temp_dir`](crate::PluginContext::temp_dir). Created on
+/// first use and removed when the last handle is dropped, so it goes away
+/// even when the command fails, panics or is cancelled; hosts that want it
+/// gone sooner call [`cleanup`](Self::cleanup).
+///
+/// Clones share the same directory.
+#[derive(Clone)]
+pub struct TempSpace {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    base: PathBuf,
+    dir: Mutex<Option<PathBuf>>,
+}
+
+impl TempSpace {
+    /// A space under the system temp directory.
+    pub fn new() -> Self {
+        Self::in_dir(std::env::temp_dir())
+    }
+
+    /// A space under `base`, e.g. a host-managed scratch directory.
+    pub fn in_dir(base: impl Into<PathBuf>) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                base: base.into(),
+                dir: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// The directory, created if this is the first use.
+    pub fn path(&self) -> Result<PathBuf, PluginError> {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+        let mut dir = self.inner.lock();
+        if let Some(dir) = &*dir {
+            return Ok(dir.clone());
+        }
+        fs::create_dir_all(&self.inner.base)?;
+        let created = unique(|n| {
+            let path = self.inner.base.join(format!(
+                "meta-{}-{}-{}",
+                std::process::id(),
+                NEXT.fetch_add(1, Ordering::Relaxed),
+                n
+            ));
+            fs::create_dir(&path).map(|()| path)
+        })?;
+        *dir = Some(created.clone());
+        Ok(created)
+    }
+
+    /// Create a new, empty file called `name` in the directory. If `name`
+    /// is taken, a numbered variant such as `1-name` is used instead.
+    pub fn create_file(&self, name: &str) -> Result<(PathBuf, File), PluginError> {
+        let dir = self.path()?;
+        unique(|n| {
+            let path = numbered(&dir, name, n);
+            OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+                .map(|file| (path, file))
+        })
+    }
+
+    /// Create a subdirectory called `name`, numbered like
+    /// [`create_file`](Self::create_file) if taken.
+    pub fn create_dir(&self, name: &str) -> Result<PathBuf, PluginError> {
+        let dir = self.path()?;
+        unique(|n| {
+            let path = numbered(&dir, name, n);
+            fs::create_dir(&path).map(|()| path)
+        })
+    }
+
+    /// Remove the directory now. A later use creates a fresh one.
+    pub fn cleanup(&self) -> Result<(), PluginError> {
+        match self.inner.lock().take() {
+            Some(dir) => match fs::remove_dir_all(dir) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            },
+            None => Ok(()),
+        }
+    }
+}
+
+impl Inner {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<PathBuf>> {
+        self.dir.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        if let Some(dir) = self.lock().take() {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+}
+
+impl Default for TempSpace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for TempSpace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TempSpace")
+            .field("dir", &*self.inner.lock())
+            .finish_non_exhaustive()
+    }
+}
+
+fn numbered(dir: &Path, name: &str, n: usize) -> PathBuf {
+    match n {
+        0 => dir.join(name),
+        n => dir.join(format!("{}-{}", n, name)),
+    }
+}
+
+/// Call `create` with 0, 1, 2, ... until it does not fail with
+/// `AlreadyExists`.
+fn unique<T>(mut create: impl FnMut(usize) -> io::Result<T>) -> Result<T, PluginError> {
+    let mut n = 0;
+    loop {
+        match create(n) {
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => n += 1,
+            result => return Ok(result?),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn test_removed_with_last_handle() {
+        let space = TempSpace::new();
+        let shared = space.clone();
+        let dir = space.path().unwrap();
+        assert_eq!(shared.path().unwrap(), dir);
+
+        let (first, mut file) = space.create_file("patch.diff").unwrap();
+        file.write_all(b"diff").unwrap();
+        let (second, _) = shared.create_file("patch.diff").unwrap();
+        assert_eq!(first, dir.join("patch.diff"));
+        assert_eq!(second, dir.join("1-patch.diff"));
+        assert!(space.create_dir("checkout").unwrap().is_dir());
+
+        drop(space);
+        assert!(dir.exists());
+        drop(shared);
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn test_cleanup() {
+        let space = TempSpace::new();
+        assert!(format!("{:?}", space).contains("None"));
+        let dir = space.path().unwrap();
+        space.cleanup().unwrap();
+        assert!(!dir.exists());
+        let fresh = space.path().unwrap();
+        assert_ne!(fresh, dir);
+        space.cleanup().unwrap();
+    }
+}
@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+
+/// The user's `--color` setting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorChoice {
+    /// Color when writing to a terminal (the default)
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// What the host knows about the terminal it is writing to. Plugins style
+/// their output from this instead of probing the terminal themselves, so
+/// the whole CLI agrees on whether to emit ANSI codes.
+///
+/// The default describes a pipe: no TTY, no size, colors decided by
+/// [`ColorChoice::Auto`] and therefore off.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TerminalInfo {
+    /// Whether stdout is a terminal
+    pub is_tty: bool,
+    pub color: ColorChoice,
+    /// Columns, if known
+    pub width: Option<u16>,
+    /// Rows, if known
+    pub height: Option<u16>,
+}
+
+impl TerminalInfo {
+    /// Whether output should contain ANSI color codes.
+    pub fn use_color(&self) -> bool {
+        match self.color {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => self.is_tty,
+        }
+    }
+
+    /// Width to wrap text at: the terminal width, or `fallback` when
+    /// output is not going to a terminal of known size.
+    pub fn width_or(&self, fallback: u16) -> u16 {
+        self.width.unwrap_or(fallback)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_use_color() {
+        let pipe = TerminalInfo::default();
+        assert!(!pipe.use_color());
+        assert_eq!(pipe.width_or(80), 80);
+
+        let tty = TerminalInfo {
+            is_tty: true,
+            width: Some(120),
+            ..TerminalInfo::default()
+        };
+        assert!(tty.use_color());
+        assert_eq!(tty.width_or(80), 120);
+        assert!(!TerminalInfo {
+            color: ColorChoice::Never,
+            ..tty
+        }
+        .use_color());
+        assert!(TerminalInfo {
+            color: ColorChoice::Always,
+            ..pipe
+        }
+        .use_color());
+    }
+}
@@ -0,0 +1,489 @@
+//! Helpers for testing plugins without a host. Requires the `testkit`
+//! feature, usually as a dev-dependency:
+//!
+//! ```ignore
+//! let ctx = MockContext::builder().repo("api").answer(Answer::Confirm(true)).build();
+//! let run = PluginTester::new(MyPlugin::default()).run("sync", &[], &ctx);
+//! run.assert_success();
+//! assert!(run.stdout.contains("synced api"));
+//! ```
+
+mod help;
+
+pub use help::{assert_help_snapshot, normalize_help, render_help, UPDATE_SNAPSHOTS_ENV};
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use semver::Version;
+
+use crate::{
+    CapturedOutput, CommandOutcome, Env, ExecutionMode, HostInfo, NonInteractivePrompter,
+    OutputFormat, PipedStdin, Plugin, PluginConfig, PluginContext, PluginError, ProjectInfo,
+    Prompter,
+};
+
+/// A scripted reply for [`ScriptedPrompter`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Answer {
+    Confirm(bool),
+    Input(String),
+    Select(usize),
+    MultiSelect(Vec<usize>),
+    /// Accept whatever default the plugin offered
+    Default,
+}
+
+/// Answers prompts from a queue, in order. Once the queue is empty it
+/// behaves like [`NonInteractivePrompter`]. Panics when the next answer
+/// does not fit the question, since that is a bug in the test.
+#[derive(Debug, Default)]
+pub struct ScriptedPrompter {
+    answers: Mutex<VecDeque<Answer>>,
+    asked: Mutex<Vec<String>>,
+}
+
+impl ScriptedPrompter {
+    pub fn new(answers: impl IntoIterator<Item = Answer>) -> Self {
+        Self {
+            answers: Mutex::new(answers.into_iter().collect()),
+            asked: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Messages of every prompt shown so far.
+    pub fn asked(&self) -> Vec<String> {
+        self.asked.lock().unwrap().clone()
+    }
+
+    fn next(&self, message: &str) -> Answer {
+        self.asked.lock().unwrap().push(message.to_string());
+        self.answers
+            .lock()
+            .unwrap()
+            .pop_front()
+            .unwrap_or(Answer::Default)
+    }
+}
+
+impl Prompter for ScriptedPrompter {
+    fn confirm(&self, message: &str, default: bool) -> Result<bool, PluginError> {
+        match self.next(message) {
+            Answer::Confirm(yes) => Ok(yes),
+            Answer::Default => NonInteractivePrompter.confirm(message, default),
+            other => panic!("'{}' is a confirm prompt, scripted {:?}", message, other),
+        }
+    }
+
+    fn input(&self, message: &str, default: Option<&str>) -> Result<String, PluginError> {
+        match self.next(message) {
+            Answer::Input(text) => Ok(text),
+            Answer::Default => NonInteractivePrompter.input(message, default),
+            other => panic!("'{}' is an input prompt, scripted {:?}", message, other),
+        }
+    }
+
+    fn select(
+        &self,
+        message: &str,
+        items: &[&str],
+        default: Option<usize>,
+    ) -> Result<usize, PluginError> {
+        match self.next(message) {
+            Answer::Select(index) if index < items.len() => Ok(index),
+            Answer::Default => NonInteractivePrompter.select(message, items, default),
+            other => panic!(
+                "'{}' selects from {:?}, scripted {:?}",
+                message, items, other
+            ),
+        }
+    }
+
+    fn multi_select(
+        &self,
+        message: &str,
+        items: &[&str],
+        defaults: &[bool],
+    ) -> Result<Vec<usize>, PluginError> {
+        match self.next(message) {
+            Answer::MultiSelect(indices) if indices.iter().all(|&i| i < items.len()) => Ok(indices),
+            Answer::Default => NonInteractivePrompter.multi_select(message, items, defaults),
+            other => panic!(
+                "'{}' selects from {:?}, scripted {:?}",
+                message, items, other
+            ),
+        }
+    }
+}
+
+/// A [`PluginContext`] wired to captured output and scripted prompts.
+/// Derefs to the context, so it can be passed wherever `&PluginContext`
+/// is expected.
+pub struct MockContext {
+    ctx: PluginContext,
+    output: Arc<CapturedOutput>,
+    prompter: Arc<ScriptedPrompter>,
+    /// Temporary workspace to delete on drop
+    created: Option<PathBuf>,
+}
+
+impl MockContext {
+    pub fn builder() -> MockContextBuilder {
+        MockContextBuilder::default()
+    }
+
+    pub fn context(&self) -> &PluginContext {
+        &self.ctx
+    }
+
+    /// The workspace root; a temporary directory with
+    /// [`create_dirs`](MockContextBuilder::create_dirs).
+    pub fn workspace(&self) -> &Path {
+        self.ctx.workspace_root()
+    }
+
+    /// Everything written to `ctx.stdout()` so far, including scoped writers.
+    pub fn stdout(&self) -> String {
+        self.output.stdout()
+    }
+
+    pub fn stderr(&self) -> String {
+        self.output.stderr()
+    }
+
+    pub fn prompter(&self) -> &ScriptedPrompter {
+        &self.prompter
+    }
+}
+
+impl std::ops::Deref for MockContext {
+    type Target = PluginContext;
+
+    fn deref(&self) -> &PluginContext {
+        &self.ctx
+    }
+}
+
+impl Drop for MockContext {
+    fn drop(&mut self) {
+        if let Some(dir) = &self.created {
+            let _ = std::fs::remove_dir_all(dir);
+        }
+    }
+}
+
+/// Builder for [`MockContext`]. Defaults to an empty workspace at
+/// `/workspace`, host version `0.0.0`, an empty environment and no
+/// scripted answers.
+#[derive(Default)]
+pub struct MockContextBuilder {
+    workspace: Option<PathBuf>,
+    cwd: Option<PathBuf>,
+    host_version: Option<String>,
+    projects: Vec<ProjectInfo>,
+    answers: Vec<Answer>,
+    config: Option<PluginConfig>,
+    env: Env,
+    output_format: OutputFormat,
+    execution_mode: ExecutionMode,
+    read_only: bool,
+    stdin: PipedStdin,
+    create_dirs: bool,
+}
+
+impl MockContextBuilder {
+    pub fn workspace(mut self, root: impl Into<PathBuf>) -> Self {
+        self.workspace = Some(root.into());
+        self
+    }
+
+    /// Defaults to the workspace root.
+    pub fn cwd(mut self, cwd: impl Into<PathBuf>) -> Self {
+        self.cwd = Some(cwd.into());
+        self
+    }
+
+    pub fn host_version(mut self, version: impl Into<String>) -> Self {
+        self.host_version = Some(version.into());
+        self
+    }
+
+    /// Add a project checked out at `name` inside the workspace.
+    pub fn repo(self, name: &str) -> Self {
+        self.project(ProjectInfo::new(
+            name,
+            name,
+            format!("git@example.com:{}.git", name),
+        ))
+    }
+
+    pub fn project(mut self, project: ProjectInfo) -> Self {
+        self.projects.push(project);
+        self
+    }
+
+    pub fn answer(mut self, answer: Answer) -> Self {
+        self.answers.push(answer);
+        self
+    }
+
+    /// The plugin's `.meta` section.
+    pub fn config(mut self, namespace: &str, section: serde_json::Value) -> Self {
+        self.config = Some(PluginConfig::new(namespace, section));
+        self
+    }
+
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.env = self.env.with_var(key, value);
+        self
+    }
+
+    /// Configure the secret `name`, as the host's default provider would
+    /// find it in the environment.
+    pub fn secret(self, name: &str, value: &str) -> Self {
+        self.env(&crate::secret_env_var(name), value)
+    }
+
+    pub fn output_format(mut self, format: OutputFormat) -> Self {
+        self.output_format = format;
+        self
+    }
+
+    /// Pipe `input` in, as in `echo input | meta ...`.
+    pub fn stdin(mut self, input: impl Into<String>) -> Self {
+        self.stdin = PipedStdin::piped(std::io::Cursor::new(input.into()));
+        self
+    }
+
+    pub fn dry_run(mut self) -> Self {
+        self.execution_mode = ExecutionMode::DryRun;
+        self
+    }
+
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// Create the workspace in a fresh temporary directory, with an empty
+    /// directory per repo, so [`RepoHandle::run_in`](crate::RepoHandle::run_in)
+    /// works. Removed when the context is dropped. Overrides
+    /// [`workspace`](Self::workspace).
+    pub fn create_dirs(mut self) -> Self {
+        self.create_dirs = true;
+        self
+    }
+
+    pub fn build(self) -> MockContext {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+        let (root, created) = if self.create_dirs {
+            let root = std::env::temp_dir().join(format!(
+                "meta-testkit-{}-{}",
+                std::process::id(),
+                NEXT.fetch_add(1, Ordering::Relaxed)
+            ));
+            for project in &self.projects {
+                std::fs::create_dir_all(root.join(&project.path))
+                    .expect("create testkit workspace");
+            }
+            std::fs::create_dir_all(&root).expect("create testkit workspace");
+            (root.clone(), Some(root))
+        } else {
+            let root = self
+                .workspace
+                .unwrap_or_else(|| PathBuf::from("/workspace"));
+            (root, None)
+        };
+
+        let output = Arc::new(CapturedOutput::new());
+        let prompter = Arc::new(ScriptedPrompter::new(self.answers));
+        let cwd = self.cwd.unwrap_or_else(|| root.clone());
+        let mut ctx = PluginContext::new(
+            &root,
+            cwd,
+            self.host_version.unwrap_or_else(|| "0.0.0".to_string()),
+        )
+        .with_projects(self.projects)
+        .with_env(self.env)
+        .with_output_format(self.output_format)
+        .with_execution_mode(self.execution_mode)
+        .with_read_only(self.read_only)
+        .with_stdin(self.stdin)
+        .with_output(output.clone())
+        .with_prompter(prompter.clone());
+        if let Some(config) = self.config {
+            ctx = ctx.with_config(config);
+        }
+        MockContext {
+            ctx,
+            output,
+            prompter,
+            created,
+        }
+    }
+}
+
+/// Drives a plugin the way the host would, for assertions in tests.
+pub struct PluginTester<P: Plugin> {
+    plugin: P,
+}
+
+impl<P: Plugin> PluginTester<P> {
+    /// Load `plugin` against a host reporting version `0.0.0` and no
+    /// features; use [`with_host`](Self::with_host) to test
+    /// [`required_host`](Plugin::required_host) and feature checks.
+    pub fn new(plugin: P) -> Self {
+        Self::with_host(plugin, &HostInfo::new(Version::new(0, 0, 0)))
+            .expect("plugin failed to load")
+    }
+
+    /// Check `required_host` and run `on_load`, as the host does.
+    pub fn with_host(mut plugin: P, host: &HostInfo) -> anyhow::Result<Self> {
+        host.check_required(&plugin)?;
+        plugin.on_load(host)?;
+        Ok(Self { plugin })
+    }
+
+    pub fn plugin(&self) -> &P {
+        &self.plugin
+    }
+
+    /// Validate and run `command`, as the host does, and collect its
+    /// outcome together with what it wrote to `ctx` during this run.
+    pub fn run(&self, command: &str, args: &[&str], ctx: &MockContext) -> TestRun {
+        let (stdout_before, stderr_before) = (ctx.stdout().len(), ctx.stderr().len());
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        let result = match self.plugin.validate(command, &args) {
+            Ok(()) => self.plugin.execute(command, &args, ctx),
+            Err(e) => Err(e.into()),
+        };
+        TestRun {
+            outcome: CommandOutcome::from_result(&result),
+            result,
+            stdout: ctx.stdout()[stdout_before..].to_string(),
+            stderr: ctx.stderr()[stderr_before..].to_string(),
+        }
+    }
+}
+
+impl<P: Plugin> Drop for PluginTester<P> {
+    fn drop(&mut self) {
+        self.plugin.on_unload();
+    }
+}
+
+/// Result of [`PluginTester::run`].
+#[derive(Debug)]
+pub struct TestRun {
+    pub result: anyhow::Result<()>,
+    pub outcome: CommandOutcome,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl TestRun {
+    #[track_caller]
+    pub fn assert_success(&self) -> &Self {
+        if let Err(e) = &self.result {
+            panic!("command failed: {:#}\nstderr:\n{}", e, self.stderr);
+        }
+        self
+    }
+
+    #[track_caller]
+    pub fn assert_exit_code(&self, code: i32) -> &Self {
+        assert_eq!(
+            self.outcome.exit_code, code,
+            "unexpected exit code; outcome: {:?}",
+            self.outcome
+        );
+        self
+    }
+
+    /// The first [`PluginError`] in the failure's chain, if any.
+    pub fn error(&self) -> Option<&PluginError> {
+        self.result.as_ref().err().and_then(PluginError::find)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    struct Releaser;
+
+    impl Plugin for Releaser {
+        fn name(&self) -> &'static str {
+            "releaser"
+        }
+        fn commands(&self) -> Vec<&'static str> {
+            vec!["release"]
+        }
+        fn execute(
+            &self,
+            _command: &str,
+            args: &[String],
+            ctx: &PluginContext,
+        ) -> anyhow::Result<()> {
+            let tag = ctx
+                .prompter()
+                .input("Tag", args.first().map(String::as_str))?;
+            if !ctx
+                .prompter()
+                .confirm(&format!("Release {}?", tag), false)?
+            {
+                return Err(PluginError::ExitCode(3).into());
+            }
+            for repo in ctx.repos() {
+                repo.run_in(|dir| {
+                    writeln!(ctx.scoped_stdout(&repo.name), "tagged {}", tag)?;
+                    Ok(dir.to_path_buf())
+                })?;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_plugin_tester_with_scripted_answers() {
+        let ctx = MockContext::builder()
+            .repo("api")
+            .repo("web")
+            .answer(Answer::Input("v2".to_string()))
+            .answer(Answer::Confirm(true))
+            .create_dirs()
+            .build();
+        let tester = PluginTester::new(Releaser);
+        let run = tester.run("release", &[], &ctx);
+        run.assert_success().assert_exit_code(0);
+        assert_eq!(run.stdout, "[api] tagged v2\n[web] tagged v2\n");
+        assert_eq!(ctx.prompter().asked(), ["Tag", "Release v2?"]);
+
+        // Answers exhausted: defaults apply, and confirm defaults to no.
+        let run = tester.run("release", &["v3"], &ctx);
+        run.assert_exit_code(3);
+        assert!(matches!(run.error(), Some(PluginError::ExitCode(3))));
+        assert!(run.stdout.is_empty());
+
+        let workspace = ctx.workspace().to_path_buf();
+        drop(ctx);
+        assert!(!workspace.exists());
+    }
+
+    #[test]
+    fn test_missing_checkouts_fail() {
+        let ctx = MockContext::builder()
+            .repo("api")
+            .workspace("/nonexistent/workspace")
+            .answer(Answer::Default)
+            .answer(Answer::Confirm(true))
+            .build();
+        let run = PluginTester::new(Releaser).run("release", &["v1"], &ctx);
+        assert!(matches!(run.error(), Some(PluginError::RepoMissing { .. })));
+        assert_eq!(run.outcome.exit_code, 1);
+    }
+}
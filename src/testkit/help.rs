@@ -0,0 +1,243 @@
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::{CommandSpec, Plugin};
+
+/// Set to update help snapshots instead of comparing against them.
+pub const UPDATE_SNAPSHOTS_ENV: &str = "META_UPDATE_SNAPSHOTS";
+
+/// Render everything a plugin contributes to help — its command specs and
+/// its custom help for the top level and for each command — as one
+/// canonical, [normalized](normalize_help) document.
+pub fn render_help(plugin: &dyn Plugin) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "plugin: {} {}", plugin.name(), plugin.version());
+    if !plugin.description().is_empty() {
+        let _ = writeln!(out, "{}", plugin.description());
+    }
+
+    let _ = writeln!(out, "\ncommands:");
+    for spec in plugin.command_specs() {
+        render_spec(&mut out, &spec, "  ");
+    }
+
+    let topics = std::iter::once(None).chain(plugin.commands().into_iter().map(Some));
+    for topic in topics {
+        let args: Vec<String> = topic.iter().map(|c| c.to_string()).collect();
+        if let Some((mode, help)) = plugin.help_output(&args) {
+            let _ = writeln!(
+                out,
+                "\nhelp{} ({:?}):\n{}",
+                topic.map(|c| format!(" {}", c)).unwrap_or_default(),
+                mode,
+                help.render()
+            );
+        }
+    }
+    normalize_help(&out)
+}
+
+/// One spec and, indented beneath it, its subcommands.
+fn render_spec(out: &mut String, spec: &CommandSpec, indent: &str) {
+    let hidden = if spec.hidden { " (hidden)" } else { "" };
+    let experimental = if spec.experimental {
+        " (experimental)"
+    } else {
+        ""
+    };
+    let deprecated = spec
+        .deprecated
+        .as_ref()
+        .map(|d| format!(" (deprecated since {})", d.since))
+        .unwrap_or_default();
+    let _ = writeln!(
+        out,
+        "{}{}{}{}{}",
+        indent,
+        spec.usage_line(),
+        hidden,
+        experimental,
+        deprecated
+    );
+    if !spec.about.is_empty() {
+        let _ = writeln!(out, "{}  {}", indent, spec.about);
+    }
+    for arg in &spec.args {
+        let _ = writeln!(out, "{}  {}  {}", indent, arg.usage(), arg.help);
+    }
+    if !spec.aliases.is_empty() {
+        let _ = writeln!(out, "{}  aliases: {}", indent, spec.aliases.join(", "));
+    }
+    let nested = format!("{}  ", indent);
+    for sub in &spec.subcommands {
+        render_spec(out, sub, &nested);
+    }
+}
+
+/// Make help text comparable across terminals: strip ANSI escapes and
+/// trailing whitespace, expand tabs, shrink alignment padding (runs of
+/// two or more spaces after text) to exactly two spaces, and end with a
+/// single newline.
+pub fn normalize_help(text: &str) -> String {
+    let mut lines: Vec<String> = strip_ansi(text)
+        .replace("\r\n", "\n")
+        .replace('\t', "    ")
+        .lines()
+        .map(|line| {
+            let indent = line.len() - line.trim_start().len();
+            let mut normalized = line[..indent].to_string();
+            let mut spaces = 0;
+            for c in line[indent..].trim_end().chars() {
+                if c == ' ' {
+                    spaces += 1;
+                    continue;
+                }
+                normalized.push_str(&" ".repeat(spaces.min(2)));
+                spaces = 0;
+                normalized.push(c);
+            }
+            normalized
+        })
+        .collect();
+    while lines.last().is_some_and(|l| l.is_empty()) {
+        lines.pop();
+    }
+    lines.join("\n") + "\n"
+}
+
+fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+        // CSI sequences end at the first byte in `@..=~`.
+        if chars.next() == Some('[') {
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Compare [`render_help`] against the snapshot at `path`, panicking with
+/// both versions on a difference. Run with `META_UPDATE_SNAPSHOTS=1` to
+/// write the current help instead, e.g. for a new plugin or an intended
+/// change.
+#[track_caller]
+pub fn assert_help_snapshot(plugin: &dyn Plugin, path: impl AsRef<Path>) {
+    let path = path.as_ref();
+    let actual = render_help(plugin);
+    if std::env::var_os(UPDATE_SNAPSHOTS_ENV).is_some() {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).expect("create snapshot directory");
+        }
+        std::fs::write(path, &actual).expect("write help snapshot");
+        return;
+    }
+    let expected = match std::fs::read_to_string(path) {
+        Ok(expected) => normalize_help(&expected),
+        Err(e) => panic!(
+            "cannot read help snapshot {}: {}; run with {}=1 to create it",
+            path.display(),
+            e,
+            UPDATE_SNAPSHOTS_ENV
+        ),
+    };
+    if actual != expected {
+        panic!(
+            "help for '{}' differs from {}; run with {}=1 to accept\n--- snapshot\n{}\n--- current\n{}",
+            plugin.name(),
+            path.display(),
+            UPDATE_SNAPSHOTS_ENV,
+            expected,
+            actual
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArgSpec, HelpBody, HelpMode, PluginContext};
+
+    struct Documented;
+
+    impl Plugin for Documented {
+        fn name(&self) -> &'static str {
+            "git"
+        }
+        fn commands(&self) -> Vec<&'static str> {
+            vec!["clone", "status"]
+        }
+        fn version(&self) -> &'static str {
+            "1.4.0"
+        }
+        fn command_specs(&self) -> Vec<CommandSpec> {
+            vec![
+                CommandSpec::new("clone")
+                    .about("Clone all repos")
+                    .arg(ArgSpec::positional("url").required())
+                    .arg(ArgSpec::flag("shallow").help("Depth 1")),
+                CommandSpec::new("status").alias("st"),
+            ]
+        }
+        fn execute(
+            &self,
+            _command: &str,
+            _args: &[String],
+            _ctx: &PluginContext,
+        ) -> anyhow::Result<()> {
+            Ok(())
+        }
+        fn get_help_output(&self, args: &[String]) -> Option<(HelpMode, HelpBody)> {
+            match args.first().map(String::as_str) {
+                None => Some((
+                    HelpMode::Append,
+                    "\x1b[1mGit\x1b[0m commands:\n  clone       Clone\t\n".into(),
+                )),
+                Some(_) => None,
+            }
+        }
+    }
+
+    #[test]
+    fn test_render_help_is_canonical() {
+        assert_eq!(
+            render_help(&Documented),
+            "plugin: git 1.4.0\n\
+             \n\
+             commands:\n\
+             \x20 clone <url> [--shallow]\n\
+             \x20   Clone all repos\n\
+             \x20   <url>\n\
+             \x20   [--shallow]  Depth 1\n\
+             \x20 status\n\
+             \x20   aliases: st\n\
+             \n\
+             help (Append):\n\
+             Git commands:\n\
+             \x20 clone  Clone\n"
+        );
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let path = std::env::temp_dir().join(format!("meta-help-{}.txt", std::process::id()));
+        std::fs::write(
+            &path,
+            render_help(&Documented).replace("clone  Clone", "clone \t Clone"),
+        )
+        .unwrap();
+        assert_help_snapshot(&Documented, &path);
+        std::fs::write(&path, "plugin: git 1.3.0\n").unwrap();
+        let mismatch = std::panic::catch_unwind(|| assert_help_snapshot(&Documented, &path));
+        assert!(mismatch.is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,243 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::telemetry::duration_ms;
+
+/// Collects command and span timings for `meta --timings`. The host
+/// creates one per run, wraps each plugin command in
+/// [`time_command`](Self::time_command) and hands a clone to the plugin
+/// with [`PluginContext::with_timings`](crate::PluginContext::with_timings).
+///
+/// The default handle is disabled and records nothing.
+#[derive(Clone, Default)]
+pub struct Timings {
+    state: Option<Arc<Mutex<State>>>,
+}
+
+#[derive(Default)]
+struct State {
+    running: Vec<CommandTiming>,
+    finished: Vec<CommandTiming>,
+}
+
+impl Timings {
+    pub fn new() -> Self {
+        Self {
+            state: Some(Arc::default()),
+        }
+    }
+
+    pub fn disabled() -> Self {
+        Self { state: None }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.state.is_some()
+    }
+
+    /// Run `f`, recording its wall time as `plugin command`. Spans
+    /// started while it runs are attributed to it; commands nested
+    /// through [`PluginContext::invoke`](crate::PluginContext::invoke)
+    /// are recorded separately.
+    pub fn time_command<R>(&self, plugin: &str, command: &str, f: impl FnOnce() -> R) -> R {
+        let Some(state) = &self.state else {
+            return f();
+        };
+        lock(state).running.push(CommandTiming {
+            plugin: plugin.to_string(),
+            command: command.to_string(),
+            wall: Duration::ZERO,
+            spans: Vec::new(),
+        });
+        let started = Instant::now();
+        let result = f();
+        let wall = started.elapsed();
+
+        let mut state = lock(state);
+        if let Some(mut timing) = state.running.pop() {
+            timing.wall = wall;
+            state.finished.push(timing);
+        }
+        result
+    }
+
+    /// Start a span named `name`, recorded against the running command
+    /// when the returned guard drops.
+    pub fn timer(&self, name: impl Into<String>) -> Timer {
+        Timer {
+            timings: self.clone(),
+            name: name.into(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Commands finished so far, in the order they finished.
+    pub fn report(&self) -> TimingReport {
+        TimingReport {
+            commands: self
+                .state
+                .as_ref()
+                .map(|state| lock(state).finished.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    fn record_span(&self, span: SpanTiming) {
+        if let Some(state) = &self.state {
+            if let Some(command) = lock(state).running.last_mut() {
+                command.spans.push(span);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Timings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Timings")
+            .field("enabled", &self.is_enabled())
+            .finish_non_exhaustive()
+    }
+}
+
+fn lock(state: &Mutex<State>) -> std::sync::MutexGuard<'_, State> {
+    state.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// A running span from [`PluginContext::timer`](crate::PluginContext::timer).
+/// Records its duration when dropped.
+#[must_use = "the span ends when the timer is dropped"]
+pub struct Timer {
+    timings: Timings,
+    name: String,
+    started: Instant,
+}
+
+impl Timer {
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        self.timings.record_span(SpanTiming {
+            name: std::mem::take(&mut self.name),
+            duration: self.started.elapsed(),
+        });
+    }
+}
+
+/// Timings of one `meta` run, printed by `meta --timings`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimingReport {
+    pub commands: Vec<CommandTiming>,
+}
+
+/// Wall time of one plugin command and the spans it reported.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommandTiming {
+    pub plugin: String,
+    pub command: String,
+    #[serde(rename = "wall_ms", with = "duration_ms")]
+    pub wall: Duration,
+    #[serde(default)]
+    pub spans: Vec<SpanTiming>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpanTiming {
+    pub name: String,
+    #[serde(rename = "duration_ms", with = "duration_ms")]
+    pub duration: Duration,
+}
+
+impl TimingReport {
+    pub fn total(&self) -> Duration {
+        self.commands.iter().map(|c| c.wall).sum()
+    }
+}
+
+impl fmt::Display for TimingReport {
+    /// One line per command, its spans indented below it:
+    ///
+    /// ```text
+    /// git pull  1.204s
+    ///   fetch   0.950s
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows: Vec<(String, Duration)> = self
+            .commands
+            .iter()
+            .flat_map(|c| {
+                std::iter::once((format!("{} {}", c.plugin, c.command), c.wall)).chain(
+                    c.spans
+                        .iter()
+                        .map(|s| (format!("  {}", s.name), s.duration)),
+                )
+            })
+            .collect();
+        let width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+        for (label, duration) in rows {
+            writeln!(f, "{label:<width$}  {:.3}s", duration.as_secs_f64())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spans_attach_to_running_command() {
+        let timings = Timings::new();
+        timings.time_command("git", "pull", || {
+            drop(timings.timer("fetch"));
+            timings.time_command("git", "status", || {});
+            drop(timings.timer("merge"));
+        });
+        drop(timings.timer("outside"));
+
+        let report = timings.report();
+        let names: Vec<_> = report.commands.iter().map(|c| c.command.as_str()).collect();
+        assert_eq!(names, ["status", "pull"]);
+        assert!(report.commands[0].spans.is_empty());
+        let spans: Vec<_> = report.commands[1]
+            .spans
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(spans, ["fetch", "merge"]);
+        assert!(report.to_string().contains("\n  fetch "));
+
+        let disabled = Timings::default();
+        assert_eq!(disabled.time_command("git", "pull", || 3), 3);
+        assert!(disabled.report().commands.is_empty());
+    }
+
+    #[test]
+    fn test_report_serde() {
+        let report = TimingReport {
+            commands: vec![CommandTiming {
+                plugin: "git".to_string(),
+                command: "pull".to_string(),
+                wall: Duration::from_millis(1204),
+                spans: vec![SpanTiming {
+                    name: "fetch".to_string(),
+                    duration: Duration::from_millis(950),
+                }],
+            }],
+        };
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["commands"][0]["wall_ms"], 1204);
+        assert_eq!(json["commands"][0]["spans"][0]["duration_ms"], 950);
+        assert_eq!(
+            serde_json::from_value::<TimingReport>(json).unwrap(),
+            report
+        );
+        assert_eq!(report.to_string(), "git pull  1.204s\n  fetch   0.950s\n");
+    }
+}
@@ -0,0 +1,37 @@
+use tracing::span::Id;
+use tracing::Dispatch;
+
+/// The host's tracing subscriber and active span, carried into plugins so
+/// their work shows up as child spans of the host command.
+///
+/// `tracing` keeps its dispatcher in per-library globals, so a plugin's
+/// spans would otherwise go nowhere; [`PluginContext::in_trace_span`](crate::PluginContext::in_trace_span)
+/// re-installs the host dispatcher for the duration of a call.
+#[derive(Debug, Clone)]
+pub struct TraceParent {
+    dispatch: Dispatch,
+    span: Option<Id>,
+}
+
+impl TraceParent {
+    pub fn new(dispatch: Dispatch, span: Option<Id>) -> Self {
+        Self { dispatch, span }
+    }
+
+    /// Capture the caller's current dispatcher and span. Hosts call this
+    /// just before invoking a plugin.
+    pub fn current() -> Self {
+        Self {
+            dispatch: tracing::dispatcher::get_default(Dispatch::clone),
+            span: tracing::Span::current().id(),
+        }
+    }
+
+    pub fn dispatch(&self) -> &Dispatch {
+        &self.dispatch
+    }
+
+    pub fn span_id(&self) -> Option<&Id> {
+        self.span.as_ref()
+    }
+}
@@ -0,0 +1,225 @@
+use std::fmt;
+use std::io::{Read, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Plugin, PluginContext, PluginError};
+
+/// What a [`TuiPlugin`] needs from the terminal, sent to the host before
+/// it hands the terminal over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TuiRequirements {
+    /// Draw on the alternate screen, restoring the user's scrollback after
+    #[serde(default = "yes")]
+    pub alternate_screen: bool,
+    /// Read keys one at a time, without echo or line buffering
+    #[serde(default = "yes")]
+    pub raw_mode: bool,
+    #[serde(default)]
+    pub mouse: bool,
+    /// Smallest usable (columns, rows); the host refuses smaller terminals
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_size: Option<(u16, u16)>,
+}
+
+fn yes() -> bool {
+    true
+}
+
+impl Default for TuiRequirements {
+    fn default() -> Self {
+        Self {
+            alternate_screen: true,
+            raw_mode: true,
+            mouse: false,
+            min_size: None,
+        }
+    }
+}
+
+/// The terminal while a [`TuiPlugin`] owns it. The host has stopped its
+/// own rendering (progress bars, spinners) and set the terminal up as
+/// requested; dropping the handle gives it back, so the host restores
+/// the terminal even if the plugin fails or panics.
+pub struct TerminalHandle {
+    width: u16,
+    height: u16,
+    input: Box<dyn Read + Send>,
+    output: Box<dyn Write + Send>,
+    release: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl TerminalHandle {
+    /// A handle over `input` and `output`, usually the host's tty.
+    /// `release` runs once, when the handle is dropped.
+    pub fn new(
+        (width, height): (u16, u16),
+        input: Box<dyn Read + Send>,
+        output: Box<dyn Write + Send>,
+        release: impl FnOnce() + Send + 'static,
+    ) -> Self {
+        Self {
+            width,
+            height,
+            input,
+            output,
+            release: Some(Box::new(release)),
+        }
+    }
+
+    /// (columns, rows) when the terminal was handed over.
+    pub fn size(&self) -> (u16, u16) {
+        (self.width, self.height)
+    }
+
+    /// Keys and other input events, raw.
+    pub fn input(&mut self) -> &mut dyn Read {
+        &mut *self.input
+    }
+
+    /// Where to draw; escape sequences go straight to the terminal.
+    pub fn output(&mut self) -> &mut dyn Write {
+        &mut *self.output
+    }
+}
+
+impl Drop for TerminalHandle {
+    fn drop(&mut self) {
+        let _ = self.output.flush();
+        if let Some(release) = self.release.take() {
+            release();
+        }
+    }
+}
+
+impl fmt::Debug for TerminalHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TerminalHandle")
+            .field("size", &self.size())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Host side of the handover, installed with
+/// [`PluginContext::with_terminal_host`] by hosts that advertise
+/// [`Feature::Tui`](crate::Feature::Tui).
+pub trait TerminalHost: Send + Sync {
+    /// Suspend host output, prepare the terminal and hand it over. Fails
+    /// with [`PluginError::NonInteractive`] when there is no terminal, or
+    /// when it is smaller than `requirements.min_size`.
+    fn acquire(&self, requirements: &TuiRequirements) -> Result<TerminalHandle, PluginError>;
+}
+
+/// A full-screen interactive mode, such as a repo picker or dashboard.
+///
+/// Implement this alongside [`Plugin`] and forward `Plugin::execute` to
+/// [`execute_tui`] for the interactive commands:
+///
+/// ```ignore
+/// impl Plugin for Dashboard {
+///     fn execute(&self, command: &str, args: &[String], ctx: &PluginContext) -> anyhow::Result<()> {
+///         meta_plugin_api::execute_tui(self, command, args, ctx)
+///     }
+/// }
+/// ```
+pub trait TuiPlugin: Plugin {
+    fn tui_requirements(&self, _command: &str) -> TuiRequirements {
+        TuiRequirements::default()
+    }
+
+    fn run_tui(
+        &self,
+        command: &str,
+        args: &[String],
+        terminal: &mut TerminalHandle,
+        ctx: &PluginContext,
+    ) -> anyhow::Result<()>;
+}
+
+/// Take the terminal from the host and run [`TuiPlugin::run_tui`],
+/// giving the terminal back afterwards. Fails with
+/// [`PluginError::Unavailable`] when the host cannot hand it over.
+pub fn execute_tui<P: TuiPlugin + ?Sized>(
+    plugin: &P,
+    command: &str,
+    args: &[String],
+    ctx: &PluginContext,
+) -> anyhow::Result<()> {
+    let mut terminal = ctx
+        .terminal_host()?
+        .acquire(&plugin.tui_requirements(command))?;
+    plugin.run_tui(command, args, &mut terminal, ctx)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+
+    struct Picker;
+
+    impl Plugin for Picker {
+        fn name(&self) -> &'static str {
+            "picker"
+        }
+        fn commands(&self) -> Vec<&'static str> {
+            vec!["pick"]
+        }
+        fn execute(
+            &self,
+            command: &str,
+            args: &[String],
+            ctx: &PluginContext,
+        ) -> anyhow::Result<()> {
+            execute_tui(self, command, args, ctx)
+        }
+    }
+
+    impl TuiPlugin for Picker {
+        fn run_tui(
+            &self,
+            _command: &str,
+            _args: &[String],
+            terminal: &mut TerminalHandle,
+            _ctx: &PluginContext,
+        ) -> anyhow::Result<()> {
+            let mut key = [0];
+            terminal.input().read_exact(&mut key)?;
+            assert_eq!(terminal.size(), (80, 24));
+            anyhow::bail!("picked {}", key[0] as char)
+        }
+    }
+
+    struct FakeTerminal(Arc<AtomicBool>);
+
+    impl TerminalHost for FakeTerminal {
+        fn acquire(&self, requirements: &TuiRequirements) -> Result<TerminalHandle, PluginError> {
+            assert!(requirements.raw_mode);
+            let released = self.0.clone();
+            Ok(TerminalHandle::new(
+                (80, 24),
+                Box::new(std::io::Cursor::new(b"q".to_vec())),
+                Box::new(std::io::sink()),
+                move || released.store(true, Ordering::SeqCst),
+            ))
+        }
+    }
+
+    #[test]
+    fn test_execute_tui_releases_terminal() {
+        let ctx = PluginContext::new("/work", "/work", "1.0.0");
+        let err = Picker.execute("pick", &[], &ctx).unwrap_err();
+        assert!(matches!(
+            PluginError::find(&err),
+            Some(PluginError::Unavailable(_))
+        ));
+
+        let released = Arc::new(AtomicBool::new(false));
+        let ctx = ctx.with_terminal_host(Arc::new(FakeTerminal(released.clone())));
+        let err = Picker.execute("pick", &[], &ctx).unwrap_err();
+        assert_eq!(err.to_string(), "picked q");
+        assert!(released.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,216 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+/// How to reverse one change. Entries outlive the plugin process (`meta
+/// undo` runs later), so actions are data rather than closures.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum UndoAction {
+    /// The host runs `program` with `args`, e.g. `git branch feature
+    /// 1a2b3c`, in `cwd` (the workspace root if unset)
+    Run {
+        program: String,
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cwd: Option<PathBuf>,
+    },
+    /// The host passes `data` back to the recording plugin's
+    /// [`Plugin::undo`](crate::Plugin::undo)
+    Plugin { data: serde_json::Value },
+    /// Nothing can be done automatically; the host shows the
+    /// description so the user can do it by hand
+    Manual,
+}
+
+impl UndoAction {
+    pub fn run<S: Into<String>>(
+        program: impl Into<String>,
+        args: impl IntoIterator<Item = S>,
+    ) -> Self {
+        UndoAction::Run {
+            program: program.into(),
+            args: args.into_iter().map(Into::into).collect(),
+            cwd: None,
+        }
+    }
+
+    /// Like [`run`](Self::run), in `cwd`, usually a repo checkout.
+    pub fn run_in<S: Into<String>>(
+        cwd: impl Into<PathBuf>,
+        program: impl Into<String>,
+        args: impl IntoIterator<Item = S>,
+    ) -> Self {
+        UndoAction::Run {
+            program: program.into(),
+            args: args.into_iter().map(Into::into).collect(),
+            cwd: Some(cwd.into()),
+        }
+    }
+
+    pub fn plugin(data: serde_json::Value) -> Self {
+        UndoAction::Plugin { data }
+    }
+}
+
+/// One recorded inverse action.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UndoEntry {
+    /// Shown by `meta undo`, e.g. `restore branch feature in api`
+    pub description: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+    #[serde(flatten)]
+    pub action: UndoAction,
+}
+
+/// Everything one plugin command recorded, as the host stores it for
+/// `meta undo`. Entries are undone last first.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UndoRecord {
+    pub plugin: String,
+    pub command: String,
+    pub entries: Vec<UndoEntry>,
+}
+
+impl UndoRecord {
+    /// Entries in the order they should be undone.
+    pub fn undo_order(&self) -> impl Iterator<Item = &UndoEntry> {
+        self.entries.iter().rev()
+    }
+}
+
+/// Where plugins record how to reverse what a command changed, through
+/// [`PluginContext::undo`](crate::PluginContext::undo). The host creates
+/// one per command and turns it into an [`UndoRecord`] after the command
+/// ends, even if it failed halfway.
+///
+/// The default journal is disabled and forgets everything.
+#[derive(Clone, Default)]
+pub struct UndoJournal {
+    entries: Option<Arc<Mutex<Vec<UndoEntry>>>>,
+}
+
+impl UndoJournal {
+    pub fn new() -> Self {
+        Self {
+            entries: Some(Arc::default()),
+        }
+    }
+
+    pub fn disabled() -> Self {
+        Self { entries: None }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.entries.is_some()
+    }
+
+    /// Record how to undo a change just made. Record after the change
+    /// succeeds, so a failed step is not "undone".
+    pub fn record(&self, description: impl Into<String>, action: UndoAction) {
+        self.push(UndoEntry {
+            description: description.into(),
+            repo: None,
+            action,
+        });
+    }
+
+    /// Like [`record`](Self::record), for a change to one repo.
+    pub fn record_in(&self, repo: &str, description: impl Into<String>, action: UndoAction) {
+        self.push(UndoEntry {
+            description: description.into(),
+            repo: Some(repo.to_string()),
+            action,
+        });
+    }
+
+    /// Entries recorded so far, oldest first.
+    pub fn entries(&self) -> Vec<UndoEntry> {
+        self.entries
+            .as_ref()
+            .map(|entries| lock(entries).clone())
+            .unwrap_or_default()
+    }
+
+    /// Drain the journal into the record for `plugin command`.
+    pub fn take_record(&self, plugin: &str, command: &str) -> UndoRecord {
+        UndoRecord {
+            plugin: plugin.to_string(),
+            command: command.to_string(),
+            entries: self
+                .entries
+                .as_ref()
+                .map(|entries| std::mem::take(&mut *lock(entries)))
+                .unwrap_or_default(),
+        }
+    }
+
+    fn push(&self, entry: UndoEntry) {
+        if let Some(entries) = &self.entries {
+            lock(entries).push(entry);
+        }
+    }
+}
+
+impl fmt::Debug for UndoJournal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UndoJournal")
+            .field("enabled", &self.is_enabled())
+            .finish_non_exhaustive()
+    }
+}
+
+fn lock(entries: &Mutex<Vec<UndoEntry>>) -> std::sync::MutexGuard<'_, Vec<UndoEntry>> {
+    entries.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_journal_records_and_drains() {
+        let journal = UndoJournal::new();
+        let shared = journal.clone();
+        journal.record_in(
+            "api",
+            "restore branch feature in api",
+            UndoAction::run_in("/work/api", "git", ["branch", "feature", "1a2b3c"]),
+        );
+        shared.record(
+            "restore .meta",
+            UndoAction::plugin(serde_json::json!({"backup": "meta.bak"})),
+        );
+        assert_eq!(journal.entries().len(), 2);
+
+        let record = journal.take_record("git", "prune");
+        assert!(shared.entries().is_empty());
+        let order: Vec<_> = record
+            .undo_order()
+            .map(|e| e.description.as_str())
+            .collect();
+        assert_eq!(order, ["restore .meta", "restore branch feature in api"]);
+
+        let json = serde_json::to_value(&record).unwrap();
+        assert_eq!(
+            json["entries"][0],
+            serde_json::json!({
+                "description": "restore branch feature in api",
+                "repo": "api",
+                "kind": "run",
+                "program": "git",
+                "args": ["branch", "feature", "1a2b3c"],
+                "cwd": "/work/api"
+            })
+        );
+        assert_eq!(serde_json::from_value::<UndoRecord>(json).unwrap(), record);
+
+        let disabled = UndoJournal::default();
+        disabled.record("nothing", UndoAction::Manual);
+        assert!(disabled.entries().is_empty());
+    }
+}
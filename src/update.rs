@@ -0,0 +1,141 @@
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+/// Where newer releases of a plugin are published, from
+/// [`Plugin::update_info`](crate::Plugin::update_info). `meta plugin
+/// upgrade` looks up the newest version there and, for binary sources,
+/// downloads it from [`download_url`](Self::download_url).
+///
+/// Templates may use `{version}` (without a leading `v`) and `{target}`
+/// (the Rust target triple, e.g. `x86_64-unknown-linux-gnu`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum UpdateSource {
+    /// A crate installed with `cargo install`
+    CratesIo { name: String },
+    /// Releases of `owner/repo` on GitHub, tagged `v<version>`
+    GithubReleases {
+        repo: String,
+        /// Release asset name template, e.g. `meta-git-{target}.tar.gz`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        asset: Option<String>,
+    },
+    /// Direct download from a URL template
+    Url { template: String },
+}
+
+impl UpdateSource {
+    pub fn crates_io(name: impl Into<String>) -> Self {
+        UpdateSource::CratesIo { name: name.into() }
+    }
+
+    pub fn github_releases(repo: impl Into<String>, asset: impl Into<String>) -> Self {
+        UpdateSource::GithubReleases {
+            repo: repo.into(),
+            asset: Some(asset.into()),
+        }
+    }
+
+    pub fn url(template: impl Into<String>) -> Self {
+        UpdateSource::Url {
+            template: template.into(),
+        }
+    }
+
+    /// Where to download `version` built for `target`. None for crates,
+    /// which are built from source, and for GitHub releases without an
+    /// asset template.
+    pub fn download_url(&self, version: &Version, target: &str) -> Option<String> {
+        match self {
+            UpdateSource::CratesIo { .. } => None,
+            UpdateSource::GithubReleases { repo, asset } => Some(format!(
+                "https://github.com/{}/releases/download/v{}/{}",
+                repo,
+                version,
+                expand(asset.as_deref()?, version, target)
+            )),
+            UpdateSource::Url { template } => Some(expand(template, version, target)),
+        }
+    }
+}
+
+fn expand(template: &str, version: &Version, target: &str) -> String {
+    template
+        .replace("{version}", &version.to_string())
+        .replace("{target}", target)
+}
+
+/// Parse a version or a release tag such as `v1.4.0`.
+pub fn parse_version(tag: &str) -> Result<Version, semver::Error> {
+    Version::parse(tag.strip_prefix('v').unwrap_or(tag))
+}
+
+/// The newest of `available` that is newer than `current`. Pre-releases
+/// are only considered with `include_prerelease`, or when `current` is
+/// itself a pre-release.
+pub fn newest_update<'a>(
+    current: &Version,
+    available: impl IntoIterator<Item = &'a Version>,
+    include_prerelease: bool,
+) -> Option<&'a Version> {
+    let include_prerelease = include_prerelease || !current.pre.is_empty();
+    available
+        .into_iter()
+        .filter(|v| include_prerelease || v.pre.is_empty())
+        .filter(|v| *v > current)
+        .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_download_url() {
+        let version = parse_version("v1.4.0").unwrap();
+        let target = "x86_64-unknown-linux-gnu";
+        assert_eq!(
+            UpdateSource::github_releases("harmony-labs/meta-git", "meta-git-{target}.tar.gz")
+                .download_url(&version, target)
+                .as_deref(),
+            Some("https://github.com/harmony-labs/meta-git/releases/download/v1.4.0/meta-git-x86_64-unknown-linux-gnu.tar.gz")
+        );
+        assert_eq!(
+            UpdateSource::url("https://dl.example.com/{version}/{target}/meta-jira")
+                .download_url(&version, "aarch64-apple-darwin")
+                .as_deref(),
+            Some("https://dl.example.com/1.4.0/aarch64-apple-darwin/meta-jira")
+        );
+        assert_eq!(
+            UpdateSource::crates_io("meta-git").download_url(&version, target),
+            None
+        );
+        assert_eq!(
+            serde_json::to_value(UpdateSource::crates_io("meta-git")).unwrap(),
+            serde_json::json!({"kind": "crates_io", "name": "meta-git"})
+        );
+    }
+
+    #[test]
+    fn test_newest_update() {
+        let available: Vec<Version> = ["1.2.0", "1.3.0", "1.4.0-beta.1", "0.9.0"]
+            .iter()
+            .map(|v| parse_version(v).unwrap())
+            .collect();
+        let current = Version::new(1, 2, 0);
+        assert_eq!(
+            newest_update(&current, &available, false),
+            Some(&Version::new(1, 3, 0))
+        );
+        assert_eq!(
+            newest_update(&current, &available, true).map(Version::to_string),
+            Some("1.4.0-beta.1".to_string())
+        );
+        assert_eq!(
+            newest_update(&Version::new(2, 0, 0), &available, true),
+            None
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Env;
+
+/// Who is running `meta`, from
+/// [`PluginContext::user`](crate::PluginContext::user), so commits, tags
+/// and tickets created by different plugins are attributed the same way.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserIdentity {
+    /// git `user.name`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// git `user.email`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    /// Operating system login name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    /// Set when running under a CI service
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ci: Option<CiIdentity>,
+}
+
+/// The CI service a command runs under, and who triggered the build.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CiIdentity {
+    /// e.g. `github_actions`, or `ci` when only `CI` is set
+    pub provider: String,
+    /// e.g. the GitHub user who pushed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor: Option<String>,
+}
+
+/// (provider, variable that detects it, variable naming the actor)
+const CI_PROVIDERS: [(&str, &str, &str); 5] = [
+    ("github_actions", "GITHUB_ACTIONS", "GITHUB_ACTOR"),
+    ("gitlab_ci", "GITLAB_CI", "GITLAB_USER_LOGIN"),
+    ("buildkite", "BUILDKITE", "BUILDKITE_BUILD_CREATOR"),
+    ("circleci", "CIRCLECI", "CIRCLE_USERNAME"),
+    ("jenkins", "JENKINS_URL", "BUILD_USER_ID"),
+];
+
+impl CiIdentity {
+    /// The CI service `env` belongs to, if any.
+    pub fn detect(env: &Env) -> Option<Self> {
+        let var = |name: &str| env.get(name).filter(|v| !v.is_empty());
+        CI_PROVIDERS
+            .iter()
+            .find(|(_, marker, _)| var(marker).is_some())
+            .map(|(provider, _, actor)| CiIdentity {
+                provider: provider.to_string(),
+                actor: var(actor).map(str::to_string),
+            })
+            .or_else(|| {
+                var("CI")
+                    .filter(|v| *v != "false" && *v != "0")
+                    .map(|_| CiIdentity {
+                        provider: "ci".to_string(),
+                        actor: None,
+                    })
+            })
+    }
+}
+
+impl UserIdentity {
+    /// Detect the identity the way git would see it from `dir`:
+    /// `GIT_AUTHOR_NAME`/`GIT_AUTHOR_EMAIL` if set, otherwise `git config`.
+    /// Anything that cannot be found is None.
+    pub fn detect(env: &Env, dir: &Path) -> Self {
+        let var = |name: &str| env.get(name).filter(|v| !v.is_empty()).map(str::to_string);
+        let git_config = |key: &str| {
+            let output = env
+                .command("git")
+                .args(["config", "--get", key])
+                .current_dir(dir)
+                .output()
+                .ok()
+                .filter(|o| o.status.success())?;
+            let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            (!value.is_empty()).then_some(value)
+        };
+        Self {
+            name: var("GIT_AUTHOR_NAME").or_else(|| git_config("user.name")),
+            email: var("GIT_AUTHOR_EMAIL").or_else(|| git_config("user.email")),
+            username: var("USER").or_else(|| var("USERNAME")),
+            ci: CiIdentity::detect(env),
+        }
+    }
+
+    /// `Name <email>`, as in a commit author line, when both are known.
+    pub fn signature(&self) -> Option<String> {
+        Some(format!(
+            "{} <{}>",
+            self.name.as_ref()?,
+            self.email.as_ref()?
+        ))
+    }
+
+    /// The best name to show: the git name, the CI actor, then the login.
+    pub fn display_name(&self) -> Option<&str> {
+        self.name
+            .as_deref()
+            .or_else(|| self.ci.as_ref()?.actor.as_deref())
+            .or(self.username.as_deref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_from_env() {
+        let env = Env::new()
+            .with_var("GIT_AUTHOR_NAME", "Ada Lovelace")
+            .with_var("GIT_AUTHOR_EMAIL", "ada@example.com")
+            .with_var("USER", "ada")
+            .with_var("CI", "true")
+            .with_var("GITHUB_ACTIONS", "true")
+            .with_var("GITHUB_ACTOR", "ada-gh");
+        let user = UserIdentity::detect(&env, &std::env::temp_dir());
+        assert_eq!(
+            user.signature().as_deref(),
+            Some("Ada Lovelace <ada@example.com>")
+        );
+        assert_eq!(user.username.as_deref(), Some("ada"));
+        assert_eq!(
+            user.ci,
+            Some(CiIdentity {
+                provider: "github_actions".to_string(),
+                actor: Some("ada-gh".to_string()),
+            })
+        );
+
+        let ci_only = UserIdentity {
+            ci: CiIdentity::detect(&Env::new().with_var("CI", "1")),
+            username: Some("runner".to_string()),
+            ..UserIdentity::default()
+        };
+        assert_eq!(ci_only.ci.as_ref().unwrap().provider, "ci");
+        assert_eq!(ci_only.display_name(), Some("runner"));
+        assert_eq!(
+            CiIdentity::detect(&Env::new().with_var("CI", "false")),
+            None
+        );
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::BTreeMap;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// Result of a command for other code to consume, returned by
+/// [`Plugin::execute_for_value`](crate::Plugin::execute_for_value). A
+/// dashboard plugin can collect `status` values from several plugins
+/// without parsing their output.
+///
+/// Serializes as plain JSON: `null`, booleans, numbers, strings, arrays
+/// and objects.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PluginValue {
+    #[default]
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    List(Vec<PluginValue>),
+    Map(BTreeMap<String, PluginValue>),
+}
+
+impl PluginValue {
+    /// Convert any serializable value, e.g. a plugin's own status struct.
+    pub fn from_serialize<T: Serialize>(value: &T) -> Result<Self, serde_json::Error> {
+        serde_json::to_value(value).map(Self::from)
+    }
+
+    /// Convert back into a typed value.
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        serde_json::from_value(self.clone().into())
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, PluginValue::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            PluginValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            PluginValue::Int(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Any number, as a float.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            PluginValue::Int(n) => Some(*n as f64),
+            PluginValue::Float(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            PluginValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[PluginValue]> {
+        match self {
+            PluginValue::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&BTreeMap<String, PluginValue>> {
+        match self {
+            PluginValue::Map(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// The entry `key` of a map.
+    pub fn get(&self, key: &str) -> Option<&PluginValue> {
+        self.as_map()?.get(key)
+    }
+}
+
+impl From<serde_json::Value> for PluginValue {
+    /// Integers beyond `i64` become floats.
+    fn from(value: serde_json::Value) -> Self {
+        use serde_json::Value;
+
+        match value {
+            Value::Null => PluginValue::Null,
+            Value::Bool(b) => PluginValue::Bool(b),
+            Value::Number(n) => match n.as_i64() {
+                Some(n) => PluginValue::Int(n),
+                None => PluginValue::Float(n.as_f64().unwrap_or(f64::NAN)),
+            },
+            Value::String(s) => PluginValue::String(s),
+            Value::Array(items) => PluginValue::List(items.into_iter().map(Self::from).collect()),
+            Value::Object(map) => {
+                PluginValue::Map(map.into_iter().map(|(k, v)| (k, Self::from(v))).collect())
+            }
+        }
+    }
+}
+
+impl From<PluginValue> for serde_json::Value {
+    /// Non-finite floats become `null`, as in JSON.
+    fn from(value: PluginValue) -> Self {
+        use serde_json::Value;
+
+        match value {
+            PluginValue::Null => Value::Null,
+            PluginValue::Bool(b) => Value::Bool(b),
+            PluginValue::Int(n) => Value::from(n),
+            PluginValue::Float(n) => Value::from(n),
+            PluginValue::String(s) => Value::String(s),
+            PluginValue::List(items) => Value::Array(items.into_iter().map(Value::from).collect()),
+            PluginValue::Map(map) => {
+                Value::Object(map.into_iter().map(|(k, v)| (k, Value::from(v))).collect())
+            }
+        }
+    }
+}
+
+impl From<bool> for PluginValue {
+    fn from(b: bool) -> Self {
+        PluginValue::Bool(b)
+    }
+}
+
+impl From<i64> for PluginValue {
+    fn from(n: i64) -> Self {
+        PluginValue::Int(n)
+    }
+}
+
+impl From<f64> for PluginValue {
+    fn from(n: f64) -> Self {
+        PluginValue::Float(n)
+    }
+}
+
+impl From<&str> for PluginValue {
+    fn from(s: &str) -> Self {
+        PluginValue::String(s.to_string())
+    }
+}
+
+impl From<String> for PluginValue {
+    fn from(s: String) -> Self {
+        PluginValue::String(s)
+    }
+}
+
+impl<T: Into<PluginValue>> From<Vec<T>> for PluginValue {
+    fn from(items: Vec<T>) -> Self {
+        PluginValue::List(items.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<PluginValue>> FromIterator<(String, T)> for PluginValue {
+    fn from_iter<I: IntoIterator<Item = (String, T)>>(entries: I) -> Self {
+        PluginValue::Map(entries.into_iter().map(|(k, v)| (k, v.into())).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Status {
+        repo: String,
+        dirty: bool,
+        ahead: i64,
+    }
+
+    #[test]
+    fn test_round_trip_through_types() {
+        let status = Status {
+            repo: "api".to_string(),
+            dirty: true,
+            ahead: 2,
+        };
+        let value = PluginValue::from_serialize(&status).unwrap();
+        assert_eq!(value.get("repo").and_then(PluginValue::as_str), Some("api"));
+        assert_eq!(value.get("ahead").and_then(PluginValue::as_i64), Some(2));
+        assert_eq!(value.deserialize::<Status>().unwrap(), status);
+
+        let json = r#"{"count":3,"ratio":0.5,"tags":["a",null],"ok":false}"#;
+        let parsed: PluginValue = serde_json::from_str(json).unwrap();
+        assert_eq!(parsed.get("count"), Some(&PluginValue::Int(3)));
+        assert_eq!(parsed.get("ratio"), Some(&PluginValue::Float(0.5)));
+        assert_eq!(
+            parsed.get("tags"),
+            Some(&PluginValue::from(vec![
+                PluginValue::from("a"),
+                PluginValue::Null
+            ]))
+        );
+        assert_eq!(
+            serde_json::to_string(&parsed).unwrap(),
+            r#"{"count":3,"ok":false,"ratio":0.5,"tags":["a",null]}"#
+        );
+    }
+}
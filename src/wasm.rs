@@ -0,0 +1,389 @@
+//! Plugins compiled to WebAssembly, shipped as a single portable `.wasm`
+//! file instead of one `cdylib` per OS.
+//!
+//! Every call between host and guest passes one JSON document through
+//! guest memory, so the types in this module are the whole contract. A
+//! guest module exports its linear memory as `memory` plus:
+//!
+//! | Export             | Signature              | Input → output                          |
+//! |--------------------|------------------------|-----------------------------------------|
+//! | `meta_api_version` | `() -> i32`            | [`PLUGIN_API_VERSION`]                  |
+//! | `meta_alloc`       | `(len) -> ptr`         | buffer the host writes input into       |
+//! | `meta_dealloc`     | `(ptr, len)`           | frees a buffer returned by the guest    |
+//! | `meta_name`        | `() -> i64`            | UTF-8 plugin name                       |
+//! | `meta_commands`    | `() -> i64`            | JSON array of command names             |
+//! | `meta_execute`     | `(ptr, len) -> i64`    | [`ExecuteRequest`] → [`CallResult`]     |
+//! | `meta_help`        | `(ptr, len) -> i64`    | JSON array of args → `Option<HelpReply>` |
+//! | `meta_on_load`     | `(ptr, len) -> i64`    | [`LoadRequest`] → [`CallResult`]        |
+//! | `meta_on_unload`   | `()`                   |                                         |
+//!
+//! Input buffers are allocated with `meta_alloc` and owned by the guest
+//! once passed in. `i64` results pack a guest buffer as `ptr << 32 | len`;
+//! the host copies it out and releases it with `meta_dealloc`.
+//!
+//! The host provides two imports in the `meta` module:
+//! `write(stream, ptr, len)` for [`STDOUT`]/[`STDERR`] output and
+//! `log(level, ptr, len)` with `level` numbered like [`log::Level`].
+//!
+//! Guests export all of this with [`declare_wasm_plugin!`](crate::declare_wasm_plugin).
+//! Hosts enable the `wasm` feature and load modules with [`WasmPlugin`].
+//!
+//! [`PLUGIN_API_VERSION`]: crate::PLUGIN_API_VERSION
+
+pub use crate::protocol::{CallResult, ExecuteRequest, HelpReply, LoadRequest};
+
+#[cfg(feature = "wasm")]
+mod host;
+
+#[cfg(feature = "wasm")]
+pub use host::WasmPlugin;
+
+/// Stream number for `meta.write` to the host's stdout.
+pub const STDOUT: u32 = 1;
+/// Stream number for `meta.write` to the host's stderr.
+pub const STDERR: u32 = 2;
+
+/// Write `text` to the host's stdout or stderr. Guests have no stdio of
+/// their own, so `println!` output would otherwise be lost. Inside
+/// `execute`, [`PluginContext::stdout`](crate::PluginContext::stdout)
+/// writes through this as well.
+#[cfg(target_arch = "wasm32")]
+pub fn write(stream: u32, text: &str) {
+    unsafe { guest::host_write(stream, text.as_ptr() as u32, text.len() as u32) }
+}
+
+/// Export a plugin type from a `wasm32` module; the WASM counterpart of
+/// [`declare_plugin!`](crate::declare_plugin). Expands to nothing on
+/// other targets, so a plugin crate can build natively and for WASM.
+///
+/// ```ignore
+/// meta_plugin_api::declare_wasm_plugin!(MyPlugin);
+/// // or, with an explicit constructor:
+/// meta_plugin_api::declare_wasm_plugin!(MyPlugin, MyPlugin::new);
+/// ```
+#[macro_export]
+macro_rules! declare_wasm_plugin {
+    ($plugin_type:ty) => {
+        $crate::declare_wasm_plugin!(
+            $plugin_type,
+            <$plugin_type as ::std::default::Default>::default
+        );
+    };
+    ($plugin_type:ty, $constructor:expr) => {
+        #[cfg(target_arch = "wasm32")]
+        const _: () = {
+            use $crate::wasm::guest;
+
+            fn construct() -> ::std::boxed::Box<dyn $crate::Plugin> {
+                let plugin: $plugin_type = ($constructor)();
+                ::std::boxed::Box::new(plugin)
+            }
+
+            #[no_mangle]
+            pub extern "C" fn meta_api_version() -> u32 {
+                $crate::PLUGIN_API_VERSION
+            }
+
+            #[no_mangle]
+            pub extern "C" fn meta_alloc(len: u32) -> u32 {
+                guest::alloc(len)
+            }
+
+            #[no_mangle]
+            pub unsafe extern "C" fn meta_dealloc(ptr: u32, len: u32) {
+                guest::dealloc(ptr, len)
+            }
+
+            #[no_mangle]
+            pub extern "C" fn meta_name() -> u64 {
+                guest::with_plugin(construct, |p| guest::pack(p.name().as_bytes().to_vec()))
+            }
+
+            #[no_mangle]
+            pub extern "C" fn meta_commands() -> u64 {
+                guest::with_plugin(construct, |p| guest::pack(guest::handle_commands(p)))
+            }
+
+            #[no_mangle]
+            pub unsafe extern "C" fn meta_execute(ptr: u32, len: u32) -> u64 {
+                let input = guest::take(ptr, len);
+                guest::with_plugin(construct, |p| guest::pack(guest::handle_execute(p, &input)))
+            }
+
+            #[no_mangle]
+            pub unsafe extern "C" fn meta_help(ptr: u32, len: u32) -> u64 {
+                let input = guest::take(ptr, len);
+                guest::with_plugin(construct, |p| guest::pack(guest::handle_help(p, &input)))
+            }
+
+            #[no_mangle]
+            pub unsafe extern "C" fn meta_on_load(ptr: u32, len: u32) -> u64 {
+                let input = guest::take(ptr, len);
+                guest::with_plugin(construct, |p| guest::pack(guest::handle_on_load(p, &input)))
+            }
+
+            #[no_mangle]
+            pub extern "C" fn meta_on_unload() {
+                guest::with_plugin(construct, |p| p.on_unload())
+            }
+        };
+    };
+}
+
+/// Glue used by [`declare_wasm_plugin!`](crate::declare_wasm_plugin).
+#[doc(hidden)]
+pub mod guest {
+    use super::{CallResult, ExecuteRequest, HelpReply, LoadRequest};
+    use crate::Plugin;
+
+    pub fn handle_commands(plugin: &dyn Plugin) -> Vec<u8> {
+        serde_json::to_vec(&plugin.commands()).unwrap_or_default()
+    }
+
+    pub fn handle_execute(plugin: &dyn Plugin, input: &[u8]) -> Vec<u8> {
+        let result = serde_json::from_slice::<ExecuteRequest>(input)
+            .map_err(anyhow::Error::from)
+            .and_then(|request| {
+                let ctx = request.context.into_context();
+                #[cfg(target_arch = "wasm32")]
+                let ctx = ctx.with_output(std::sync::Arc::new(GuestOutput));
+                plugin.execute(&request.command, &request.args, &ctx)
+            });
+        serde_json::to_vec(&CallResult::from_result(&result)).unwrap_or_default()
+    }
+
+    pub fn handle_help(plugin: &dyn Plugin, input: &[u8]) -> Vec<u8> {
+        let args: Vec<String> = serde_json::from_slice(input).unwrap_or_default();
+        let reply = plugin
+            .get_help_output(&args)
+            .map(|(mode, body)| HelpReply::new(mode, body));
+        serde_json::to_vec(&reply).unwrap_or_default()
+    }
+
+    pub fn handle_on_load(plugin: &mut dyn Plugin, input: &[u8]) -> Vec<u8> {
+        let request = serde_json::from_slice::<LoadRequest>(input);
+        let level = request
+            .as_ref()
+            .map_or(log::LevelFilter::Trace, |r| r.log_level());
+        let result = request
+            .map_err(anyhow::Error::from)
+            .and_then(|request| plugin.on_load(&request.host));
+        if result.is_ok() {
+            plugin.set_logger(&GuestLogger, level);
+        }
+        serde_json::to_vec(&CallResult::from_result(&result)).unwrap_or_default()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub use wasm32::*;
+
+    #[cfg(target_arch = "wasm32")]
+    mod wasm32 {
+        use std::cell::RefCell;
+
+        use crate::Plugin;
+
+        #[link(wasm_import_module = "meta")]
+        extern "C" {
+            #[link_name = "write"]
+            pub(crate) fn host_write(stream: u32, ptr: u32, len: u32);
+            #[link_name = "log"]
+            pub(crate) fn host_log(level: u32, ptr: u32, len: u32);
+        }
+
+        thread_local! {
+            static PLUGIN: RefCell<Option<Box<dyn Plugin>>> = const { RefCell::new(None) };
+        }
+
+        /// Run `f` on the module's plugin, constructing it on first use.
+        pub fn with_plugin<R>(
+            construct: fn() -> Box<dyn Plugin>,
+            f: impl FnOnce(&mut dyn Plugin) -> R,
+        ) -> R {
+            PLUGIN.with(|cell| {
+                let mut plugin = cell.borrow_mut();
+                f(plugin.get_or_insert_with(construct).as_mut())
+            })
+        }
+
+        pub fn alloc(len: u32) -> u32 {
+            Box::into_raw(vec![0u8; len as usize].into_boxed_slice()) as *mut u8 as u32
+        }
+
+        /// # Safety
+        /// `ptr` and `len` must describe a buffer from [`alloc`] or [`pack`].
+        pub unsafe fn dealloc(ptr: u32, len: u32) {
+            drop(take(ptr, len));
+        }
+
+        /// Reclaim an input buffer the host filled.
+        ///
+        /// # Safety
+        /// `ptr` and `len` must describe a buffer from [`alloc`].
+        pub unsafe fn take(ptr: u32, len: u32) -> Box<[u8]> {
+            Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+                ptr as *mut u8,
+                len as usize,
+            ))
+        }
+
+        /// Sends [`PluginContext::stdout`](crate::PluginContext::stdout)
+        /// and `stderr` to the host's `meta.write` import.
+        pub struct GuestOutput;
+
+        impl crate::OutputSink for GuestOutput {
+            fn write(&self, stream: crate::OutputStream, bytes: &[u8]) -> std::io::Result<()> {
+                let stream = match stream {
+                    crate::OutputStream::Stdout => super::super::STDOUT,
+                    crate::OutputStream::Stderr => super::super::STDERR,
+                };
+                unsafe { host_write(stream, bytes.as_ptr() as u32, bytes.len() as u32) };
+                Ok(())
+            }
+        }
+
+        /// Hand `bytes` to the host as a packed `ptr << 32 | len`.
+        pub fn pack(bytes: Vec<u8>) -> u64 {
+            let len = bytes.len() as u64;
+            let ptr = Box::into_raw(bytes.into_boxed_slice()) as *mut u8 as u64;
+            (ptr << 32) | len
+        }
+    }
+
+    /// Forwards the guest's `log` records to the host's `meta.log` import.
+    struct GuestLogger;
+
+    impl log::Log for GuestLogger {
+        fn enabled(&self, _metadata: &log::Metadata) -> bool {
+            true
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        fn log(&self, record: &log::Record) {
+            let message = record.args().to_string();
+            unsafe {
+                host_log(
+                    record.level() as u32,
+                    message.as_ptr() as u32,
+                    message.len() as u32,
+                )
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        fn log(&self, _record: &log::Record) {}
+
+        fn flush(&self) {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HelpBody, HelpMode, Plugin, PluginContext, PluginError};
+
+    struct Greeter;
+
+    impl Plugin for Greeter {
+        fn name(&self) -> &'static str {
+            "greeter"
+        }
+        fn commands(&self) -> Vec<&'static str> {
+            vec!["greet"]
+        }
+        fn execute(
+            &self,
+            command: &str,
+            args: &[String],
+            ctx: &PluginContext,
+        ) -> anyhow::Result<()> {
+            match (command, args.first()) {
+                ("greet", Some(_)) if ctx.host_version() == "1.0.0" => Ok(()),
+                ("greet", _) => Err(PluginError::ExitCode(2).into()),
+                _ => Err(anyhow::anyhow!("unknown command {}", command)),
+            }
+        }
+        fn get_help_output(&self, _args: &[String]) -> Option<(HelpMode, HelpBody)> {
+            Some((HelpMode::Append, HelpBody::markdown("greet *NAME*")))
+        }
+    }
+
+    fn execute(command: &str, args: &[&str]) -> CallResult {
+        let request = ExecuteRequest {
+            command: command.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+            context: PluginContext::new("/work", "/work", "1.0.0").snapshot(),
+        };
+        let output = guest::handle_execute(&Greeter, &serde_json::to_vec(&request).unwrap());
+        serde_json::from_slice(&output).unwrap()
+    }
+
+    #[test]
+    fn test_guest_execute_round_trip() {
+        assert_eq!(execute("greet", &["ada"]), CallResult::default());
+        assert_eq!(serde_json::to_string(&CallResult::default()).unwrap(), "{}");
+
+        let failed = execute("greet", &[]);
+        assert_eq!(failed.exit_code, Some(2));
+        let err = failed.into_result().unwrap_err();
+        assert!(matches!(
+            PluginError::find(&err),
+            Some(PluginError::ExitCode(2))
+        ));
+
+        let unknown = execute("wave", &[]);
+        assert_eq!(unknown.error.as_deref(), Some("unknown command wave"));
+    }
+
+    #[derive(Default)]
+    struct LevelRecorder(std::sync::Mutex<Option<log::LevelFilter>>);
+
+    impl Plugin for LevelRecorder {
+        fn name(&self) -> &'static str {
+            "levels"
+        }
+        fn commands(&self) -> Vec<&'static str> {
+            Vec::new()
+        }
+        fn execute(&self, _: &str, _: &[String], _: &PluginContext) -> anyhow::Result<()> {
+            Ok(())
+        }
+        fn set_logger(&self, _logger: &'static dyn log::Log, level: log::LevelFilter) {
+            *self.0.lock().unwrap() = Some(level);
+        }
+    }
+
+    #[test]
+    fn test_guest_logs_at_host_level() {
+        let host = crate::HostInfo::new(semver::Version::new(1, 0, 0));
+        let request = LoadRequest {
+            log_level: Some("warn".to_string()),
+            ..LoadRequest::new(host.clone())
+        };
+        let mut plugin = LevelRecorder::default();
+        let input = serde_json::to_vec(&request).unwrap();
+        assert_eq!(guest::handle_on_load(&mut plugin, &input), b"{}");
+        assert_eq!(*plugin.0.lock().unwrap(), Some(log::LevelFilter::Warn));
+
+        // A bare HostInfo from an older host still loads
+        let mut plugin = LevelRecorder::default();
+        let input = serde_json::to_vec(&host).unwrap();
+        assert_eq!(guest::handle_on_load(&mut plugin, &input), b"{}");
+        assert_eq!(*plugin.0.lock().unwrap(), Some(log::LevelFilter::Trace));
+    }
+
+    #[test]
+    fn test_guest_commands_and_help() {
+        assert_eq!(guest::handle_commands(&Greeter), b"[\"greet\"]");
+        let reply = guest::handle_help(&Greeter, b"[]");
+        assert_eq!(
+            std::str::from_utf8(&reply).unwrap(),
+            r#"{"mode":"append","text":"greet *NAME*","markdown":true}"#
+        );
+        let reply: Option<HelpReply> = serde_json::from_slice(&reply).unwrap();
+        assert_eq!(
+            reply.unwrap().into_parts(),
+            (HelpMode::Append, HelpBody::markdown("greet *NAME*"))
+        );
+    }
+}
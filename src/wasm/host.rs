@@ -0,0 +1,377 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use wasmtime::{Caller, Config, Engine, Instance, Linker, Memory, Module, Store};
+
+use super::{CallResult, ExecuteRequest, HelpReply, LoadRequest, STDERR};
+use crate::cancel::Watchdog;
+use crate::{
+    check_compatibility, HelpBody, HelpMode, HostInfo, OutputSink, OutputStream, Plugin,
+    PluginContext, PluginError, StdioSink,
+};
+
+struct HostState {
+    /// Log target for records coming from `meta.log`
+    log_target: String,
+    /// Destination of `meta.write`, switched to the context's sink for
+    /// the duration of `execute`
+    output: Arc<dyn OutputSink>,
+}
+
+/// A WASM module loaded with wasmtime and driven through the [`Plugin`]
+/// trait, so the host treats it like any native plugin.
+pub struct WasmPlugin {
+    /// Its own engine, so bumping the epoch interrupts only this module
+    engine: Engine,
+    store: Mutex<Store<HostState>>,
+    instance: Instance,
+    memory: Memory,
+    name: &'static str,
+    commands: OnceLock<Vec<&'static str>>,
+}
+
+impl WasmPlugin {
+    /// Compile and instantiate a `.wasm` file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, PluginError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)
+            .map_err(|e| PluginError::LoadError(format!("{}: {}", path.display(), e)))?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Compile and instantiate a module from memory (binary or text format).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PluginError> {
+        let load_error = |e: wasmtime::Error| PluginError::LoadError(format!("{:#}", e));
+        let mut config = Config::new();
+        config.epoch_interruption(true);
+        let engine = Engine::new(&config).map_err(load_error)?;
+        let module = Module::new(&engine, bytes).map_err(load_error)?;
+        let mut linker = Linker::new(&engine);
+        linker
+            .func_wrap("meta", "write", host_write)
+            .and_then(|l| l.func_wrap("meta", "log", host_log))
+            .map_err(load_error)?;
+        let mut store = Store::new(
+            &engine,
+            HostState {
+                log_target: "wasm".to_string(),
+                output: Arc::new(StdioSink),
+            },
+        );
+        // The epoch only moves when a command is interrupted
+        store.set_epoch_deadline(1);
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .map_err(load_error)?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| PluginError::LoadError("module does not export memory".to_string()))?;
+
+        let version = instance
+            .get_typed_func::<(), u32>(&mut store, "meta_api_version")
+            .and_then(|f| f.call(&mut store, ()))
+            .map_err(load_error)?;
+        check_compatibility(crate::PLUGIN_API_VERSION, version)?;
+
+        let plugin = Self {
+            engine,
+            store: Mutex::new(store),
+            instance,
+            memory,
+            name: "",
+            commands: OnceLock::new(),
+        };
+        let name = plugin
+            .call("meta_name", None)
+            .map_err(|e| PluginError::LoadError(format!("{:#}", e)))?;
+        let name = String::from_utf8(name)
+            .map_err(|_| PluginError::LoadError("plugin name is not UTF-8".to_string()))?;
+        plugin.store.lock().unwrap().data_mut().log_target = name.clone();
+        // Plugin names are `&'static str`; a module is loaded once per
+        // process, so leaking its name (and command list) is bounded.
+        Ok(Self {
+            name: Box::leak(name.into_boxed_str()),
+            ..plugin
+        })
+    }
+
+    /// Call a `meta_*` export, passing `input` through guest memory when
+    /// given, and copy out the packed buffer it returns.
+    fn call(&self, export: &str, input: Option<&[u8]>) -> anyhow::Result<Vec<u8>> {
+        let mut store = self.store.lock().unwrap();
+        let store = &mut *store;
+        // Relative to the current epoch, which an earlier interrupt moved
+        store.set_epoch_deadline(1);
+        let packed = match input {
+            Some(input) => {
+                let len = u32::try_from(input.len())?;
+                let ptr = self
+                    .instance
+                    .get_typed_func::<u32, u32>(&mut *store, "meta_alloc")?
+                    .call(&mut *store, len)?;
+                self.memory.write(&mut *store, ptr as usize, input)?;
+                self.instance
+                    .get_typed_func::<(u32, u32), u64>(&mut *store, export)?
+                    .call(&mut *store, (ptr, len))?
+            }
+            None => self
+                .instance
+                .get_typed_func::<(), u64>(&mut *store, export)?
+                .call(&mut *store, ())?,
+        };
+        let (ptr, len) = ((packed >> 32) as u32, packed as u32);
+        // Check before allocating, so a bogus length cannot make the host
+        // allocate gigabytes
+        let end = ptr as u64 + len as u64;
+        if end > self.memory.data_size(&*store) as u64 {
+            anyhow::bail!(
+                "{} returned {} bytes at {}, outside guest memory",
+                export,
+                len,
+                ptr
+            );
+        }
+        let mut output = vec![0u8; len as usize];
+        self.memory.read(&mut *store, ptr as usize, &mut output)?;
+        self.instance
+            .get_typed_func::<(u32, u32), ()>(&mut *store, "meta_dealloc")?
+            .call(&mut *store, (ptr, len))?;
+        Ok(output)
+    }
+
+    fn call_json<T: serde::de::DeserializeOwned>(
+        &self,
+        export: &str,
+        input: Option<&[u8]>,
+    ) -> anyhow::Result<T> {
+        let output = self.call(export, input)?;
+        Ok(serde_json::from_slice(&output)?)
+    }
+}
+
+impl Plugin for WasmPlugin {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn commands(&self) -> Vec<&'static str> {
+        self.commands
+            .get_or_init(
+                || match self.call_json::<Vec<String>>("meta_commands", None) {
+                    Ok(commands) => commands
+                        .into_iter()
+                        .map(|c| &*Box::leak(c.into_boxed_str()))
+                        .collect(),
+                    Err(e) => {
+                        log::warn!("{}: failed to list commands: {:#}", self.name, e);
+                        Vec::new()
+                    }
+                },
+            )
+            .clone()
+    }
+
+    fn execute(&self, command: &str, args: &[String], ctx: &PluginContext) -> anyhow::Result<()> {
+        let request = serde_json::to_vec(&ExecuteRequest {
+            command: command.to_string(),
+            args: args.to_vec(),
+            context: ctx.snapshot(),
+        })?;
+        let previous = std::mem::replace(
+            &mut self.store.lock().unwrap().data_mut().output,
+            ctx.output_sink().clone(),
+        );
+        // A stuck guest never yields, so interrupt it by bumping the epoch
+        let engine = self.engine.clone();
+        let watcher = Watchdog::start(
+            ctx.cancellation().clone(),
+            ctx.deadline().copied(),
+            move || engine.increment_epoch(),
+        );
+        let result = self.call_json::<CallResult>("meta_execute", Some(&request));
+        drop(watcher);
+        self.store.lock().unwrap().data_mut().output = previous;
+        match result {
+            Ok(result) => result.into_result(),
+            // An interrupted guest traps; say why instead
+            Err(e) => match Watchdog::reason(ctx.cancellation(), ctx.deadline()) {
+                Some(reason) => Err(reason.into()),
+                None => Err(e),
+            },
+        }
+    }
+
+    fn get_help_output(&self, args: &[String]) -> Option<(HelpMode, HelpBody)> {
+        let args = serde_json::to_vec(args).ok()?;
+        match self.call_json::<Option<HelpReply>>("meta_help", Some(&args)) {
+            Ok(reply) => reply.map(HelpReply::into_parts),
+            Err(e) => {
+                log::warn!("{}: failed to get help: {:#}", self.name, e);
+                None
+            }
+        }
+    }
+
+    fn on_load(&mut self, host: &HostInfo) -> anyhow::Result<()> {
+        let host = serde_json::to_vec(&LoadRequest::new(host.clone()))?;
+        self.call_json::<CallResult>("meta_on_load", Some(&host))?
+            .into_result()
+    }
+
+    fn on_unload(&mut self) {
+        let mut store = self.store.lock().unwrap();
+        let result = self
+            .instance
+            .get_typed_func::<(), ()>(&mut *store, "meta_on_unload")
+            .and_then(|f| f.call(&mut *store, ()));
+        if let Err(e) = result {
+            log::warn!("{}: on_unload failed: {:#}", self.name, e);
+        }
+    }
+}
+
+/// Copy a `(ptr, len)` string out of the calling module's memory.
+fn guest_str(caller: &mut Caller<'_, HostState>, ptr: u32, len: u32) -> Option<String> {
+    let memory = caller.get_export("memory")?.into_memory()?;
+    let bytes = memory
+        .data(&*caller)
+        .get(ptr as usize..(ptr as usize).checked_add(len as usize)?)?;
+    Some(String::from_utf8_lossy(bytes).into_owned())
+}
+
+fn host_write(mut caller: Caller<'_, HostState>, stream: u32, ptr: u32, len: u32) {
+    if let Some(text) = guest_str(&mut caller, ptr, len) {
+        let stream = if stream == STDERR {
+            OutputStream::Stderr
+        } else {
+            OutputStream::Stdout
+        };
+        let _ = caller.data().output.write(stream, text.as_bytes());
+    }
+}
+
+fn host_log(mut caller: Caller<'_, HostState>, level: u32, ptr: u32, len: u32) {
+    let level = match level {
+        1 => log::Level::Error,
+        2 => log::Level::Warn,
+        3 => log::Level::Info,
+        4 => log::Level::Debug,
+        _ => log::Level::Trace,
+    };
+    if let Some(message) = guest_str(&mut caller, ptr, len) {
+        log::log!(target: &caller.data().log_target, level, "{}", message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Deadline;
+    use std::thread;
+    use std::time::Duration;
+
+    /// A hand-written guest with canned replies; every buffer it returns
+    /// lives in a data segment, so alloc is a bump pointer and dealloc a no-op.
+    const GUEST: &str = r#"
+        (module
+          (import "meta" "write" (func $write (param i32 i32 i32)))
+          (memory (export "memory") 1)
+          (global $next (mut i32) (i32.const 1024))
+          (data (i32.const 16) "wat-echo")
+          (data (i32.const 32) "[\"hello\"]")
+          (data (i32.const 64) "{\"error\":\"boom\",\"exit_code\":3}")
+          (data (i32.const 128) "{\"mode\":\"append\",\"text\":\"wat help\"}")
+          (data (i32.const 192) "{}")
+          (func (export "meta_api_version") (result i32) (i32.const 1))
+          (func (export "meta_alloc") (param $len i32) (result i32)
+            (global.get $next)
+            (global.set $next (i32.add (global.get $next) (local.get $len))))
+          (func (export "meta_dealloc") (param i32 i32))
+          (func (export "meta_name") (result i64) (i64.const 0x0000001000000008))
+          (func (export "meta_commands") (result i64) (i64.const 0x0000002000000009))
+          (func (export "meta_execute") (param i32 i32) (result i64)
+            (call $write (i32.const 1) (i32.const 16) (i32.const 8))
+            (i64.const 0x000000400000001e))
+          (func (export "meta_help") (param i32 i32) (result i64) (i64.const 0x0000008000000023))
+          (func (export "meta_on_load") (param i32 i32) (result i64) (i64.const 0x000000c000000002))
+          (func (export "meta_on_unload")))
+    "#;
+
+    #[test]
+    fn test_wasm_plugin_round_trip() {
+        let mut plugin = WasmPlugin::from_bytes(GUEST.as_bytes()).unwrap();
+        plugin
+            .on_load(&HostInfo::new(semver::Version::new(1, 0, 0)))
+            .unwrap();
+        assert_eq!(plugin.name(), "wat-echo");
+        assert_eq!(plugin.commands(), vec!["hello"]);
+        assert_eq!(
+            plugin.get_help_output(&[]),
+            Some((HelpMode::Append, "wat help".into()))
+        );
+
+        let captured = Arc::new(crate::CapturedOutput::new());
+        let ctx = PluginContext::new("/work", "/work", "1.0.0").with_output(captured.clone());
+        let err = plugin
+            .execute("hello", &["x".to_string()], &ctx)
+            .unwrap_err();
+        assert!(matches!(
+            PluginError::find(&err),
+            Some(PluginError::ExitCode(3))
+        ));
+        assert_eq!(captured.stdout(), "wat-echo");
+        plugin.on_unload();
+    }
+
+    #[test]
+    fn test_stuck_guest_is_interrupted() {
+        let guest = GUEST.replace(
+            "(call $write (i32.const 1) (i32.const 16) (i32.const 8))",
+            "(loop $spin (br $spin))",
+        );
+        let plugin = WasmPlugin::from_bytes(guest.as_bytes()).unwrap();
+        let ctx = PluginContext::new("/work", "/work", "1.0.0")
+            .with_deadline(Deadline::after(Duration::from_millis(50)));
+        let err = plugin.execute("hello", &[], &ctx).unwrap_err();
+        assert!(matches!(
+            PluginError::find(&err),
+            Some(PluginError::TimedOut(_))
+        ));
+
+        let ctx = PluginContext::new("/work", "/work", "1.0.0");
+        let token = ctx.cancellation().clone();
+        let cancel = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            token.cancel();
+        });
+        let err = plugin.execute("hello", &[], &ctx).unwrap_err();
+        assert!(matches!(
+            PluginError::find(&err),
+            Some(PluginError::Cancelled)
+        ));
+        cancel.join().unwrap();
+        // Still usable after an interrupt
+        assert_eq!(plugin.commands(), vec!["hello"]);
+    }
+
+    #[test]
+    fn test_rejects_buffer_outside_guest_memory() {
+        let guest = r#"
+            (module
+              (memory (export "memory") 1)
+              (func (export "meta_api_version") (result i32) (i32.const 1))
+              (func (export "meta_dealloc") (param i32 i32))
+              (func (export "meta_name") (result i64) (i64.const 0x00000010fffffff0)))
+        "#;
+        let err = WasmPlugin::from_bytes(guest.as_bytes()).err().unwrap();
+        assert!(err.to_string().contains("outside guest memory"), "{}", err);
+    }
+
+    #[test]
+    fn test_rejects_module_without_abi() {
+        let err = WasmPlugin::from_bytes(b"(module (memory (export \"memory\") 1))")
+            .err()
+            .unwrap();
+        assert!(matches!(err, PluginError::LoadError(_)));
+    }
+}